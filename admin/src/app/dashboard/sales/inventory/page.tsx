@@ -8,7 +8,7 @@ import {IColumnDefinition, ModelList} from '@/components/model/model-list'
 import {IPaginatedResponse} from '@/types/models'
 import {formatApiDate} from '@/lib/date-utils'
 import {buildItemUrl} from '@/lib/utils'
-import {IconAdjustments, IconEdit, IconMinus, IconPlus, IconShoppingCart, IconClockDollar, IconClockX} from '@tabler/icons-react'
+import {IconAdjustments, IconEdit, IconMinus, IconPlus, IconShoppingCart, IconClockDollar, IconClockX, IconClockExclamation} from '@tabler/icons-react'
 import {Alert} from '@/components/ui/alert'
 import {Button} from '@/components/ui/button'
 import {Card, CardBody} from '@/components/ui/card'
@@ -20,7 +20,7 @@ interface InventoryTransaction {
     tableId: string;
     tableName: string;
     itemId: string;
-    transactionType: 'sale' | 'rent' | 'release' | 'add' | 'remove' | 'update' | 'adjust';
+    transactionType: 'sale' | 'rent' | 'release' | 'overdue' | 'add' | 'remove' | 'update' | 'adjust';
     quantityChange: number | null;
     previousData: any;
     newData: any;
@@ -34,6 +34,7 @@ const TRANSACTION_TYPE_CONFIG = {
     sale: {label: 'Sale', icon: IconShoppingCart, colorClass: 'text-success', bgClass: 'bg-success/20'},
     rent: {label: 'Rented', icon: IconClockDollar, colorClass: 'text-accent', bgClass: 'bg-accent/20'},
     release: {label: 'Released', icon: IconClockX, colorClass: 'text-secondary', bgClass: 'bg-secondary/20'},
+    overdue: {label: 'Overdue', icon: IconClockExclamation, colorClass: 'text-error', bgClass: 'bg-error/20'},
     add: {label: 'Added', icon: IconPlus, colorClass: 'text-info', bgClass: 'bg-info/20'},
     remove: {label: 'Removed', icon: IconMinus, colorClass: 'text-warning', bgClass: 'bg-warning/20'},
     update: {label: 'Updated', icon: IconEdit, colorClass: 'text-primary', bgClass: 'bg-primary/20'},
@@ -20,6 +20,13 @@ import {ProtectedColumnBadge} from '@/components/tables/protected-column-indicat
 import {TablePageHeader} from '@/components/tables/page-header'
 import {toDisplayName, toInternalName, hasColumnNameIssues, getColumnNameIssues} from '@/utils/column-name-utils'
 
+// Public API exposure of a column (tokens can override it per table and column)
+const COLUMN_VISIBILITY_OPTIONS = [
+    {value: 'visible', label: 'Visible'},
+    {value: 'masked', label: 'Masked'},
+    {value: 'hidden', label: 'Hidden'}
+]
+
 interface TableColumnsPageProps {
     tableSchema?: TableSchema | null;
     tableId?: string;
@@ -357,6 +364,26 @@ export default function TableColumnsPage({tableSchema = null, tableId}: TableCol
             editableInline: true,
             editType: 'text'
         },
+        {
+            key: 'visibility',
+            label: 'API',
+            sortable: false,
+            filterable: false,
+            filterType: 'select',
+            className: 'w-20 sm:w-24',
+            filterOptions: COLUMN_VISIBILITY_OPTIONS,
+            render: (column) => {
+                const visibility = column.visibility || 'visible'
+                return (
+                    <Badge color={visibility === 'visible' ? 'neutral' : 'warning'} size="sm" className="text-xs whitespace-nowrap">
+                        {COLUMN_VISIBILITY_OPTIONS.find(opt => opt.value === visibility)?.label || visibility}
+                    </Badge>
+                )
+            },
+            editableInline: true,
+            editType: 'select',
+            editOptions: COLUMN_VISIBILITY_OPTIONS
+        },
         {
             key: 'created_at',
             label: 'Created',
@@ -425,6 +452,7 @@ export default function TableColumnsPage({tableSchema = null, tableId}: TableCol
                 type: sourceColumn.type,
                 isRequired: sourceColumn.isRequired,
                 allowDuplicates: sourceColumn.allowDuplicates,
+                visibility: sourceColumn.visibility,
                 defaultValue: sourceColumn.defaultValue,
                 position: sourceColumn.position + 1 // Position after the source column
             }
@@ -48,6 +48,8 @@ export default function TableEditPage({tableSchema = null, tableId}: TableEditPa
         tableType: tableSchema ? getTableType(tableSchema.table) : 'default',
         productIdColumn: (tableSchema?.table as any)?.productIdColumn || '',
         rentalPeriod: ((tableSchema?.table as any)?.rentalPeriod as RentalPeriod) || 'month',
+        currency: tableSchema?.table.currency || 'USD',
+        slug: tableSchema?.table.slug || '',
         forSale: Boolean(tableSchema?.table.forSale)
     })
 
@@ -85,6 +87,8 @@ export default function TableEditPage({tableSchema = null, tableId}: TableEditPa
                     tableType: tableType,
                     productIdColumn: schema.table.productIdColumn || '',
                     rentalPeriod: (schema.table.rentalPeriod as RentalPeriod) || 'month',
+                    currency: schema.table.currency || 'USD',
+                    slug: schema.table.slug || '',
                     forSale: tableType === 'sale'
                 })
 
@@ -238,6 +242,8 @@ export default function TableEditPage({tableSchema = null, tableId}: TableEditPa
                 tableType: formData.tableType,
                 productIdColumn: formData.productIdColumn || null,
                 rentalPeriod: formData.tableType === 'rent' ? formData.rentalPeriod : undefined,
+                currency: formData.currency,
+                slug: formData.slug.trim() || null,
                 forSale: formData.tableType === 'sale' // Backwards compatibility
             }
 
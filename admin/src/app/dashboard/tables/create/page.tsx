@@ -56,6 +56,8 @@ export default function CreateTablePage() {
         tableType: 'default',
         productIdColumn: '',
         rentalPeriod: 'month',
+        currency: 'USD',
+        slug: '',
         forSale: false, // Deprecated, kept for backwards compatibility
         columns: [
             {
@@ -224,6 +226,8 @@ export default function CreateTablePage() {
                     tableType: formData.tableType,
                     productIdColumn: formData.productIdColumn || undefined,
                     rentalPeriod: formData.tableType === 'rent' ? formData.rentalPeriod : undefined,
+                    currency: formData.currency,
+                    slug: formData.slug.trim() || undefined,
                     forSale: formData.tableType === 'sale', // Backwards compatibility
                     columns: formData.columns.map(col => ({
                         name: col.name.trim(),
@@ -243,6 +247,8 @@ export default function CreateTablePage() {
                         tableType: formData.tableType,
                         productIdColumn: formData.productIdColumn,
                         rentalPeriod: formData.rentalPeriod,
+                        currency: formData.currency,
+                        slug: formData.slug,
                         forSale: formData.tableType === 'sale'
                     }}
                     errors={errors}
@@ -18,6 +18,7 @@ interface Token {
     allowedDomains: string | null;
     tableAccess: string[] | null; // Array of table IDs
     expiresAt: string | null;
+    customerId: string | null;
     createdAt?: string;
     updatedAt?: string;
 }
@@ -128,6 +129,30 @@ const renderTokenForm = (
                 </div>
             </div>
 
+            {/* Customer Binding Field */}
+            <div className="form-control w-full">
+                <label className="label">
+                    <span className="label-text">Customer ID (Optional)</span>
+                </label>
+                <Input
+                    type="text"
+                    color={errors?.customerId ? 'error' : 'default'}
+                    className="w-full"
+                    value={data.customerId || ''}
+                    onChange={(e) => setData('customerId', e.target.value.trim() || null)}
+                    disabled={processing || readonly}
+                    placeholder="e.g., customer-123"
+                />
+                <InputError message={errors?.customerId}/>
+                <div className="label">
+          <span className="label-text-alt">
+            Bind the token to one customer: its purchases, rentals and releases are made for that customer only,
+            and its sales and rentals lookups only return theirs.
+            <span className="text-primary font-semibold"> Leave empty to allow any customer.</span>
+          </span>
+                </div>
+            </div>
+
             {/* Expiry Date Field */}
             <div className="form-control w-full">
                 <label className="label">
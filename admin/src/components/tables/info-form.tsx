@@ -15,7 +15,8 @@ import {
   IconClock,
   IconInfoCircle,
   IconTag,
-  IconCalendar
+  IconCalendar,
+  IconCurrencyDollar
 } from '@tabler/icons-react'
 import type { TableType, RentalPeriod, TableColumn } from '@/types/dynamic-tables'
 
@@ -28,6 +29,8 @@ export interface TableInfoData {
   tableType: TableType
   productIdColumn: string
   rentalPeriod: RentalPeriod
+  currency: string
+  slug: string
   /** @deprecated Use tableType instead */
   forSale?: boolean
 }
@@ -55,6 +58,16 @@ const RENTAL_PERIOD_OPTIONS: { value: RentalPeriod; label: string }[] = [
   { value: 'year', label: 'Per Year' }
 ]
 
+const CURRENCY_OPTIONS: { value: string; label: string }[] = [
+  { value: 'USD', label: 'USD - US Dollar' },
+  { value: 'EUR', label: 'EUR - Euro' },
+  { value: 'GBP', label: 'GBP - British Pound' },
+  { value: 'CHF', label: 'CHF - Swiss Franc' },
+  { value: 'CAD', label: 'CAD - Canadian Dollar' },
+  { value: 'AUD', label: 'AUD - Australian Dollar' },
+  { value: 'JPY', label: 'JPY - Japanese Yen' }
+]
+
 export function TableInfoForm({
   data,
   errors = {},
@@ -107,6 +120,20 @@ export function TableInfoForm({
             />
             <InputError message={errors.name} />
 
+            <Input
+              type="text"
+              value={data.slug}
+              onChange={(e) => onChange('slug', e.target.value.toLowerCase())}
+              label="API Slug"
+              placeholder="e.g. spring-catalog"
+              maxLength={64}
+              className={errors.slug ? 'input-error' : ''}
+            />
+            <p className="text-xs text-base-content/60">
+              Optional. Public API clients can use it instead of the table ID
+            </p>
+            <InputError message={errors.slug} />
+
             <Textarea
               value={data.description}
               onChange={(e) => onChange('description', e.target.value)}
@@ -220,6 +247,27 @@ export function TableInfoForm({
                   )}
                 </div>
 
+                {/* Base Currency */}
+                <div className="form-control">
+                  <label className="label">
+                    <span className="label-text flex items-center gap-1">
+                      <Icon iconNode={IconCurrencyDollar} className="h-4 w-4" />
+                      Currency
+                    </span>
+                    <div className="tooltip tooltip-left" data-tip="Currency the table's prices are in; the public API converts them on request (?currency=EUR)">
+                      <Icon iconNode={IconInfoCircle} className="h-4 w-4 text-base-content/40 cursor-help" />
+                    </div>
+                  </label>
+                  <Select
+                    value={data.currency || 'USD'}
+                    onChange={(e) => onChange('currency', e.target.value)}
+                    options={CURRENCY_OPTIONS.some(o => o.value === data.currency) || !data.currency
+                      ? CURRENCY_OPTIONS
+                      : [{ value: data.currency, label: data.currency }, ...CURRENCY_OPTIONS]}
+                    className="select-sm"
+                  />
+                </div>
+
                 {/* Rental Period (only for rent tables) */}
                 {data.tableType === 'rent' && (
                   <div className="form-control">
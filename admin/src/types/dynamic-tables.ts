@@ -77,12 +77,17 @@ export interface UserTable extends BaseModel {
   tableType?: TableType // New: Table type (default, sale, rent)
   productIdColumn?: string | null // Column name that serves as product identifier/title
   rentalPeriod?: RentalPeriod | null // Billing period for rent tables (default: 'month')
+  currency?: string // ISO 4217 code the currency columns are priced in (default: 'USD')
+  slug?: string | null // Stable public API handle, usable instead of the id
   createdAt: string
   updatedAt: string
   ownerDisplayName?: string // Friendly display name for the owner
   rowCount?: number // Total number of data rows in this table
 }
 
+/** Public API exposure of a column: masked shows e.g. j***@x.com, or a phone's last 4 digits */
+export type ColumnVisibility = 'visible' | 'hidden' | 'masked'
+
 export interface TableColumn extends BaseModel {
   id: string
   tableId: string
@@ -92,6 +97,7 @@ export interface TableColumn extends BaseModel {
   allowDuplicates: boolean
   defaultValue?: string | null
   position: number
+  visibility?: ColumnVisibility // What the public API shows of this column
   createdAt: string
 }
 
@@ -130,6 +136,8 @@ export interface CreateTableRequest {
   tableType?: TableType
   productIdColumn?: string
   rentalPeriod?: RentalPeriod
+  currency?: string
+  slug?: string
   forSale?: boolean // @deprecated Use tableType instead
   columns: CreateColumnRequest[]
 }
@@ -150,6 +158,8 @@ export interface UpdateTableRequest {
   tableType?: TableType
   productIdColumn?: string | null
   rentalPeriod?: RentalPeriod
+  currency?: string
+  slug?: string | null
   forSale?: boolean // @deprecated Use tableType instead
 }
 
@@ -212,6 +222,8 @@ export interface TableFormData {
   tableType: TableType
   productIdColumn: string
   rentalPeriod: RentalPeriod
+  currency: string
+  slug: string
   forSale: boolean // @deprecated Use tableType instead
   columns: ColumnFormData[]
 }
@@ -21,6 +21,7 @@ export interface Rental {
   rentalStatus: RentalStatus
   rentedAt: string // ISO date string
   releasedAt: string | null // ISO date string
+  overdueAt: string | null // ISO date string, set when flagged overdue
   notes: string | null
   createdAt: string // ISO date string
   updatedAt: string // ISO date string
@@ -0,0 +1,8 @@
+-- Migration 009: Token Rate Limits
+-- Adds per-token rate limits enforced by the Rust public API
+-- JSON: { "read": { "perMinute": 600, "burst": 100 }, "write": { "perMinute": 30, "burst": 5 } }
+-- NULL (or a missing read/write entry) falls back to the worker's RATE_LIMIT_* defaults
+
+ALTER TABLE "tokens" ADD COLUMN "rateLimits" TEXT;
+
+-- Schema version: 009 - Per-token rate limits for the public API
@@ -0,0 +1,19 @@
+-- Migration 010: API Usage Metering
+-- Hourly per-token, per-route usage written by the Rust public API
+-- Used for reseller billing and exposed to each token via GET /api/public/usage
+
+CREATE TABLE IF NOT EXISTS apiUsage (
+    tokenId TEXT NOT NULL,
+    route TEXT NOT NULL,                    -- Route pattern, e.g. 'GET /api/public/tables/:id/items'
+    hour TEXT NOT NULL,                     -- UTC hour bucket: 'YYYY-MM-DD HH:00:00'
+    requestCount INTEGER NOT NULL DEFAULT 0,
+    errorCount INTEGER NOT NULL DEFAULT 0,  -- Responses with status >= 400
+    rowsReturned INTEGER NOT NULL DEFAULT 0,
+    bytesReturned INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (tokenId, route, hour)
+);
+
+-- Index for per-token reports over a time range
+CREATE INDEX IF NOT EXISTS idx_apiUsage_tokenId_hour ON apiUsage(tokenId, hour);
+
+-- Schema version: 010 - Per-token API usage metering
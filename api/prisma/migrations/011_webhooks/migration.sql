@@ -0,0 +1,49 @@
+-- Migration 011: Webhooks
+-- Token-scoped webhook subscriptions and their delivery log, written by the Rust public API
+-- Events come from public API writes and from a cron scan of inventoryTransactions
+
+CREATE TABLE IF NOT EXISTS webhookSubscriptions (
+    id TEXT PRIMARY KEY,
+    tokenId TEXT NOT NULL,
+    url TEXT NOT NULL,
+    events TEXT NOT NULL,                   -- JSON array of event types, e.g. '["item.sold_out"]'
+    secret TEXT NOT NULL,                   -- HMAC-SHA256 signing secret, returned once on creation
+    active INTEGER NOT NULL DEFAULT 1,
+    createdAt DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (tokenId) REFERENCES tokens(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhookSubscriptions_tokenId ON webhookSubscriptions(tokenId);
+
+CREATE TABLE IF NOT EXISTS webhookDeliveries (
+    id TEXT PRIMARY KEY,
+    subscriptionId TEXT NOT NULL,
+    tokenId TEXT NOT NULL,
+    eventId TEXT NOT NULL,
+    event TEXT NOT NULL,
+    payload TEXT NOT NULL,                  -- JSON body as sent
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'delivered', 'failed')),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    nextAttemptAt INTEGER,                  -- Epoch seconds of the next retry; NULL once delivered or failed
+    responseStatus INTEGER,                 -- HTTP status of the last attempt
+    lastError TEXT,
+    createdAt DATETIME DEFAULT CURRENT_TIMESTAMP,
+    deliveredAt DATETIME,
+    FOREIGN KEY (subscriptionId) REFERENCES webhookSubscriptions(id) ON DELETE CASCADE
+);
+
+-- Event IDs are derived from their source, so the public API write and the cron scan
+-- of the same change queue one delivery per subscription
+CREATE UNIQUE INDEX IF NOT EXISTS idx_webhookDeliveries_subscriptionId_eventId ON webhookDeliveries(subscriptionId, eventId);
+-- Delivery log per token, newest first
+CREATE INDEX IF NOT EXISTS idx_webhookDeliveries_tokenId_createdAt ON webhookDeliveries(tokenId, createdAt);
+-- Retry scan
+CREATE INDEX IF NOT EXISTS idx_webhookDeliveries_status_nextAttemptAt ON webhookDeliveries(status, nextAttemptAt);
+
+-- Positions of the cron scans over append-only tables
+CREATE TABLE IF NOT EXISTS webhookCursors (
+    name TEXT PRIMARY KEY,                  -- Scanned table, e.g. 'inventoryTransactions'
+    position INTEGER NOT NULL               -- Last processed rowid
+);
+
+-- Schema version: 011 - Webhook subscriptions and deliveries
@@ -0,0 +1,65 @@
+-- Migration 012: Rental expiry and cache warming
+-- Per-table rules for the Rust public API's scheduled handler: active rentals older than
+-- `periods` rental periods (userTables.rentalPeriod) plus grace are flagged overdue or released
+-- Also counts cacheable record queries so the most requested ones can be pre-warmed
+
+CREATE TABLE IF NOT EXISTS rentalExpiryRules (
+    tableId TEXT PRIMARY KEY,
+    action TEXT NOT NULL DEFAULT 'flag' CHECK (action IN ('flag', 'release')),
+    periods INTEGER NOT NULL DEFAULT 1 CHECK (periods > 0), -- Rental periods before a rental is overdue
+    graceMinutes INTEGER NOT NULL DEFAULT 0 CHECK (graceMinutes >= 0),
+    enabled INTEGER NOT NULL DEFAULT 1,
+    createdAt DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updatedAt DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (tableId) REFERENCES userTables(id) ON DELETE CASCADE
+);
+
+-- Set when a rental is flagged overdue; the rental stays active
+ALTER TABLE rentals ADD COLUMN overdueAt DATETIME;
+
+CREATE INDEX IF NOT EXISTS idx_rentals_status_table_rentedAt ON rentals(rentalStatus, tableId, rentedAt);
+
+-- Allow the 'overdue' transaction type. SQLite can't alter a CHECK constraint, so the table is
+-- rebuilt; rowids are kept because the webhook scan cursor (webhookCursors) points at them
+CREATE TABLE inventoryTransactions_new (
+    id TEXT PRIMARY KEY,
+    tableId TEXT NOT NULL,
+    tableName TEXT NOT NULL,
+    itemId TEXT NOT NULL,
+    transactionType TEXT NOT NULL CHECK (transactionType IN ('sale', 'rent', 'release', 'overdue', 'add', 'remove', 'update', 'adjust')),
+    quantityChange INTEGER,
+    previousData TEXT,
+    newData TEXT,
+    referenceId TEXT,
+    createdBy TEXT NOT NULL,
+    createdAt DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+INSERT INTO inventoryTransactions_new (rowid, id, tableId, tableName, itemId, transactionType, quantityChange, previousData, newData, referenceId, createdBy, createdAt)
+SELECT rowid, id, tableId, tableName, itemId, transactionType, quantityChange, previousData, newData, referenceId, createdBy, createdAt
+FROM inventoryTransactions;
+
+DROP TABLE inventoryTransactions;
+ALTER TABLE inventoryTransactions_new RENAME TO inventoryTransactions;
+
+CREATE INDEX IF NOT EXISTS idx_inventory_tableId ON inventoryTransactions(tableId);
+CREATE INDEX IF NOT EXISTS idx_inventory_itemId ON inventoryTransactions(itemId);
+CREATE INDEX IF NOT EXISTS idx_inventory_transactionType ON inventoryTransactions(transactionType);
+CREATE INDEX IF NOT EXISTS idx_inventory_referenceId ON inventoryTransactions(referenceId);
+CREATE INDEX IF NOT EXISTS idx_inventory_createdBy ON inventoryTransactions(createdBy);
+CREATE INDEX IF NOT EXISTS idx_inventory_createdAt ON inventoryTransactions(createdAt);
+CREATE INDEX IF NOT EXISTS idx_inventory_table_type ON inventoryTransactions(tableId, transactionType);
+
+-- Requests per cacheable records query (unrestricted tokens, no column selection)
+CREATE TABLE IF NOT EXISTS popularQueries (
+    filters TEXT NOT NULL,                  -- where[...] conditions as a JSON object with sorted keys
+    pageLimit INTEGER NOT NULL,
+    pageOffset INTEGER NOT NULL,
+    hits INTEGER NOT NULL DEFAULT 0,
+    lastRequestedAt DATETIME DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (filters, pageLimit, pageOffset)
+);
+
+CREATE INDEX IF NOT EXISTS idx_popularQueries_lastRequestedAt ON popularQueries(lastRequestedAt);
+
+-- Schema version: 012 - Rental expiry rules, overdue rentals and popular query tracking
@@ -0,0 +1,9 @@
+-- Migration 013: Customer-Bound Tokens
+-- Binds a token to one external customer ID, enforced by the Rust public API:
+-- buy/rent bodies get it injected (or are rejected when they name another customer),
+-- releases must target that customer's rentals, and sales/rentals reads only return theirs
+-- NULL = the token may act for any customer
+
+ALTER TABLE "tokens" ADD COLUMN "customerId" TEXT;
+
+-- Schema version: 013 - Customer-bound tokens for the public API
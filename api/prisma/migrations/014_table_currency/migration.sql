@@ -0,0 +1,8 @@
+-- Migration 014: Table Base Currency
+-- The ISO 4217 code a table's currency-typed columns are priced in. The Rust public API
+-- converts those columns on read (?currency=EUR) with the exchange rates the admin API
+-- stores in KV under currency:rates
+
+ALTER TABLE "userTables" ADD COLUMN "currency" TEXT NOT NULL DEFAULT 'USD';
+
+-- Schema version: 014 - Per-table base currency for converted public reads
@@ -0,0 +1,10 @@
+-- Migration 015: Token Price Rules
+-- Per-token markup for resellers, applied by the Rust public API to price/fee in every
+-- read response and passed to buy/rent as the unitPrice the sale or rental records
+-- JSON: { default?: Rule, tables?: { [tableId]: Rule } }
+--   Rule: { percent?: number, fixed?: number, rounding?: 'none' | 'cents' | 'whole' | 'ninety_nine' }
+-- NULL = catalog prices
+
+ALTER TABLE "tokens" ADD COLUMN "priceRules" TEXT;
+
+-- Schema version: 015 - Per-token price markup rules
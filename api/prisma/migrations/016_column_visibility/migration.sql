@@ -0,0 +1,10 @@
+-- Migration 016: Column Visibility
+-- What the Rust public API shows of each column: visible, hidden (left out of every
+-- response, and not filterable) or masked (e.g. j***@x.com, last 4 digits of a phone)
+-- Tokens can override it per table and column:
+--   tokens.columnRules JSON: { [tableId]: { [column]: 'visible' | 'hidden' | 'masked' } }
+
+ALTER TABLE "tableColumns" ADD COLUMN "visibility" TEXT NOT NULL DEFAULT 'visible';
+ALTER TABLE "tokens" ADD COLUMN "columnRules" TEXT;
+
+-- Schema version: 016 - Column visibility and masking for the public API
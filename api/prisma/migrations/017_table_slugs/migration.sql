@@ -0,0 +1,9 @@
+-- Migration 017: Table Slugs
+-- An optional stable handle for a table (e.g. "spring-catalog"). The Rust public API
+-- accepts a slug, or the table name, wherever /api/public/tables/:id/... takes a table
+-- id, and /api/public/records?tables=slug1,slug2 restricts a query to those tables
+
+ALTER TABLE "userTables" ADD COLUMN "slug" TEXT;
+CREATE UNIQUE INDEX "userTables_slug_key" ON "userTables"("slug");
+
+-- Schema version: 017 - Table slugs for public API addressing
@@ -0,0 +1,52 @@
+-- Migration 018: Attribute Index
+-- One row per non-null field of each tableData row, so the Rust public API's where[...]
+-- filters and values facets are index lookups instead of a json_extract scan of every row.
+-- textValue is LOWER() of the value as json_extract returns it (the case-insensitive
+-- equality the filters use); numValue holds numbers for numeric ordering and ranges.
+-- Triggers keep it in sync with every write to tableData, whichever API makes it.
+
+CREATE TABLE IF NOT EXISTS tableDataAttributes (
+    rowId TEXT NOT NULL,
+    columnName TEXT NOT NULL,               -- Key in tableData.data
+    tableId TEXT NOT NULL,
+    textValue TEXT,                         -- LOWER(value); objects and arrays as JSON text
+    numValue REAL,                          -- The value when it is a number
+    PRIMARY KEY (rowId, columnName)
+) WITHOUT ROWID;
+
+-- Filters: rows of a column with a value (the primary key makes it covering)
+CREATE INDEX IF NOT EXISTS idx_tableDataAttributes_column_text ON tableDataAttributes(columnName, textValue);
+
+-- Facets: rows of some tables having a column
+CREATE INDEX IF NOT EXISTS idx_tableDataAttributes_table_column ON tableDataAttributes(tableId, columnName);
+
+-- Rows whose data isn't a JSON object have no attributes
+CREATE TRIGGER IF NOT EXISTS tableData_attributes_insert AFTER INSERT ON tableData
+BEGIN
+    INSERT INTO tableDataAttributes (rowId, columnName, tableId, textValue, numValue)
+    SELECT NEW.id, j.key, NEW.tableId, LOWER(j.value), CASE WHEN j.type IN ('integer', 'real') THEN j.value END
+    FROM json_each(CASE WHEN json_valid(NEW.data) THEN CASE WHEN json_type(NEW.data) = 'object' THEN NEW.data END END) j
+    WHERE j.type <> 'null';
+END;
+
+CREATE TRIGGER IF NOT EXISTS tableData_attributes_update AFTER UPDATE OF data, tableId ON tableData
+BEGIN
+    DELETE FROM tableDataAttributes WHERE rowId = OLD.id;
+    INSERT INTO tableDataAttributes (rowId, columnName, tableId, textValue, numValue)
+    SELECT NEW.id, j.key, NEW.tableId, LOWER(j.value), CASE WHEN j.type IN ('integer', 'real') THEN j.value END
+    FROM json_each(CASE WHEN json_valid(NEW.data) THEN CASE WHEN json_type(NEW.data) = 'object' THEN NEW.data END END) j
+    WHERE j.type <> 'null';
+END;
+
+CREATE TRIGGER IF NOT EXISTS tableData_attributes_delete AFTER DELETE ON tableData
+BEGIN
+    DELETE FROM tableDataAttributes WHERE rowId = OLD.id;
+END;
+
+-- Backfill existing rows
+INSERT OR IGNORE INTO tableDataAttributes (rowId, columnName, tableId, textValue, numValue)
+SELECT d.id, j.key, d.tableId, LOWER(j.value), CASE WHEN j.type IN ('integer', 'real') THEN j.value END
+FROM tableData d, json_each(CASE WHEN json_valid(d.data) THEN CASE WHEN json_type(d.data) = 'object' THEN d.data END END) j
+WHERE j.type <> 'null';
+
+-- Schema version: 018 - Attribute index for public API filters and facets
@@ -0,0 +1,27 @@
+-- Migration 019: Table Row Counts
+-- A row counter on each table, kept by triggers on every tableData write, so listing
+-- tables reads a column instead of running COUNT(*) over tableData per table
+
+ALTER TABLE "userTables" ADD COLUMN "rowCount" INTEGER NOT NULL DEFAULT 0;
+
+CREATE TRIGGER IF NOT EXISTS tableData_rowCount_insert AFTER INSERT ON tableData
+BEGIN
+    UPDATE userTables SET rowCount = rowCount + 1 WHERE id = NEW.tableId;
+END;
+
+CREATE TRIGGER IF NOT EXISTS tableData_rowCount_delete AFTER DELETE ON tableData
+BEGIN
+    UPDATE userTables SET rowCount = rowCount - 1 WHERE id = OLD.tableId;
+END;
+
+CREATE TRIGGER IF NOT EXISTS tableData_rowCount_move AFTER UPDATE OF tableId ON tableData
+WHEN OLD.tableId <> NEW.tableId
+BEGIN
+    UPDATE userTables SET rowCount = rowCount - 1 WHERE id = OLD.tableId;
+    UPDATE userTables SET rowCount = rowCount + 1 WHERE id = NEW.tableId;
+END;
+
+-- Backfill existing tables
+UPDATE userTables SET rowCount = (SELECT COUNT(*) FROM tableData WHERE tableData.tableId = userTables.id);
+
+-- Schema version: 019 - Maintained row counts for table listings
@@ -0,0 +1,12 @@
+-- Migration 020: Token Owner
+-- Records the account that created each token, like userTables.createdBy/userId
+-- The public API scopes tokens without a tableAccess list to the public/shared tables
+-- of that account (previously such tokens could read nothing)
+-- Nothing records who created existing tokens, so there is no owner to backfill: an ownerless
+-- token without a tableAccess list reads nothing, as every such token did before. Tokens
+-- created before this migration were all given a non-empty list, which still applies
+
+ALTER TABLE "tokens" ADD COLUMN "createdBy" TEXT;
+ALTER TABLE "tokens" ADD COLUMN "userId" TEXT;
+
+-- Schema version: 020 - Token owner for public API table scoping
@@ -0,0 +1,25 @@
+-- Migration 021: Table Shares
+-- Who a 'shared' table is shared with: a token, or a user (every token that user created),
+-- and what they may do with it. rights is a JSON array: ["read"] or ["read", "buy"]
+-- (buy covers both buying from sale tables and renting from rent tables)
+-- The public API only serves shared tables to tokens they are shared with, besides
+-- the tokens that list them in tableAccess and the tokens of the table's owner
+
+CREATE TABLE IF NOT EXISTS "tableShares" (
+    "id" TEXT NOT NULL PRIMARY KEY,
+    "tableId" TEXT NOT NULL,
+    "tokenId" TEXT,
+    "userId" TEXT,
+    "rights" TEXT NOT NULL DEFAULT '["read"]',
+    "createdAt" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CONSTRAINT "tableShares_tableId_fkey" FOREIGN KEY ("tableId") REFERENCES "userTables" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CONSTRAINT "tableShares_tokenId_fkey" FOREIGN KEY ("tokenId") REFERENCES "tokens" ("id") ON DELETE CASCADE ON UPDATE CASCADE,
+    CHECK (("tokenId" IS NULL) <> ("userId" IS NULL))
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS "tableShares_tableId_tokenId_key" ON "tableShares"("tableId", "tokenId");
+CREATE UNIQUE INDEX IF NOT EXISTS "tableShares_tableId_userId_key" ON "tableShares"("tableId", "userId");
+CREATE INDEX IF NOT EXISTS "idx_tableShares_tokenId" ON "tableShares"("tokenId");
+CREATE INDEX IF NOT EXISTS "idx_tableShares_userId" ON "tableShares"("userId");
+
+-- Schema version: 021 - Share lists for shared tables
@@ -0,0 +1,7 @@
+-- Migration 022: Attribute Number Index
+-- where[...] filters with a numeric value also match numbers equal to it (where[price]=12.50
+-- finds a price of 12.5), looked up through tableDataAttributes.numValue (migration 018)
+
+CREATE INDEX IF NOT EXISTS idx_tableDataAttributes_column_num ON tableDataAttributes(columnName, numValue);
+
+-- Schema version: 022 - Numeric attribute index for public API filters
@@ -30,6 +30,11 @@ export const BUILT_IN_COLUMN_TYPES = [
 
 export type BuiltInColumnType = typeof BUILT_IN_COLUMN_TYPES[number]
 
+/**
+ * What the public API shows of a column (see ColumnVisibility)
+ */
+export const COLUMN_VISIBILITIES = ['visible', 'hidden', 'masked'] as const
+
 /**
  * Check if a type is a built-in column type
  */
@@ -0,0 +1,15 @@
+import { z } from 'zod';
+
+/**
+ * Currency validation schemas
+ */
+
+// ISO 4217 code, e.g. USD
+export const CurrencyCodeSchema = z.string().regex(/^[A-Z]{3}$/, 'Currency must be a 3-letter ISO 4217 code');
+
+// Exchange rates used by the Rust public API's ?currency= conversion:
+// units of each currency per one unit of base (the base itself is implied at 1)
+export const CurrencyRatesSchema = z.object({
+  base: CurrencyCodeSchema,
+  rates: z.record(CurrencyCodeSchema, z.number().positive()),
+});
@@ -5,3 +5,5 @@
 export * from './tokens.js';
 export * from './users.js';
 export * from './allowed-emails.js';
+export * from './currency.js';
+export * from './tables.js';
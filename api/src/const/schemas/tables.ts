@@ -0,0 +1,30 @@
+import { z } from 'zod';
+
+/**
+ * Table validation schemas
+ */
+
+// Stable public API handle for a table, e.g. spring-catalog (lowercase words joined by hyphens)
+export const TableSlugSchema = z
+  .string()
+  .max(64, 'Slug must be at most 64 characters')
+  .regex(/^[a-z0-9]+(?:-[a-z0-9]+)*$/, 'Slug must be lowercase letters, digits and single hyphens');
+
+// Public API rights a share grants; every share can read, buy also places buy and rent orders
+export const TableShareRightSchema = z.enum(['read', 'buy']);
+
+// One entry of a shared table's share list: a token, or a user (every token they created)
+export const TableShareSchema = z
+  .object({
+    tokenId: z.string().min(1).optional(),
+    userId: z.string().min(1).optional(),
+    rights: z.array(TableShareRightSchema).default(['read'])
+  })
+  .refine(share => (share.tokenId === undefined) !== (share.userId === undefined), {
+    message: 'A share names either a tokenId or a userId'
+  });
+
+// PUT /api/tables/:id/shares replaces the whole list
+export const UpdateTableSharesSchema = z.object({
+  shares: z.array(TableShareSchema).max(500, 'A table can be shared with at most 500 tokens or users')
+});
@@ -4,6 +4,47 @@ import { z } from 'zod';
  * Token validation schemas
  */
 
+// Per-token rate limits enforced by the Rust public API (null = worker defaults)
+// Reads and writes (buy/rent/release) are limited separately
+const RateLimitSchema = z.object({
+  perMinute: z.number().int().positive(),
+  burst: z.number().int().positive().optional(),
+});
+
+export const RateLimitsSchema = z.object({
+  read: RateLimitSchema.optional(),
+  write: RateLimitSchema.optional(),
+});
+
+// Reseller markup on price/fee applied by the Rust public API (null = catalog prices)
+// A table's own rule replaces the default rule; rounding is applied after the markup
+const PriceRuleSchema = z.object({
+  percent: z.number().min(0).optional(),
+  fixed: z.number().min(0).optional(),
+  rounding: z.enum(['none', 'cents', 'whole', 'ninety_nine']).optional(), // default: cents
+});
+
+export const PriceRulesSchema = z.object({
+  default: PriceRuleSchema.optional(),
+  tables: z.record(z.string(), PriceRuleSchema).optional(),
+});
+
+// Per-token override of column visibility in the Rust public API: { [tableId]: { [column]: visibility } }
+// Columns without an entry keep the table's setting (tableColumns.visibility)
+export const ColumnRulesSchema = z.record(
+  z.string(),
+  z.record(z.string(), z.enum(['visible', 'hidden', 'masked']))
+);
+
+// Table IDs the token can use, or each table's operations: { [tableId]: ["read", "buy", "rent", "release"] }
+// A plain list grants every operation on its tables
+export const TableOperationSchema = z.enum(['read', 'buy', 'rent', 'release']);
+
+export const TableAccessSchema = z.union([
+  z.array(z.string()),
+  z.record(z.string(), z.array(TableOperationSchema))
+]);
+
 // Note: isAdmin defaults to false - regular API tokens can ONLY access /api/public/* routes
 // Admin tokens (isAdmin=true) can access ALL routes, typically for frontend/admin use
 export const CreateTokenSchema = z.object({
@@ -12,7 +53,11 @@ export const CreateTokenSchema = z.object({
   isAdmin: z.boolean().default(false), // false = public routes only, true = all routes
   allowedIps: z.string().nullable().optional(), // JSON array of IPs/CIDR ranges
   allowedDomains: z.string().nullable().optional(), // JSON array of domain patterns
-  tableAccess: z.array(z.string()).default([]), // Array of table IDs the token can access
+  tableAccess: TableAccessSchema.default([]), // Table IDs (or per-table operations) the token can access
+  rateLimits: RateLimitsSchema.nullable().optional(), // Read/write limits, null = worker defaults
+  customerId: z.string().min(1).nullable().optional(), // Bind orders to one customer, null = any
+  priceRules: PriceRulesSchema.nullable().optional(), // Markup on price/fee, null = catalog prices
+  columnRules: ColumnRulesSchema.nullable().optional(), // Column visibility overrides, null = table settings
   expiresAt: z.string().datetime().nullable().optional(),
 });
 
@@ -22,7 +67,11 @@ export const UpdateTokenSchema = z.object({
   isAdmin: z.boolean().optional(),
   allowedIps: z.string().nullable().optional(),
   allowedDomains: z.string().nullable().optional(),
-  tableAccess: z.array(z.string()).optional(),
+  tableAccess: TableAccessSchema.optional(),
+  rateLimits: RateLimitsSchema.nullable().optional(),
+  customerId: z.string().min(1).nullable().optional(),
+  priceRules: PriceRulesSchema.nullable().optional(),
+  columnRules: ColumnRulesSchema.nullable().optional(),
   expiresAt: z.string().datetime().nullable().optional(),
 });
 
@@ -7,9 +7,12 @@
  * - auth:token:{tokenString} - Token authentication cache
  * - table:metadata:{tableId} - Table metadata cache
  * - table:columns:{tableId} - Table columns cache
+ * - table:shares:token:{tokenId} - Shares naming a token or its user (read by the public API)
  * - rowcount:{tableId} - Row count cache
  * - item:{tableId}:{itemId} - Item data cache
  * - access:{userId}:{tableId} - Permission/access cache
+ * - generation:tables - Data generation, replaced with a random token on every write
+ * - currency:rates - Exchange rates used by the public API's ?currency= conversion
  */
 
 import type { Token } from '@prisma/client'
@@ -24,6 +27,15 @@ interface QueryResultsCache {
   cachedAt: number
 }
 
+/**
+ * Exchange rate table: units of each currency per one unit of `base`
+ */
+export interface CurrencyRates {
+  base: string
+  rates: Record<string, number>
+  updatedAt: string
+}
+
 /**
  * Simple hash function for generating short cache keys
  * Uses djb2 algorithm - fast and produces good distribution
@@ -182,6 +194,22 @@ export class CacheService {
     }
   }
 
+  // ============================================
+  // Token Shares Cache
+  // ============================================
+
+  /**
+   * Invalidate the cached shares of tokens (call when a table's share list changes)
+   * @param tokenIds - IDs of the tokens named by the old or new list, directly or by user
+   */
+  async invalidateTokenShares(tokenIds: string[]): Promise<void> {
+    try {
+      await Promise.all(tokenIds.map(id => this.cache.delete(`table:shares:token:${id}`)))
+    } catch (error) {
+      console.error('Failed to invalidate token shares cache:', error)
+    }
+  }
+
   // ============================================
   // Row Count Cache (Phase 4)
   // ============================================
@@ -438,10 +466,39 @@ export class CacheService {
 
   /**
    * TTL for query results cache (60 seconds)
-   * Short TTL because data changes frequently, but helps with burst traffic
+   * Writes invalidate immediately via the data generation; the TTL is only a safety net
    */
   private static readonly QUERY_RESULTS_TTL = 60
 
+  private static readonly DATA_GENERATION_KEY = 'generation:tables'
+
+  /**
+   * Get the current data generation (one KV read per query)
+   * A missing key means nothing has been written since caching began ('0')
+   */
+  async getDataGeneration(): Promise<string> {
+    try {
+      return (await this.cache.get(CacheService.DATA_GENERATION_KEY)) ?? '0'
+    } catch {
+      return '0'
+    }
+  }
+
+  /**
+   * Replace the data generation (shared with the Rust public API worker)
+   * Every query cache key embeds it, so replacing it makes all cached results
+   * unreachable immediately. It is a random token rather than read + 1: KV has
+   * no compare-and-set, and two writers incrementing the same value would
+   * write the same generation and leave results cached between them reachable.
+   */
+  async bumpDataGeneration(): Promise<void> {
+    try {
+      await this.cache.put(CacheService.DATA_GENERATION_KEY, crypto.randomUUID())
+    } catch (error) {
+      console.error('Failed to bump data generation:', error)
+    }
+  }
+
   /**
    * Generate cache key for query results
    * Uses hash to keep key length under 512 bytes (KV limit)
@@ -451,15 +508,16 @@ export class CacheService {
    * @param offset - Pagination offset
    * @returns Cache key string (max ~40 chars: "query:" + hash + ":" + hash + ":X:X")
    */
-  private generateQueryCacheKey(
+  private async generateQueryCacheKey(
     tableIds: string[],
     whereConditions: Record<string, string>,
     limit: number,
     offset: number
-  ): string {
-    // Hash the table IDs (can be many UUIDs)
-    const sortedTableIds = [...tableIds].sort().join(',')
-    const tableIdsHash = hashString(sortedTableIds)
+  ): Promise<string> {
+    // Hash the table IDs with the current generation (can be many UUIDs)
+    const sortedTableIds = [...tableIds].sort()
+    const generation = await this.getDataGeneration()
+    const tableIdsHash = hashString(`${sortedTableIds.join(',')}@${generation}`)
 
     // Hash the conditions (can have long values)
     const sortedConditions = Object.entries(whereConditions)
@@ -487,8 +545,8 @@ export class CacheService {
     limit: number,
     offset: number
   ): Promise<{ records: any[]; total: number } | null> {
-    const cacheKey = this.generateQueryCacheKey(tableIds, whereConditions, limit, offset)
     try {
+      const cacheKey = await this.generateQueryCacheKey(tableIds, whereConditions, limit, offset)
       const cached = await this.cache.get(cacheKey, 'json') as QueryResultsCache | null
       if (!cached) return null
 
@@ -523,8 +581,8 @@ export class CacheService {
     records: any[],
     total: number
   ): Promise<void> {
-    const cacheKey = this.generateQueryCacheKey(tableIds, whereConditions, limit, offset)
     try {
+      const cacheKey = await this.generateQueryCacheKey(tableIds, whereConditions, limit, offset)
       const cacheData: QueryResultsCache = {
         records,
         total,
@@ -542,30 +600,43 @@ export class CacheService {
   /**
    * Invalidate query results cache for specific tables
    * Call this when tableData changes (add/update/delete rows)
-   * @param tableIds - Table IDs whose data changed
+   * Replaces the data generation instead of listing keys - query keys are hashed,
+   * so they can't be matched by table ID; old entries simply age out via TTL
+   * @param tableIds - Table IDs whose data changed (nothing to do when empty)
    */
   async invalidateQueryResults(tableIds: string[]): Promise<void> {
-    try {
-      // List all query cache keys and delete those containing any of the tableIds
-      const list = await this.cache.list({ prefix: 'query:' })
-      const deletePromises: Promise<void>[] = []
+    if (tableIds.length > 0) {
+      await this.bumpDataGeneration()
+    }
+  }
 
-      for (const key of list.keys) {
-        // Check if this query cache involves any of the changed tables
-        for (const tableId of tableIds) {
-          if (key.name.includes(tableId)) {
-            deletePromises.push(this.cache.delete(key.name))
-            break
-          }
-        }
-      }
+  // ============================================
+  // Currency Rates (for /api/public/* ?currency=)
+  // ============================================
 
-      await Promise.all(deletePromises)
-    } catch (error) {
-      console.error('Failed to invalidate query results cache:', error)
+  private static readonly CURRENCY_RATES_KEY = 'currency:rates'
+
+  /**
+   * Get the stored exchange rates
+   * @returns Rate table or null if none was stored yet
+   */
+  async getCurrencyRates(): Promise<CurrencyRates | null> {
+    try {
+      const cached = await this.cache.get(CacheService.CURRENCY_RATES_KEY, 'json')
+      return cached as CurrencyRates | null
+    } catch {
+      return null
     }
   }
 
+  /**
+   * Store exchange rates (kept until replaced)
+   * @param rates - Rate table to store
+   */
+  async setCurrencyRates(rates: CurrencyRates): Promise<void> {
+    await this.cache.put(CacheService.CURRENCY_RATES_KEY, JSON.stringify(rates))
+  }
+
   // ============================================
   // Bulk Invalidation Helpers
   // ============================================
@@ -0,0 +1,53 @@
+/**
+ * Reseller price rules
+ * Token priceRules markup, computed the same way as the Rust public API (public-api/src/pricing.rs)
+ */
+
+interface PriceRule {
+  percent?: number
+  fixed?: number
+  rounding?: 'none' | 'cents' | 'whole' | 'ninety_nine' // default: cents
+}
+
+interface PriceRules {
+  default?: PriceRule
+  tables?: Record<string, PriceRule>
+}
+
+const roundAmount = (amount: number): number => Math.round(amount * 100) / 100
+
+/**
+ * Apply a rule: the percentage first, then the fixed amount, then rounding
+ * Rounding up starts from cents so float noise can't push an exact amount up a step
+ */
+function applyRule(rule: PriceRule, amount: number): number {
+  const marked = amount * (1 + (rule.percent ?? 0) / 100) + (rule.fixed ?? 0)
+  switch (rule.rounding ?? 'cents') {
+    case 'none':
+      return marked
+    case 'whole':
+      return Math.ceil(roundAmount(marked))
+    case 'ninety_nine':
+      return roundAmount(Math.ceil(roundAmount(marked) + 0.01) - 0.01)
+    default:
+      return roundAmount(marked)
+  }
+}
+
+/**
+ * The price a token is charged for an item of a table
+ * A table's own rule replaces the default one; rules that don't parse mean catalog prices, as in Rust
+ */
+export function markedUpPrice(priceRules: string | null | undefined, tableId: string, price: number): number {
+  if (!priceRules) {
+    return price
+  }
+  let rules: PriceRules
+  try {
+    rules = JSON.parse(priceRules)
+  } catch {
+    return price
+  }
+  const rule = rules?.tables?.[tableId] ?? rules?.default
+  return rule ? applyRule(rule, price) : price
+}
@@ -3,6 +3,7 @@ import type { Bindings } from '@/types/bindings.js'
 import { getPrismaClient } from '@/lib/database.js'
 import type { UserContext } from '@/types/database.js'
 import type { UserTable, TableAccessLevel } from '@/types/dynamic-tables.js'
+import { parseTableAccess, tableAccessIds } from '@/utils/tableAccess.js'
 
 type Variables = {
   user: UserContext
@@ -89,14 +90,9 @@ function hasTokenTableAccess(user: UserContext, tableId: string): boolean {
   }
 
   // Check if token has explicit table access
-  if (user.token && user.token.tableAccess) {
-    try {
-      const allowedTables = JSON.parse(user.token.tableAccess)
-      return allowedTables.includes(tableId)
-    } catch (error) {
-      console.error('Error parsing token table access:', error)
-      return false
-    }
+  const access = parseTableAccess(user.token?.tableAccess)
+  if (access) {
+    return tableAccessIds(access).includes(tableId)
   }
 
   return false
@@ -3,6 +3,7 @@ import {CacheService} from '@/lib/cache-service.js'
 import type {Bindings} from '@/types/bindings.js'
 import type {ParsedTableData, ParsedTableDataRow, TableColumn, TableDataMassAction, TableDataRow} from '@/types/dynamic-tables.js'
 import type {PrismaClient} from '@prisma/client'
+import {parseTableAccess, tableAccessIds} from '@/utils/tableAccess.js'
 
 /**
  * Repository for table data operations
@@ -204,9 +205,12 @@ export class TableDataRepository {
             }
         })
 
-        // Invalidate row count cache since a row was added
+        // Invalidate row count and query caches since a row was added
         if (this.cache) {
-            await this.cache.invalidateRowCount(tableId)
+            await Promise.all([
+                this.cache.invalidateRowCount(tableId),
+                this.cache.invalidateQueryResults([tableId])
+            ])
         }
 
         return {
@@ -236,9 +240,12 @@ export class TableDataRepository {
             }
         })
 
-        // Invalidate item cache since data changed
+        // Invalidate item and query caches since data changed
         if (this.cache) {
-            await this.cache.invalidateItemData(tableId, rowId)
+            await Promise.all([
+                this.cache.invalidateItemData(tableId, rowId),
+                this.cache.invalidateQueryResults([tableId])
+            ])
         }
 
         return {
@@ -271,7 +278,8 @@ export class TableDataRepository {
         if (this.cache) {
             await Promise.all([
                 this.cache.invalidateItemData(tableId, rowId),
-                this.cache.invalidateRowCount(tableId)
+                this.cache.invalidateRowCount(tableId),
+                this.cache.invalidateQueryResults([tableId])
             ])
         }
 
@@ -285,15 +293,9 @@ export class TableDataRepository {
      */
     async checkTableAccess(tableId: string, userId: string, userContext?: any): Promise<boolean> {
         // Check if token has explicit table access (no caching needed - token data already validated)
-        if (userContext?.token?.tableAccess) {
-            try {
-                const allowedTables = JSON.parse(userContext.token.tableAccess)
-                if (Array.isArray(allowedTables) && allowedTables.includes(tableId)) {
-                    return true // Token has explicit access to this table
-                }
-            } catch (error) {
-                console.error('Error parsing token tableAccess:', error)
-            }
+        const access = parseTableAccess(userContext?.token?.tableAccess)
+        if (access && tableAccessIds(access).includes(tableId)) {
+            return true // Token has explicit access to this table
         }
 
         // Check if it's a standalone token (admin-token, frontend-token)
@@ -401,7 +403,9 @@ export class TableDataRepository {
                         // Invalidate item caches
                         ...rowIds.map(rowId => this.cache!.invalidateItemData(tableId, rowId)),
                         // Invalidate row count
-                        this.cache.invalidateRowCount(tableId)
+                        this.cache.invalidateRowCount(tableId),
+                        // Invalidate query results
+                        this.cache.invalidateQueryResults([tableId])
                     ])
                 }
 
@@ -455,11 +459,12 @@ export class TableDataRepository {
                     updatedCount++
                 }
 
-                // Invalidate item caches for updated rows
+                // Invalidate item caches for updated rows and query results
                 if (this.cache && updatedCount > 0) {
-                    await Promise.all(
-                        rowIds.map(rowId => this.cache!.invalidateItemData(tableId, rowId))
-                    )
+                    await Promise.all([
+                        ...rowIds.map(rowId => this.cache!.invalidateItemData(tableId, rowId)),
+                        this.cache.invalidateQueryResults([tableId])
+                    ])
                 }
 
                 return { count: updatedCount }
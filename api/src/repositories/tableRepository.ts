@@ -351,7 +351,9 @@ export class TableRepository {
         visibility: tableData.visibility,
         tableType: tableData.tableType || 'default',
         productIdColumn: tableData.productIdColumn || null,
-        rentalPeriod: tableData.rentalPeriod || (tableData.tableType === 'rent' ? 'month' : null)
+        rentalPeriod: tableData.rentalPeriod || (tableData.tableType === 'rent' ? 'month' : null),
+        currency: tableData.currency || 'USD',
+        slug: tableData.slug || null
       }
     })
 
@@ -368,7 +370,8 @@ export class TableRepository {
           allowDuplicates: col.allowDuplicates ?? true,
           // Convert defaultValue to string for DB storage (preserves 'false', '0' as strings)
           defaultValue: col.defaultValue != null ? String(col.defaultValue) : null,
-          position: col.position || i
+          position: col.position || i,
+          visibility: col.visibility || 'visible'
         }
       })
     }).filter(Boolean) as any[]
@@ -437,6 +440,14 @@ export class TableRepository {
       updateData.rentalPeriod = updates.rentalPeriod
     }
 
+    if (updates.currency !== undefined) {
+      updateData.currency = updates.currency
+    }
+
+    if (updates.slug !== undefined) {
+      updateData.slug = updates.slug
+    }
+
     if (Object.keys(updateData).length === 0) {
       throw new Error('No fields to update')
     }
@@ -499,6 +510,18 @@ export class TableRepository {
     return table
   }
 
+  /**
+   * Check if a slug is used by a table other than `excludeTableId`
+   */
+  async isSlugTaken(slug: string, excludeTableId?: string): Promise<boolean> {
+    const table = await this.prisma.userTable.findUnique({
+      where: { slug },
+      select: { id: true }
+    })
+
+    return !!table && table.id !== excludeTableId
+  }
+
   /**
    * Check if user owns table using Prisma ORM
    */
@@ -619,7 +642,7 @@ export class TableRepository {
    */
   async addColumn(
     tableId: string,
-    columnData: { name: string; type: string; isRequired: boolean; allowDuplicates?: boolean; defaultValue?: string; position?: number }
+    columnData: { name: string; type: string; isRequired: boolean; allowDuplicates?: boolean; defaultValue?: string; position?: number; visibility?: string }
   ): Promise<TableColumn> {
     let position = columnData.position
 
@@ -661,7 +684,8 @@ export class TableRepository {
         isRequired: columnData.isRequired,
         allowDuplicates: columnData.allowDuplicates ?? true,
         defaultValue: columnData.defaultValue || null,
-        position: position
+        position: position,
+        visibility: columnData.visibility || 'visible'
       }
     })
 
@@ -682,7 +706,7 @@ export class TableRepository {
   async updateColumn(
     tableId: string,
     columnId: string,
-    updates: { name?: string; type?: string; isRequired?: boolean; allowDuplicates?: boolean; defaultValue?: string; position?: number }
+    updates: { name?: string; type?: string; isRequired?: boolean; allowDuplicates?: boolean; defaultValue?: string; position?: number; visibility?: string }
   ): Promise<TableColumn> {
     // Build update data object only for provided fields
     const updateData: any = {}
@@ -711,6 +735,10 @@ export class TableRepository {
       updateData.position = updates.position
     }
 
+    if (updates.visibility !== undefined) {
+      updateData.visibility = updates.visibility
+    }
+
     if (Object.keys(updateData).length === 0) {
       throw new Error('No fields to update')
     }
@@ -784,9 +812,12 @@ export class TableRepository {
       where: { tableId }
     })
 
-    // Invalidate row count cache since all data was cleared
+    // Invalidate row count and query caches since all data was cleared
     if (this.cache) {
-      await this.cache.invalidateRowCount(tableId)
+      await Promise.all([
+        this.cache.invalidateRowCount(tableId),
+        this.cache.invalidateQueryResults([tableId])
+      ])
     }
   }
 
@@ -806,9 +837,12 @@ export class TableRepository {
       }
     })
 
-    // Invalidate row count cache since a row was added
+    // Invalidate row count and query caches since a row was added
     if (this.cache) {
-      await this.cache.invalidateRowCount(tableId)
+      await Promise.all([
+        this.cache.invalidateRowCount(tableId),
+        this.cache.invalidateQueryResults([tableId])
+      ])
     }
   }
 
@@ -1115,6 +1149,11 @@ export class TableRepository {
       }
     }
 
+    // Invalidate query caches since row keys changed
+    if (this.cache && updatedCount > 0) {
+      await this.cache.invalidateQueryResults([tableId])
+    }
+
     return updatedCount
   }
 
@@ -0,0 +1,33 @@
+import { Hono } from 'hono';
+import type { Bindings } from '@/types/bindings.js';
+import type { HonoVariables } from '@/types/hono.js';
+import { adminOnlyMiddleware } from '@/middleware/admin.js';
+import { CacheService } from '@/lib/cache-service.js';
+
+const app = new Hono<{ Bindings: Bindings; Variables: HonoVariables }>();
+
+/**
+ * GET /api/admin/currency-rates
+ * Get the exchange rates used by the public API's currency conversion (admin only)
+ */
+app.get('/currency-rates', adminOnlyMiddleware, async (c) => {
+  try {
+    const rates = await new CacheService(c.env.KV).getCurrencyRates();
+    if (!rates) {
+      return c.json({
+        error: 'No currency rates stored',
+        details: 'PUT /api/admin/currency-rates to set them'
+      }, 404);
+    }
+    return c.json(rates, 200);
+
+  } catch (error) {
+    console.error('❌ Currency rates error:', error);
+    return c.json({
+      error: 'Failed to fetch currency rates',
+      details: error instanceof Error ? error.message : 'Unknown error'
+    }, 500);
+  }
+});
+
+export default app;
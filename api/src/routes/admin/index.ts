@@ -5,6 +5,8 @@ import type { HonoVariables } from '@/types/hono.js';
 // Import individual route handlers
 import postGenerateDummyTables from './post.generate-dummy-tables.js';
 import getStats from './get.stats.js';
+import getCurrencyRates from './get.currency-rates.js';
+import putCurrencyRates from './put.currency-rates.js';
 import moduleRoutes from './modules/index.js';
 
 /**
@@ -16,6 +18,8 @@ const app = new Hono<{ Bindings: Bindings; Variables: HonoVariables }>();
 // Mount routes
 app.route('/', postGenerateDummyTables);
 app.route('/', getStats);
+app.route('/', getCurrencyRates);
+app.route('/', putCurrencyRates);
 app.route('/modules', moduleRoutes);
 
 export default app;
@@ -0,0 +1,39 @@
+import { Hono } from 'hono';
+import { zValidator } from '@hono/zod-validator';
+import type { Bindings } from '@/types/bindings.js';
+import type { HonoVariables } from '@/types/hono.js';
+import { adminOnlyMiddleware } from '@/middleware/admin.js';
+import { CacheService, type CurrencyRates } from '@/lib/cache-service.js';
+import { CurrencyRatesSchema } from '@/const/schemas/currency.js';
+
+const app = new Hono<{ Bindings: Bindings; Variables: HonoVariables }>();
+
+/**
+ * PUT /api/admin/currency-rates
+ * Replace the exchange rates used by the public API's currency conversion (admin only)
+ * Body: { base: 'USD', rates: { EUR: 0.92, GBP: 0.79 } }
+ */
+app.put('/currency-rates', adminOnlyMiddleware, zValidator('json', CurrencyRatesSchema), async (c) => {
+  try {
+    const body = c.req.valid('json');
+    const rates: CurrencyRates = {
+      base: body.base,
+      rates: { ...body.rates, [body.base]: 1 },
+      updatedAt: new Date().toISOString()
+    };
+
+    await new CacheService(c.env.KV).setCurrencyRates(rates);
+    console.log('💱 Currency rates updated:', { base: rates.base, currencies: Object.keys(rates.rates).length });
+
+    return c.json(rates, 200);
+
+  } catch (error) {
+    console.error('❌ Currency rates update error:', error);
+    return c.json({
+      error: 'Failed to update currency rates',
+      details: error instanceof Error ? error.message : 'Unknown error'
+    }, 500);
+  }
+});
+
+export default app;
@@ -3,6 +3,7 @@ import { getPrismaClient } from '@/lib/database.js';
 import { adminReadAuthMiddleware } from '@/middleware/auth.js';
 import type { Bindings } from '@/types/bindings.js';
 import type { UserContext } from '@/types/database.js';
+import { restrictedTableIds } from '@/utils/tableAccess.js';
 
 const app = new Hono<{
   Bindings: Bindings;
@@ -15,27 +16,18 @@ const app = new Hono<{
  *
  * Returns tables based on the token's tableAccess configuration:
  * - If tableAccess is null/empty, returns all tables (unrestricted access)
- * - If tableAccess contains table IDs, returns only those tables
+ * - If tableAccess names tables, returns only those it grants read on
  */
 app.get('/', adminReadAuthMiddleware, async (c) => {
   try {
     const database = getPrismaClient(c.env);
     const user = c.get('user');
 
-    // Parse token's table access
-    let allowedTableIds: string[] | null = null;
-
-    if (user.token?.tableAccess) {
-      try {
-        allowedTableIds = JSON.parse(user.token.tableAccess);
-      } catch {
-        // If parsing fails, treat as unrestricted
-        allowedTableIds = null;
-      }
-    }
+    // Parse token's table access (a list of IDs or per-table operations)
+    const allowedTableIds = restrictedTableIds(user.token?.tableAccess);
 
     // Build query based on token access
-    const whereCondition = allowedTableIds && allowedTableIds.length > 0
+    const whereCondition = allowedTableIds
       ? { id: { in: allowedTableIds } }
       : {}; // Empty condition = all tables
 
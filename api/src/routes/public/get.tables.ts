@@ -2,6 +2,7 @@ import { Hono } from 'hono';
 import type { Bindings } from '@/types/bindings.js';
 import type { UserContext } from '@/types/database.js';
 import { PublicSalesService } from '@/services/publicSalesService/index.js';
+import { getAllowedTableIds } from '@/utils/tableAccess.js';
 
 const app = new Hono<{
   Bindings: Bindings;
@@ -22,23 +23,8 @@ app.get('/tables', async (c) => {
   const user = c.get('user');
 
   try {
-    // Get allowed table IDs from token's tableAccess
-    let allowedTableIds: string[] | null = null;
-
-    // Admin and frontend tokens have unrestricted access
-    if (user.tokenId !== 'admin-token' && user.tokenId !== 'frontend-token') {
-      if (user.token?.tableAccess) {
-        try {
-          allowedTableIds = JSON.parse(user.token.tableAccess);
-        } catch (e) {
-          console.error('Error parsing tableAccess:', e);
-          allowedTableIds = [];
-        }
-      } else {
-        // Token has no tableAccess defined - no tables accessible
-        allowedTableIds = [];
-      }
-    }
+    // Tables the token's tableAccess grants read on (null for admin/frontend: unrestricted)
+    const allowedTableIds = getAllowedTableIds(user);
 
     const tables = await service.getAllPublicTables(c, allowedTableIds);
     return c.json({
@@ -37,13 +37,23 @@ app.post('/buy', readAuthMiddleware, async (c) => {
       }, 400);
     }
 
+    // Tokens with price rules are charged the marked-up price the Rust public API passes along
+    // (the service recomputes it from the rules); other tokens always pay the catalog price
+    const priceRules = user?.token?.priceRules;
+    if (priceRules && (typeof data.unitPrice !== 'number' || !Number.isFinite(data.unitPrice))) {
+      return c.json({
+        error: 'Tokens with price rules must order through the public API'
+      }, 400);
+    }
+
     const purchaseRequest: CreateSaleRequest = {
       tableId: data.tableId,
       itemId: data.itemId,
       customerId: data.customerId,
       quantitySold: data.quantitySold,
       paymentMethod: data.paymentMethod,
-      notes: data.notes
+      notes: data.notes,
+      unitPrice: priceRules ? data.unitPrice : undefined
     };
 
     const result = await service.purchaseItem(c, user!, purchaseRequest);
@@ -41,11 +41,21 @@ app.post('/rent', readAuthMiddleware, async (c) => {
       }, 400);
     }
 
+    // Tokens with price rules are charged the marked-up price the Rust public API passes along
+    // (the service recomputes it from the rules); other tokens always pay the catalog price
+    const priceRules = user?.token?.priceRules;
+    if (priceRules && (typeof data.unitPrice !== 'number' || !Number.isFinite(data.unitPrice))) {
+      return c.json({
+        error: 'Tokens with price rules must order through the public API'
+      }, 400);
+    }
+
     const rentalRequest: CreateRentalRequest = {
       tableId: data.tableId,
       itemId: data.itemId,
       customerId: data.customerId,
-      notes: data.notes
+      notes: data.notes,
+      unitPrice: priceRules ? data.unitPrice : undefined
     };
 
     const result = await service.rentItem(c, user!, rentalRequest);
@@ -2,6 +2,8 @@
  * Shared utilities for table routes
  */
 
+import type { TableShare } from '@prisma/client';
+
 /**
  * Intelligent header detection by comparing first row with table columns
  * Uses multiple comparison strategies as suggested by user
@@ -95,3 +97,28 @@ export function generateColumnMappings(headers: string[], tableColumns: any[]):
 
   return mappings;
 }
+
+/**
+ * Normalise share rights for storage: every share can read
+ */
+export function normalizeRights(rights: string[]): string[] {
+  return ['read', ...(rights.includes('buy') ? ['buy'] : [])];
+}
+
+/**
+ * A stored share as the API returns it
+ */
+export function formatShare(share: TableShare) {
+  let rights: string[] = ['read'];
+  try {
+    rights = normalizeRights(JSON.parse(share.rights));
+  } catch {
+    // Unreadable rights fall back to read-only, as the public API treats them
+  }
+  return {
+    id: share.id,
+    tokenId: share.tokenId,
+    userId: share.userId,
+    rights
+  };
+}
@@ -4,6 +4,7 @@ import { TableService } from '@/services/tableService/index.js'
 import { TableDataService } from '@/services/tableDataService/index.js'
 import { validateDataset } from '@/services/validationService.js'
 import { getPrismaClient } from '@/lib/database.js'
+import { CacheService } from '@/lib/cache-service.js'
 import type { Bindings } from '@/types/bindings.js'
 import type { UserContext } from '@/types/database.js'
 
@@ -91,6 +92,15 @@ app.delete('/:id/invalid-rows', adminDeleteAuthMiddleware, async (c) => {
       }
     })
 
+    // Invalidate row count and query caches since rows were deleted
+    if (c.env.KV && deleteResult.count > 0) {
+      const cache = new CacheService(c.env.KV)
+      await Promise.all([
+        cache.invalidateRowCount(tableId),
+        cache.invalidateQueryResults([tableId])
+      ])
+    }
+
     return c.json({
       data: {
         deletedCount: deleteResult.count,
@@ -0,0 +1,47 @@
+import { Hono } from 'hono';
+import { getPrismaClient } from '@/lib/database.js';
+import { adminReadAuthMiddleware } from '@/middleware/auth.js';
+import { formatApiDate } from '@/lib/date-utils.js';
+import type { Bindings } from '@/types/bindings.js';
+import type { UserContext } from '@/types/database.js';
+import { formatShare } from './_shared.js';
+
+const app = new Hono<{
+  Bindings: Bindings;
+  Variables: { user: UserContext };
+}>();
+
+/**
+ * Get the tokens and users a table is shared with
+ * GET /api/tables/:id/shares
+ */
+app.get('/:id/shares', adminReadAuthMiddleware, async (c) => {
+  try {
+    const database = getPrismaClient(c.env);
+    const tableId = c.req.param('id');
+
+    const table = await database.userTable.findUnique({
+      where: { id: tableId },
+      select: { id: true, visibility: true }
+    });
+    if (!table) {
+      return c.json({ error: 'Table not found' }, 404);
+    }
+
+    const shares = await database.tableShare.findMany({
+      where: { tableId },
+      orderBy: { createdAt: 'asc' }
+    });
+
+    return c.json({
+      tableId: table.id,
+      visibility: table.visibility,
+      shares: shares.map(share => ({ ...formatShare(share), createdAt: formatApiDate(share.createdAt) }))
+    });
+  } catch (error) {
+    console.error('Error fetching table shares:', error);
+    return c.json({ error: 'Failed to fetch table shares' }, 500);
+  }
+});
+
+export default app;
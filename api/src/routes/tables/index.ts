@@ -37,6 +37,10 @@ import validateTable from './get.id.validate.js';
 import deleteInvalidRows from './delete.id.invalid-rows.js';
 import previewColumnType from './post.id.columns.columnId.preview-type.js';
 
+// Share routes
+import getShares from './get.id.shares.js';
+import updateShares from './put.id.shares.js';
+
 /**
  * Table Management Routes
  */
@@ -80,6 +84,10 @@ app.route('/', validateTable);        // GET /:id/validate, GET /:id/validate/ru
 app.route('/', deleteInvalidRows);    // DELETE /:id/invalid-rows
 app.route('/', previewColumnType);    // POST /:id/columns/:columnId/preview-type
 
+// Share routes (before /:id single table routes)
+app.route('/', getShares);            // GET /:id/shares
+app.route('/', updateShares);         // PUT /:id/shares
+
 // Single table routes (must be last - /:id is catch-all)
 app.route('/', getTable);             // GET /:id
 app.route('/', updateTable);          // PUT /:id
@@ -0,0 +1,109 @@
+import { Hono } from 'hono';
+import { zValidator } from '@hono/zod-validator';
+import { getPrismaClient } from '@/lib/database.js';
+import { CacheService } from '@/lib/cache-service.js';
+import { adminWriteAuthMiddleware } from '@/middleware/auth.js';
+import { UpdateTableSharesSchema } from '@/const/schemas/tables.js';
+import type { Bindings } from '@/types/bindings.js';
+import type { UserContext } from '@/types/database.js';
+import { formatShare, normalizeRights } from './_shared.js';
+
+const app = new Hono<{
+  Bindings: Bindings;
+  Variables: { user: UserContext };
+}>();
+
+/**
+ * Replace the tokens and users a table is shared with
+ * PUT /api/tables/:id/shares
+ * Shares only take effect while the table's visibility is 'shared'
+ */
+app.put('/:id/shares', adminWriteAuthMiddleware, zValidator('json', UpdateTableSharesSchema), async (c) => {
+  try {
+    const database = getPrismaClient(c.env);
+    const tableId = c.req.param('id');
+    const { shares } = c.req.valid('json');
+
+    const table = await database.userTable.findUnique({
+      where: { id: tableId },
+      select: { id: true, visibility: true }
+    });
+    if (!table) {
+      return c.json({ error: 'Table not found' }, 404);
+    }
+
+    // One entry per token or user
+    const keys = shares.map(share => (share.tokenId ? `token:${share.tokenId}` : `user:${share.userId}`));
+    if (new Set(keys).size !== keys.length) {
+      return c.json({
+        error: 'Duplicate share',
+        errors: { shares: 'Each token and user can be listed only once' }
+      }, 400);
+    }
+
+    const tokenIds = shares.flatMap(share => (share.tokenId ? [share.tokenId] : []));
+    if (tokenIds.length > 0) {
+      const existing = await database.token.findMany({
+        where: { id: { in: tokenIds } },
+        select: { id: true }
+      });
+      const known = new Set(existing.map(token => token.id));
+      const unknown = tokenIds.filter(id => !known.has(id));
+      if (unknown.length > 0) {
+        return c.json({
+          error: 'Unknown token',
+          errors: { shares: `Unknown token IDs: ${unknown.join(', ')}` }
+        }, 400);
+      }
+    }
+
+    const previous = await database.tableShare.findMany({
+      where: { tableId },
+      select: { tokenId: true, userId: true }
+    });
+
+    await database.$transaction([
+      database.tableShare.deleteMany({ where: { tableId } }),
+      ...shares.map(share =>
+        database.tableShare.create({
+          data: {
+            tableId,
+            tokenId: share.tokenId ?? null,
+            userId: share.userId ?? null,
+            rights: JSON.stringify(normalizeRights(share.rights))
+          }
+        })
+      )
+    ]);
+
+    // The public API caches each token's shares; drop those of every token named before or now
+    if (c.env.KV) {
+      const named = [...previous, ...shares];
+      const userIds = named.flatMap(share => (share.userId ? [share.userId] : []));
+      const userTokens = userIds.length > 0
+        ? await database.token.findMany({ where: { userId: { in: userIds } }, select: { id: true } })
+        : [];
+      const affected = new Set([
+        ...named.flatMap(share => (share.tokenId ? [share.tokenId] : [])),
+        ...userTokens.map(token => token.id)
+      ]);
+      await new CacheService(c.env.KV).invalidateTokenShares([...affected]);
+    }
+
+    const saved = await database.tableShare.findMany({
+      where: { tableId },
+      orderBy: { createdAt: 'asc' }
+    });
+
+    return c.json({
+      tableId: table.id,
+      visibility: table.visibility,
+      shares: saved.map(formatShare)
+    });
+  } catch (error) {
+    console.error('Error updating table shares:', error);
+    return c.json({ error: 'Failed to update table shares' }, 500);
+  }
+});
+
+export default app;
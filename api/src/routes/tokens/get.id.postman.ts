@@ -2,6 +2,7 @@ import { Hono } from 'hono';
 import { getPrismaClient } from '@/lib/database.js';
 import { adminWriteAuthMiddleware } from '@/middleware/auth.js';
 import { PostmanGeneratorService } from '@/services/postman-generator.js';
+import { parseTableAccess } from '@/utils/tableAccess.js';
 
 const app = new Hono();
 
@@ -56,7 +57,7 @@ app.get('/:id/postman', adminWriteAuthMiddleware, async (c) => {
 
     const tokenWithParsedAccess = {
       ...token,
-      tableAccess: token.tableAccess ? JSON.parse(token.tableAccess) : null,
+      tableAccess: parseTableAccess(token.tableAccess),
     };
 
     // Convert tables to format expected by PostmanGeneratorService (with forSale for compatibility)
@@ -2,6 +2,7 @@ import { Hono } from 'hono';
 import { getPrismaClient } from '@/lib/database.js';
 import { adminWriteAuthMiddleware } from '@/middleware/auth.js';
 import { formatApiDate } from '@/lib/date-utils.js';
+import { parseTableAccess, restrictTableAccess, tableAccessIds, type TableAccess } from '@/utils/tableAccess.js';
 
 const app = new Hono();
 
@@ -29,6 +30,10 @@ app.get('/:id', adminWriteAuthMiddleware, async (c) => {
         allowedIps: true,
         allowedDomains: true,
         tableAccess: true,
+        rateLimits: true,
+        customerId: true,
+        priceRules: true,
+        columnRules: true,
         expiresAt: true,
         createdAt: true,
         updatedAt: true,
@@ -39,17 +44,15 @@ app.get('/:id', adminWriteAuthMiddleware, async (c) => {
       return c.json({ error: 'Token not found' }, 404);
     }
 
-    // Filter tableAccess to only include existing tables
-    let validTableAccess: string[] = [];
-    if (token.tableAccess) {
-      const tableIds: string[] = JSON.parse(token.tableAccess);
-      if (tableIds.length > 0) {
-        const existingTables = await database.userTable.findMany({
-          where: { id: { in: tableIds } },
-          select: { id: true }
-        });
-        validTableAccess = existingTables.map(t => t.id);
-      }
+    // Filter tableAccess to only include existing tables, keeping per-table operations
+    let validTableAccess: TableAccess = [];
+    const access = parseTableAccess(token.tableAccess);
+    if (access && tableAccessIds(access).length > 0) {
+      const existingTables = await database.userTable.findMany({
+        where: { id: { in: tableAccessIds(access) } },
+        select: { id: true }
+      });
+      validTableAccess = restrictTableAccess(access, existingTables.map(t => t.id));
     }
 
     const response = {
@@ -99,6 +99,10 @@ app.get('/', adminWriteAuthMiddleware, async (c) => {
           allowedIps: true,
           allowedDomains: true,
           tableAccess: true,
+          rateLimits: true,
+          customerId: true,
+          priceRules: true,
+          columnRules: true,
           expiresAt: true,
           createdAt: true,
           updatedAt: true,
@@ -4,6 +4,8 @@ import { getPrismaClient } from '@/lib/database.js';
 import { adminWriteAuthMiddleware } from '@/middleware/auth.js';
 import { formatApiDate } from '@/lib/date-utils.js';
 import { invalidateTokenCache } from '@/lib/token-service.js';
+import { TableAccessSchema } from '@/const/schemas/tokens.js';
+import { restrictTableAccess, tableAccessIds } from '@/utils/tableAccess.js';
 
 const app = new Hono<{ Bindings: Bindings }>();
 
@@ -31,7 +33,7 @@ app.patch('/:id', adminWriteAuthMiddleware, async (c) => {
     }
 
     // Validate specific field updates for inline editing
-    const allowedFields = ['name', 'permissions', 'isAdmin', 'allowedIps', 'allowedDomains', 'tableAccess', 'expiresAt'];
+    const allowedFields = ['name', 'permissions', 'isAdmin', 'allowedIps', 'allowedDomains', 'tableAccess', 'rateLimits', 'customerId', 'priceRules', 'columnRules', 'expiresAt'];
     const updateData: any = {};
 
     for (const [key, value] of Object.entries(updates)) {
@@ -54,17 +56,26 @@ app.patch('/:id', adminWriteAuthMiddleware, async (c) => {
         } else if (key === 'allowedIps' || key === 'allowedDomains') {
           updateData[key] = value || null;
         } else if (key === 'tableAccess') {
-          // Filter to only include existing tables
-          if (value && Array.isArray(value) && value.length > 0) {
+          // Filter to only include existing tables, keeping per-table operations
+          const parsed = TableAccessSchema.safeParse(value);
+          if (value && !parsed.success) {
+            return c.json({ error: 'Invalid tableAccess' }, 400);
+          }
+          const requestedTableIds = parsed.success ? tableAccessIds(parsed.data) : [];
+          if (parsed.success && requestedTableIds.length > 0) {
             const existingTables = await database.userTable.findMany({
-              where: { id: { in: value as string[] } },
+              where: { id: { in: requestedTableIds } },
               select: { id: true }
             });
-            const validTableIds = existingTables.map(t => t.id);
-            updateData[key] = validTableIds.length > 0 ? JSON.stringify(validTableIds) : null;
+            const validTableAccess = restrictTableAccess(parsed.data, existingTables.map(t => t.id));
+            updateData[key] = tableAccessIds(validTableAccess).length > 0 ? JSON.stringify(validTableAccess) : null;
           } else {
             updateData[key] = null;
           }
+        } else if (key === 'rateLimits' || key === 'priceRules' || key === 'columnRules') {
+          updateData[key] = value ? JSON.stringify(value) : null;
+        } else if (key === 'customerId') {
+          updateData[key] = typeof value === 'string' && value.trim() ? value.trim() : null;
         } else if (key === 'expiresAt') {
           updateData[key] = value ? new Date(value as string) : null;
         }
@@ -87,6 +98,10 @@ app.patch('/:id', adminWriteAuthMiddleware, async (c) => {
         allowedIps: true,
         allowedDomains: true,
         tableAccess: true,
+        rateLimits: true,
+        customerId: true,
+        priceRules: true,
+        columnRules: true,
         expiresAt: true,
         createdAt: true,
         updatedAt: true,
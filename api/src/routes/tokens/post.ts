@@ -3,7 +3,9 @@ import { zValidator } from '@hono/zod-validator';
 import { getPrismaClient } from '@/lib/database.js';
 import { adminWriteAuthMiddleware } from '@/middleware/auth.js';
 import { formatApiDate } from '@/lib/date-utils.js';
+import { getUserInfo } from '@/utils/common.js';
 import { CreateTokenSchema } from '@/const/schemas/tokens.js';
+import { restrictTableAccess, tableAccessIds, type TableAccess } from '@/utils/tableAccess.js';
 import { generateSecureToken } from './_shared.js';
 
 const app = new Hono();
@@ -16,6 +18,8 @@ app.post('/', adminWriteAuthMiddleware, zValidator('json', CreateTokenSchema), a
   try {
     const database = getPrismaClient(c.env);
     const tokenData = c.req.valid('json');
+    // The public API scopes tokens without a table list to their creator's public/shared tables
+    const { userId, userEmail } = getUserInfo(c, c.get('user'));
 
     // Generate secure token
     const tokenValue = generateSecureToken();
@@ -32,18 +36,20 @@ app.post('/', adminWriteAuthMiddleware, zValidator('json', CreateTokenSchema), a
       }, 400);
     }
 
-    // Filter tableAccess to only include existing tables
-    let validTableAccess: string[] = [];
-    if (tokenData.tableAccess && tokenData.tableAccess.length > 0) {
+    // Filter tableAccess to only include existing tables, keeping per-table operations
+    let validTableAccess: TableAccess = [];
+    const requestedTableIds = tableAccessIds(tokenData.tableAccess);
+    if (requestedTableIds.length > 0) {
       const existingTables = await database.userTable.findMany({
-        where: { id: { in: tokenData.tableAccess } },
+        where: { id: { in: requestedTableIds } },
         select: { id: true }
       });
-      validTableAccess = existingTables.map(t => t.id);
+      validTableAccess = restrictTableAccess(tokenData.tableAccess, existingTables.map(t => t.id));
     }
+    const hasTableAccess = tableAccessIds(validTableAccess).length > 0;
 
     // Validate that at least one table is selected (unless no tables exist in system)
-    if (validTableAccess.length === 0) {
+    if (!hasTableAccess) {
       // Check if there are any tables in the system
       const tablesCount = await database.userTable.count();
       if (tablesCount > 0) {
@@ -62,8 +68,14 @@ app.post('/', adminWriteAuthMiddleware, zValidator('json', CreateTokenSchema), a
         isAdmin: tokenData.isAdmin || false, // Default: regular API tokens can only access /api/public/*
         allowedIps: tokenData.allowedIps || null,
         allowedDomains: tokenData.allowedDomains || null,
-        tableAccess: JSON.stringify(validTableAccess),
+        tableAccess: hasTableAccess ? JSON.stringify(validTableAccess) : null,
+        rateLimits: tokenData.rateLimits ? JSON.stringify(tokenData.rateLimits) : null,
+        customerId: tokenData.customerId || null,
+        priceRules: tokenData.priceRules ? JSON.stringify(tokenData.priceRules) : null,
+        columnRules: tokenData.columnRules ? JSON.stringify(tokenData.columnRules) : null,
         expiresAt: tokenData.expiresAt ? new Date(tokenData.expiresAt) : null,
+        createdBy: userEmail,
+        userId,
       },
       select: {
         id: true,
@@ -74,6 +86,10 @@ app.post('/', adminWriteAuthMiddleware, zValidator('json', CreateTokenSchema), a
         allowedIps: true,
         allowedDomains: true,
         tableAccess: true,
+        rateLimits: true,
+        customerId: true,
+        priceRules: true,
+        columnRules: true,
         expiresAt: true,
         createdAt: true,
         updatedAt: true,
@@ -5,6 +5,7 @@ import { getPrismaClient } from '@/lib/database.js';
 import { adminWriteAuthMiddleware } from '@/middleware/auth.js';
 import { formatApiDate } from '@/lib/date-utils.js';
 import { UpdateTokenSchema } from '@/const/schemas/tokens.js';
+import { restrictTableAccess, tableAccessIds, type TableAccess } from '@/utils/tableAccess.js';
 import { invalidateTokenCache } from '@/lib/token-service.js';
 
 const app = new Hono<{ Bindings: Bindings }>();
@@ -46,18 +47,19 @@ app.put('/:id', adminWriteAuthMiddleware, zValidator('json', UpdateTokenSchema),
       }
     }
 
-    // Filter tableAccess to only include existing tables
-    let validTableAccess: string[] | undefined;
+    // Filter tableAccess to only include existing tables, keeping per-table operations
+    let validTableAccess: TableAccess | undefined;
     if (tokenData.tableAccess !== undefined) {
-      if (tokenData.tableAccess.length > 0) {
+      const requestedTableIds = tableAccessIds(tokenData.tableAccess);
+      if (requestedTableIds.length > 0) {
         const existingTables = await database.userTable.findMany({
-          where: { id: { in: tokenData.tableAccess } },
+          where: { id: { in: requestedTableIds } },
           select: { id: true }
         });
-        validTableAccess = existingTables.map(t => t.id);
+        validTableAccess = restrictTableAccess(tokenData.tableAccess, existingTables.map(t => t.id));
 
         // Require at least one valid table if tables exist in system
-        if (validTableAccess.length === 0) {
+        if (tableAccessIds(validTableAccess).length === 0) {
           const tablesCount = await database.userTable.count();
           if (tablesCount > 0) {
             return c.json({
@@ -80,6 +82,16 @@ app.put('/:id', adminWriteAuthMiddleware, zValidator('json', UpdateTokenSchema),
         ...(tokenData.allowedIps !== undefined && { allowedIps: tokenData.allowedIps }),
         ...(tokenData.allowedDomains !== undefined && { allowedDomains: tokenData.allowedDomains }),
         ...(validTableAccess !== undefined && { tableAccess: JSON.stringify(validTableAccess) }),
+        ...(tokenData.rateLimits !== undefined && {
+          rateLimits: tokenData.rateLimits ? JSON.stringify(tokenData.rateLimits) : null
+        }),
+        ...(tokenData.customerId !== undefined && { customerId: tokenData.customerId || null }),
+        ...(tokenData.priceRules !== undefined && {
+          priceRules: tokenData.priceRules ? JSON.stringify(tokenData.priceRules) : null
+        }),
+        ...(tokenData.columnRules !== undefined && {
+          columnRules: tokenData.columnRules ? JSON.stringify(tokenData.columnRules) : null
+        }),
         ...(tokenData.expiresAt !== undefined && {
           expiresAt: tokenData.expiresAt ? new Date(tokenData.expiresAt) : null
         }),
@@ -93,6 +105,10 @@ app.put('/:id', adminWriteAuthMiddleware, zValidator('json', UpdateTokenSchema),
         allowedIps: true,
         allowedDomains: true,
         tableAccess: true,
+        rateLimits: true,
+        customerId: true,
+        priceRules: true,
+        columnRules: true,
         expiresAt: true,
         createdAt: true,
         updatedAt: true,
@@ -4,6 +4,8 @@
  * Regular tokens can only access /api/public/* routes
  */
 
+import { readableTableIds, tableAccessIds, type TableAccess } from '@/utils/tableAccess.js';
+
 interface PostmanCollection {
   info: {
     name: string;
@@ -52,7 +54,7 @@ interface Token {
   token: string;
   name: string;
   permissions: string;
-  tableAccess: string[] | null;
+  tableAccess: TableAccess | null;
   allowedIps: string | null;
   allowedDomains: string | null;
   expiresAt: Date | null;
@@ -146,11 +148,12 @@ export class PostmanGeneratorService {
   /**
    * Filter tables based on token's table access
    */
-  private filterAccessibleTables(tables: Table[], tableAccess: string[] | null): Table[] {
-    if (!tableAccess || tableAccess.length === 0) {
+  private filterAccessibleTables(tables: Table[], tableAccess: TableAccess | null): Table[] {
+    if (!tableAccess || tableAccessIds(tableAccess).length === 0) {
       return tables; // If no restrictions, all tables are accessible
     }
-    return tables.filter((table) => tableAccess.includes(table.id));
+    const readable = readableTableIds(tableAccess);
+    return tables.filter((table) => readable.includes(table.id));
   }
 
   /**
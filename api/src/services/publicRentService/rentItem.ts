@@ -7,6 +7,7 @@ import { TableRepository } from '@/repositories/tableRepository.js'
 import { TableDataRepository } from '@/repositories/tableDataRepository.js'
 import { RentalRepository } from '@/repositories/rentalRepository.js'
 import { InventoryTrackingService } from '@/services/inventoryTrackingService/index.js'
+import { markedUpPrice } from '@/lib/price-rules.js'
 
 /**
  * Rent an item (create rental and mark item as unavailable)
@@ -56,6 +57,11 @@ export async function rentItem(
       return { error: 'Item is not available for rent', status: 403 }
     }
 
+    // A token with price rules pays exactly its marked-up price
+    if (data.unitPrice !== undefined && data.unitPrice !== markedUpPrice(user.token?.priceRules, data.tableId, price)) {
+      return { error: 'unitPrice does not match the token price rules', status: 400 }
+    }
+
     // 5. Check rental state machine - can we rent this item?
     if (!canRentItem(used, available)) {
       if (used) {
@@ -86,7 +92,7 @@ export async function rentItem(
       { id: table.id, name: table.name },
       { id: row.id, data: parsedData },
       rentalNumber,
-      price
+      data.unitPrice ?? price
     )
 
     // 8. Update item state - mark as not available (rented)
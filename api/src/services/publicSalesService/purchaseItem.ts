@@ -6,6 +6,7 @@ import type { ParsedTableData } from '@/types/dynamic-tables.js'
 import { TableRepository } from '@/repositories/tableRepository.js'
 import { TableDataRepository } from '@/repositories/tableDataRepository.js'
 import { SalesService } from '@/services/salesService/index.js'
+import { markedUpPrice } from '@/lib/price-rules.js'
 
 /**
  * Check item availability for purchase
@@ -106,6 +107,11 @@ export async function purchaseItem(
       return { error: 'Item is not available for sale', status: 403 }
     }
 
+    // A token with price rules pays exactly its marked-up price
+    if (data.unitPrice !== undefined && data.unitPrice !== markedUpPrice(user.token?.priceRules, data.tableId, price)) {
+      return { error: 'unitPrice does not match the token price rules', status: 400 }
+    }
+
     const quantitySold = data.quantitySold || 1
     if (currentQty < quantitySold) {
       return {
@@ -65,8 +65,11 @@ export async function createSale(
       throw new Error(`Insufficient quantity available. Available: ${availability.currentQuantity}, Requested: ${availability.requestedQuantity}`)
     }
 
-    // 5. Calculate sale amounts
-    const unitPrice = itemData.price
+    // 5. Calculate sale amounts (at the marked-up price a reseller token was quoted, if any)
+    if (saleData.unitPrice !== undefined && saleData.unitPrice < itemData.price) {
+      throw new Error('Unit price cannot be below the item price')
+    }
+    const unitPrice = saleData.unitPrice ?? itemData.price
     const totalAmount = unitPrice * requestedQuantity
 
     // 6. Generate sale number
@@ -222,10 +222,13 @@ export async function importTableData(
         throw error
     }
 
-    // Invalidate row count cache after import
+    // Invalidate row count and query caches after import
     if (env.KV && importedRows > 0) {
         const cache = new CacheService(env.KV)
-        await cache.invalidateRowCount(tableId)
+        await Promise.all([
+            cache.invalidateRowCount(tableId),
+            cache.invalidateQueryResults([tableId])
+        ])
     }
 
     // Return results with validation summary
@@ -5,6 +5,7 @@ import type { TableRepository } from '@/repositories/tableRepository.js'
 import type { ZodCompatibleValidator } from '@/validators/zodCompatibleValidator.js'
 import { getUserInfo, isUserAdmin, createErrorResponse, createSuccessResponse } from '@/utils/common.js'
 import { validateColumnName } from '@/utils/column-name-utils.js'
+import { COLUMN_VISIBILITIES } from '@/config/columnTypes.js'
 import { ModuleRepository } from '@/repositories/moduleRepository.js'
 
 /**
@@ -38,6 +39,9 @@ export async function addColumn(
     if (!data.name || !data.type) {
       return createErrorResponse('Validation failed', 'Column name and type are required', 400)
     }
+    if (data.visibility !== undefined && !(COLUMN_VISIBILITIES as readonly string[]).includes(data.visibility)) {
+      return createErrorResponse('Validation failed', `Column visibility must be one of: ${COLUMN_VISIBILITIES.join(', ')}`, 400)
+    }
 
     // Validate module column type configuration if it's a module type
     if (data.type.includes(':')) {
@@ -71,6 +75,10 @@ export async function addColumn(
       columnData.position = data.position
     }
 
+    if (data.visibility !== undefined) {
+      columnData.visibility = data.visibility
+    }
+
     const column = await repository.addColumn(tableId, columnData)
 
     return createSuccessResponse(
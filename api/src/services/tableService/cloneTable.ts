@@ -65,7 +65,8 @@ export async function cloneTable(
         isRequired: col.isRequired,
         allowDuplicates: col.allowDuplicates,
         defaultValue: col.defaultValue,
-        position: col.position
+        position: col.position,
+        visibility: col.visibility
       }))
     }
 
@@ -62,6 +62,10 @@ export async function createTable(
       ;(data as any).tableType = data.forSale ? 'sale' : 'default'
     }
 
+    if (data.slug && await repository.isSlugTaken(data.slug)) {
+      return createErrorResponse('Slug already in use', `Another table already uses the slug "${data.slug}"`, 409)
+    }
+
     const tableType = (data.tableType || 'default') as TableType
 
     // If table has a special type, automatically add required columns if not present
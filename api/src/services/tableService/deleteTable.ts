@@ -4,6 +4,7 @@ import type { TableRepository } from '@/repositories/tableRepository.js'
 import type { ZodCompatibleValidator } from '@/validators/zodCompatibleValidator.js'
 import { getUserInfo, isUserAdmin, createErrorResponse, createSuccessResponse } from '@/utils/common.js'
 import { getPrismaClient } from '@/lib/database.js'
+import { parseTableAccess, restrictTableAccess, tableAccessIds } from '@/utils/tableAccess.js'
 
 /**
  * Delete table and all its data
@@ -50,7 +51,7 @@ export async function deleteTable(
 }
 
 /**
- * Remove a deleted table ID from all tokens' tableAccess arrays
+ * Remove a deleted table ID from all tokens' tableAccess (lists or per-table operations)
  */
 async function cleanupTokenTableAccess(c: Context, deletedTableId: string): Promise<void> {
   try {
@@ -73,20 +74,19 @@ async function cleanupTokenTableAccess(c: Context, deletedTableId: string): Prom
     for (const token of tokens) {
       if (!token.tableAccess) continue
 
-      try {
-        const tableIds: string[] = JSON.parse(token.tableAccess)
-        if (tableIds.includes(deletedTableId)) {
-          const updatedTableIds = tableIds.filter(id => id !== deletedTableId)
-          await prisma.token.update({
-            where: { id: token.id },
-            data: {
-              tableAccess: updatedTableIds.length > 0 ? JSON.stringify(updatedTableIds) : null
-            }
-          })
-        }
-      } catch {
-        // Skip tokens with invalid JSON in tableAccess
-        continue
+      // Skip tokens with invalid JSON in tableAccess
+      const access = parseTableAccess(token.tableAccess)
+      if (!access) continue
+
+      const tableIds = tableAccessIds(access)
+      if (tableIds.includes(deletedTableId)) {
+        const updatedAccess = restrictTableAccess(access, tableIds.filter(id => id !== deletedTableId))
+        await prisma.token.update({
+          where: { id: token.id },
+          data: {
+            tableAccess: tableAccessIds(updatedAccess).length > 0 ? JSON.stringify(updatedAccess) : null
+          }
+        })
       }
     }
   } catch (error) {
@@ -5,6 +5,7 @@ import type { TableRepository } from '@/repositories/tableRepository.js'
 import type { ZodCompatibleValidator } from '@/validators/zodCompatibleValidator.js'
 import { getUserInfo, isUserAdmin, createErrorResponse, createSuccessResponse } from '@/utils/common.js'
 import { validateColumnName } from '@/utils/column-name-utils.js'
+import { COLUMN_VISIBILITIES } from '@/config/columnTypes.js'
 
 /**
  * Update column in table
@@ -34,6 +35,10 @@ export async function updateColumn(
       return createErrorResponse('Access denied', 'You can only modify tables you created', 403)
     }
 
+    if (data.visibility !== undefined && !(COLUMN_VISIBILITIES as readonly string[]).includes(data.visibility)) {
+      return createErrorResponse('Validation failed', `Column visibility must be one of: ${COLUMN_VISIBILITIES.join(', ')}`, 400)
+    }
+
     // Get column details to check if it's protected
     const column = await repository.getColumn(tableId, columnId)
     if (!column) {
@@ -106,6 +111,7 @@ export async function updateColumn(
     if (data.type !== undefined) updateData.type = data.type
     if (data.isRequired !== undefined) updateData.isRequired = data.isRequired
     if (data.allowDuplicates !== undefined) updateData.allowDuplicates = data.allowDuplicates
+    if (data.visibility !== undefined) updateData.visibility = data.visibility
     if (data.defaultValue !== undefined && data.defaultValue !== null) {
       updateData.defaultValue = data.defaultValue
     }
@@ -59,6 +59,10 @@ export async function updateTable(
       return createErrorResponse('Table not found', 'Table does not exist', 404)
     }
 
+    if (data.slug && await repository.isSlugTaken(data.slug, tableId)) {
+      return createErrorResponse('Slug already in use', `Another table already uses the slug "${data.slug}"`, 409)
+    }
+
     // Handle conversion to special table types - validate columns exist or auto-create them
     const newTableType = data.tableType as TableType | undefined
     if (newTableType && newTableType !== 'default' && newTableType !== currentTable.tableType) {
@@ -38,7 +38,11 @@ export interface Token {
   isAdmin: boolean // Admin tokens can access all routes, regular tokens only public routes
   allowedIps: string | null // JSON array of IPs/CIDR ranges
   allowedDomains: string | null // JSON array of domain patterns
-  tableAccess: string | null // JSON array of accessible table IDs
+  tableAccess: string | null // JSON: [tableId, ...] or { [tableId]: [read | buy | rent | release] }
+  rateLimits: string | null // JSON: { read?: { perMinute, burst? }, write?: { perMinute, burst? } }
+  customerId: string | null // Customer the token orders for and reads orders of (null = any)
+  priceRules: string | null // JSON: { default?: Rule, tables?: { [tableId]: Rule } } (null = catalog prices)
+  columnRules: string | null // JSON: { [tableId]: { [column]: visible | hidden | masked } } (null = column defaults)
   expiresAt: Date | null
   createdAt: Date
   updatedAt: Date
@@ -69,6 +69,13 @@ export type TableVisibility = 'private' | 'public' | 'shared'
  */
 export type TableType = 'default' | 'sale' | 'rent'
 
+/**
+ * What the public API shows of a column
+ * - 'hidden': left out of every response and not filterable
+ * - 'masked': partly shown (emails as j***@x.com, phones as their last 4 digits)
+ */
+export type ColumnVisibility = 'visible' | 'hidden' | 'masked'
+
 /**
  * Rental period options for rent-type tables
  */
@@ -88,6 +95,8 @@ export interface UserTable {
   tableType: TableType // 'default' | 'sale' | 'rent'
   productIdColumn: string | null // Column name that serves as product identifier/title for e-commerce tables
   rentalPeriod: RentalPeriod | null // Billing period for rent tables (default: 'month')
+  currency: string // ISO 4217 code the currency columns are priced in (default: 'USD')
+  slug: string | null // Stable public API handle, usable instead of the id
   createdAt: Date
   updatedAt: Date
   rowCount?: number // Optional: Total number of data rows in this table
@@ -106,6 +115,7 @@ export interface TableColumn {
   allowDuplicates: boolean
   defaultValue: string | null
   position: number
+  visibility: ColumnVisibility
   createdAt: Date
 }
 
@@ -169,6 +179,8 @@ export interface CreateTableRequest {
   tableType?: TableType // 'default' | 'sale' | 'rent' - determines auto-created columns
   productIdColumn?: string // Column name that serves as product identifier/title
   rentalPeriod?: RentalPeriod // Billing period for rent tables (default: 'month')
+  currency?: string // ISO 4217 code the currency columns are priced in (default: 'USD')
+  slug?: string // Stable public API handle, usable instead of the id
   /** @deprecated Use tableType instead. Will be converted to tableType='sale' if true */
   forSale?: boolean
   userId?: string  // Optional user ID for session-based creation
@@ -185,6 +197,7 @@ export interface CreateColumnRequest {
   allowDuplicates?: boolean
   defaultValue?: string | number | boolean
   position: number
+  visibility?: ColumnVisibility
 }
 
 /**
@@ -197,6 +210,8 @@ export interface UpdateTableRequest {
   tableType?: TableType // 'default' | 'sale' | 'rent' - can convert between types
   productIdColumn?: string | null // Column name that serves as product identifier/title
   rentalPeriod?: RentalPeriod // Billing period for rent tables
+  currency?: string // ISO 4217 code the currency columns are priced in
+  slug?: string | null // Stable public API handle; null clears it
   /** @deprecated Use tableType instead. Will be converted to tableType='sale' if true */
   forSale?: boolean
 }
@@ -211,6 +226,7 @@ export interface UpdateColumnRequest {
   allowDuplicates?: boolean
   defaultValue?: string
   position?: number
+  visibility?: ColumnVisibility
 }
 
 /**
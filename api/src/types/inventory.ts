@@ -4,7 +4,7 @@
 /**
  * Inventory transaction types
  */
-export type InventoryTransactionType = 'sale' | 'rent' | 'release' | 'add' | 'remove' | 'update' | 'adjust'
+export type InventoryTransactionType = 'sale' | 'rent' | 'release' | 'overdue' | 'add' | 'remove' | 'update' | 'adjust'
 
 /**
  * Inventory transaction record
@@ -225,6 +225,7 @@ export function getTransactionTypeDisplayName(type: InventoryTransactionType): s
     case 'sale': return 'Sale'
     case 'rent': return 'Rented'
     case 'release': return 'Released'
+    case 'overdue': return 'Overdue'
     case 'add': return 'Added'
     case 'remove': return 'Removed'
     case 'update': return 'Updated'
@@ -25,6 +25,7 @@ export interface Rental {
     rentalStatus: RentalStatus
     rentedAt: Date // When the item was rented
     releasedAt: Date | null // When the item was released (null if still active)
+    overdueAt: Date | null // When a rental expiry rule flagged it overdue (still active)
     notes: string | null
     createdAt: Date
     updatedAt: Date
@@ -56,6 +57,7 @@ export interface CreateRentalRequest {
     itemId: string
     customerId: string
     notes?: string
+    unitPrice?: number // Marked-up price from a token's price rules, must match what they give
 }
 
 /**
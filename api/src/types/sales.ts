@@ -55,6 +55,7 @@ export interface CreateSaleRequest {
     quantitySold?: number // Defaults to 1
     paymentMethod?: string
     notes?: string
+    unitPrice?: number // Marked-up price from a token's price rules, must match what they give
 }
 
 /**
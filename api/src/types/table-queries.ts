@@ -72,6 +72,7 @@ export interface AddColumnRequest {
     allowDuplicates?: boolean | string
     defaultValue?: string | null
     position?: number
+    visibility?: 'visible' | 'hidden' | 'masked'
 }
 
 export interface UpdateColumnRequest {
@@ -81,4 +82,5 @@ export interface UpdateColumnRequest {
     allowDuplicates?: boolean
     defaultValue?: string | null
     position?: number
+    visibility?: 'visible' | 'hidden' | 'masked'
 }
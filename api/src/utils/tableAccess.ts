@@ -5,6 +5,69 @@ import type { UserTable } from '@/types/dynamic-tables.js'
  * Utility functions for checking table access in public API
  */
 
+/**
+ * Operations a token's tableAccess can grant on a table
+ */
+export type TableOperation = 'read' | 'buy' | 'rent' | 'release'
+
+/**
+ * tokens.tableAccess: table IDs granted every operation, or each table's operations
+ * e.g. ["tbl1", "tbl2"] or {"tbl1": ["read"], "tbl2": ["read", "buy"]}
+ */
+export type TableAccess = string[] | Record<string, TableOperation[]>
+
+/**
+ * Parse a stored tableAccess value; null when missing or malformed
+ */
+export function parseTableAccess(json: string | null | undefined): TableAccess | null {
+  if (!json) return null
+  try {
+    const access = JSON.parse(json)
+    if (Array.isArray(access) || (access && typeof access === 'object')) {
+      return access
+    }
+  } catch (e) {
+    console.error('Error parsing tableAccess:', e)
+  }
+  return null
+}
+
+/**
+ * Every table a tableAccess value mentions, whatever it grants
+ */
+export function tableAccessIds(access: TableAccess): string[] {
+  return Array.isArray(access) ? access : Object.keys(access)
+}
+
+/**
+ * Tables a tableAccess value grants read on
+ */
+export function readableTableIds(access: TableAccess): string[] {
+  if (Array.isArray(access)) return access
+  return Object.entries(access)
+    .filter(([, operations]) => Array.isArray(operations) && operations.includes('read'))
+    .map(([tableId]) => tableId)
+}
+
+/**
+ * Tables a stored tableAccess value restricts a token to reading, or null when it names
+ * no table at all (missing, malformed or empty: unrestricted)
+ */
+export function restrictedTableIds(json: string | null | undefined): string[] | null {
+  const access = parseTableAccess(json)
+  if (!access || tableAccessIds(access).length === 0) return null
+  return readableTableIds(access)
+}
+
+/**
+ * Keep only the given tables in a tableAccess value, in the same format
+ */
+export function restrictTableAccess(access: TableAccess, tableIds: string[]): TableAccess {
+  const keep = new Set(tableIds)
+  if (Array.isArray(access)) return access.filter(id => keep.has(id))
+  return Object.fromEntries(Object.entries(access).filter(([tableId]) => keep.has(tableId)))
+}
+
 /**
  * Check if a user (token) has access to a specific table
  * Access is granted if:
@@ -21,16 +84,10 @@ export function hasTableAccess(
     return true
   }
 
-  // Check if token has explicit tableAccess for this table
-  if (user?.token?.tableAccess) {
-    try {
-      const allowedTableIds = JSON.parse(user.token.tableAccess)
-      if (Array.isArray(allowedTableIds) && allowedTableIds.includes(table.id)) {
-        return true
-      }
-    } catch (e) {
-      console.error('Error parsing tableAccess:', e)
-    }
+  // Check if token has explicit read access to this table
+  const access = parseTableAccess(user?.token?.tableAccess)
+  if (access && readableTableIds(access).includes(table.id)) {
+    return true
   }
 
   // Fallback: check visibility
@@ -48,16 +105,10 @@ export function getAllowedTableIds(user: UserContext | undefined): string[] | nu
     return null
   }
 
-  // Parse tableAccess from token
-  if (user?.token?.tableAccess) {
-    try {
-      const allowedTableIds = JSON.parse(user.token.tableAccess)
-      if (Array.isArray(allowedTableIds)) {
-        return allowedTableIds
-      }
-    } catch (e) {
-      console.error('Error parsing tableAccess:', e)
-    }
+  // Parse tableAccess from token; only tables granted read are listed
+  const access = parseTableAccess(user?.token?.tableAccess)
+  if (access) {
+    return readableTableIds(access)
   }
 
   // Token has no tableAccess - empty array means no explicit access
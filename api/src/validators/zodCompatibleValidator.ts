@@ -8,7 +8,9 @@ import type {
   TableDataMassAction,
   ParsedTableData
 } from '@/types/dynamic-tables.js'
-import { BUILT_IN_COLUMN_TYPES } from '@/config/columnTypes.js'
+import { BUILT_IN_COLUMN_TYPES, COLUMN_VISIBILITIES } from '@/config/columnTypes.js'
+import { CurrencyCodeSchema } from '@/const/schemas/currency.js'
+import { TableSlugSchema } from '@/const/schemas/tables.js'
 
 /**
  * Zod schemas that match existing types exactly
@@ -42,7 +44,8 @@ function createColumnSchema(moduleColumnTypes: string[] = []) {
     isRequired: z.boolean().default(false),
     allowDuplicates: z.boolean().default(true),
     defaultValue: z.any().optional(),
-    position: z.number().optional()
+    position: z.number().optional(),
+    visibility: z.enum(COLUMN_VISIBILITIES).default('visible')
   })
 }
 
@@ -63,6 +66,8 @@ function createCreateTableRequestSchema(moduleColumnTypes: string[] = []) {
       message: "Table type must be one of: default, sale, rent"
     }).default('default'),
     forSale: z.boolean().optional(),
+    currency: CurrencyCodeSchema.optional(),
+    slug: TableSlugSchema.optional(),
     user_id: z.string().optional(),
     columns: z.array(createColumnSchema(moduleColumnTypes)).min(1, 'At least one column is required')
   })
@@ -79,6 +84,8 @@ const CreateTableRequestSchema = z.object({
   }).default('default'),
   /** @deprecated Use tableType instead */
   forSale: z.boolean().optional(),
+  currency: CurrencyCodeSchema.optional(),
+  slug: TableSlugSchema.optional(),
   user_id: z.string().optional(), // For compatibility
   columns: z.array(ColumnSchema).min(1, 'At least one column is required')
 })
@@ -92,6 +99,8 @@ const UpdateTableRequestSchema = z.object({
   tableType: z.enum(['default', 'sale', 'rent'], {
     message: "Table type must be one of: default, sale, rent"
   }).optional(),
+  currency: CurrencyCodeSchema.optional(),
+  slug: TableSlugSchema.nullable().optional(), // null clears it
   /** @deprecated Use tableType instead */
   forSale: z.boolean().optional()
 }).refine(
@@ -0,0 +1,5 @@
+//! Self-hosted public API server - see `src/native.rs` for configuration
+
+fn main() -> std::io::Result<()> {
+    store_public_api::native::serve(store_public_api::native::ServerConfig::from_env())
+}
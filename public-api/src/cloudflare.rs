@@ -0,0 +1,92 @@
+use wasm_bindgen::JsValue;
+use worker::*;
+
+use crate::store::{Cache, SqlDatabase};
+use crate::telemetry::RequestMetrics;
+use crate::webhooks::WebhookSender;
+
+// ============================================================================
+// D1, KV AND FETCH BACKENDS
+// ============================================================================
+
+/// D1 behind the SqlDatabase trait; every query is timed and counted in the request metrics
+pub struct D1Sql<'a> {
+    db: D1Database,
+    metrics: &'a RequestMetrics,
+}
+
+impl<'a> D1Sql<'a> {
+    pub fn new(env: &Env, metrics: &'a RequestMetrics) -> Result<Self> {
+        Ok(Self { db: env.d1("DB")?, metrics })
+    }
+}
+
+/// JSON parameter to a D1 binding (D1 has no bigint support, so numbers go as f64)
+fn to_js(value: &serde_json::Value) -> JsValue {
+    match value {
+        serde_json::Value::Null => JsValue::NULL,
+        serde_json::Value::Bool(b) => JsValue::from_bool(*b),
+        serde_json::Value::Number(n) => JsValue::from_f64(n.as_f64().unwrap_or_default()),
+        serde_json::Value::String(s) => JsValue::from_str(s),
+        other => JsValue::from_str(&other.to_string()),
+    }
+}
+
+impl SqlDatabase for D1Sql<'_> {
+    async fn query(&self, sql: &str, params: &[serde_json::Value]) -> Result<Vec<serde_json::Value>> {
+        let bindings: Vec<JsValue> = params.iter().map(to_js).collect();
+        let stmt = self.db.prepare(sql).bind(&bindings)?;
+        self.metrics.db(stmt.all()).await?.results()
+    }
+}
+
+/// Workers KV behind the Cache trait; every operation is timed in the request metrics
+pub struct KvCache<'a> {
+    kv: kv::KvStore,
+    metrics: &'a RequestMetrics,
+}
+
+impl<'a> KvCache<'a> {
+    pub fn new(env: &Env, metrics: &'a RequestMetrics) -> Result<Self> {
+        Ok(Self { kv: env.kv("KV")?, metrics })
+    }
+}
+
+impl Cache for KvCache<'_> {
+    async fn get(&self, key: &str) -> Option<String> {
+        self.metrics.cache(self.kv.get(key).text()).await.ok().flatten()
+    }
+
+    async fn put(&self, key: &str, value: String, ttl: Option<u64>) {
+        if let Ok(mut builder) = self.kv.put(key, value) {
+            if let Some(ttl) = ttl {
+                builder = builder.expiration_ttl(ttl);
+            }
+            let _ = self.metrics.cache(builder.execute()).await;
+        }
+    }
+
+    async fn delete(&self, key: &str) {
+        let _ = self.metrics.cache(self.kv.delete(key)).await;
+    }
+}
+
+/// Webhook requests through the worker's fetch
+pub struct FetchSender;
+
+impl WebhookSender for FetchSender {
+    async fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> std::result::Result<u16, String> {
+        let request_headers = Headers::new();
+        for (name, value) in headers {
+            request_headers.set(name, value).map_err(|e| e.to_string())?;
+        }
+        let mut init = RequestInit::new();
+        init.with_method(Method::Post);
+        init.with_headers(request_headers);
+        init.with_body(Some(JsValue::from_str(body)));
+
+        let request = Request::new_with_init(url, &init).map_err(|e| e.to_string())?;
+        let response = Fetch::Request(request).send().await.map_err(|e| e.to_string())?;
+        Ok(response.status_code())
+    }
+}
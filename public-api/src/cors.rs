@@ -0,0 +1,151 @@
+use worker::*;
+
+use crate::routes;
+use crate::utils::{self, Vars};
+
+// ============================================================================
+// CORS
+// ============================================================================
+
+/// Preflight cache lifetime; browsers cap this themselves (Chromium at 7200)
+const DEFAULT_MAX_AGE: u32 = 7200;
+
+/// Request headers every route accepts; routes add their own in the route table
+const COMMON_REQUEST_HEADERS: &[&str] = &["Authorization", "Content-Type", "X-Request-Id"];
+
+/// Response headers browser clients may read
+const EXPOSED_HEADERS: &str =
+    "X-Request-Id, Server-Timing, X-RateLimit-Limit, X-RateLimit-Remaining, X-RateLimit-Reset, Retry-After";
+
+/// What to send as Access-Control-Allow-Origin
+#[derive(Debug, PartialEq, Eq)]
+enum AllowOrigin {
+    /// Neither the token nor the global config restricts origins - keep the public `*`
+    Any,
+    /// Echo this origin back (credentials allowed)
+    Origin(String),
+    /// Echo this origin back without allowing credentials - a preflight from an origin only
+    /// the token (not yet known) could allow
+    Unverified(String),
+    /// Origin not allowed - send no Allow-Origin so the browser blocks the read
+    Denied,
+}
+
+/// Global origin patterns from the CORS_ALLOWED_ORIGINS var (comma separated)
+fn global_patterns(vars: &impl Vars) -> Vec<String> {
+    vars.get("CORS_ALLOWED_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+/// Token `allowedDomains` JSON column - an array of domain patterns
+fn token_patterns(allowed_domains: Option<&str>) -> Vec<String> {
+    allowed_domains
+        .and_then(|s| serde_json::from_str::<Vec<String>>(s).ok())
+        .unwrap_or_default()
+}
+
+/// Match an Origin against a pattern, using the same rules as the API's domain whitelist:
+/// full origins (`https://shop.example.com`), exact hosts, `*.example.com`, `localhost:*`
+/// and bare domains that also cover their subdomains
+fn origin_matches(origin: &str, pattern: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    if pattern.contains("://") {
+        return origin.eq_ignore_ascii_case(pattern.trim_end_matches('/'));
+    }
+
+    let host_port = origin.split_once("://").map(|(_, rest)| rest).unwrap_or(origin).to_ascii_lowercase();
+    let host = host_port.split(':').next().unwrap_or_default();
+    let pattern = pattern.to_ascii_lowercase();
+
+    if let Some(base) = pattern.strip_prefix("*.") {
+        return host.ends_with(&format!(".{}", base));
+    }
+    if let Some(base) = pattern.strip_suffix(":*") {
+        return host == base;
+    }
+    host_port == pattern || host == pattern || host.ends_with(&format!(".{}", pattern))
+}
+
+fn resolve_origin(origin: Option<&str>, token_domains: Option<&str>, global: &[String]) -> AllowOrigin {
+    let token = token_patterns(token_domains);
+    if token.is_empty() && global.is_empty() {
+        return AllowOrigin::Any;
+    }
+    match origin {
+        Some(o) if token.iter().chain(global).any(|p| origin_matches(o, p)) => AllowOrigin::Origin(o.to_string()),
+        _ => AllowOrigin::Denied,
+    }
+}
+
+fn allow_origin_headers(allow: AllowOrigin) -> Vec<(&'static str, String)> {
+    match allow {
+        AllowOrigin::Any => vec![
+            ("Access-Control-Allow-Origin", "*".to_string()),
+            ("Timing-Allow-Origin", "*".to_string()),
+        ],
+        AllowOrigin::Origin(origin) => vec![
+            ("Access-Control-Allow-Origin", origin.clone()),
+            ("Access-Control-Allow-Credentials", "true".to_string()),
+            ("Timing-Allow-Origin", origin),
+        ],
+        AllowOrigin::Unverified(origin) => vec![("Access-Control-Allow-Origin", origin)],
+        AllowOrigin::Denied => vec![],
+    }
+}
+
+/// CORS headers for an actual (non-preflight) response
+/// The origin is echoed only when it matches the token's allowedDomains or the global allow-list;
+/// when neither restricts origins the response stays readable from anywhere (`*`)
+pub fn response_headers(vars: &impl Vars, origin: Option<&str>, token_domains: Option<&str>) -> Vec<(&'static str, String)> {
+    let mut headers = allow_origin_headers(resolve_origin(origin, token_domains, &global_patterns(vars)));
+    headers.push(("Access-Control-Expose-Headers", EXPOSED_HEADERS.to_string()));
+    headers.push(("Vary", "Origin".to_string()));
+    headers
+}
+
+/// Headers answering a CORS preflight for a path
+/// Preflights carry no Authorization header, so the token's allowedDomains can't be consulted:
+/// any origin may send the request, and the actual response decides whether the browser can read it.
+/// Only origins on the global allow-list are told they may send credentials.
+pub fn preflight_headers(vars: &impl Vars, origin: Option<String>, path: &str) -> Vec<(&'static str, String)> {
+    let global = global_patterns(vars);
+    let allow = match origin {
+        Some(o) if global.iter().any(|p| origin_matches(&o, p)) => AllowOrigin::Origin(o),
+        Some(o) => AllowOrigin::Unverified(o),
+        None => AllowOrigin::Any,
+    };
+
+    let mut methods: Vec<String> = routes::methods_for(path).iter().map(|m| m.to_string()).collect();
+    methods.push(Method::Options.to_string());
+
+    let mut request_headers: Vec<&str> = COMMON_REQUEST_HEADERS.to_vec();
+    for header in routes::headers_for(path) {
+        if !request_headers.contains(&header) {
+            request_headers.push(header);
+        }
+    }
+
+    let max_age = utils::var_or(vars, "CORS_MAX_AGE", DEFAULT_MAX_AGE);
+
+    let mut headers = allow_origin_headers(allow);
+    headers.push(("Access-Control-Allow-Methods", methods.join(", ")));
+    headers.push(("Access-Control-Allow-Headers", request_headers.join(", ")));
+    headers.push(("Access-Control-Max-Age", max_age.to_string()));
+    headers.push(("Vary", "Origin, Access-Control-Request-Method, Access-Control-Request-Headers".to_string()));
+    headers
+}
+
+/// Answer a CORS preflight in the worker
+pub fn preflight(req: &Request, env: &Env) -> Result<Response> {
+    let headers = Headers::new();
+    for (name, value) in preflight_headers(env, req.headers().get("Origin")?, &req.path()) {
+        headers.set(name, &value)?;
+    }
+    Ok(Response::empty()?.with_status(204).with_headers(headers))
+}
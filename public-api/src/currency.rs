@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::error::{ApiError, ApiResult};
+use crate::store::{Cache, TableStore};
+
+// ============================================================================
+// CURRENCY CONVERSION
+// ============================================================================
+//
+// `?currency=` converts money columns from each table's base currency (userTables.currency).
+
+/// KV key of the exchange rates, written by PUT /api/admin/currency-rates
+pub const CACHE_KEY_CURRENCY_RATES: &str = "currency:rates";
+
+/// Field added to each converted record
+const CONVERSION_FIELD: &str = "currencyConversion";
+
+/// Exchange rates: units of each currency per one unit of `base`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyRates {
+    pub base: String,
+    pub rates: HashMap<String, f64>,
+    pub updated_at: String,
+}
+
+impl CurrencyRates {
+    /// Units of `to` per unit of `from`, when both are known
+    pub fn rate(&self, from: &str, to: &str) -> Option<f64> {
+        let per_base = |code: &str| match code == self.base {
+            true => Some(1.0),
+            false => self.rates.get(code).copied().filter(|rate| *rate > 0.0),
+        };
+        Some(per_base(to)? / per_base(from)?)
+    }
+}
+
+/// A money column of a table: `currency`-typed, or the protected price/fee columns of sale
+/// and rent tables
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyColumn {
+    pub table_id: String,
+    pub name: String,
+    /// The table's base currency
+    pub currency: String,
+}
+
+/// How one table's records are converted
+#[derive(Debug)]
+struct TableConversion {
+    from: String,
+    rate: f64,
+    columns: Vec<String>,
+}
+
+/// The conversion a request asked for, per table
+#[derive(Debug)]
+pub struct Conversion {
+    to: String,
+    rates_updated_at: String,
+    tables: HashMap<String, TableConversion>,
+}
+
+/// The `currency` query parameter: a 3-letter ISO 4217 code
+fn requested_currency(query: &HashMap<String, String>) -> ApiResult<Option<String>> {
+    match query.get("currency").map(|c| c.trim().to_uppercase()) {
+        None => Ok(None),
+        Some(code) if code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase()) => Ok(Some(code)),
+        Some(_) => Err(ApiError::InvalidParameter("currency must be a 3-letter ISO 4217 code".to_string())),
+    }
+}
+
+/// Round a converted amount to cents, like the currency column type stores it
+pub fn round_amount(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+/// A money field as a number: numbers as-is, text amounts parsed
+pub fn amount(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+impl Conversion {
+    /// The conversion for records of `table_ids` when the request has a `currency` parameter.
+    /// An unknown target currency is the caller's mistake; a table priced in a currency without
+    /// a rate, or no rates at all, is ours.
+    pub async fn from_query<S: TableStore, C: Cache>(
+        store: &S,
+        cache: &C,
+        query: &HashMap<String, String>,
+        table_ids: &[String],
+    ) -> ApiResult<Option<Self>> {
+        let Some(to) = requested_currency(query)? else {
+            return Ok(None);
+        };
+        let rates = cache
+            .get(CACHE_KEY_CURRENCY_RATES)
+            .await
+            .and_then(|json| serde_json::from_str::<CurrencyRates>(&json).ok())
+            .ok_or_else(|| ApiError::CurrencyUnavailable("No exchange rates have been set".to_string()))?;
+        if rates.rate(&rates.base, &to).is_none() {
+            return Err(ApiError::InvalidParameter(format!("No exchange rate for {}", to)));
+        }
+
+        let mut tables: HashMap<String, TableConversion> = HashMap::new();
+        for column in store.currency_columns(table_ids).await? {
+            if let Some(table) = tables.get_mut(&column.table_id) {
+                table.columns.push(column.name);
+                continue;
+            }
+            let rate = rates
+                .rate(&column.currency, &to)
+                .ok_or_else(|| ApiError::CurrencyUnavailable(format!("No exchange rate for {}", column.currency)))?;
+            tables.insert(
+                column.table_id,
+                TableConversion { from: column.currency, rate, columns: vec![column.name] },
+            );
+        }
+        Ok(Some(Self { to, rates_updated_at: rates.updated_at, tables }))
+    }
+
+    /// Convert a table's money fields in place, returning the conversion applied (None when the
+    /// table has no money columns). Text amounts become numbers; other values are left alone.
+    pub fn convert(&self, table_id: &str, fields: &mut serde_json::Map<String, serde_json::Value>) -> Option<serde_json::Value> {
+        let table = self.tables.get(table_id)?;
+        for column in &table.columns {
+            let Some(value) = fields.get_mut(column) else {
+                continue;
+            };
+            if let Some(amount) = amount(value) {
+                *value = serde_json::json!(round_amount(amount * table.rate));
+            }
+        }
+        Some(serde_json::json!({
+            "from": table.from,
+            "to": self.to,
+            "rate": table.rate,
+            "ratesUpdatedAt": self.rates_updated_at,
+        }))
+    }
+
+    /// Convert a flattened record (as from `flatten_record`), adding the conversion applied
+    pub fn convert_record(&self, record: &mut serde_json::Value) {
+        let serde_json::Value::Object(fields) = record else {
+            return;
+        };
+        let Some(table_id) = fields.get("tableId").and_then(|id| id.as_str()).map(str::to_string) else {
+            return;
+        };
+        if let Some(applied) = self.convert(&table_id, fields) {
+            fields.insert(CONVERSION_FIELD.to_string(), applied);
+        }
+    }
+
+    /// Convert an item in the nested `{id, data, ...}` shape, adding the conversion applied
+    pub fn convert_item(&self, table_id: &str, item: &mut serde_json::Value) {
+        let Some(serde_json::Value::Object(data)) = item.get_mut("data") else {
+            return;
+        };
+        if let Some(applied) = self.convert(table_id, data)
+            && let serde_json::Value::Object(item) = item
+        {
+            item.insert(CONVERSION_FIELD.to_string(), applied);
+        }
+    }
+}
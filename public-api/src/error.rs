@@ -0,0 +1,230 @@
+use serde::Serialize;
+use worker::*;
+
+use crate::utils;
+
+// ============================================================================
+// API ERRORS
+// ============================================================================
+
+/// Seconds clients should wait before retrying after a storage or upstream failure
+const RETRY_AFTER_UNAVAILABLE: u32 = 5;
+
+pub type ApiResult<T> = std::result::Result<T, ApiError>;
+
+/// Every error the public API can answer with; each has a stable machine-readable code
+#[derive(Debug)]
+pub enum ApiError {
+    Unauthorized,
+    TokenExpired,
+    RouteNotFound,
+    MethodNotAllowed,
+    RateLimited,
+    TableNotFound,
+    TableForbidden,
+    /// A table name that several of the token's tables share
+    TableAmbiguous(String),
+    TableTypeUnsupported,
+    ItemNotFound,
+    WebhookNotFound,
+    /// The token is bound to a different customer than the request names
+    CustomerMismatch,
+    /// The token may read the table but not do this with it (buy, ...)
+    OperationForbidden(&'static str),
+    /// A `where[...]` filter the API can't evaluate
+    InvalidFilter(String),
+    /// A missing or malformed query parameter
+    InvalidParameter(String),
+    /// `?currency=` can't be served: no exchange rates, or none for a table's currency
+    CurrencyUnavailable(String),
+    /// The TypeScript API behind the service binding is missing or failed (cause is logged only)
+    UpstreamUnavailable(String),
+    /// D1 failure (cause is logged only, never rendered). KV has no variant: cache failures read as
+    /// misses and the request falls through to D1
+    Db(Error),
+    /// Anything else raised with `?` (cause is logged only, never rendered)
+    Internal(Error),
+}
+
+/// `application/problem+json` body (RFC 9457), keeping the legacy `success`/`error` fields
+#[derive(Debug, Serialize)]
+struct Problem<'a> {
+    #[serde(rename = "type")]
+    problem_type: &'a str,
+    title: &'a str,
+    status: u16,
+    detail: String,
+    code: &'a str,
+    #[serde(rename = "requestId")]
+    request_id: &'a str,
+    retryable: bool,
+    success: bool,
+    error: String,
+}
+
+impl ApiError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::Unauthorized => "UNAUTHORIZED",
+            ApiError::TokenExpired => "TOKEN_EXPIRED",
+            ApiError::RouteNotFound => "ROUTE_NOT_FOUND",
+            ApiError::MethodNotAllowed => "METHOD_NOT_ALLOWED",
+            ApiError::RateLimited => "RATE_LIMITED",
+            ApiError::TableNotFound => "TABLE_NOT_FOUND",
+            ApiError::TableForbidden => "TABLE_FORBIDDEN",
+            ApiError::TableAmbiguous(_) => "TABLE_AMBIGUOUS",
+            ApiError::TableTypeUnsupported => "TABLE_TYPE_UNSUPPORTED",
+            ApiError::ItemNotFound => "ITEM_NOT_FOUND",
+            ApiError::WebhookNotFound => "WEBHOOK_NOT_FOUND",
+            ApiError::CustomerMismatch => "CUSTOMER_MISMATCH",
+            ApiError::OperationForbidden(_) => "OPERATION_FORBIDDEN",
+            ApiError::InvalidFilter(_) => "INVALID_FILTER",
+            ApiError::InvalidParameter(_) => "INVALID_PARAMETER",
+            ApiError::CurrencyUnavailable(_) => "CURRENCY_UNAVAILABLE",
+            ApiError::UpstreamUnavailable(_) => "UPSTREAM_UNAVAILABLE",
+            ApiError::Db(_) => "DB_ERROR",
+            ApiError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        match self {
+            ApiError::Unauthorized | ApiError::TokenExpired => 401,
+            ApiError::TableForbidden
+            | ApiError::TableTypeUnsupported
+            | ApiError::CustomerMismatch
+            | ApiError::OperationForbidden(_) => 403,
+            ApiError::RouteNotFound | ApiError::TableNotFound | ApiError::ItemNotFound | ApiError::WebhookNotFound => 404,
+            ApiError::MethodNotAllowed => 405,
+            ApiError::TableAmbiguous(_) => 409,
+            ApiError::RateLimited => 429,
+            ApiError::InvalidFilter(_) | ApiError::InvalidParameter(_) => 400,
+            ApiError::CurrencyUnavailable(_)
+            | ApiError::UpstreamUnavailable(_)
+            | ApiError::Db(_) => 503,
+            ApiError::Internal(_) => 500,
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            ApiError::Unauthorized => "Unauthorized",
+            ApiError::TokenExpired => "Token expired",
+            ApiError::RouteNotFound => "Not found",
+            ApiError::MethodNotAllowed => "Method not allowed",
+            ApiError::RateLimited => "Rate limit exceeded",
+            ApiError::TableNotFound => "Table not found",
+            ApiError::TableForbidden => "Table not accessible",
+            ApiError::TableAmbiguous(_) => "Table name is ambiguous",
+            ApiError::TableTypeUnsupported => "Table type not supported",
+            ApiError::ItemNotFound => "Item not found",
+            ApiError::WebhookNotFound => "Webhook not found",
+            ApiError::CustomerMismatch => "Customer not allowed",
+            ApiError::OperationForbidden(_) => "Operation not allowed",
+            ApiError::InvalidFilter(_) => "Invalid filter",
+            ApiError::InvalidParameter(_) => "Invalid parameter",
+            ApiError::CurrencyUnavailable(_) => "Currency conversion unavailable",
+            ApiError::UpstreamUnavailable(_) => "Upstream unavailable",
+            ApiError::Db(_) => "Database unavailable",
+            ApiError::Internal(_) => "Internal server error",
+        }
+    }
+
+    /// Client-facing explanation; internal causes stay in the logs
+    fn detail(&self) -> String {
+        match self {
+            ApiError::Unauthorized => "A valid Bearer token is required".to_string(),
+            ApiError::TokenExpired => "This token has expired".to_string(),
+            ApiError::RouteNotFound => "No endpoint matches this path".to_string(),
+            ApiError::MethodNotAllowed => "This endpoint does not support the request method".to_string(),
+            ApiError::RateLimited => "Too many requests for this token, retry after the Retry-After delay".to_string(),
+            ApiError::TableNotFound => "Table not found".to_string(),
+            ApiError::TableForbidden => "Table is not accessible with this token".to_string(),
+            ApiError::TableAmbiguous(name) => {
+                format!("Several tables are named \"{}\"; use the table ID or slug instead", name)
+            }
+            ApiError::TableTypeUnsupported => "This endpoint only supports sale and rent tables".to_string(),
+            ApiError::ItemNotFound => "Item not found".to_string(),
+            ApiError::WebhookNotFound => "No webhook subscription with this ID for this token".to_string(),
+            ApiError::CustomerMismatch => "This token can only act for its own customer".to_string(),
+            ApiError::OperationForbidden(operation) => {
+                format!("This token's access to this table doesn't include {}", operation)
+            }
+            ApiError::InvalidFilter(detail) | ApiError::InvalidParameter(detail) | ApiError::CurrencyUnavailable(detail) => {
+                detail.clone()
+            }
+            ApiError::UpstreamUnavailable(_) => "The order service is temporarily unavailable".to_string(),
+            ApiError::Db(_) => "The database is temporarily unavailable".to_string(),
+            ApiError::Internal(_) => "An unexpected error occurred".to_string(),
+        }
+    }
+
+    /// Retry hint for transient failures
+    fn retry_after(&self) -> Option<u32> {
+        match self {
+            ApiError::UpstreamUnavailable(_) | ApiError::Db(_) => Some(RETRY_AFTER_UNAVAILABLE),
+            _ => None,
+        }
+    }
+
+    /// Render as `application/problem+json` headers and body, logging the internal cause of server errors
+    pub fn render(&self, request_id: &str) -> Result<(Vec<(&'static str, String)>, String)> {
+        match self {
+            ApiError::UpstreamUnavailable(cause) => {
+                utils::log_error(&format!("[{}] {}: {}", request_id, self.code(), cause))
+            }
+            ApiError::Db(e) | ApiError::Internal(e) => {
+                utils::log_error(&format!("[{}] {}: {:?}", request_id, self.code(), e))
+            }
+            _ => {}
+        }
+
+        let detail = self.detail();
+        let retry_after = self.retry_after();
+        let problem = Problem {
+            problem_type: "about:blank",
+            title: self.title(),
+            status: self.status(),
+            code: self.code(),
+            request_id,
+            retryable: retry_after.is_some() || matches!(self, ApiError::RateLimited),
+            success: false,
+            error: detail.clone(),
+            detail,
+        };
+
+        let mut headers = vec![
+            ("Content-Type", "application/problem+json".to_string()),
+            ("X-Worker", "rust".to_string()),
+        ];
+        if let Some(seconds) = retry_after {
+            headers.push(("Retry-After", seconds.to_string()));
+        }
+        Ok((headers, serde_json::to_string(&problem)?))
+    }
+
+    /// Render as an `application/problem+json` worker response
+    pub fn into_response(self, request_id: &str) -> Result<Response> {
+        let (extra, body) = self.render(request_id)?;
+        let headers = Headers::new();
+        for (name, value) in extra {
+            headers.set(name, &value)?;
+        }
+        Ok(Response::ok(body)?.with_status(self.status()).with_headers(headers))
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::D1(_) => ApiError::Db(e),
+            _ => ApiError::Internal(e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(e: serde_json::Error) -> Self {
+        ApiError::Internal(e.into())
+    }
+}
@@ -0,0 +1,240 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use worker::Result;
+
+use crate::error::{ApiError, ApiResult};
+use crate::masking::ColumnMasks;
+use crate::store::{Cache, TableStore};
+
+// ============================================================================
+// MODULE LABELS
+// ============================================================================
+//
+// Columns typed by a module (`@store/phone-numbers:provider`) store the codes of the
+// module's data source, e.g. "p_12". `?expand=labels` on item and records reads turns
+// each such value into `{value, label}`, with labels from the manifests of active
+// installedModules: a static source lists them itself, an API source's options are
+// read from the KV entry the TS DataSourceService caches them in. Codes without a
+// known label get `label: null`; the public API never calls module sources itself.
+
+/// KV prefix of module data source options, written by the TS DataSourceService
+const CACHE_PREFIX_MODULE_OPTIONS: &str = "module:api-cache:";
+
+/// A module-typed column of a table
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleColumn {
+    pub table_id: String,
+    pub name: String,
+    /// `<moduleId>:<columnTypeId>`
+    #[serde(rename = "type")]
+    pub column_type: String,
+}
+
+/// An active module's manifest JSON
+#[derive(Debug, Deserialize)]
+pub struct ModuleManifest {
+    pub id: String,
+    pub manifest: String,
+}
+
+/// The parts of a module manifest labels come from
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    #[serde(default)]
+    column_types: Vec<ManifestColumnType>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ManifestColumnType {
+    id: String,
+    #[serde(default)]
+    multi_value: bool,
+    source: Option<DataSource>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DataSource {
+    #[serde(rename = "type")]
+    source_type: String,
+    #[serde(default)]
+    values: Vec<StaticValue>,
+}
+
+/// A static source value: a bare code, or a code with its label
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum StaticValue {
+    Plain(String),
+    Labelled { value: String, label: String },
+}
+
+/// A cached API source: `{options: [{value, label}], cachedAt}`
+#[derive(Debug, Deserialize)]
+struct CachedOptions {
+    options: Vec<CachedOption>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CachedOption {
+    value: String,
+    label: String,
+}
+
+/// The labels of one module column type's codes
+#[derive(Debug, Default)]
+struct TypeLabels {
+    multi_value: bool,
+    labels: HashMap<String, String>,
+}
+
+/// Label lookups for the module-typed columns of the tables a request reads
+#[derive(Debug, Default)]
+pub struct Labels {
+    /// Table ID -> column -> column type
+    columns: HashMap<String, HashMap<String, String>>,
+    types: HashMap<String, TypeLabels>,
+}
+
+/// Whether the request asked for `expand=labels`; other expansions are unknown
+fn wants_labels(query: &HashMap<String, String>) -> ApiResult<bool> {
+    let Some(expand) = query.get("expand") else {
+        return Ok(false);
+    };
+    let mut wants = false;
+    for part in expand.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        match part {
+            "labels" => wants = true,
+            other => return Err(ApiError::InvalidParameter(format!("Unknown expand value: {}", other))),
+        }
+    }
+    Ok(wants)
+}
+
+impl Labels {
+    /// Labels for `table_ids` when the request has `expand=labels`. Columns the token may
+    /// not see in full are left out, so a label can't give away a masked code.
+    pub async fn from_query<S: TableStore, C: Cache>(
+        store: &S,
+        cache: &C,
+        query: &HashMap<String, String>,
+        table_ids: &[String],
+        masks: &ColumnMasks,
+    ) -> ApiResult<Option<Self>> {
+        if !wants_labels(query)? {
+            return Ok(None);
+        }
+        Ok(Some(Self::load(store, cache, table_ids, masks).await?))
+    }
+
+    async fn load<S: TableStore, C: Cache>(store: &S, cache: &C, table_ids: &[String], masks: &ColumnMasks) -> Result<Self> {
+        let mut columns: HashMap<String, HashMap<String, String>> = HashMap::new();
+        let mut module_ids: Vec<String> = vec![];
+        for column in store.module_columns(table_ids).await? {
+            let Some((module_id, _)) = column.column_type.split_once(':') else {
+                continue;
+            };
+            if masks.restricts(&column.table_id, &column.name) {
+                continue;
+            }
+            if !module_ids.iter().any(|id| id == module_id) {
+                module_ids.push(module_id.to_string());
+            }
+            columns.entry(column.table_id).or_default().insert(column.name, column.column_type);
+        }
+
+        let mut types: HashMap<String, TypeLabels> = HashMap::new();
+        for module in store.module_manifests(&module_ids).await? {
+            // A manifest that doesn't parse just has no labels, like an uninstalled module
+            let manifest: Manifest = serde_json::from_str(&module.manifest).unwrap_or_default();
+            for column_type in manifest.column_types {
+                let type_id = format!("{}:{}", module.id, column_type.id);
+                if !columns.values().any(|table| table.values().any(|t| *t == type_id)) {
+                    continue;
+                }
+                let labels = match column_type.source {
+                    Some(source) if source.source_type == "static" => source
+                        .values
+                        .into_iter()
+                        .map(|value| match value {
+                            StaticValue::Plain(value) => (value.clone(), value),
+                            StaticValue::Labelled { value, label } => (value, label),
+                        })
+                        .collect(),
+                    Some(source) if source.source_type == "api" => cache
+                        .get(&format!("{}{}", CACHE_PREFIX_MODULE_OPTIONS, type_id))
+                        .await
+                        .and_then(|json| serde_json::from_str::<CachedOptions>(&json).ok())
+                        .map(|cached| cached.options.into_iter().map(|o| (o.value, o.label)).collect())
+                        .unwrap_or_default(),
+                    _ => HashMap::new(),
+                };
+                types.insert(type_id, TypeLabels { multi_value: column_type.multi_value, labels });
+            }
+        }
+        Ok(Self { columns, types })
+    }
+
+    /// `{value, label}` for one code
+    fn label(labels: &TypeLabels, value: &serde_json::Value) -> serde_json::Value {
+        let code = match value {
+            serde_json::Value::String(s) => s.clone(),
+            serde_json::Value::Number(n) => n.to_string(),
+            _ => return value.clone(),
+        };
+        serde_json::json!({"value": value, "label": labels.labels.get(&code)})
+    }
+
+    /// A module column's value with its labels: one `{value, label}`, or a list of them for
+    /// multi-value types (stored as an array or a JSON array string). Empty values stay as they are.
+    fn expand_value(labels: &TypeLabels, value: &serde_json::Value) -> serde_json::Value {
+        if matches!(value, serde_json::Value::Null) || value.as_str().is_some_and(str::is_empty) {
+            return value.clone();
+        }
+        if !labels.multi_value {
+            return Self::label(labels, value);
+        }
+        let values = match value {
+            serde_json::Value::Array(values) => values.clone(),
+            serde_json::Value::String(s) => match serde_json::from_str::<serde_json::Value>(s) {
+                Ok(serde_json::Value::Array(values)) => values,
+                _ => vec![value.clone()],
+            },
+            _ => vec![value.clone()],
+        };
+        values.iter().map(|value| Self::label(labels, value)).collect()
+    }
+
+    /// Expand a table's module-typed fields in place
+    pub fn expand(&self, table_id: &str, fields: &mut serde_json::Map<String, serde_json::Value>) {
+        let Some(columns) = self.columns.get(table_id) else {
+            return;
+        };
+        // Types of inactive modules expand too, just without labels
+        let unknown = TypeLabels::default();
+        for (column, column_type) in columns {
+            if let Some(value) = fields.get_mut(column) {
+                *value = Self::expand_value(self.types.get(column_type).unwrap_or(&unknown), value);
+            }
+        }
+    }
+
+    /// Expand a flattened record (as from `flatten_record`)
+    pub fn expand_record(&self, record: &mut serde_json::Value) {
+        let serde_json::Value::Object(fields) = record else {
+            return;
+        };
+        if let Some(table_id) = fields.get("tableId").and_then(|id| id.as_str()).map(str::to_string) {
+            self.expand(&table_id, fields);
+        }
+    }
+
+    /// Expand an item in the nested `{id, data, ...}` shape
+    pub fn expand_item(&self, table_id: &str, item: &mut serde_json::Value) {
+        if let Some(serde_json::Value::Object(data)) = item.get_mut("data") {
+            self.expand(table_id, data);
+        }
+    }
+}
@@ -1,17 +1,64 @@
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
 use std::collections::HashMap;
 use wasm_bindgen::JsValue;
 use worker::*;
 
+mod cloudflare;
+mod cors;
+mod currency;
+mod error;
+mod labels;
+mod masking;
+#[cfg(any(test, feature = "native"))]
+pub mod native;
+mod orders;
+mod pricing;
+mod rate_limit;
+mod routes;
+mod scheduled;
+#[cfg(any(test, feature = "native"))]
+mod sqlite;
+mod store;
+mod tables;
+mod telemetry;
+mod usage;
 mod utils;
+mod webhooks;
+
+#[cfg(test)]
+mod tests;
+
+use cloudflare::{D1Sql, KvCache};
+use error::{ApiError, ApiResult};
+use rate_limit::{RateLimit, RateLimitScope};
+use routes::{Route, RouteKind, RouteParams, ROUTES};
+use currency::Conversion;
+use labels::Labels;
+use masking::ColumnMasks;
+use pricing::PriceRules;
+use scheduled::QueryHit;
+use store::{Cache, OrderStore, SqlStore, TableStore, TokenStore, UsageStore, WebhookStore};
+use tables::{allowed_table_ids, authorized_table, resolve_table};
+use telemetry::{RequestMetrics, REQUEST_ID_HEADER};
+use utils::Vars;
 
 // ============================================================================
 // CACHE KEYS AND CONSTANTS
 // ============================================================================
 
 const CACHE_KEY_PUBLIC_TABLES: &str = "public:tables:all";
-const CACHE_TTL_QUERY_RESULTS: u64 = 60; // 60 seconds for query results
-const CACHE_TTL_PUBLIC_TABLES: u64 = 300; // 5 minutes for public tables list
+const CACHE_KEY_DATA_GENERATION: &str = "generation:tables"; // Replaced with a random token by every write
+const CACHE_KEY_LOCK_PREFIX: &str = "lock:"; // Short-lived, best-effort rebuild locks for stampede protection
+
+// Soft TTL: entry is fresh. Between soft and hard TTL: served stale while one request refreshes it.
+// Query results are also invalidated on write through the data generation, so their TTLs are a safety net.
+const CACHE_SOFT_TTL_QUERY_RESULTS: u64 = 60; // 60 seconds for query results
+const CACHE_HARD_TTL_QUERY_RESULTS: u64 = 600; // 10 minutes
+const CACHE_SOFT_TTL_PUBLIC_TABLES: u64 = 300; // 5 minutes for public tables list
+const CACHE_HARD_TTL_PUBLIC_TABLES: u64 = 3600; // 1 hour
+const CACHE_LOCK_TTL: u64 = 60; // KV minimum expiration TTL
+const CACHE_WARM_QUERIES: u32 = 10; // Most requested records queries rebuilt by each scheduled run
 
 // ============================================================================
 // DATA STRUCTURES
@@ -29,27 +76,11 @@ struct TableRow {
     updated_at: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct TableRecord {
-    id: String,
-    #[serde(rename = "tableId")]
-    table_id: String,
-    #[serde(rename = "tableName")]
-    table_name: String,
-    #[serde(rename = "tableType")]
-    table_type: String,
-    #[serde(flatten)]
-    data: serde_json::Value,
-    #[serde(rename = "createdAt", skip_serializing_if = "Option::is_none")]
-    created_at: Option<String>,
-    #[serde(rename = "updatedAt", skip_serializing_if = "Option::is_none")]
-    updated_at: Option<String>,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 struct PublicTable {
     id: String,
     name: String,
+    slug: Option<String>,
     description: Option<String>,
     #[serde(rename = "tableType")]
     table_type: String,
@@ -57,28 +88,36 @@ struct PublicTable {
     row_count: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct TableColumn {
-    id: String,
-    name: String,
-    #[serde(rename = "columnType")]
-    column_type: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 struct TokenInfo {
     id: String,
+    /// Admin tokens read every public table
+    #[serde(rename = "isAdmin", default, deserialize_with = "utils::flag")]
+    is_admin: bool,
+    /// Table IDs, or each table's operations: {"<tableId>": ["read", "buy", "rent", "release"]}
     #[serde(rename = "tableAccess")]
     table_access: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct ApiResponse<T> {
-    success: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    data: Option<T>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String>,
+    /// The account that created the token (an email or `token:<id>`, like userTables.createdBy)
+    #[serde(rename = "createdBy", default)]
+    created_by: Option<String>,
+    #[serde(rename = "userId", default)]
+    user_id: Option<String>,
+    #[serde(rename = "rateLimits", default)]
+    rate_limits: Option<String>,
+    #[serde(rename = "allowedDomains", default)]
+    allowed_domains: Option<String>,
+    /// Expiry as epoch seconds (normalised by the token store)
+    #[serde(rename = "expiresAt", default)]
+    expires_at: Option<i64>,
+    /// The only customer this token may order for and read orders of
+    #[serde(rename = "customerId", default)]
+    customer_id: Option<String>,
+    /// Reseller markup on price/fee (JSON, see `pricing::PriceRules`)
+    #[serde(rename = "priceRules", default)]
+    price_rules: Option<String>,
+    /// Per-table column visibility overrides (JSON, see `masking::ColumnMasks`)
+    #[serde(rename = "columnRules", default)]
+    column_rules: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -122,10 +161,36 @@ struct ValuesResponse {
 
 #[derive(Debug, Serialize, Deserialize)]
 struct SearchResponse {
-    tables: Vec<PublicTable>,
+    tables: Vec<SearchTable>,
     count: usize,
     #[serde(rename = "searchedColumns")]
     searched_columns: Vec<String>,
+    #[serde(rename = "match", skip_serializing_if = "Option::is_none")]
+    match_conditions: Option<HashMap<String, String>>,
+}
+
+/// A table found by /tables/search, with its rows matching `match[...]` when given
+#[derive(Debug, Serialize, Deserialize)]
+struct SearchTable {
+    #[serde(flatten)]
+    table: PublicTable,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    matches: Option<TableMatches>,
+}
+
+/// A table's rows matching every `match[...]` condition
+#[derive(Debug, Serialize, Deserialize)]
+struct TableMatches {
+    #[serde(rename = "tableId", skip_serializing)]
+    table_id: String,
+    #[serde(rename = "matchingRows")]
+    matching_rows: i64,
+    /// Matching rows in stock: qty above 0 in sale tables, not used in rent tables
+    #[serde(rename = "availableRows")]
+    available_rows: i64,
+    /// Lowest numeric price among the available matching rows
+    #[serde(rename = "cheapestPrice")]
+    cheapest_price: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -160,6 +225,7 @@ struct QueryResultsCache {
 struct CachedPublicTable {
     id: String,
     name: String,
+    slug: Option<String>,
     description: Option<String>,
     #[serde(rename = "tableType")]
     table_type: String,
@@ -179,40 +245,151 @@ struct PublicTablesCache {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct CachedTokenInfo {
     id: String,
+    /// Required, so entries cached before the flag was read are reloaded
+    #[serde(rename = "isAdmin")]
+    is_admin: bool,
     #[serde(rename = "tableAccess")]
     table_access: Option<String>,
+    #[serde(rename = "createdBy", default)]
+    created_by: Option<String>,
+    #[serde(rename = "userId", default)]
+    user_id: Option<String>,
+    #[serde(rename = "rateLimits", default)]
+    rate_limits: Option<String>,
+    #[serde(rename = "allowedDomains", default)]
+    allowed_domains: Option<String>,
+    #[serde(rename = "expiresAt", default)]
+    expires_at: Option<i64>,
+    #[serde(rename = "customerId", default)]
+    customer_id: Option<String>,
+    #[serde(rename = "priceRules", default)]
+    price_rules: Option<String>,
+    #[serde(rename = "columnRules", default)]
+    column_rules: Option<String>,
     #[serde(rename = "cachedAt")]
     cached_at: u64,
 }
 
+/// Cache TTLs in seconds, overridable through wrangler vars
+#[derive(Debug, Clone, Copy)]
+struct CacheConfig {
+    public_tables_soft_ttl: u64,
+    public_tables_hard_ttl: u64,
+    query_soft_ttl: u64,
+    query_hard_ttl: u64,
+    /// Records queries pre-warmed per scheduled run (0 disables)
+    warm_queries: u32,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            public_tables_soft_ttl: CACHE_SOFT_TTL_PUBLIC_TABLES,
+            public_tables_hard_ttl: CACHE_HARD_TTL_PUBLIC_TABLES,
+            query_soft_ttl: CACHE_SOFT_TTL_QUERY_RESULTS,
+            query_hard_ttl: CACHE_HARD_TTL_QUERY_RESULTS,
+            warm_queries: CACHE_WARM_QUERIES,
+        }
+    }
+}
+
+impl CacheConfig {
+    fn from_env(vars: &impl Vars) -> Self {
+        let var = |name: &str, default: u64| utils::var_or(vars, name, default);
+        // KV rejects expiration TTLs below 60 seconds
+        let public_tables_soft_ttl = var("CACHE_PUBLIC_TABLES_SOFT_TTL", CACHE_SOFT_TTL_PUBLIC_TABLES);
+        let query_soft_ttl = var("CACHE_QUERY_SOFT_TTL", CACHE_SOFT_TTL_QUERY_RESULTS);
+        Self {
+            public_tables_soft_ttl,
+            public_tables_hard_ttl: var("CACHE_PUBLIC_TABLES_HARD_TTL", CACHE_HARD_TTL_PUBLIC_TABLES)
+                .max(public_tables_soft_ttl)
+                .max(60),
+            query_soft_ttl,
+            query_hard_ttl: var("CACHE_QUERY_HARD_TTL", CACHE_HARD_TTL_QUERY_RESULTS)
+                .max(query_soft_ttl)
+                .max(60),
+            warm_queries: utils::var_or(vars, "CACHE_WARM_QUERIES", CACHE_WARM_QUERIES),
+        }
+    }
+}
+
+/// Outcome of a stale-while-revalidate cache lookup
+enum CacheLookup<T> {
+    /// Younger than the soft TTL - serve as is
+    Fresh(T),
+    /// Past the soft TTL but within the hard TTL - serve and refresh in background
+    Stale(T),
+    Miss,
+}
+
+impl<T> CacheLookup<T> {
+    fn from_age(value: T, cached_at: u64, soft_ttl: u64, hard_ttl: u64) -> Self {
+        let age = current_timestamp().saturating_sub(cached_at);
+        if age < soft_ttl {
+            CacheLookup::Fresh(value)
+        } else if age < hard_ttl {
+            CacheLookup::Stale(value)
+        } else {
+            CacheLookup::Miss
+        }
+    }
+}
+
+/// Accessible table info used to label flattened records
+#[derive(Debug, Deserialize, Clone)]
+struct QueryTable {
+    id: String,
+    name: String,
+    #[serde(rename = "tableType")]
+    table_type: String,
+}
+
+/// A distinct value of a data column in one table
+#[derive(Debug, Deserialize)]
+struct TableValue {
+    #[serde(rename = "tableId")]
+    table_id: String,
+    val: serde_json::Value,
+}
+
+/// A column of a table, for column presence checks
+#[derive(Debug, Deserialize)]
+struct ColumnName {
+    #[serde(rename = "tableId")]
+    table_id: String,
+    name: String,
+}
+
 // ============================================================================
 // HELPERS
 // ============================================================================
 
-fn cors_headers() -> Headers {
+/// Headers for JSON responses; CORS headers are added per request in `main`
+fn json_headers() -> Headers {
     let headers = Headers::new();
-    let _ = headers.set("Access-Control-Allow-Origin", "*");
-    let _ = headers.set("Access-Control-Allow-Methods", "GET, POST, OPTIONS");
-    let _ = headers.set("Access-Control-Allow-Headers", "Content-Type, Authorization");
     let _ = headers.set("Content-Type", "application/json");
     let _ = headers.set("X-Worker", "rust");
     headers
 }
 
-fn json_response<T: Serialize>(data: T, status: u16) -> Result<Response> {
+fn json_response<T: Serialize>(data: T, status: u16) -> ApiResult<Response> {
     let body = serde_json::to_string(&data)?;
     let mut response = Response::ok(body)?;
-    *response.headers_mut() = cors_headers();
+    *response.headers_mut() = json_headers();
     Ok(response.with_status(status))
 }
 
-fn error_response(message: &str, status: u16) -> Result<Response> {
-    let response = ApiResponse::<()> {
-        success: false,
-        data: None,
-        error: Some(message.to_string()),
-    };
-    json_response(response, status)
+/// Copy a response's headers into a fresh mutable set with extra headers added
+/// (headers of responses proxied through the service binding are immutable)
+fn with_extra_headers(response: Response, extra: &[(&str, String)]) -> Response {
+    let headers = Headers::new();
+    for (name, value) in response.headers().entries() {
+        let _ = headers.set(&name, &value);
+    }
+    for (name, value) in extra {
+        let _ = headers.set(name, value);
+    }
+    response.with_headers(headers)
 }
 
 /// Flatten a data JSON string into a Value with fields at top level
@@ -257,21 +434,35 @@ fn parse_query_params(url: &Url) -> HashMap<String, String> {
         .collect()
 }
 
+/// Column names end up inside a json_extract path, so only plain names are accepted
+fn is_valid_column_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 128
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | ' '))
+}
+
 /// Extract where conditions from query params (where[col]=value format)
-fn extract_where_conditions(query: &HashMap<String, String>) -> HashMap<String, String> {
+fn extract_where_conditions(query: &HashMap<String, String>) -> ApiResult<HashMap<String, String>> {
+    extract_conditions(query, "where")
+}
+
+/// Column conditions from `<param>[column]=value` query parameters
+fn extract_conditions(query: &HashMap<String, String>, param: &str) -> ApiResult<HashMap<String, String>> {
     let mut conditions = HashMap::new();
     for (key, value) in query {
-        if let Some(col) = key.strip_prefix("where[").and_then(|s| s.strip_suffix("]")) {
+        if let Some(col) = key.strip_prefix(param).and_then(|s| s.strip_prefix('[')).and_then(|s| s.strip_suffix(']')) {
+            if !is_valid_column_name(col) {
+                return Err(ApiError::InvalidFilter(format!("Invalid filter column: {}", col)));
+            }
             conditions.insert(col.to_string(), value.clone());
         }
     }
-    conditions
+    Ok(conditions)
 }
 
-/// Get current timestamp in seconds (WASM-compatible using js_sys::Date)
+/// Get current timestamp in seconds
 fn current_timestamp() -> u64 {
-    // js_sys::Date::now() returns milliseconds since UNIX epoch
-    (js_sys::Date::now() / 1000.0) as u64
+    (utils::now_ms() / 1000.0) as u64
 }
 
 /// DJB2 hash function for generating short cache keys (matching TypeScript implementation)
@@ -292,52 +483,58 @@ fn short_hash(s: &str) -> String {
 // CACHE HELPERS
 // ============================================================================
 
-/// Get token from KV cache
-async fn cache_get_token(kv: &kv::KvStore, token_string: &str) -> Option<CachedTokenInfo> {
+/// Get token from cache
+async fn cache_get_token<C: Cache>(cache: &C, token_string: &str) -> Option<CachedTokenInfo> {
     let cache_key = format!("auth:token:{}", token_string);
-    match kv.get(&cache_key).json::<CachedTokenInfo>().await {
-        Ok(Some(cached)) => Some(cached),
-        _ => None,
-    }
+    cache.get(&cache_key).await.and_then(|json| serde_json::from_str(&json).ok())
 }
 
-/// Store token in KV cache (no TTL - tokens are invalidated explicitly)
-async fn cache_set_token(kv: &kv::KvStore, token_string: &str, token_info: &TokenInfo) {
+/// Store token in cache (no TTL - tokens are invalidated explicitly)
+async fn cache_set_token<C: Cache>(cache: &C, token_string: &str, token_info: &TokenInfo) {
     let cache_key = format!("auth:token:{}", token_string);
     let cached = CachedTokenInfo {
         id: token_info.id.clone(),
+        is_admin: token_info.is_admin,
         table_access: token_info.table_access.clone(),
+        created_by: token_info.created_by.clone(),
+        user_id: token_info.user_id.clone(),
+        rate_limits: token_info.rate_limits.clone(),
+        allowed_domains: token_info.allowed_domains.clone(),
+        expires_at: token_info.expires_at,
+        customer_id: token_info.customer_id.clone(),
+        price_rules: token_info.price_rules.clone(),
+        column_rules: token_info.column_rules.clone(),
         cached_at: current_timestamp(),
     };
     if let Ok(json) = serde_json::to_string(&cached) {
         // No expiration - tokens are cached indefinitely until invalidated
-        if let Ok(builder) = kv.put(&cache_key, json) {
-            let _ = builder.execute().await;
-        }
+        cache.put(&cache_key, json, None).await;
     }
 }
 
-/// Get public tables list from KV cache
-async fn cache_get_public_tables(kv: &kv::KvStore) -> Option<Vec<CachedPublicTable>> {
-    match kv.get(CACHE_KEY_PUBLIC_TABLES).json::<PublicTablesCache>().await {
-        Ok(Some(cached)) => {
-            // Check TTL
-            let now = current_timestamp();
-            if now - cached.cached_at < CACHE_TTL_PUBLIC_TABLES {
-                Some(cached.tables)
-            } else {
-                None // Expired
-            }
-        }
-        _ => None,
+/// Get public tables list from cache
+async fn cache_get_public_tables<C: Cache>(cache: &C, config: &CacheConfig) -> CacheLookup<Vec<CachedPublicTable>> {
+    let cached = cache
+        .get(CACHE_KEY_PUBLIC_TABLES)
+        .await
+        .and_then(|json| serde_json::from_str::<PublicTablesCache>(&json).ok());
+    match cached {
+        Some(cached) => CacheLookup::from_age(
+            cached.tables,
+            cached.cached_at,
+            config.public_tables_soft_ttl,
+            config.public_tables_hard_ttl,
+        ),
+        None => CacheLookup::Miss,
     }
 }
 
-/// Store public tables list in KV cache
-async fn cache_set_public_tables(kv: &kv::KvStore, tables: &[PublicTable]) {
+/// Store public tables list in cache (dropped after the hard TTL)
+async fn cache_set_public_tables<C: Cache>(cache: &C, tables: &[PublicTable], config: &CacheConfig) {
     let cached_tables: Vec<CachedPublicTable> = tables.iter().map(|t| CachedPublicTable {
         id: t.id.clone(),
         name: t.name.clone(),
+        slug: t.slug.clone(),
         description: t.description.clone(),
         table_type: t.table_type.clone(),
         row_count: t.row_count,
@@ -349,25 +546,52 @@ async fn cache_set_public_tables(kv: &kv::KvStore, tables: &[PublicTable]) {
     };
 
     if let Ok(json) = serde_json::to_string(&cached) {
-        if let Ok(builder) = kv.put(CACHE_KEY_PUBLIC_TABLES, json) {
-            let _ = builder
-                .expiration_ttl(CACHE_TTL_PUBLIC_TABLES)
-                .execute()
-                .await;
-        }
+        cache.put(CACHE_KEY_PUBLIC_TABLES, json, Some(config.public_tables_hard_ttl)).await;
     }
 }
 
-/// Get query results from KV cache
-async fn cache_get_query_results(
-    kv: &kv::KvStore,
+/// Try to take the rebuild lock for a cache key. Best-effort only: KV has no compare-and-set
+/// and is eventually consistent, so a few requests may all take it and rebuild the same entry
+/// (harmless, they write the same results) - it just keeps most of them from doing so
+async fn cache_try_lock<C: Cache>(cache: &C, cache_key: &str) -> bool {
+    let lock_key = format!("{}{}", CACHE_KEY_LOCK_PREFIX, cache_key);
+    if cache.get(&lock_key).await.is_some() {
+        return false;
+    }
+    cache.put(&lock_key, current_timestamp().to_string(), Some(CACHE_LOCK_TTL)).await;
+    true
+}
+
+/// Release the rebuild lock for a cache key
+async fn cache_unlock<C: Cache>(cache: &C, cache_key: &str) {
+    let lock_key = format!("{}{}", CACHE_KEY_LOCK_PREFIX, cache_key);
+    cache.delete(&lock_key).await;
+}
+
+/// Get the current data generation, one KV read per query (missing key = "0")
+async fn cache_get_generation<C: Cache>(cache: &C) -> String {
+    cache.get(CACHE_KEY_DATA_GENERATION).await.unwrap_or_else(|| "0".to_string())
+}
+
+/// Invalidate cached query results after a write, as the TS API does. The generation is a
+/// fresh random token rather than read + 1, so concurrent writers can't both write the same
+/// value and leave results cached before either write reachable.
+async fn cache_bump_generation<C: Cache>(cache: &C) {
+    cache.put(CACHE_KEY_DATA_GENERATION, utils::random_hex(8), None).await;
+}
+
+/// Build query results cache key from query params and the data generation. Nothing about the
+/// token or `?currency=` goes in: conversion, markup and column masking are applied to results
+/// on the way out, so one cached entry serves every token and currency.
+fn query_cache_key(
     table_ids: &[String],
+    generation: &str,
     where_conditions: &HashMap<String, String>,
     limit: u32,
     offset: u32,
-) -> Option<QueryResultsCache> {
-    // Generate cache key from query params (matching TypeScript pattern)
-    let table_hash = short_hash(&table_ids.join(","));
+) -> String {
+    // The generation changes on every write, so a write to any table changes the key
+    let table_hash = short_hash(&format!("{}@{}", table_ids.join(","), generation));
     let where_hash = if where_conditions.is_empty() {
         "none".to_string()
     } else {
@@ -379,46 +603,36 @@ async fn cache_get_query_results(
         short_hash(&where_parts.join("&"))
     };
 
-    let cache_key = format!("query:{}:{}:{}:{}", table_hash, where_hash, limit, offset);
+    format!("query:{}:{}:{}:{}", table_hash, where_hash, limit, offset)
+}
 
-    match kv.get(&cache_key).json::<QueryResultsCache>().await {
-        Ok(Some(cached)) => {
-            // Check TTL
-            let now = current_timestamp();
-            if now - cached.cached_at < CACHE_TTL_QUERY_RESULTS {
-                Some(cached)
-            } else {
-                None // Expired
-            }
+/// Get query results from cache
+async fn cache_get_query_results<C: Cache>(
+    cache: &C,
+    cache_key: &str,
+    config: &CacheConfig,
+) -> CacheLookup<QueryResultsCache> {
+    let cached = cache
+        .get(cache_key)
+        .await
+        .and_then(|json| serde_json::from_str::<QueryResultsCache>(&json).ok());
+    match cached {
+        Some(cached) => {
+            let cached_at = cached.cached_at;
+            CacheLookup::from_age(cached, cached_at, config.query_soft_ttl, config.query_hard_ttl)
         }
-        _ => None,
+        None => CacheLookup::Miss,
     }
 }
 
-/// Store query results in KV cache
-async fn cache_set_query_results(
-    kv: &kv::KvStore,
-    table_ids: &[String],
-    where_conditions: &HashMap<String, String>,
-    limit: u32,
-    offset: u32,
+/// Store query results in cache (dropped after the hard TTL)
+async fn cache_set_query_results<C: Cache>(
+    cache: &C,
+    cache_key: &str,
     records: &[serde_json::Value],
     total: i64,
+    config: &CacheConfig,
 ) {
-    let table_hash = short_hash(&table_ids.join(","));
-    let where_hash = if where_conditions.is_empty() {
-        "none".to_string()
-    } else {
-        let mut where_parts: Vec<String> = where_conditions
-            .iter()
-            .map(|(k, v)| format!("{}={}", k, v))
-            .collect();
-        where_parts.sort();
-        short_hash(&where_parts.join("&"))
-    };
-
-    let cache_key = format!("query:{}:{}:{}:{}", table_hash, where_hash, limit, offset);
-
     let cached = QueryResultsCache {
         records: records.to_vec(),
         total,
@@ -426,12 +640,7 @@ async fn cache_set_query_results(
     };
 
     if let Ok(json) = serde_json::to_string(&cached) {
-        if let Ok(builder) = kv.put(&cache_key, json) {
-            let _ = builder
-                .expiration_ttl(CACHE_TTL_QUERY_RESULTS)
-                .execute()
-                .await;
-        }
+        cache.put(cache_key, json, Some(config.query_hard_ttl)).await;
     }
 }
 
@@ -439,14 +648,29 @@ async fn cache_set_query_results(
 // PROXY HELPER
 // ============================================================================
 
-/// Proxy a request to the TypeScript API worker via service binding
-async fn proxy_to_api(mut req: Request, env: &Env) -> Result<Response> {
+/// Check and rewrite a proxied write's body for the token: its rights on the table, its bound
+/// customer, then its price rules. Returns the body to forward instead, if it changed.
+async fn prepare_write<S: TableStore + OrderStore, C: Cache>(
+    store: &S,
+    cache: &C,
+    token: &TokenInfo,
+    kind: RouteKind,
+    body: &str,
+) -> ApiResult<Option<String>> {
+    tables::check_write(store, cache, token, kind, body).await?;
+    let bound = orders::bind_write(store, token, kind, body).await?;
+    let quoted = pricing::quote_write(store, token, kind, bound.as_deref().unwrap_or(body)).await?;
+    Ok(quoted.or(bound))
+}
+
+/// Proxy a request to the TypeScript API worker via service binding, with the body already read
+/// (and possibly rewritten by `prepare_write`)
+async fn proxy_to_api(req: &Request, body_text: &str, env: &Env, metrics: &RequestMetrics) -> ApiResult<Response> {
     // Get service binding
     let api = match env.service("API") {
         Ok(s) => s,
         Err(e) => {
-            console_error!("Service binding error: {:?}", e);
-            return error_response("Service binding not available", 503);
+            return Err(ApiError::UpstreamUnavailable(format!("Service binding error: {:?}", e)));
         }
     };
 
@@ -454,9 +678,9 @@ async fn proxy_to_api(mut req: Request, env: &Env) -> Result<Response> {
     let url = req.url()?;
     let method = req.method();
     let headers = req.headers().clone();
-
-    // For POST requests, get the body as text (JSON)
-    let body_text = req.text().await?;
+    let _ = headers.set(REQUEST_ID_HEADER, &metrics.request_id);
+    // The body may have changed length
+    let _ = headers.delete("Content-Length");
 
     // Create a new request with the same properties
     let mut init = RequestInit::new();
@@ -465,7 +689,7 @@ async fn proxy_to_api(mut req: Request, env: &Env) -> Result<Response> {
 
     // Set body if present
     if !body_text.is_empty() {
-        init.with_body(Some(JsValue::from_str(&body_text)));
+        init.with_body(Some(JsValue::from_str(body_text)));
     }
 
     let proxy_req = Request::new_with_init(url.as_str(), &init)?;
@@ -473,10 +697,7 @@ async fn proxy_to_api(mut req: Request, env: &Env) -> Result<Response> {
     // Forward to TypeScript API
     match api.fetch_request(proxy_req).await {
         Ok(response) => Ok(response),
-        Err(e) => {
-            console_error!("Proxy fetch error: {:?}", e);
-            error_response(&format!("Proxy error: {:?}", e), 502)
-        }
+        Err(e) => Err(ApiError::UpstreamUnavailable(format!("Proxy fetch error: {:?}", e))),
     }
 }
 
@@ -484,60 +705,174 @@ async fn proxy_to_api(mut req: Request, env: &Env) -> Result<Response> {
 // AUTH
 // ============================================================================
 
-async fn validate_token(req: &Request, env: &Env) -> Result<Option<TokenInfo>> {
-    let auth_header = req.headers().get("Authorization")?;
+/// Bearer token from the Authorization header
+fn bearer_token(auth_header: Option<&str>) -> ApiResult<String> {
+    auth_header
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::to_string)
+        .ok_or(ApiError::Unauthorized)
+}
 
-    let token_string = match auth_header {
-        Some(header) => {
-            if header.starts_with("Bearer ") {
-                header[7..].to_string()
-            } else {
-                return Ok(None);
+/// Resolve a bearer token through the cache, falling back to the token store
+async fn authenticate<S: TokenStore, C: Cache>(
+    storage: &Storage<S, C>,
+    token_string: &str,
+    metrics: &RequestMetrics,
+) -> ApiResult<TokenInfo> {
+    // Check cache first
+    let cached = cache_get_token(&storage.cache, token_string).await;
+    metrics.cache_lookup(cached.is_some());
+    let token_info = match cached {
+        Some(cached) => TokenInfo {
+            id: cached.id,
+            is_admin: cached.is_admin,
+            table_access: cached.table_access,
+            created_by: cached.created_by,
+            user_id: cached.user_id,
+            rate_limits: cached.rate_limits,
+            allowed_domains: cached.allowed_domains,
+            expires_at: cached.expires_at,
+            customer_id: cached.customer_id,
+            price_rules: cached.price_rules,
+            column_rules: cached.column_rules,
+        },
+        None => {
+            // Cache miss - check the database for the token
+            let token_info = storage.store.find_token(token_string).await?.ok_or(ApiError::Unauthorized)?;
+
+            // Cache live tokens; the expiry travels with the cached entry
+            if !token_expired(&token_info) {
+                cache_set_token(&storage.cache, token_string, &token_info).await;
             }
+            token_info
         }
-        None => return Ok(None),
     };
 
-    // Check KV cache first
-    let kv = env.kv("KV")?;
-    if let Some(cached) = cache_get_token(&kv, &token_string).await {
-        return Ok(Some(TokenInfo {
-            id: cached.id,
-            table_access: cached.table_access,
-        }));
+    if token_expired(&token_info) {
+        return Err(ApiError::TokenExpired);
     }
+    Ok(token_info)
+}
 
-    // Cache miss - check D1 database for token
-    let db = env.d1("DB")?;
-    let stmt = db.prepare(
-        "SELECT id, tableAccess FROM tokens WHERE token = ? AND (expiresAt IS NULL OR expiresAt = 'null' OR expiresAt > datetime('now'))"
-    );
-    let result = stmt.bind(&[token_string.clone().into()])?.first::<TokenInfo>(None).await?;
+/// Whether a token's expiry (epoch seconds) has passed
+fn token_expired(token: &TokenInfo) -> bool {
+    token.expires_at.is_some_and(|expires_at| expires_at <= current_timestamp() as i64)
+}
 
-    // Cache valid tokens
-    if let Some(ref token_info) = result {
-        cache_set_token(&kv, &token_string, token_info).await;
+// ============================================================================
+// STORAGE, LOADERS AND BACKGROUND REFRESH
+// ============================================================================
+
+/// Everything a request reads from: the stores, the cache and its TTLs
+/// Cache rebuilds found necessary while serving, and cacheable queries to count for
+/// warming, are queued here and handled after the response
+struct Storage<S, C> {
+    store: S,
+    cache: C,
+    config: CacheConfig,
+    refreshes: RefCell<Vec<Refresh>>,
+    query_hits: RefCell<Vec<QueryHit>>,
+}
+
+impl<S, C> Storage<S, C> {
+    fn new(store: S, cache: C, config: CacheConfig) -> Self {
+        Self { store, cache, config, refreshes: RefCell::new(vec![]), query_hits: RefCell::new(vec![]) }
     }
 
-    Ok(result)
+    fn queue_query_hit(&self, hit: QueryHit) {
+        self.query_hits.borrow_mut().push(hit);
+    }
+
+    fn take_query_hits(&self) -> Vec<QueryHit> {
+        self.query_hits.take()
+    }
+
+    fn queue_refresh(&self, refresh: Refresh) {
+        self.refreshes.borrow_mut().push(refresh);
+    }
+
+    fn take_refreshes(&self) -> Vec<Refresh> {
+        self.refreshes.take()
+    }
+}
+
+/// One page of a cross-table records query
+#[derive(Debug, Clone)]
+struct RecordsQuery {
+    table_ids: Vec<String>,
+    table_map: HashMap<String, QueryTable>,
+    where_conditions: HashMap<String, String>,
+    limit: u32,
+    offset: u32,
+}
+
+/// A stale cache entry to rebuild in the background (the caller already holds its lock)
+#[derive(Debug)]
+enum Refresh {
+    PublicTables,
+    QueryResults { cache_key: String, query: RecordsQuery },
 }
 
-/// Get allowed table IDs from token
-fn get_allowed_table_ids(token: &TokenInfo) -> Option<Vec<String>> {
-    // Admin and frontend tokens have unrestricted access
-    if token.id == "admin-token" || token.id == "frontend-token" {
-        return None; // null means unrestricted
+impl Refresh {
+    fn cache_key(&self) -> &str {
+        match self {
+            Refresh::PublicTables => CACHE_KEY_PUBLIC_TABLES,
+            Refresh::QueryResults { cache_key, .. } => cache_key,
+        }
     }
+}
+
+/// Load one page of flattened records across tables, plus the total match count
+async fn load_records<S: TableStore>(store: &S, query: &RecordsQuery) -> Result<(Vec<serde_json::Value>, i64)> {
+    let (rows, total) = store
+        .find_rows(&query.table_ids, &query.where_conditions, query.limit, query.offset)
+        .await?;
+
+    // Flatten records
+    let records: Vec<serde_json::Value> = rows.iter().map(|row| {
+        let table_info = query.table_map.get(&row.table_id);
+        let (name, ttype) = table_info.map(|t| (t.name.as_str(), t.table_type.as_str())).unwrap_or(("Unknown", "unknown"));
+        flatten_record(
+            &row.id, &row.table_id, name, ttype,
+            &row.data, row.created_at.as_deref(), row.updated_at.as_deref()
+        )
+    }).collect();
 
-    // Parse tableAccess JSON array
-    if let Some(ref access_str) = token.table_access {
-        if let Ok(ids) = serde_json::from_str::<Vec<String>>(access_str) {
-            return Some(ids);
+    Ok((records, total))
+}
+
+/// Rebuild a cache entry and release its lock
+async fn run_refresh<S: TableStore, C: Cache>(storage: &Storage<S, C>, refresh: &Refresh) -> Result<()> {
+    match refresh {
+        Refresh::PublicTables => {
+            let tables = storage.store.list_tables(None).await?;
+            cache_set_public_tables(&storage.cache, &tables, &storage.config).await;
+        }
+        Refresh::QueryResults { cache_key, query } => {
+            let (records, total) = load_records(&storage.store, query).await?;
+            cache_set_query_results(&storage.cache, cache_key, &records, total, &storage.config).await;
         }
     }
+    cache_unlock(&storage.cache, refresh.cache_key()).await;
+    Ok(())
+}
+
+/// Run a queued refresh against D1/KV after the response (via wait_until)
+async fn refresh_in_background(env: Env, request_id: String, refresh: Refresh) {
+    let metrics = RequestMetrics::new(request_id);
+    let result = async {
+        let storage = Storage::new(
+            SqlStore::new(D1Sql::new(&env, &metrics)?),
+            KvCache::new(&env, &metrics)?,
+            CacheConfig::from_env(&env),
+        );
+        run_refresh(&storage, &refresh).await
+    }.await;
 
-    // No access defined - empty array
-    Some(vec![])
+    if let Err(ref e) = result {
+        utils::log_error(&format!("Cache refresh failed for {}: {:?}", refresh.cache_key(), e));
+    }
+    metrics.log("REFRESH", None, refresh.cache_key(), None, if result.is_ok() { 200 } else { 500 });
 }
 
 // ============================================================================
@@ -545,183 +880,137 @@ fn get_allowed_table_ids(token: &TokenInfo) -> Option<Vec<String>> {
 // ============================================================================
 
 /// GET /api/public/tables - List all accessible public tables
-async fn get_tables(env: &Env, token: &TokenInfo) -> Result<Response> {
-    let db = env.d1("DB")?;
-    let kv = env.kv("KV")?;
-    let allowed = get_allowed_table_ids(token);
+async fn get_tables<S: TableStore, C: Cache>(storage: &Storage<S, C>, token: &TokenInfo, metrics: &RequestMetrics) -> ApiResult<TablesResponse> {
+    let allowed = allowed_table_ids(&storage.store, &storage.cache, token).await?;
 
     let tables: Vec<PublicTable> = if let Some(ref ids) = allowed {
-        if ids.is_empty() {
-            vec![]
-        } else {
-            // Token has specific table access - no caching for restricted tokens
-            let placeholders = ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-            let sql = format!(
-                "SELECT ut.id, ut.name, ut.description, ut.tableType,
-                        (SELECT COUNT(*) FROM tableData WHERE tableId = ut.id) as rowCount
-                 FROM userTables ut
-                 WHERE ut.id IN ({})
-                   AND ut.tableType IN ('sale', 'rent')
-                 ORDER BY ut.name ASC",
-                placeholders
-            );
-            let mut stmt = db.prepare(&sql);
-            let bindings: Vec<JsValue> = ids.iter().map(|id| id.clone().into()).collect();
-            stmt = stmt.bind(&bindings)?;
-            stmt.all().await?.results()?
-        }
+        // Token has specific table access - no caching for restricted tokens
+        storage.store.list_tables(Some(ids)).await?
     } else {
-        // Unrestricted access - check KV cache first
-        if let Some(cached) = cache_get_public_tables(&kv).await {
+        // Unrestricted access - check cache first
+        let lookup = cache_get_public_tables(&storage.cache, &storage.config).await;
+        metrics.cache_lookup(!matches!(lookup, CacheLookup::Miss));
+        let cached = match lookup {
+            CacheLookup::Fresh(cached) => Some(cached),
+            CacheLookup::Stale(cached) => {
+                // Serve stale, let a single request rebuild in background
+                if cache_try_lock(&storage.cache, CACHE_KEY_PUBLIC_TABLES).await {
+                    storage.queue_refresh(Refresh::PublicTables);
+                }
+                Some(cached)
+            }
+            CacheLookup::Miss => None,
+        };
+
+        if let Some(cached) = cached {
             // Convert cached tables to PublicTable
-            return json_response(TablesResponse {
+            return Ok(TablesResponse {
                 count: cached.len(),
                 tables: cached.into_iter().map(|t| PublicTable {
                     id: t.id,
                     name: t.name,
+                    slug: t.slug,
                     description: t.description,
                     table_type: t.table_type,
                     row_count: t.row_count,
                 }).collect(),
-            }, 200);
+            });
         }
 
         // Cache miss - query database
-        let stmt = db.prepare(
-            "SELECT ut.id, ut.name, ut.description, ut.tableType,
-                    (SELECT COUNT(*) FROM tableData WHERE tableId = ut.id) as rowCount
-             FROM userTables ut
-             WHERE ut.visibility IN ('public', 'shared')
-               AND ut.tableType IN ('sale', 'rent')
-             ORDER BY ut.name ASC"
-        );
-        let result: Vec<PublicTable> = stmt.all().await?.results()?;
+        let result = storage.store.list_tables(None).await?;
 
         // Cache the results
-        cache_set_public_tables(&kv, &result).await;
+        cache_set_public_tables(&storage.cache, &result, &storage.config).await;
 
         result
     };
 
-    json_response(TablesResponse {
+    Ok(TablesResponse {
         count: tables.len(),
         tables,
-    }, 200)
+    })
 }
 
-/// GET /api/public/tables/search?columns=col1,col2 - Search tables by column presence
-async fn search_tables(env: &Env, token: &TokenInfo, query: &HashMap<String, String>) -> Result<Response> {
+/// GET /api/public/tables/search?columns=col1,col2&match[col]=value - Search tables by column
+/// presence and/or by rows matching every `match[...]` condition
+async fn search_tables<S: TableStore, C: Cache>(storage: &Storage<S, C>, token: &TokenInfo, query: &HashMap<String, String>) -> ApiResult<SearchResponse> {
     let columns_param = query.get("columns").map(|s| s.as_str()).unwrap_or("");
     let search_columns: Vec<&str> = columns_param.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()).collect();
+    let match_conditions = extract_conditions(query, "match")?;
 
-    if search_columns.is_empty() {
-        return error_response("columns parameter is required", 400);
+    if search_columns.is_empty() && match_conditions.is_empty() {
+        return Err(ApiError::InvalidParameter("columns or match[...] parameter is required".to_string()));
     }
 
-    let db = env.d1("DB")?;
-    let allowed = get_allowed_table_ids(token);
-
     // Get all accessible tables first
-    let all_tables: Vec<PublicTable> = if let Some(ref ids) = allowed {
-        if ids.is_empty() {
-            vec![]
-        } else {
-            let placeholders = ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-            let sql = format!(
-                "SELECT ut.id, ut.name, ut.description, ut.tableType,
-                        (SELECT COUNT(*) FROM tableData WHERE tableId = ut.id) as rowCount
-                 FROM userTables ut
-                 WHERE ut.id IN ({}) AND ut.tableType IN ('sale', 'rent')",
-                placeholders
-            );
-            let mut stmt = db.prepare(&sql);
-            let bindings: Vec<JsValue> = ids.iter().map(|id| id.clone().into()).collect();
-            stmt = stmt.bind(&bindings)?;
-            stmt.all().await?.results()?
-        }
+    let allowed = allowed_table_ids(&storage.store, &storage.cache, token).await?;
+    let all_tables = storage.store.list_tables(allowed.as_deref()).await?;
+    let table_ids: Vec<String> = all_tables.iter().map(|t| t.id.clone()).collect();
+    let masks = ColumnMasks::load(&storage.store, token, &table_ids).await?;
+    masks.check_filters(&match_conditions)?;
+
+    let columns = if search_columns.is_empty() { vec![] } else { storage.store.column_names(&table_ids).await? };
+    // Counts and prices for every table in one query; tables without a matching row are left out
+    let mut matches: HashMap<String, TableMatches> = if match_conditions.is_empty() {
+        HashMap::new()
     } else {
-        let stmt = db.prepare(
-            "SELECT ut.id, ut.name, ut.description, ut.tableType,
-                    (SELECT COUNT(*) FROM tableData WHERE tableId = ut.id) as rowCount
-             FROM userTables ut
-             WHERE ut.visibility IN ('public', 'shared') AND ut.tableType IN ('sale', 'rent')"
-        );
-        stmt.all().await?.results()?
+        storage.store.match_rows(&table_ids, &match_conditions).await?.into_iter().map(|m| (m.table_id.clone(), m)).collect()
     };
+    let price_rules = PriceRules::for_token(token);
+
+    // Keep tables that have ALL requested columns (hidden ones don't count) and matching rows
+    let matching_tables: Vec<SearchTable> = all_tables
+        .into_iter()
+        .filter(|table| {
+            search_columns.iter().all(|sc| {
+                columns.iter().any(|c| {
+                    c.table_id == table.id && c.name.to_lowercase() == sc.to_lowercase() && !masks.hides(&table.id, &c.name)
+                })
+            })
+        })
+        .filter_map(|table| {
+            if match_conditions.is_empty() {
+                return Some(SearchTable { table, matches: None });
+            }
+            let mut found = matches.remove(&table.id)?;
+            if masks.restricts(&table.id, "price") {
+                found.cheapest_price = None;
+            } else if let (Some(rules), Some(price)) = (&price_rules, found.cheapest_price) {
+                found.cheapest_price = rules.mark_up_value(&table.id, &serde_json::json!(price)).as_f64();
+            }
+            Some(SearchTable { table, matches: Some(found) })
+        })
+        .collect();
 
-    // Filter tables that have ALL requested columns
-    #[derive(Debug, Deserialize)]
-    struct ColumnName {
-        name: String,
-    }
-
-    let mut matching_tables = vec![];
-    for table in all_tables {
-        let col_stmt = db.prepare("SELECT name FROM tableColumns WHERE tableId = ?");
-        let cols: Vec<ColumnName> = col_stmt.bind(&[table.id.clone().into()])?.all().await?.results()?;
-        let col_names: Vec<String> = cols.iter().map(|c| c.name.to_lowercase()).collect();
-
-        let has_all = search_columns.iter().all(|sc| col_names.contains(&sc.to_lowercase()));
-        if has_all {
-            matching_tables.push(table);
-        }
-    }
-
-    json_response(SearchResponse {
+    Ok(SearchResponse {
         count: matching_tables.len(),
         tables: matching_tables,
         searched_columns: search_columns.iter().map(|s| s.to_string()).collect(),
-    }, 200)
+        match_conditions: (!match_conditions.is_empty()).then_some(match_conditions),
+    })
 }
 
 /// GET /api/public/tables/:tableId/items - Get items from a specific table
-async fn get_table_items(env: &Env, token: &TokenInfo, table_id: &str, query: &HashMap<String, String>) -> Result<Response> {
-    let db = env.d1("DB")?;
+async fn get_table_items<S: TableStore, C: Cache>(storage: &Storage<S, C>, token: &TokenInfo, table_id: &str, query: &HashMap<String, String>) -> ApiResult<ItemsResponse> {
     let flat_mode = query.get("flat").map(|s| s == "true").unwrap_or(false);
 
     // Get table info and verify access
-    let table_stmt = db.prepare(
-        "SELECT id, name, tableType, visibility FROM userTables WHERE id = ?"
-    );
+    let context = resolve_table(&storage.store, &storage.cache, token, table_id).await?;
+    let table = &context.table;
 
-    #[derive(Debug, Deserialize)]
-    struct TableInfo {
-        id: String,
-        name: String,
-        #[serde(rename = "tableType")]
-        table_type: String,
-        visibility: String,
-    }
-
-    let table: Option<TableInfo> = table_stmt.bind(&[table_id.into()])?.first(None).await?;
-
-    let table = match table {
-        Some(t) => t,
-        None => return error_response("Table not found", 404),
-    };
-
-    // Check access
-    let allowed = get_allowed_table_ids(token);
-    let has_access = match allowed {
-        None => table.visibility == "public" || table.visibility == "shared",
-        Some(ref ids) => ids.contains(&table.id),
-    };
-
-    if !has_access {
-        return error_response("Table is not accessible with this token", 403);
-    }
-
-    if table.table_type != "sale" && table.table_type != "rent" {
-        return error_response("This endpoint only supports sale and rent tables", 403);
+    if !table.is_catalog() {
+        return Err(ApiError::TableTypeUnsupported);
     }
 
     // Get items
-    let data_stmt = db.prepare(
-        "SELECT id, tableId, data, createdAt, updatedAt FROM tableData WHERE tableId = ? ORDER BY createdAt DESC"
-    );
-    let rows: Vec<TableRow> = data_stmt.bind(&[table_id.into()])?.all().await?.results()?;
+    let rows = storage.store.table_rows(&table.id).await?;
+    let conversion = Conversion::from_query(&storage.store, &storage.cache, query, std::slice::from_ref(&table.id)).await?;
+    let price_rules = PriceRules::for_token(token);
+    let masks = ColumnMasks::from_columns(token, context.column_visibility());
+    let labels = Labels::from_query(&storage.store, &storage.cache, query, std::slice::from_ref(&table.id), &masks).await?;
 
-    let items: Vec<serde_json::Value> = if flat_mode {
+    let mut items: Vec<serde_json::Value> = if flat_mode {
         rows.iter().map(|row| {
             flatten_record(
                 &row.id, &row.table_id, &table.name, &table.table_type,
@@ -739,115 +1028,85 @@ async fn get_table_items(env: &Env, token: &TokenInfo, table_id: &str, query: &H
             })
         }).collect()
     };
+    if let Some(ref rules) = price_rules {
+        for item in &mut items {
+            if flat_mode {
+                rules.mark_up_record(item);
+            } else {
+                rules.mark_up_item(&table.id, item);
+            }
+        }
+    }
+    if let Some(ref conversion) = conversion {
+        for item in &mut items {
+            if flat_mode {
+                conversion.convert_record(item);
+            } else {
+                conversion.convert_item(&table.id, item);
+            }
+        }
+    }
+    for item in &mut items {
+        if flat_mode {
+            masks.apply_record(item);
+        } else {
+            masks.apply_item(&table.id, item);
+        }
+    }
+    if let Some(ref labels) = labels {
+        for item in &mut items {
+            if flat_mode {
+                labels.expand_record(item);
+            } else {
+                labels.expand_item(&table.id, item);
+            }
+        }
+    }
 
-    json_response(ItemsResponse {
+    Ok(ItemsResponse {
         count: items.len(),
         items,
-        table_id: table.id,
-        table_name: table.name,
-        table_type: table.table_type,
-    }, 200)
+        table_id: context.table.id,
+        table_name: context.table.name,
+        table_type: context.table.table_type,
+    })
 }
 
 /// GET /api/public/tables/:tableId/items/:itemId - Get single item
-async fn get_table_item(env: &Env, token: &TokenInfo, table_id: &str, item_id: &str) -> Result<Response> {
-    let db = env.d1("DB")?;
-
+async fn get_table_item<S: TableStore, C: Cache>(storage: &Storage<S, C>, token: &TokenInfo, table_id: &str, item_id: &str, query: &HashMap<String, String>) -> ApiResult<serde_json::Value> {
     // Verify table access first
-    let table_stmt = db.prepare(
-        "SELECT id, name, tableType, visibility FROM userTables WHERE id = ?"
-    );
-
-    #[derive(Debug, Deserialize)]
-    struct TableInfo {
-        id: String,
-        name: String,
-        #[serde(rename = "tableType")]
-        table_type: String,
-        visibility: String,
-    }
-
-    let table: Option<TableInfo> = table_stmt.bind(&[table_id.into()])?.first(None).await?;
-    let table = match table {
-        Some(t) => t,
-        None => return error_response("Table not found", 404),
-    };
-
-    // Check access
-    let allowed = get_allowed_table_ids(token);
-    let has_access = match allowed {
-        None => table.visibility == "public" || table.visibility == "shared",
-        Some(ref ids) => ids.contains(&table.id),
-    };
-
-    if !has_access {
-        return error_response("Table is not accessible with this token", 403);
-    }
+    let context = resolve_table(&storage.store, &storage.cache, token, table_id).await?;
+    let table = &context.table;
 
     // Get item
-    let item_stmt = db.prepare(
-        "SELECT id, tableId, data, createdAt, updatedAt FROM tableData WHERE id = ? AND tableId = ?"
+    let row = storage.store.table_row(&table.id, item_id).await?.ok_or(ApiError::ItemNotFound)?;
+    let mut item = flatten_record(
+        &row.id, &row.table_id, &table.name, &table.table_type,
+        &row.data, row.created_at.as_deref(), row.updated_at.as_deref()
     );
-    let row: Option<TableRow> = item_stmt.bind(&[item_id.into(), table_id.into()])?.first(None).await?;
-
-    match row {
-        Some(row) => {
-            let item = flatten_record(
-                &row.id, &row.table_id, &table.name, &table.table_type,
-                &row.data, row.created_at.as_deref(), row.updated_at.as_deref()
-            );
-            json_response(item, 200)
-        }
-        None => error_response("Item not found", 404),
+    if let Some(rules) = PriceRules::for_token(token) {
+        rules.mark_up_record(&mut item);
+    }
+    if let Some(conversion) = Conversion::from_query(&storage.store, &storage.cache, query, std::slice::from_ref(&table.id)).await? {
+        conversion.convert_record(&mut item);
     }
+    let masks = ColumnMasks::from_columns(token, context.column_visibility());
+    masks.apply_record(&mut item);
+    if let Some(labels) = Labels::from_query(&storage.store, &storage.cache, query, std::slice::from_ref(&table.id), &masks).await? {
+        labels.expand_record(&mut item);
+    }
+    Ok(item)
 }
 
 /// GET /api/public/tables/:tableId/items/:itemId/availability - Check item availability
-async fn get_item_availability(env: &Env, token: &TokenInfo, table_id: &str, item_id: &str, query: &HashMap<String, String>) -> Result<Response> {
+async fn get_item_availability<S: TableStore, C: Cache>(storage: &Storage<S, C>, token: &TokenInfo, table_id: &str, item_id: &str, query: &HashMap<String, String>) -> ApiResult<AvailabilityResponse> {
     let quantity: u32 = query.get("quantity").and_then(|q| q.parse().ok()).unwrap_or(1);
-    let db = env.d1("DB")?;
 
     // Verify table access
-    let table_stmt = db.prepare("SELECT id, tableType, visibility FROM userTables WHERE id = ?");
-
-    #[derive(Debug, Deserialize)]
-    struct TableInfo {
-        id: String,
-        #[serde(rename = "tableType")]
-        table_type: String,
-        visibility: String,
-    }
-
-    let table: Option<TableInfo> = table_stmt.bind(&[table_id.into()])?.first(None).await?;
-    let table = match table {
-        Some(t) => t,
-        None => return error_response("Table not found", 404),
-    };
-
-    let allowed = get_allowed_table_ids(token);
-    let has_access = match allowed {
-        None => table.visibility == "public" || table.visibility == "shared",
-        Some(ref ids) => ids.contains(&table.id),
-    };
-
-    if !has_access {
-        return error_response("Table is not accessible with this token", 403);
-    }
+    let table = resolve_table(&storage.store, &storage.cache, token, table_id).await?.table;
 
     // Get item data
-    let item_stmt = db.prepare("SELECT data FROM tableData WHERE id = ? AND tableId = ?");
-
-    #[derive(Debug, Deserialize)]
-    struct ItemData {
-        data: String,
-    }
-
-    let item: Option<ItemData> = item_stmt.bind(&[item_id.into(), table_id.into()])?.first(None).await?;
-    let item = match item {
-        Some(i) => i,
-        None => return error_response("Item not found", 404),
-    };
-
+    let item = storage.store.table_row(&table.id, item_id).await?.ok_or(ApiError::ItemNotFound)?;
     let data: serde_json::Value = serde_json::from_str(&item.data).unwrap_or(serde_json::json!({}));
 
     // Calculate availability based on table type
@@ -859,128 +1118,139 @@ async fn get_item_availability(env: &Env, token: &TokenInfo, table_id: &str, ite
         if used { 0 } else { 1 }
     };
 
-    json_response(AvailabilityResponse {
+    Ok(AvailabilityResponse {
         available: available_qty >= quantity as i64,
         available_qty,
         requested_qty: quantity,
-    }, 200)
+    })
+}
+
+/// IDs of the tables a `tables=id,slug,name` list names, each checked like a table route's
+async fn selected_tables<S: TableStore, C: Cache>(storage: &Storage<S, C>, token: &TokenInfo, refs: &str) -> ApiResult<Vec<String>> {
+    let mut ids: Vec<String> = vec![];
+    let mut shares = None;
+    for table_ref in refs.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+        let table = authorized_table(&storage.store, &storage.cache, token, table_ref, &mut shares).await?;
+        if !table.is_catalog() {
+            return Err(ApiError::TableTypeUnsupported);
+        }
+        if !ids.contains(&table.id) {
+            ids.push(table.id);
+        }
+    }
+    if ids.is_empty() {
+        return Err(ApiError::InvalidParameter("tables parameter must name at least one table".to_string()));
+    }
+    Ok(ids)
 }
 
 /// GET /api/public/records - Get records with filtering across all accessible tables
-async fn get_records(env: &Env, token: &TokenInfo, query: &HashMap<String, String>) -> Result<Response> {
-    let db = env.d1("DB")?;
-    let kv = env.kv("KV")?;
-    let where_conditions = extract_where_conditions(query);
-    let limit: u32 = query.get("limit").and_then(|l| l.parse().ok()).unwrap_or(100).min(1000);
+async fn get_records<S: TableStore, C: Cache>(storage: &Storage<S, C>, token: &TokenInfo, query: &HashMap<String, String>, metrics: &RequestMetrics) -> ApiResult<RecordsResponse> {
+    let where_conditions = extract_where_conditions(query)?;
+    let limit: u32 = query.get("limit").and_then(|l| l.parse().ok()).unwrap_or(100).clamp(1, 1000);
     let offset: u32 = query.get("offset").and_then(|o| o.parse().ok()).unwrap_or(0);
     let columns_param = query.get("columns");
 
-    let allowed = get_allowed_table_ids(token);
-
-    // Get accessible tables
-    #[derive(Debug, Deserialize, Clone)]
-    struct TableInfo {
-        id: String,
-        name: String,
-        #[serde(rename = "tableType")]
-        table_type: String,
-    }
-
-    let tables: Vec<TableInfo> = if let Some(ref ids) = allowed {
-        if ids.is_empty() {
-            vec![]
-        } else {
-            let placeholders = ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-            let sql = format!(
-                "SELECT id, name, tableType FROM userTables WHERE id IN ({}) AND tableType IN ('sale', 'rent')",
-                placeholders
-            );
-            let mut stmt = db.prepare(&sql);
-            let bindings: Vec<JsValue> = ids.iter().map(|id| id.clone().into()).collect();
-            stmt = stmt.bind(&bindings)?;
-            stmt.all().await?.results()?
-        }
-    } else {
-        let stmt = db.prepare(
-            "SELECT id, name, tableType FROM userTables WHERE visibility IN ('public', 'shared') AND tableType IN ('sale', 'rent')"
-        );
-        stmt.all().await?.results()?
+    // Get accessible tables, or only those named by `tables=`
+    let allowed = allowed_table_ids(&storage.store, &storage.cache, token).await?;
+    let selected = match query.get("tables") {
+        Some(refs) => Some(selected_tables(storage, token, refs).await?),
+        None => None,
     };
+    let tables = storage.store.query_tables(selected.as_deref().or(allowed.as_deref())).await?;
 
     if tables.is_empty() {
-        return json_response(RecordsResponse {
+        return Ok(RecordsResponse {
             records: vec![],
             count: 0,
             total: 0,
             pagination: PaginationInfo { total: 0, page: 1, limit, has_more: false },
             filters: if where_conditions.is_empty() { None } else { Some(where_conditions) },
-        }, 200);
+        });
     }
 
-    let table_ids: Vec<String> = tables.iter().map(|t| t.id.clone()).collect();
-    let table_map: HashMap<String, TableInfo> = tables.into_iter().map(|t| (t.id.clone(), t)).collect();
-
-    // Check KV cache for query results (only for unrestricted tokens without column filtering)
+    let records_query = RecordsQuery {
+        table_ids: tables.iter().map(|t| t.id.clone()).collect(),
+        table_map: tables.into_iter().map(|t| (t.id.clone(), t)).collect(),
+        where_conditions,
+        limit,
+        offset,
+    };
+    // Applied to the response only: cached results keep catalog prices in each table's base currency
+    let price_rules = PriceRules::for_token(token);
+    let conversion = Conversion::from_query(&storage.store, &storage.cache, query, &records_query.table_ids).await?;
+    let masks = ColumnMasks::load(&storage.store, token, &records_query.table_ids).await?;
+    masks.check_filters(&records_query.where_conditions)?;
+    let labels = Labels::from_query(&storage.store, &storage.cache, query, &records_query.table_ids, &masks).await?;
+
+    // Check cache for query results (only for unrestricted tokens without column filtering)
     let can_use_cache = allowed.is_none() && columns_param.is_none();
-    if can_use_cache {
-        if let Some(cached) = cache_get_query_results(&kv, &table_ids, &where_conditions, limit, offset).await {
-            let page = (offset / limit) + 1;
-            return json_response(RecordsResponse {
-                count: cached.records.len(),
-                records: cached.records,
-                total: cached.total,
-                pagination: PaginationInfo {
-                    total: cached.total,
-                    page,
-                    limit,
-                    has_more: (offset + limit) < cached.total as u32,
-                },
-                filters: if where_conditions.is_empty() { None } else { Some(where_conditions) },
-            }, 200);
-        }
+    // Cache warming replays queries over the whole catalog
+    if can_use_cache && selected.is_none() {
+        storage.queue_query_hit(QueryHit::new(&records_query.where_conditions, limit, offset));
     }
+    let cache_key = if can_use_cache {
+        let generation = cache_get_generation(&storage.cache).await;
+        Some(query_cache_key(&records_query.table_ids, &generation, &records_query.where_conditions, limit, offset))
+    } else {
+        None
+    };
+    let lookup = match cache_key {
+        Some(ref key) => {
+            let lookup = cache_get_query_results(&storage.cache, key, &storage.config).await;
+            metrics.cache_lookup(!matches!(lookup, CacheLookup::Miss));
+            lookup
+        }
+        None => CacheLookup::Miss,
+    };
+    let cached = match (lookup, &cache_key) {
+        (CacheLookup::Fresh(cached), _) => Some(cached),
+        (CacheLookup::Stale(cached), Some(key)) => {
+            // Serve stale, let a single request rebuild in background
+            if cache_try_lock(&storage.cache, key).await {
+                storage.queue_refresh(Refresh::QueryResults {
+                    cache_key: key.clone(),
+                    query: records_query.clone(),
+                });
+            }
+            Some(cached)
+        }
+        _ => None,
+    };
 
-    // Build SQL with where conditions
-    let placeholders = table_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-    let mut sql = format!(
-        "SELECT id, tableId, data, createdAt, updatedAt FROM tableData WHERE tableId IN ({})",
-        placeholders
-    );
-    let mut bindings: Vec<JsValue> = table_ids.iter().map(|id| id.clone().into()).collect();
-
-    for (col, val) in &where_conditions {
-        sql.push_str(&format!(" AND LOWER(json_extract(data, '$.{}')) = LOWER(?)", col));
-        bindings.push(val.clone().into());
+    let where_conditions = &records_query.where_conditions;
+    if let Some(cached) = cached {
+        let page = (offset / limit) + 1;
+        let mut records = cached.records;
+        if let Some(ref rules) = price_rules {
+            records.iter_mut().for_each(|record| rules.mark_up_record(record));
+        }
+        if let Some(ref conversion) = conversion {
+            records.iter_mut().for_each(|record| conversion.convert_record(record));
+        }
+        records.iter_mut().for_each(|record| masks.apply_record(record));
+        if let Some(ref labels) = labels {
+            records.iter_mut().for_each(|record| labels.expand_record(record));
+        }
+        return Ok(RecordsResponse {
+            count: records.len(),
+            records,
+            total: cached.total,
+            pagination: PaginationInfo {
+                total: cached.total,
+                page,
+                limit,
+                has_more: (offset + limit) < cached.total as u32,
+            },
+            filters: if where_conditions.is_empty() { None } else { Some(where_conditions.clone()) },
+        });
     }
 
-    // Count total
-    let count_sql = sql.replace("SELECT id, tableId, data, createdAt, updatedAt", "SELECT COUNT(*) as cnt");
-    let count_stmt = db.prepare(&count_sql).bind(&bindings)?;
-
-    #[derive(Debug, Deserialize)]
-    struct CountResult { cnt: i64 }
-    let count_result: Option<CountResult> = count_stmt.first(None).await?;
-    let total = count_result.map(|c| c.cnt).unwrap_or(0);
-
-    // Get paginated results - use inline values for limit/offset (D1 doesn't like bigint bindings)
-    sql.push_str(&format!(" ORDER BY updatedAt DESC LIMIT {} OFFSET {}", limit, offset));
-
-    let data_stmt = db.prepare(&sql).bind(&bindings)?;
-    let rows: Vec<TableRow> = data_stmt.all().await?.results()?;
-
-    // Flatten records
-    let mut records: Vec<serde_json::Value> = rows.iter().map(|row| {
-        let table_info = table_map.get(&row.table_id);
-        let (name, ttype) = table_info.map(|t| (t.name.as_str(), t.table_type.as_str())).unwrap_or(("Unknown", "unknown"));
-        flatten_record(
-            &row.id, &row.table_id, name, ttype,
-            &row.data, row.created_at.as_deref(), row.updated_at.as_deref()
-        )
-    }).collect();
+    let (mut records, total) = load_records(&storage.store, &records_query).await?;
 
     // Cache results before column filtering (for unrestricted tokens)
-    if can_use_cache {
-        cache_set_query_results(&kv, &table_ids, &where_conditions, limit, offset, &records, total).await;
+    if let Some(ref key) = cache_key {
+        cache_set_query_results(&storage.cache, key, &records, total, &storage.config).await;
     }
 
     // Filter columns if specified
@@ -1002,9 +1272,19 @@ async fn get_records(env: &Env, token: &TokenInfo, query: &HashMap<String, Strin
             rec
         }).collect();
     }
+    if let Some(ref rules) = price_rules {
+        records.iter_mut().for_each(|record| rules.mark_up_record(record));
+    }
+    if let Some(ref conversion) = conversion {
+        records.iter_mut().for_each(|record| conversion.convert_record(record));
+    }
+    records.iter_mut().for_each(|record| masks.apply_record(record));
+    if let Some(ref labels) = labels {
+        records.iter_mut().for_each(|record| labels.expand_record(record));
+    }
 
     let page = (offset / limit) + 1;
-    json_response(RecordsResponse {
+    Ok(RecordsResponse {
         count: records.len(),
         records,
         total,
@@ -1014,109 +1294,73 @@ async fn get_records(env: &Env, token: &TokenInfo, query: &HashMap<String, Strin
             limit,
             has_more: (offset + limit) < total as u32,
         },
-        filters: if where_conditions.is_empty() { None } else { Some(where_conditions) },
-    }, 200)
+        filters: if where_conditions.is_empty() { None } else { Some(where_conditions.clone()) },
+    })
 }
 
 /// GET /api/public/values/:columnName - Get distinct values for a column
-async fn get_values(env: &Env, token: &TokenInfo, column_name: &str, query: &HashMap<String, String>) -> Result<Response> {
-    let db = env.d1("DB")?;
-    let where_conditions = extract_where_conditions(query);
-    let allowed = get_allowed_table_ids(token);
-
-    // Get accessible tables
-    #[derive(Debug, Deserialize)]
-    struct TableInfo {
-        id: String,
-        name: String,
+async fn get_values<S: TableStore, C: Cache>(storage: &Storage<S, C>, token: &TokenInfo, column_name: &str, query: &HashMap<String, String>) -> ApiResult<ValuesResponse> {
+    if !is_valid_column_name(column_name) {
+        return Err(ApiError::InvalidParameter(format!("Invalid column name: {}", column_name)));
     }
+    let where_conditions = extract_where_conditions(query)?;
+    let filters = if where_conditions.is_empty() { None } else { Some(where_conditions.clone()) };
 
-    let tables: Vec<TableInfo> = if let Some(ref ids) = allowed {
-        if ids.is_empty() {
-            vec![]
-        } else {
-            let placeholders = ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-            let sql = format!(
-                "SELECT id, name FROM userTables WHERE id IN ({}) AND tableType IN ('sale', 'rent')",
-                placeholders
-            );
-            let mut stmt = db.prepare(&sql);
-            let bindings: Vec<JsValue> = ids.iter().map(|id| id.clone().into()).collect();
-            stmt = stmt.bind(&bindings)?;
-            stmt.all().await?.results()?
-        }
-    } else {
-        let stmt = db.prepare(
-            "SELECT id, name FROM userTables WHERE visibility IN ('public', 'shared') AND tableType IN ('sale', 'rent')"
-        );
-        stmt.all().await?.results()?
-    };
-
-    if tables.is_empty() {
-        return json_response(ValuesResponse {
-            column: column_name.to_string(),
-            values: vec![],
-            count: 0,
-            filters: if where_conditions.is_empty() { None } else { Some(where_conditions) },
-            tables_sampled: vec![],
-        }, 200);
-    }
-
-    // Filter tables that have the requested column
-    let mut eligible_tables = vec![];
-    for table in &tables {
-        let col_stmt = db.prepare("SELECT name FROM tableColumns WHERE tableId = ? AND LOWER(name) = LOWER(?)");
-
-        #[derive(Debug, Deserialize)]
-        struct ColName { name: String }
-
-        let col: Option<ColName> = col_stmt.bind(&[table.id.clone().into(), column_name.into()])?.first(None).await?;
-        if col.is_some() {
-            eligible_tables.push(table);
-        }
-    }
+    // Get accessible tables
+    let allowed = allowed_table_ids(&storage.store, &storage.cache, token).await?;
+    let tables = storage.store.query_tables(allowed.as_deref()).await?;
+    let all_ids: Vec<String> = tables.iter().map(|t| t.id.clone()).collect();
+    let masks = ColumnMasks::load(&storage.store, token, &all_ids).await?;
+    masks.check_filters(&where_conditions)?;
+
+    // Filter tables that have the requested column, unless it's hidden there
+    let columns = storage.store.column_names(&all_ids).await?;
+    let eligible_tables: Vec<&QueryTable> = tables
+        .iter()
+        .filter(|table| {
+            columns.iter().any(|c| c.table_id == table.id && c.name.eq_ignore_ascii_case(column_name) && !masks.hides(&table.id, &c.name))
+        })
+        .collect();
 
     if eligible_tables.is_empty() {
-        return json_response(ValuesResponse {
+        return Ok(ValuesResponse {
             column: column_name.to_string(),
             values: vec![],
             count: 0,
-            filters: if where_conditions.is_empty() { None } else { Some(where_conditions) },
+            filters,
             tables_sampled: vec![],
-        }, 200);
+        });
     }
 
     let table_ids: Vec<String> = eligible_tables.iter().map(|t| t.id.clone()).collect();
     let tables_sampled: Vec<String> = eligible_tables.iter().map(|t| t.name.clone()).collect();
 
-    // Get distinct values
-    let placeholders = table_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-    let mut sql = format!(
-        "SELECT DISTINCT json_extract(data, '$.{}') as val FROM tableData WHERE tableId IN ({}) AND json_extract(data, '$.{}') IS NOT NULL",
-        column_name, placeholders, column_name
-    );
-    let mut bindings: Vec<JsValue> = table_ids.iter().map(|id| id.clone().into()).collect();
-
-    for (col, val) in &where_conditions {
-        sql.push_str(&format!(" AND LOWER(json_extract(data, '$.{}')) = LOWER(?)", col));
-        bindings.push(val.clone().into());
-    }
-
-    let stmt = db.prepare(&sql).bind(&bindings)?;
-
-    #[derive(Debug, Deserialize)]
-    struct ValueRow { val: serde_json::Value }
-
-    let rows: Vec<ValueRow> = stmt.all().await?.results()?;
-    let values: Vec<serde_json::Value> = rows.into_iter().map(|r| r.val).collect();
+    // Get distinct values; marked-up and masked values differ per table, so those are read with their table
+    let price_rules = PriceRules::for_token(token).filter(|_| PriceRules::is_priced(column_name));
+    let values = if price_rules.is_some() || table_ids.iter().any(|id| masks.restricts(id, column_name)) {
+        let mut values: Vec<serde_json::Value> = vec![];
+        for TableValue { table_id, val } in storage.store.distinct_table_values(&table_ids, column_name, &where_conditions).await? {
+            let value = match price_rules {
+                Some(ref rules) => rules.mark_up_value(&table_id, &val),
+                None => val,
+            };
+            let value = masks.mask_value(&table_id, column_name, &value);
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+        values
+    } else {
+        storage.store.distinct_values(&table_ids, column_name, &where_conditions).await?
+    };
 
-    json_response(ValuesResponse {
+    Ok(ValuesResponse {
         column: column_name.to_string(),
         count: values.len(),
         values,
-        filters: if where_conditions.is_empty() { None } else { Some(where_conditions) },
+        filters,
         tables_sampled,
-    }, 200)
+    })
 }
 
 // ============================================================================
@@ -1124,113 +1368,211 @@ async fn get_values(env: &Env, token: &TokenInfo, column_name: &str, query: &Has
 // ============================================================================
 
 #[event(fetch)]
-async fn main(req: Request, env: Env, _ctx: Context) -> Result<Response> {
+async fn main(req: Request, env: Env, ctx: Context) -> Result<Response> {
     utils::set_panic_hook();
 
+    let method = req.method();
+    let path = req.path();
+    let origin = req.headers().get("Origin").ok().flatten();
+    let metrics = RequestMetrics::new(telemetry::request_id_from(&req));
+    let mut token = None;
+    let mut route_label = None;
+
+    let response = match handle(req, &env, &ctx, &metrics, &mut token, &mut route_label).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(&metrics.request_id)?,
+    };
+
+    // Preflights answer CORS themselves; everything else gets origin-aware headers for its token
+    let mut extra = if method == Method::Options {
+        vec![]
+    } else {
+        let allowed_domains = token.as_ref().and_then(|t| t.allowed_domains.as_deref());
+        cors::response_headers(&env, origin.as_deref(), allowed_domains)
+    };
+    extra.push((REQUEST_ID_HEADER, metrics.request_id.clone()));
+    extra.push(("Server-Timing", metrics.server_timing()));
+    let response = with_extra_headers(response, &extra);
+
+    metrics.log(
+        method.as_ref(),
+        route_label.as_deref(),
+        &path,
+        token.as_ref().map(|t| t.id.as_str()),
+        response.status_code(),
+    );
+    Ok(response)
+}
+
+/// Authenticate, route, rate limit and dispatch one request
+/// Records the token and route label for CORS and the request log as soon as they're known
+async fn handle(
+    req: Request,
+    env: &Env,
+    ctx: &Context,
+    metrics: &RequestMetrics,
+    authed: &mut Option<TokenInfo>,
+    route_label: &mut Option<String>,
+) -> ApiResult<Response> {
     let url = req.url()?;
     let path = url.path();
     let method = req.method();
 
     // Handle CORS preflight
     if method == Method::Options {
-        let mut response = Response::ok("")?;
-        *response.headers_mut() = cors_headers();
-        return Ok(response);
+        return Ok(cors::preflight(&req, env)?);
     }
 
     // Health check endpoint (no auth required)
     if path == "/health" || path == "/api/public/health" {
-        return json_response(serde_json::json!({
-            "status": "ok",
-            "service": "store-public-api",
-            "runtime": "rust",
-            "routes": [
-                "GET /api/public/tables",
-                "GET /api/public/tables/search",
-                "GET /api/public/tables/:id/items",
-                "GET /api/public/tables/:id/items/:itemId",
-                "GET /api/public/tables/:id/items/:itemId/availability",
-                "GET /api/public/records",
-                "GET /api/public/values/:column"
-            ]
-        }), 200);
+        return json_response(health("rust"), 200);
     }
 
+    let storage = Storage::new(
+        SqlStore::new(D1Sql::new(env, metrics)?),
+        KvCache::new(env, metrics)?,
+        CacheConfig::from_env(env),
+    );
+
     // All other endpoints require authentication
-    let token = match validate_token(&req, &env).await? {
-        Some(t) => t,
-        None => return error_response("Unauthorized", 401),
-    };
+    let token_string = bearer_token(req.headers().get("Authorization")?.as_deref())?;
+    let token = metrics.auth(authenticate(&storage, &token_string, metrics)).await?;
+    *authed = Some(token.clone());
 
-    let query = parse_query_params(&url);
+    let (route, params) = find_route(&method, path)?;
+    *route_label = Some(route.label());
 
-    // Route handling - order matters for path matching!
-    match method {
-        Method::Get => {
-            // /api/public/tables/search?columns=...
-            if path == "/api/public/tables/search" {
-                return search_tables(&env, &token, &query).await;
-            }
+    // Per-token rate limiting - writes draw from a separate, smaller bucket
+    let scope = RateLimitScope::for_method(&method);
+    let limit = RateLimit::resolve(env, token.rate_limits.as_deref(), scope);
+    let rate_limit = rate_limit::check_rate_limit(env, &token.id, scope, limit).await;
+    let rate_limit_headers = rate_limit.as_ref().map(|d| d.headers()).unwrap_or_default();
 
-            // /api/public/tables/:id/items/:itemId/availability
-            if path.starts_with("/api/public/tables/") && path.ends_with("/availability") {
-                let parts: Vec<&str> = path.split('/').collect();
-                if parts.len() == 8 && parts[5] == "items" {
-                    let table_id = parts[4];
-                    let item_id = parts[6];
-                    return get_item_availability(&env, &token, table_id, item_id, &query).await;
-                }
+    let response = if rate_limit.as_ref().is_some_and(|d| !d.allowed) {
+        ApiError::RateLimited.into_response(&metrics.request_id)?
+    } else {
+        // Render handler errors here so they still carry the rate limit headers
+        let query = parse_query_params(&url);
+        let mut req = req;
+        let body = req.text().await?;
+        let result = if route.kind.is_proxied() {
+            match prepare_write(&storage.store, &storage.cache, &token, route.kind, &body).await {
+                Ok(bound) => proxy_to_api(&req, bound.as_deref().unwrap_or(&body), env, metrics).await,
+                Err(e) => Err(e),
             }
-
-            // /api/public/tables/:id/items/:itemId
-            if path.starts_with("/api/public/tables/") && path.contains("/items/") {
-                let parts: Vec<&str> = path.split('/').collect();
-                if parts.len() == 7 && parts[5] == "items" {
-                    let table_id = parts[4];
-                    let item_id = parts[6];
-                    return get_table_item(&env, &token, table_id, item_id).await;
-                }
+        } else {
+            match dispatch(&storage, &token, route, &params, &query, &body, metrics).await {
+                Ok((status, data)) => json_response(data, status),
+                Err(e) => Err(e),
             }
+        };
+        match result {
+            Ok(response) => response,
+            Err(e) => e.into_response(&metrics.request_id)?,
+        }
+    };
 
-            // /api/public/tables/:id/items
-            if path.starts_with("/api/public/tables/") && path.ends_with("/items") {
-                let table_id = path
-                    .strip_prefix("/api/public/tables/")
-                    .and_then(|s| s.strip_suffix("/items"))
-                    .unwrap_or("");
-                if !table_id.is_empty() {
-                    return get_table_items(&env, &token, table_id, &query).await;
-                }
-            }
+    // Rebuild stale cache entries after the response is sent
+    for refresh in storage.take_refreshes() {
+        ctx.wait_until(refresh_in_background(env.clone(), metrics.request_id.clone(), refresh));
+    }
+    let query_hits = storage.take_query_hits();
+    if !query_hits.is_empty() {
+        ctx.wait_until(scheduled::record_query_hits_in_background(env.clone(), metrics.request_id.clone(), query_hits));
+    }
+    let mut response = with_extra_headers(response, &rate_limit_headers);
 
-            // /api/public/tables
-            if path == "/api/public/tables" {
-                return get_tables(&env, &token).await;
-            }
+    // Meter usage after the response is sent
+    if let Ok(copy) = response.cloned() {
+        ctx.wait_until(usage::record_response(env.clone(), metrics.request_id.clone(), token.id.clone(), route.label(), copy));
+    }
 
-            // /api/public/records
-            if path == "/api/public/records" {
-                return get_records(&env, &token, &query).await;
-            }
+    // Fire webhooks for writes the order service accepted
+    if route.kind.is_proxied() && (200..300).contains(&response.status_code())
+        && let Ok(copy) = response.cloned()
+    {
+        ctx.wait_until(webhooks::notify_write(env.clone(), metrics.request_id.clone(), route.kind, copy));
+    }
 
-            // /api/public/values/:columnName
-            if path.starts_with("/api/public/values/") {
-                let column_name = path.strip_prefix("/api/public/values/").unwrap_or("");
-                if !column_name.is_empty() {
-                    return get_values(&env, &token, column_name, &query).await;
-                }
-            }
+    Ok(response)
+}
+
+/// Route for an authenticated request: 404 for unknown paths, 405 for other methods
+fn find_route(method: &Method, path: &str) -> ApiResult<(&'static Route, RouteParams)> {
+    match routes::match_route(method, path) {
+        Some(matched) => Ok(matched),
+        None if matches!(method, Method::Get | Method::Post | Method::Delete) => Err(ApiError::RouteNotFound),
+        None => Err(ApiError::MethodNotAllowed),
+    }
+}
 
-            error_response("Not found", 404)
+/// Health check body; `runtime` tells the worker and the native server apart
+fn health(runtime: &str) -> serde_json::Value {
+    serde_json::json!({
+        "status": "ok",
+        "service": "store-public-api",
+        "runtime": runtime,
+        "routes": ROUTES.iter().map(Route::label).collect::<Vec<_>>()
+    })
+}
+
+/// Call the handler for a matched route, returning the status and body to send
+/// Proxied writes never get here: each runtime forwards them to the order service itself
+async fn dispatch<S: TableStore + OrderStore + UsageStore + WebhookStore, C: Cache>(
+    storage: &Storage<S, C>,
+    token: &TokenInfo,
+    route: &Route,
+    params: &RouteParams,
+    query: &HashMap<String, String>,
+    body: &str,
+    metrics: &RequestMetrics,
+) -> ApiResult<(u16, serde_json::Value)> {
+    let data = match route.kind {
+        RouteKind::Tables => serde_json::to_value(get_tables(storage, token, metrics).await?)?,
+        RouteKind::TablesSearch => serde_json::to_value(search_tables(storage, token, query).await?)?,
+        RouteKind::TableItems => serde_json::to_value(get_table_items(storage, token, &params[0], query).await?)?,
+        RouteKind::TableItem => get_table_item(storage, token, &params[0], &params[1], query).await?,
+        RouteKind::ItemAvailability => serde_json::to_value(get_item_availability(storage, token, &params[0], &params[1], query).await?)?,
+        RouteKind::Records => serde_json::to_value(get_records(storage, token, query, metrics).await?)?,
+        RouteKind::Values => serde_json::to_value(get_values(storage, token, &params[0], query).await?)?,
+        RouteKind::Usage => serde_json::to_value(usage::get_usage(&storage.store, token, query).await?)?,
+        RouteKind::Sales => serde_json::to_value(orders::list_sales(&storage.store, &storage.cache, token, query).await?)?,
+        RouteKind::Rentals => serde_json::to_value(orders::list_rentals(&storage.store, &storage.cache, token, query).await?)?,
+        RouteKind::Webhooks => serde_json::to_value(webhooks::list_subscriptions(&storage.store, token).await?)?,
+        RouteKind::WebhookDeliveries => serde_json::to_value(webhooks::list_deliveries(&storage.store, token, query).await?)?,
+        RouteKind::CreateWebhook => {
+            let subscription = webhooks::create_subscription(&storage.store, token, body).await?;
+            return Ok((201, serde_json::to_value(subscription)?));
         }
-        Method::Post => {
-            // POST endpoints (buy, rent, release) are write operations
-            // Proxy these to the TypeScript API which has the business logic
-            if path == "/api/public/buy" || path == "/api/public/rent" || path == "/api/public/release" {
-                return proxy_to_api(req, &env).await;
-            }
-            error_response("Not found", 404)
+        RouteKind::DeleteWebhook => serde_json::to_value(webhooks::delete_subscription(&storage.store, token, &params[0]).await?)?,
+        // Writes are proxied before dispatch; reaching here is a routing bug, not a reason to panic the isolate
+        RouteKind::Buy | RouteKind::Rent | RouteKind::Release => {
+            return Err(ApiError::Internal(Error::RustError("write route reached dispatch".to_string())));
         }
-        _ => error_response("Method not allowed", 405),
+    };
+    Ok((200, data))
+}
+
+/// Cron trigger: expire overdue rentals, warm the caches, then queue webhooks for inventory
+/// changes made since the last run and send what's due
+#[event(scheduled)]
+async fn scheduled(event: ScheduledEvent, env: Env, _ctx: ScheduleContext) {
+    utils::set_panic_hook();
+
+    let metrics = RequestMetrics::new(telemetry::request_id_or_generate(None));
+    let result = async {
+        let storage = Storage::new(
+            SqlStore::new(D1Sql::new(&env, &metrics)?),
+            KvCache::new(&env, &metrics)?,
+            CacheConfig::from_env(&env),
+        );
+        scheduled::run_all(&storage, &cloudflare::FetchSender, current_timestamp() as i64, &metrics.request_id).await;
+        Ok::<_, worker::Error>(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => utils::log(&format!("[{}] Scheduled {} done", metrics.request_id, event.cron())),
+        Err(e) => utils::log_error(&format!("[{}] Scheduled {} failed: {:?}", metrics.request_id, event.cron(), e)),
     }
 }
@@ -0,0 +1,211 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use worker::Result;
+
+use crate::error::{ApiError, ApiResult};
+use crate::store::TableStore;
+use crate::TokenInfo;
+
+// ============================================================================
+// COLUMN VISIBILITY
+// ============================================================================
+//
+// Hidden and masked columns (tableColumns.visibility), overridden per token by columnRules.
+
+/// A column of a table with its public API visibility
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColumnVisibility {
+    pub table_id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub column_type: String,
+    pub visibility: String,
+}
+
+/// tableColumns.visibility, or a token's override of it
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Visibility {
+    Visible,
+    Hidden,
+    Masked,
+}
+
+/// Token `columnRules` JSON column: `{ [tableId]: { [column]: visibility } }`
+type ColumnRules = HashMap<String, HashMap<String, Visibility>>;
+
+/// How a masked value is shown, from the column type
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mask {
+    /// First letter and domain: `j***@x.com`
+    Email,
+    /// Last 4 digits: `***1234`
+    Phone,
+    /// First character: `A***`
+    Text,
+}
+
+/// What a token may not see of a column
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Restriction {
+    Hidden,
+    Masked(Mask),
+}
+
+const MASK: &str = "***";
+
+impl Mask {
+    fn for_type(column_type: &str) -> Self {
+        match column_type {
+            "email" => Mask::Email,
+            "phone" => Mask::Phone,
+            _ => Mask::Text,
+        }
+    }
+
+    fn apply(self, value: &serde_json::Value) -> serde_json::Value {
+        let text = match value {
+            serde_json::Value::Null => return serde_json::Value::Null,
+            serde_json::Value::String(s) => s.trim(),
+            // Numbers and flags have no part worth showing
+            _ => return MASK.into(),
+        };
+        let first = |s: &str| s.chars().next().map(String::from).unwrap_or_default();
+        let masked = match self {
+            Mask::Email => match text.split_once('@') {
+                Some((local, domain)) => format!("{}{}@{}", first(local), MASK, domain),
+                None => format!("{}{}", first(text), MASK),
+            },
+            Mask::Phone => {
+                let digits: Vec<char> = text.chars().filter(char::is_ascii_digit).collect();
+                match digits.len() {
+                    0..4 => MASK.to_string(),
+                    n => format!("{}{}", MASK, digits[n - 4..].iter().collect::<String>()),
+                }
+            }
+            Mask::Text => format!("{}{}", first(text), MASK),
+        };
+        masked.into()
+    }
+}
+
+/// The hidden and masked columns of the tables a request reads, for one token
+#[derive(Debug, Default)]
+pub struct ColumnMasks {
+    tables: HashMap<String, HashMap<String, Restriction>>,
+}
+
+impl ColumnMasks {
+    /// Restrictions of `table_ids` for a token: the token's rule for a column, else the table's
+    pub async fn load<S: TableStore>(store: &S, token: &TokenInfo, table_ids: &[String]) -> Result<Self> {
+        let rules = Self::rules(token);
+        // Overrides can restrict columns the tables show, so then every column is needed
+        let overrides = table_ids.iter().any(|id| rules.contains_key(id));
+        let columns = store.column_visibility(table_ids, overrides).await?;
+        Ok(Self::from_columns(token, columns))
+    }
+
+    fn rules(token: &TokenInfo) -> ColumnRules {
+        token
+            .column_rules
+            .as_deref()
+            .and_then(|rules| serde_json::from_str(rules).ok())
+            .unwrap_or_default()
+    }
+
+    /// Restrictions for a token from columns already at hand (e.g. a resolved table's)
+    pub fn from_columns(token: &TokenInfo, columns: Vec<ColumnVisibility>) -> Self {
+        let rules = Self::rules(token);
+        let mut tables: HashMap<String, HashMap<String, Restriction>> = HashMap::new();
+        for column in columns {
+            let default = match column.visibility.as_str() {
+                "hidden" => Visibility::Hidden,
+                "masked" => Visibility::Masked,
+                _ => Visibility::Visible,
+            };
+            let visibility = rules
+                .get(&column.table_id)
+                .and_then(|columns| columns.get(&column.name))
+                .copied()
+                .unwrap_or(default);
+            let restriction = match visibility {
+                Visibility::Visible => continue,
+                Visibility::Hidden => Restriction::Hidden,
+                Visibility::Masked => Restriction::Masked(Mask::for_type(&column.column_type)),
+            };
+            tables.entry(column.table_id).or_default().insert(column.name, restriction);
+        }
+        Self { tables }
+    }
+
+    fn restriction(&self, table_id: &str, column: &str) -> Option<Restriction> {
+        self.tables.get(table_id)?.get(column).copied()
+    }
+
+    /// Whether a table's column is hidden from the token
+    pub fn hides(&self, table_id: &str, column: &str) -> bool {
+        self.restriction(table_id, column) == Some(Restriction::Hidden)
+    }
+
+    /// Whether a table's column is hidden or masked
+    pub fn restricts(&self, table_id: &str, column: &str) -> bool {
+        self.restriction(table_id, column).is_some()
+    }
+
+    /// Refuse `where[...]` filters on a column any of the tables hides or masks, since equality
+    /// filters would reveal the full value
+    pub fn check_filters(&self, filters: &HashMap<String, String>) -> ApiResult<()> {
+        let restricted = filters.keys().find(|filter| {
+            self.tables.values().any(|columns| columns.keys().any(|column| column.eq_ignore_ascii_case(filter)))
+        });
+        match restricted {
+            Some(column) => Err(ApiError::InvalidFilter(format!("Cannot filter on column: {}", column))),
+            None => Ok(()),
+        }
+    }
+
+    /// A table's value of a column as the token may see it
+    pub fn mask_value(&self, table_id: &str, column: &str, value: &serde_json::Value) -> serde_json::Value {
+        match self.restriction(table_id, column) {
+            Some(Restriction::Masked(mask)) => mask.apply(value),
+            _ => value.clone(),
+        }
+    }
+
+    /// Drop a table's hidden fields and mask its masked ones, in place
+    pub fn apply(&self, table_id: &str, fields: &mut serde_json::Map<String, serde_json::Value>) {
+        let Some(columns) = self.tables.get(table_id) else {
+            return;
+        };
+        for (column, restriction) in columns {
+            match restriction {
+                Restriction::Hidden => {
+                    fields.remove(column);
+                }
+                Restriction::Masked(mask) => {
+                    if let Some(value) = fields.get_mut(column) {
+                        *value = mask.apply(value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Apply to a flattened record (as from `flatten_record`)
+    pub fn apply_record(&self, record: &mut serde_json::Value) {
+        let serde_json::Value::Object(fields) = record else {
+            return;
+        };
+        if let Some(table_id) = fields.get("tableId").and_then(|id| id.as_str()).map(str::to_string) {
+            self.apply(&table_id, fields);
+        }
+    }
+
+    /// Apply to an item in the nested `{id, data, ...}` shape
+    pub fn apply_item(&self, table_id: &str, item: &mut serde_json::Value) {
+        if let Some(serde_json::Value::Object(data)) = item.get_mut("data") {
+            self.apply(table_id, data);
+        }
+    }
+}
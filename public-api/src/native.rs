@@ -0,0 +1,532 @@
+//! Self-hosted server: the same routes and handlers as the worker, over a local SQLite
+//! copy of the D1 database and an in-process cache, for deployments without Cloudflare.
+//!
+//! Configured from the environment:
+//! - `DATABASE_PATH` - SQLite file with the D1 schema and data (default `public-api.sqlite`)
+//! - `LISTEN_ADDR` - address to bind (default `0.0.0.0:8788`)
+//! - `SERVER_THREADS` - worker threads, each with its own connection (default 4)
+//! - `UPSTREAM_API_URL` - base URL of the TypeScript API that handles buy/rent/release;
+//!   when unset those routes answer 503
+//! - `SCHEDULE_INTERVAL` - seconds between scheduled runs (rental expiry, cache warming, webhook
+//!   scan and retries, default 60),
+//!   standing in for the worker's cron trigger; 0 disables them
+//! - `CURRENCY_RATES` - exchange rates for `?currency=` conversion, as returned by
+//!   `GET /api/admin/currency-rates` (the worker reads them from KV instead)
+//!
+//! plus the worker's own vars (`CACHE_*`, `RATE_LIMIT_*`, `CORS_*`), read the same way.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use worker::{Method, Url};
+
+use crate::currency::{CurrencyRates, CACHE_KEY_CURRENCY_RATES};
+use crate::error::{ApiError, ApiResult};
+use crate::rate_limit::{self, Bucket, RateLimit, RateLimitDecision, RateLimitScope};
+use crate::sqlite::SqliteDb;
+use crate::routes::Route;
+use crate::store::{Cache, OrderStore, SqlDatabase, SqlStore, TableStore, TokenStore, UsageStore, WebhookStore};
+use crate::scheduled;
+use crate::telemetry::{self, RequestMetrics, REQUEST_ID_HEADER};
+use crate::utils::{self, Vars};
+use crate::webhooks::{self, WebhookSender};
+use crate::{
+    authenticate, bearer_token, cache_bump_generation, cors, current_timestamp, dispatch, find_route, health, parse_query_params,
+    prepare_write, run_refresh, usage, CacheConfig, Storage, TokenInfo,
+};
+
+/// Entries kept in the in-process cache before expired ones are swept
+const CACHE_SWEEP_THRESHOLD: usize = 10_000;
+
+/// How long a proxied write may take upstream
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a webhook endpoint may take to answer
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Hop-by-hop headers that must not be forwarded in either direction
+const HOP_BY_HOP_HEADERS: &[&str] = &["connection", "content-length", "host", "keep-alive", "transfer-encoding", "upgrade"];
+
+// ============================================================================
+// CONFIGURATION
+// ============================================================================
+
+/// Process environment variables, standing in for wrangler vars
+pub struct ProcessEnv;
+
+impl Vars for ProcessEnv {
+    fn get(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub database: PathBuf,
+    pub listen: String,
+    pub threads: usize,
+    pub upstream: Option<String>,
+    /// Seconds between scheduled runs; None disables them
+    pub schedule_interval: Option<u64>,
+    /// Exchange rates JSON to seed the cache with
+    pub currency_rates: Option<String>,
+}
+
+impl ServerConfig {
+    pub fn from_env() -> Self {
+        Self {
+            database: ProcessEnv.get("DATABASE_PATH").unwrap_or_else(|| "public-api.sqlite".to_string()).into(),
+            listen: ProcessEnv.get("LISTEN_ADDR").unwrap_or_else(|| "0.0.0.0:8788".to_string()),
+            threads: utils::var_or(&ProcessEnv, "SERVER_THREADS", 4usize).max(1),
+            upstream: ProcessEnv.get("UPSTREAM_API_URL").filter(|url| !url.is_empty()),
+            schedule_interval: Some(utils::var_or(&ProcessEnv, "SCHEDULE_INTERVAL", 60u64)).filter(|&secs| secs > 0),
+            currency_rates: ProcessEnv.get("CURRENCY_RATES").filter(|rates| !rates.is_empty()),
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+// ============================================================================
+// IN-PROCESS CACHE AND RATE LIMITER
+// ============================================================================
+
+/// Cached values with their expiry in epoch milliseconds
+type CacheEntries = HashMap<String, (String, Option<f64>)>;
+
+/// KV stand-in shared by every thread; TTLs are enforced on read
+#[derive(Clone, Default)]
+pub struct LocalCache {
+    entries: Arc<Mutex<CacheEntries>>,
+}
+
+impl Cache for LocalCache {
+    async fn get(&self, key: &str) -> Option<String> {
+        let mut entries = lock(&self.entries);
+        match entries.get(key) {
+            Some((_, Some(expires_at))) if *expires_at <= utils::now_ms() => {
+                entries.remove(key);
+                None
+            }
+            entry => entry.map(|(value, _)| value.clone()),
+        }
+    }
+
+    async fn put(&self, key: &str, value: String, ttl: Option<u64>) {
+        let now = utils::now_ms();
+        let mut entries = lock(&self.entries);
+        if entries.len() >= CACHE_SWEEP_THRESHOLD {
+            entries.retain(|_, (_, expires_at)| expires_at.is_none_or(|at| at > now));
+        }
+        entries.insert(key.to_string(), (value, ttl.map(|ttl| now + ttl as f64 * 1000.0)));
+    }
+
+    async fn delete(&self, key: &str) {
+        lock(&self.entries).remove(key);
+    }
+}
+
+/// The RateLimiter Durable Object's token buckets, kept in memory
+#[derive(Default)]
+struct LocalRateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl LocalRateLimiter {
+    fn check(&self, token_id: &str, scope: RateLimitScope, limit: RateLimit) -> RateLimitDecision {
+        let key = format!("{}:{}", token_id, scope.as_str());
+        let mut buckets = lock(&self.buckets);
+        let (bucket, decision) = rate_limit::take(buckets.get(&key).copied(), limit, utils::now_ms());
+        buckets.insert(key, bucket);
+        decision
+    }
+}
+
+/// A backend with its operations timed in the request metrics, as the D1/KV ones are
+struct Timed<'a, T> {
+    inner: &'a T,
+    metrics: &'a RequestMetrics,
+}
+
+impl<T: SqlDatabase> SqlDatabase for Timed<'_, T> {
+    async fn query(&self, sql: &str, params: &[serde_json::Value]) -> worker::Result<Vec<serde_json::Value>> {
+        self.metrics.db(self.inner.query(sql, params)).await
+    }
+}
+
+impl<T: Cache> Cache for Timed<'_, T> {
+    async fn get(&self, key: &str) -> Option<String> {
+        self.metrics.cache(self.inner.get(key)).await
+    }
+
+    async fn put(&self, key: &str, value: String, ttl: Option<u64>) {
+        self.metrics.cache(self.inner.put(key, value, ttl)).await
+    }
+
+    async fn delete(&self, key: &str) {
+        self.metrics.cache(self.inner.delete(key)).await
+    }
+}
+
+/// Webhook requests through an HTTP client
+struct HttpSender(ureq::Agent);
+
+impl WebhookSender for HttpSender {
+    async fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> Result<u16, String> {
+        let mut call = self.0.post(url);
+        for (name, value) in headers {
+            call = call.set(name, value);
+        }
+        match call.send_string(body) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => Ok(response.status()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+// ============================================================================
+// REQUESTS AND REPLIES
+// ============================================================================
+
+/// An incoming request, independent of the HTTP library
+pub(crate) struct NativeRequest {
+    pub method: Method,
+    /// Path and query string
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl NativeRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A response ready to send
+#[derive(Debug)]
+pub(crate) struct Reply {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Reply {
+    fn new(status: u16, headers: Vec<(&str, String)>, body: String) -> Self {
+        let headers = headers.into_iter().map(|(n, v)| (n.to_string(), v)).collect();
+        Self { status, headers, body }
+    }
+
+    fn json(status: u16, data: serde_json::Value) -> ApiResult<Self> {
+        let headers = vec![("Content-Type", "application/json".to_string()), ("X-Worker", "rust".to_string())];
+        Ok(Self::new(status, headers, serde_json::to_string(&data)?))
+    }
+
+    fn problem(error: ApiError, request_id: &str) -> Self {
+        let (headers, body) = error.render(request_id).unwrap_or_default();
+        Self::new(error.status(), headers, body)
+    }
+
+    fn add_headers(&mut self, extra: Vec<(&str, String)>) {
+        self.headers.extend(extra.into_iter().map(|(n, v)| (n.to_string(), v)));
+    }
+
+    #[cfg(test)]
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+fn is_hop_by_hop(name: &str) -> bool {
+    HOP_BY_HOP_HEADERS.iter().any(|h| h.eq_ignore_ascii_case(name))
+}
+
+// ============================================================================
+// SERVER
+// ============================================================================
+
+/// State shared by every thread
+pub(crate) struct Server {
+    config: ServerConfig,
+    cache: LocalCache,
+    limiter: LocalRateLimiter,
+    upstream: ureq::Agent,
+    webhooks: HttpSender,
+}
+
+impl Server {
+    pub fn new(config: ServerConfig) -> Self {
+        let cache = LocalCache::default();
+        match config.currency_rates.as_deref().map(serde_json::from_str::<CurrencyRates>) {
+            Some(Ok(rates)) => {
+                let rates = serde_json::to_string(&rates).unwrap_or_default();
+                lock(&cache.entries).insert(CACHE_KEY_CURRENCY_RATES.to_string(), (rates, None));
+            }
+            Some(Err(e)) => utils::log_error(&format!("Ignoring malformed CURRENCY_RATES: {}", e)),
+            None => {}
+        }
+        Self {
+            config,
+            cache,
+            limiter: LocalRateLimiter::default(),
+            upstream: ureq::AgentBuilder::new().timeout(UPSTREAM_TIMEOUT).build(),
+            webhooks: HttpSender(ureq::AgentBuilder::new().timeout(WEBHOOK_TIMEOUT).redirects(0).build()),
+        }
+    }
+
+    /// Serve one request: build the reply and `send` it, then do what the worker defers with
+    /// wait_until - rebuild stale cache entries, meter usage and fire webhooks for writes
+    pub fn respond(&self, db: &SqliteDb, req: &NativeRequest, send: impl FnOnce(Reply)) {
+        futures_executor::block_on(async {
+            let metrics = RequestMetrics::new(telemetry::request_id_or_generate(
+                req.header(REQUEST_ID_HEADER).map(str::to_string),
+            ));
+            let storage = Storage::new(
+                SqlStore::new(Timed { inner: db, metrics: &metrics }),
+                Timed { inner: &self.cache, metrics: &metrics },
+                CacheConfig::from_env(&ProcessEnv),
+            );
+            let mut token = None;
+            let mut route = None;
+
+            let mut reply = match self.handle(req, &storage, &metrics, &mut token, &mut route).await {
+                Ok(reply) => reply,
+                Err(e) => Reply::problem(e, &metrics.request_id),
+            };
+
+            // Preflights answer CORS themselves; everything else gets origin-aware headers for its token
+            if req.method != Method::Options {
+                let allowed_domains = token.as_ref().and_then(|t| t.allowed_domains.as_deref());
+                reply.add_headers(cors::response_headers(&ProcessEnv, req.header("Origin"), allowed_domains));
+            }
+            reply.add_headers(vec![
+                (REQUEST_ID_HEADER, metrics.request_id.clone()),
+                ("Server-Timing", metrics.server_timing()),
+            ]);
+
+            let path = req.url.split('?').next().unwrap_or_default();
+            let token_id = token.as_ref().map(|t| t.id.as_str());
+            let route_label = route.map(Route::label);
+            metrics.log(req.method.as_ref(), route_label.as_deref(), path, token_id, reply.status);
+
+            // The TS API bumps the worker's generation in KV, which the in-process cache never sees
+            let status = reply.status;
+            if route.is_some_and(|r| r.kind.is_proxied()) && (200..300).contains(&status) {
+                cache_bump_generation(&storage.cache).await;
+            }
+            let metered = route_label.as_ref().map(|_| reply.body.clone());
+            send(reply);
+
+            for refresh in storage.take_refreshes() {
+                let refresh_metrics = RequestMetrics::new(metrics.request_id.clone());
+                let result = run_refresh(&storage, &refresh).await;
+                if let Err(ref e) = result {
+                    utils::log_error(&format!("Cache refresh failed for {}: {:?}", refresh.cache_key(), e));
+                }
+                refresh_metrics.log("REFRESH", None, refresh.cache_key(), None, if result.is_ok() { 200 } else { 500 });
+            }
+            if let Err(e) = scheduled::record_query_hits(&storage.store, &storage.take_query_hits()).await {
+                utils::log_error(&format!("[{}] Query hit recording failed: {:?}", metrics.request_id, e));
+            }
+            if let (Some(token_id), Some(label), Some(body)) = (token_id, route_label.as_deref(), metered) {
+                usage::record(&storage.store, token_id, label, status, &body).await;
+                if let Some(route) = route.filter(|r| r.kind.is_proxied() && (200..300).contains(&status)) {
+                    let now = current_timestamp() as i64;
+                    if let Err(e) = webhooks::on_write(&storage.store, &storage.cache, &self.webhooks, route.kind, &body, now).await {
+                        utils::log_error(&format!("[{}] Webhook dispatch failed: {:?}", metrics.request_id, e));
+                    }
+                }
+            }
+        })
+    }
+
+    /// The worker's cron trigger: expire overdue rentals, warm the in-process cache, queue webhooks
+    /// for new inventory changes and send what's due
+    pub fn run_scheduled(&self, db: &SqliteDb) {
+        let request_id = telemetry::request_id_or_generate(None);
+        let storage = Storage::new(SqlStore::new(db.clone()), self.cache.clone(), CacheConfig::from_env(&ProcessEnv));
+        let now = current_timestamp() as i64;
+        futures_executor::block_on(scheduled::run_all(&storage, &self.webhooks, now, &request_id));
+    }
+
+    /// Authenticate, route, rate limit and dispatch one request, like the worker's `handle`
+    async fn handle<S, C>(
+        &self,
+        req: &NativeRequest,
+        storage: &Storage<S, C>,
+        metrics: &RequestMetrics,
+        authed: &mut Option<TokenInfo>,
+        matched: &mut Option<&'static Route>,
+    ) -> ApiResult<Reply>
+    where
+        S: TableStore + TokenStore + OrderStore + UsageStore + WebhookStore,
+        C: Cache,
+    {
+        let url = Url::parse(&format!("http://localhost{}", req.url))
+            .map_err(|_| ApiError::InvalidParameter("Malformed request URL".to_string()))?;
+        let path = url.path();
+
+        if req.method == Method::Options {
+            let origin = req.header("Origin").map(str::to_string);
+            return Ok(Reply::new(204, cors::preflight_headers(&ProcessEnv, origin, path), String::new()));
+        }
+
+        if path == "/health" || path == "/api/public/health" {
+            return Reply::json(200, health("rust-native"));
+        }
+
+        let token_string = bearer_token(req.header("Authorization"))?;
+        let token = metrics.auth(authenticate(storage, &token_string, metrics)).await?;
+        *authed = Some(token.clone());
+
+        let (route, params) = find_route(&req.method, path)?;
+        *matched = Some(route);
+
+        let scope = RateLimitScope::for_method(&req.method);
+        let limit = RateLimit::resolve(&ProcessEnv, token.rate_limits.as_deref(), scope);
+        let decision = self.limiter.check(&token.id, scope, limit);
+
+        let mut reply = if !decision.allowed {
+            Reply::problem(ApiError::RateLimited, &metrics.request_id)
+        } else {
+            let query = parse_query_params(&url);
+            let body = String::from_utf8_lossy(&req.body);
+            let result = if route.kind.is_proxied() {
+                match prepare_write(&storage.store, &storage.cache, &token, route.kind, &body).await {
+                    Ok(bound) => self.proxy(req, bound.as_deref().map_or(&req.body[..], str::as_bytes), metrics),
+                    Err(e) => Err(e),
+                }
+            } else {
+                match dispatch(storage, &token, route, &params, &query, &body, metrics).await {
+                    Ok((status, data)) => Reply::json(status, data),
+                    Err(e) => Err(e),
+                }
+            };
+            result.unwrap_or_else(|e| Reply::problem(e, &metrics.request_id))
+        };
+        reply.add_headers(decision.headers());
+        Ok(reply)
+    }
+
+    /// Forward a write to the TypeScript API at UPSTREAM_API_URL with the given body
+    fn proxy(&self, req: &NativeRequest, body: &[u8], metrics: &RequestMetrics) -> ApiResult<Reply> {
+        let upstream = self
+            .config
+            .upstream
+            .as_deref()
+            .ok_or_else(|| ApiError::UpstreamUnavailable("UPSTREAM_API_URL is not set".to_string()))?;
+
+        let mut call = self
+            .upstream
+            .request(req.method.as_ref(), &format!("{}{}", upstream.trim_end_matches('/'), req.url));
+        for (name, value) in &req.headers {
+            if !is_hop_by_hop(name) && !name.eq_ignore_ascii_case(REQUEST_ID_HEADER) {
+                call = call.set(name, value);
+            }
+        }
+        call = call.set(REQUEST_ID_HEADER, &metrics.request_id);
+
+        // Upstream error statuses are passed through as they are
+        let response = match call.send_bytes(body) {
+            Ok(response) | Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(ApiError::UpstreamUnavailable(format!("Proxy fetch error: {}", e))),
+        };
+
+        let status = response.status();
+        let headers = response
+            .headers_names()
+            .into_iter()
+            .filter(|name| !is_hop_by_hop(name))
+            .filter_map(|name| response.header(&name).map(|value| (name.clone(), value.to_string())))
+            .collect();
+        let body = response
+            .into_string()
+            .map_err(|e| ApiError::UpstreamUnavailable(format!("Proxy read error: {}", e)))?;
+        Ok(Reply { status, headers, body })
+    }
+
+    /// Read a tiny_http request, respond and finish its deferred work
+    fn serve_request(&self, db: &SqliteDb, mut request: tiny_http::Request) {
+        let mut body = vec![];
+        if let Err(e) = request.as_reader().read_to_end(&mut body) {
+            utils::log_error(&format!("Failed to read request body: {}", e));
+            return;
+        }
+        let req = NativeRequest {
+            method: Method::from(request.method().as_str().to_string()),
+            url: request.url().to_string(),
+            headers: request
+                .headers()
+                .iter()
+                .map(|h| (h.field.as_str().to_string(), h.value.as_str().to_string()))
+                .collect(),
+            body,
+        };
+
+        self.respond(db, &req, |reply| {
+            let mut response = tiny_http::Response::from_data(reply.body).with_status_code(reply.status);
+            for (name, value) in reply.headers {
+                if let Ok(header) = tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()) {
+                    response.add_header(header);
+                }
+            }
+            if let Err(e) = request.respond(response) {
+                utils::log_error(&format!("Failed to send response: {}", e));
+            }
+        });
+    }
+}
+
+/// Run the server until the process exits
+pub fn serve(config: ServerConfig) -> std::io::Result<()> {
+    // Fail fast on a missing or unreadable database rather than on the first request
+    SqliteDb::open(&config.database).map_err(std::io::Error::other)?;
+    let http = Arc::new(tiny_http::Server::http(&config.listen).map_err(std::io::Error::other)?);
+
+    utils::log(&format!(
+        "store-public-api listening on {} ({} threads, database {}, upstream {})",
+        config.listen,
+        config.threads,
+        config.database.display(),
+        config.upstream.as_deref().unwrap_or("none")
+    ));
+
+    let server = Arc::new(Server::new(config));
+    if let Some(interval) = server.config.schedule_interval {
+        let server = server.clone();
+        std::thread::spawn(move || -> std::io::Result<()> {
+            let db = SqliteDb::open(&server.config.database).map_err(std::io::Error::other)?;
+            loop {
+                std::thread::sleep(Duration::from_secs(interval));
+                server.run_scheduled(&db);
+            }
+        });
+    }
+
+    let workers: Vec<_> = (0..server.config.threads)
+        .map(|_| {
+            let (http, server) = (http.clone(), server.clone());
+            std::thread::spawn(move || -> std::io::Result<()> {
+                let db = SqliteDb::open(&server.config.database).map_err(std::io::Error::other)?;
+                for request in http.incoming_requests() {
+                    server.serve_request(&db, request);
+                }
+                Ok(())
+            })
+        })
+        .collect();
+
+    for worker in workers {
+        worker.join().map_err(|_| std::io::Error::other("server thread panicked"))??;
+    }
+    Ok(())
+}
@@ -0,0 +1,268 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+
+use crate::error::{ApiError, ApiResult};
+use crate::masking::ColumnMasks;
+use crate::routes::RouteKind;
+use crate::store::{Cache, OrderStore, TableStore};
+use crate::tables::{allowed_table_ids, authorized_table};
+use crate::usage::parse_datetime;
+use crate::{PaginationInfo, TokenInfo};
+
+// ============================================================================
+// SALES AND RENTALS
+// ============================================================================
+//
+// Read-only sales and rentals of the tables a token can read items from (and of its
+// customer, for customer-bound tokens).
+
+const SALE_STATUSES: &[&str] = &["pending", "completed", "cancelled", "refunded"];
+const RENTAL_STATUSES: &[&str] = &["active", "released", "cancelled"];
+
+/// Filters shared by both lookups; dates are `YYYY-MM-DD HH:MM:SS` (UTC)
+#[derive(Debug, Default)]
+pub struct OrderFilters {
+    pub customer_id: Option<String>,
+    pub table_id: Option<String>,
+    pub status: Option<String>,
+    /// Sales by createdAt, rentals by rentedAt
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+/// A JSON column stored as text, expanded (null when it doesn't parse)
+fn json_text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<serde_json::Value, D::Error> {
+    let text = Option::<String>::deserialize(deserializer)?;
+    Ok(text.and_then(|t| serde_json::from_str(&t).ok()).unwrap_or(serde_json::Value::Null))
+}
+
+/// sales row; `itemSnapshot` is the item as it was sold
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Sale {
+    id: String,
+    sale_number: String,
+    table_id: String,
+    table_name: String,
+    item_id: String,
+    #[serde(deserialize_with = "json_text")]
+    item_snapshot: serde_json::Value,
+    customer_id: String,
+    quantity_sold: i64,
+    unit_price: f64,
+    total_amount: f64,
+    sale_status: String,
+    payment_method: Option<String>,
+    notes: Option<String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+}
+
+/// rentals row; `itemSnapshot` is the item as it was rented
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rental {
+    id: String,
+    rental_number: String,
+    table_id: String,
+    table_name: String,
+    item_id: String,
+    #[serde(deserialize_with = "json_text")]
+    item_snapshot: serde_json::Value,
+    customer_id: String,
+    unit_price: f64,
+    rental_status: String,
+    rented_at: Option<String>,
+    released_at: Option<String>,
+    overdue_at: Option<String>,
+    notes: Option<String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SalesResponse {
+    sales: Vec<Sale>,
+    count: usize,
+    total: i64,
+    pagination: PaginationInfo,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RentalsResponse {
+    rentals: Vec<Rental>,
+    count: usize,
+    total: i64,
+    pagination: PaginationInfo,
+}
+
+/// Read the common query parameters: pagination, then filters with `status` checked against `statuses`.
+/// A customer-bound token is filtered to its customer; asking for another one is refused.
+fn parse_query(
+    token: &TokenInfo,
+    query: &HashMap<String, String>,
+    statuses: &[&str],
+) -> ApiResult<(OrderFilters, u32, u32)> {
+    let limit: u32 = query.get("limit").and_then(|l| l.parse().ok()).unwrap_or(50).clamp(1, 200);
+    let offset: u32 = query.get("offset").and_then(|o| o.parse().ok()).unwrap_or(0);
+
+    let date = |name: &str| -> ApiResult<Option<String>> {
+        match query.get(name) {
+            Some(v) => parse_datetime(v)
+                .map(Some)
+                .ok_or_else(|| ApiError::InvalidParameter(format!("{} must be a date or ISO timestamp", name))),
+            None => Ok(None),
+        }
+    };
+    let requested = query.get("customerId").filter(|c| !c.is_empty()).cloned();
+    let customer_id = match (&token.customer_id, requested) {
+        (Some(bound), Some(requested)) if *bound != requested => return Err(ApiError::CustomerMismatch),
+        (Some(bound), _) => Some(bound.clone()),
+        (None, requested) => requested,
+    };
+    let filters = OrderFilters {
+        customer_id,
+        table_id: query.get("tableId").filter(|t| !t.is_empty()).cloned(),
+        status: query.get("status").cloned(),
+        from: date("from")?,
+        to: date("to")?,
+    };
+    if let Some(ref status) = filters.status
+        && !statuses.contains(&status.as_str())
+    {
+        return Err(ApiError::InvalidParameter(format!("status must be one of {}", statuses.join(", "))));
+    }
+    Ok((filters, limit, offset))
+}
+
+/// Tables the lookup may read: the requested one if the token can access it, otherwise
+/// every table the token can access (None = every public table)
+async fn scope<S: TableStore, C: Cache>(
+    store: &S,
+    cache: &C,
+    token: &TokenInfo,
+    filters: &OrderFilters,
+) -> ApiResult<Option<Vec<String>>> {
+    match filters.table_id {
+        Some(ref table_id) => Ok(Some(vec![authorized_table(store, cache, token, table_id, &mut None).await?.id])),
+        None => Ok(allowed_table_ids(store, cache, token).await?),
+    }
+}
+
+/// Hide and mask each order's item snapshot like its table's items, with the masks of the
+/// tables on this page
+async fn mask_snapshots<'a, S: TableStore>(
+    store: &S,
+    token: &TokenInfo,
+    snapshots: Vec<(&'a str, &'a mut serde_json::Value)>,
+) -> ApiResult<()> {
+    let mut table_ids: Vec<String> = vec![];
+    for (table_id, _) in &snapshots {
+        if !table_ids.iter().any(|id| id == table_id) {
+            table_ids.push(table_id.to_string());
+        }
+    }
+    let masks = ColumnMasks::load(store, token, &table_ids).await?;
+    for (table_id, snapshot) in snapshots {
+        if let serde_json::Value::Object(fields) = snapshot {
+            masks.apply(table_id, fields);
+        }
+    }
+    Ok(())
+}
+
+fn pagination(total: i64, limit: u32, offset: u32) -> PaginationInfo {
+    PaginationInfo {
+        total,
+        page: (offset / limit) + 1,
+        limit,
+        has_more: (offset + limit) < total as u32,
+    }
+}
+
+/// GET /api/public/sales?customerId=&status=&tableId=&from=&to= - Sales in accessible tables, newest first
+pub async fn list_sales<S: TableStore + OrderStore, C: Cache>(
+    store: &S,
+    cache: &C,
+    token: &TokenInfo,
+    query: &HashMap<String, String>,
+) -> ApiResult<SalesResponse> {
+    let (filters, limit, offset) = parse_query(token, query, SALE_STATUSES)?;
+    let table_ids = scope(store, cache, token, &filters).await?;
+    let (mut sales, total) = match table_ids {
+        Some(ref ids) if ids.is_empty() => (vec![], 0),
+        _ => store.sales(table_ids.as_deref(), &filters, limit, offset).await?,
+    };
+    let snapshots = sales.iter_mut().map(|s| (s.table_id.as_str(), &mut s.item_snapshot)).collect();
+    mask_snapshots(store, token, snapshots).await?;
+    Ok(SalesResponse { count: sales.len(), sales, total, pagination: pagination(total, limit, offset) })
+}
+
+/// GET /api/public/rentals?customerId=&status=&tableId=&from=&to= - Rentals in accessible tables, newest first
+pub async fn list_rentals<S: TableStore + OrderStore, C: Cache>(
+    store: &S,
+    cache: &C,
+    token: &TokenInfo,
+    query: &HashMap<String, String>,
+) -> ApiResult<RentalsResponse> {
+    let (filters, limit, offset) = parse_query(token, query, RENTAL_STATUSES)?;
+    let table_ids = scope(store, cache, token, &filters).await?;
+    let (mut rentals, total) = match table_ids {
+        Some(ref ids) if ids.is_empty() => (vec![], 0),
+        _ => store.rentals(table_ids.as_deref(), &filters, limit, offset).await?,
+    };
+    let snapshots = rentals.iter_mut().map(|r| (r.table_id.as_str(), &mut r.item_snapshot)).collect();
+    mask_snapshots(store, token, snapshots).await?;
+    Ok(RentalsResponse { count: rentals.len(), rentals, total, pagination: pagination(total, limit, offset) })
+}
+
+// ============================================================================
+// CUSTOMER-BOUND WRITES
+// ============================================================================
+
+/// Non-empty string field of a write body
+fn body_str<'a>(body: &'a serde_json::Map<String, serde_json::Value>, key: &str) -> Option<&'a str> {
+    body.get(key).and_then(|v| v.as_str()).filter(|v| !v.is_empty())
+}
+
+/// Hold a proxied write by a customer-bound token to its customer before it's forwarded.
+/// Buy and rent bodies without a `customerId` get the token's; releases must target one of
+/// its rentals (unknown rentals are left for the order service to reject).
+/// Returns the body to forward instead, if it changed.
+pub async fn bind_write<S: OrderStore>(
+    store: &S,
+    token: &TokenInfo,
+    kind: RouteKind,
+    body: &str,
+) -> ApiResult<Option<String>> {
+    let Some(ref bound) = token.customer_id else {
+        return Ok(None);
+    };
+    let mut data = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(data)) => data,
+        _ => return Err(ApiError::InvalidParameter("Request body must be a JSON object".to_string())),
+    };
+
+    match kind {
+        RouteKind::Buy | RouteKind::Rent => match body_str(&data, "customerId") {
+            Some(customer_id) if customer_id == bound => Ok(None),
+            Some(_) => Err(ApiError::CustomerMismatch),
+            None => {
+                data.insert("customerId".to_string(), bound.as_str().into());
+                Ok(Some(serde_json::Value::Object(data).to_string()))
+            }
+        },
+        RouteKind::Release => {
+            let owner = match (body_str(&data, "rentalId"), body_str(&data, "tableId"), body_str(&data, "itemId")) {
+                (Some(rental_id), _, _) => store.rental_customer(rental_id).await?,
+                (None, Some(table_id), Some(item_id)) => store.active_rental_customer(table_id, item_id).await?,
+                _ => None,
+            };
+            match owner {
+                Some(ref owner) if owner != bound => Err(ApiError::CustomerMismatch),
+                _ => Ok(None),
+            }
+        }
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,150 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::currency::{amount, round_amount};
+use crate::error::{ApiError, ApiResult};
+use crate::routes::RouteKind;
+use crate::store::TableStore;
+use crate::TokenInfo;
+
+// ============================================================================
+// RESELLER PRICE RULES
+// ============================================================================
+//
+// A token's priceRules mark up `price` and `fee` on reads and in forwarded buy/rent bodies,
+// in the table's base currency (before any `?currency=` conversion).
+
+/// Fields a price rule marks up
+const PRICED_FIELDS: &[&str] = &["price", "fee"];
+
+/// How a marked-up amount is rounded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    None,
+    /// To the nearest cent
+    #[default]
+    Cents,
+    /// Up to a whole unit
+    Whole,
+    /// Up to the next .99
+    NinetyNine,
+}
+
+/// Percentage and/or fixed markup; the percentage applies first
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PriceRule {
+    #[serde(default)]
+    pub percent: f64,
+    #[serde(default)]
+    pub fixed: f64,
+    #[serde(default)]
+    pub rounding: Rounding,
+}
+
+impl PriceRule {
+    /// The marked-up amount (api/src/lib/price-rules.ts repeats this to check forwarded unitPrices)
+    pub fn apply(&self, amount: f64) -> f64 {
+        let marked = amount * (1.0 + self.percent / 100.0) + self.fixed;
+        // Rounding up starts from cents so float noise can't push an exact amount up a step
+        match self.rounding {
+            Rounding::None => marked,
+            Rounding::Cents => round_amount(marked),
+            Rounding::Whole => round_amount(marked).ceil(),
+            Rounding::NinetyNine => round_amount((round_amount(marked) + 0.01).ceil() - 0.01),
+        }
+    }
+}
+
+/// Token `priceRules` JSON column: a table's own rule replaces the default one
+#[derive(Debug, Default, Deserialize)]
+pub struct PriceRules {
+    default: Option<PriceRule>,
+    #[serde(default)]
+    tables: HashMap<String, PriceRule>,
+}
+
+impl PriceRules {
+    /// The token's rules, if it has any (rules that don't parse are ignored, like rate limits)
+    pub fn for_token(token: &TokenInfo) -> Option<Self> {
+        token.price_rules.as_deref().and_then(|rules| serde_json::from_str(rules).ok())
+    }
+
+    fn rule(&self, table_id: &str) -> Option<&PriceRule> {
+        self.tables.get(table_id).or(self.default.as_ref())
+    }
+
+    /// Whether a column is one price rules mark up
+    pub fn is_priced(column: &str) -> bool {
+        PRICED_FIELDS.contains(&column)
+    }
+
+    /// A table's amount marked up; values that aren't amounts are returned as they are
+    pub fn mark_up_value(&self, table_id: &str, value: &serde_json::Value) -> serde_json::Value {
+        match (self.rule(table_id), amount(value)) {
+            (Some(rule), Some(amount)) => serde_json::json!(rule.apply(amount)),
+            _ => value.clone(),
+        }
+    }
+
+    /// Mark up a table's price/fee fields in place
+    pub fn mark_up(&self, table_id: &str, fields: &mut serde_json::Map<String, serde_json::Value>) {
+        for field in PRICED_FIELDS {
+            if let Some(value) = fields.get_mut(*field) {
+                *value = self.mark_up_value(table_id, value);
+            }
+        }
+    }
+
+    /// Mark up a flattened record (as from `flatten_record`)
+    pub fn mark_up_record(&self, record: &mut serde_json::Value) {
+        let serde_json::Value::Object(fields) = record else {
+            return;
+        };
+        if let Some(table_id) = fields.get("tableId").and_then(|id| id.as_str()).map(str::to_string) {
+            self.mark_up(&table_id, fields);
+        }
+    }
+
+    /// Mark up an item in the nested `{id, data, ...}` shape
+    pub fn mark_up_item(&self, table_id: &str, item: &mut serde_json::Value) {
+        if let Some(serde_json::Value::Object(data)) = item.get_mut("data") {
+            self.mark_up(table_id, data);
+        }
+    }
+}
+
+/// Set the marked-up `unitPrice` on a proxied buy/rent by a token with price rules, whatever
+/// the body said. Unknown items fail with `ItemNotFound`; bodies without a tableId/itemId, and
+/// items without a price, are left for the order service to reject. Returns the body to forward
+/// instead, if it changed.
+pub async fn quote_write<S: TableStore>(
+    store: &S,
+    token: &TokenInfo,
+    kind: RouteKind,
+    body: &str,
+) -> ApiResult<Option<String>> {
+    if !matches!(kind, RouteKind::Buy | RouteKind::Rent) {
+        return Ok(None);
+    }
+    let Some(rules) = PriceRules::for_token(token) else {
+        return Ok(None);
+    };
+    let mut data = match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(data)) => data,
+        _ => return Err(ApiError::InvalidParameter("Request body must be a JSON object".to_string())),
+    };
+    let field = |key: &str| data.get(key).and_then(|v| v.as_str()).filter(|v| !v.is_empty()).map(str::to_string);
+    let (Some(table_id), Some(item_id)) = (field("tableId"), field("itemId")) else {
+        return Ok(None);
+    };
+
+    let row = store.table_row(&table_id, &item_id).await?.ok_or(ApiError::ItemNotFound)?;
+    let item: serde_json::Value = serde_json::from_str(&row.data).unwrap_or(serde_json::json!({}));
+    let Some(price) = item.get("price").and_then(amount).filter(|price| *price > 0.0) else {
+        return Ok(None);
+    };
+    let unit_price = rules.rule(&table_id).map_or(price, |rule| rule.apply(price));
+    data.insert("unitPrice".to_string(), serde_json::json!(unit_price));
+    Ok(Some(serde_json::Value::Object(data).to_string()))
+}
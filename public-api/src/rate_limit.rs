@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use worker::*;
+
+use crate::utils::{self, Vars};
+
+// ============================================================================
+// RATE LIMITS
+// ============================================================================
+
+const DEFAULT_READ_PER_MINUTE: u32 = 600;
+const DEFAULT_READ_BURST: u32 = 100;
+const DEFAULT_WRITE_PER_MINUTE: u32 = 60;
+const DEFAULT_WRITE_BURST: u32 = 10;
+
+/// Which bucket a request draws from - writes (POST/DELETE) are limited separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitScope {
+    Read,
+    Write,
+}
+
+impl RateLimitScope {
+    pub fn for_method(method: &Method) -> Self {
+        match method {
+            Method::Post | Method::Delete => RateLimitScope::Write,
+            _ => RateLimitScope::Read,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitScope::Read => "read",
+            RateLimitScope::Write => "write",
+        }
+    }
+}
+
+/// Token bucket settings: refills at `per_minute`, holds at most `burst` requests
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct RateLimit {
+    #[serde(rename = "perMinute")]
+    pub per_minute: u32,
+    pub burst: u32,
+}
+
+/// Token `rateLimits` JSON column - each scope is optional
+#[derive(Debug, Default, Deserialize)]
+struct TokenRateLimits {
+    read: Option<TokenRateLimit>,
+    write: Option<TokenRateLimit>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenRateLimit {
+    #[serde(rename = "perMinute")]
+    per_minute: u32,
+    burst: Option<u32>,
+}
+
+impl RateLimit {
+    /// Resolve the limit for a scope: token override first, then RATE_LIMIT_* vars, then built-in defaults
+    pub fn resolve(vars: &impl Vars, token_limits: Option<&str>, scope: RateLimitScope) -> Self {
+        let var = |name: &str, default: u32| utils::var_or(vars, name, default);
+        let default = match scope {
+            RateLimitScope::Read => RateLimit {
+                per_minute: var("RATE_LIMIT_READ_PER_MINUTE", DEFAULT_READ_PER_MINUTE),
+                burst: var("RATE_LIMIT_READ_BURST", DEFAULT_READ_BURST),
+            },
+            RateLimitScope::Write => RateLimit {
+                per_minute: var("RATE_LIMIT_WRITE_PER_MINUTE", DEFAULT_WRITE_PER_MINUTE),
+                burst: var("RATE_LIMIT_WRITE_BURST", DEFAULT_WRITE_BURST),
+            },
+        };
+
+        let limits: TokenRateLimits = token_limits
+            .and_then(|s| serde_json::from_str(s).ok())
+            .unwrap_or_default();
+        let token_limit = match scope {
+            RateLimitScope::Read => limits.read,
+            RateLimitScope::Write => limits.write,
+        };
+
+        match token_limit {
+            Some(l) => RateLimit {
+                per_minute: l.per_minute.max(1),
+                burst: l.burst.unwrap_or(l.per_minute).max(1),
+            },
+            None => default,
+        }
+    }
+}
+
+/// Outcome of drawing one request from a bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    /// Bucket capacity, the unit `remaining` counts down from
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again
+    pub reset: u64,
+    /// Seconds until the next request would be allowed (0 when allowed)
+    #[serde(rename = "retryAfter")]
+    pub retry_after: u64,
+}
+
+impl RateLimitDecision {
+    /// Standard rate limit headers for the response
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = vec![
+            ("X-RateLimit-Limit", self.limit.to_string()),
+            ("X-RateLimit-Remaining", self.remaining.to_string()),
+            ("X-RateLimit-Reset", self.reset.to_string()),
+        ];
+        if !self.allowed {
+            headers.push(("Retry-After", self.retry_after.max(1).to_string()));
+        }
+        headers
+    }
+}
+
+/// Draw one request from a token bucket for this token and scope
+/// Fails open (returns None) when the RATE_LIMITER binding is missing or unreachable,
+/// so a limiter outage never takes the catalog down with it
+pub async fn check_rate_limit(
+    env: &Env,
+    token_id: &str,
+    scope: RateLimitScope,
+    limit: RateLimit,
+) -> Option<RateLimitDecision> {
+    let result = async {
+        let namespace = env.durable_object("RATE_LIMITER")?;
+        let stub = namespace
+            .id_from_name(&format!("{}:{}", token_id, scope.as_str()))?
+            .get_stub()?;
+
+        let mut init = RequestInit::new();
+        init.with_method(Method::Post);
+        init.with_body(Some(serde_json::to_string(&limit)?.into()));
+        let req = Request::new_with_init("https://rate-limiter/take", &init)?;
+
+        let mut response = stub.fetch_with_request(req).await?;
+        response.json::<RateLimitDecision>().await
+    }
+    .await;
+
+    match result {
+        Ok(decision) => Some(decision),
+        Err(e) => {
+            utils::log_error(&format!("Rate limiter unavailable: {:?}", e));
+            None
+        }
+    }
+}
+
+// ============================================================================
+// TOKEN BUCKET
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Bucket {
+    tokens: f64,
+    #[serde(rename = "updatedAtMs")]
+    updated_at_ms: f64,
+}
+
+/// Refill the bucket for elapsed time, then try to take one request from it
+pub fn take(bucket: Option<Bucket>, limit: RateLimit, now_ms: f64) -> (Bucket, RateLimitDecision) {
+    let capacity = limit.burst as f64;
+    let refill_per_ms = limit.per_minute as f64 / 60_000.0;
+
+    let mut bucket = bucket.unwrap_or(Bucket { tokens: capacity, updated_at_ms: now_ms });
+    let elapsed = (now_ms - bucket.updated_at_ms).max(0.0);
+    bucket.tokens = (bucket.tokens + elapsed * refill_per_ms).min(capacity);
+    bucket.updated_at_ms = now_ms;
+
+    let allowed = bucket.tokens >= 1.0;
+    if allowed {
+        bucket.tokens -= 1.0;
+    }
+
+    let secs_until = |tokens: f64| ((tokens.max(0.0) / refill_per_ms) / 1000.0).ceil() as u64;
+    let decision = RateLimitDecision {
+        allowed,
+        limit: limit.burst,
+        remaining: bucket.tokens.floor() as u32,
+        reset: secs_until(capacity - bucket.tokens),
+        retry_after: if allowed { 0 } else { secs_until(1.0 - bucket.tokens) },
+    };
+
+    (bucket, decision)
+}
+
+const BUCKET_STORAGE_KEY: &str = "bucket";
+
+/// One instance per token and scope (named "{tokenId}:{scope}"), holding its bucket in storage
+/// so it survives eviction (and in memory between requests)
+/// A Durable Object rather than a KV counter: KV allows ~1 write/sec per key and isn't atomic
+#[durable_object(fetch)]
+pub struct RateLimiter {
+    state: State,
+    bucket: RefCell<Option<Bucket>>,
+}
+
+impl DurableObject for RateLimiter {
+    fn new(state: State, _env: Env) -> Self {
+        Self {
+            state,
+            bucket: RefCell::new(None),
+        }
+    }
+
+    async fn fetch(&self, mut req: Request) -> Result<Response> {
+        let limit: RateLimit = req.json().await?;
+        let storage = self.state.storage();
+        let cached = *self.bucket.borrow();
+        let previous = match cached {
+            Some(bucket) => Some(bucket),
+            None => storage.get(BUCKET_STORAGE_KEY).await?,
+        };
+        let (bucket, decision) = take(previous, limit, js_sys::Date::now());
+        *self.bucket.borrow_mut() = Some(bucket);
+        storage.put(BUCKET_STORAGE_KEY, bucket).await?;
+        Response::from_json(&decision)
+    }
+}
@@ -0,0 +1,141 @@
+use percent_encoding::percent_decode_str;
+use worker::Method;
+
+// ============================================================================
+// ROUTE TABLE
+// ============================================================================
+
+/// Every public API endpoint served (or proxied) by the worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteKind {
+    Tables,
+    TablesSearch,
+    TableItems,
+    TableItem,
+    ItemAvailability,
+    Records,
+    Values,
+    Usage,
+    Sales,
+    Rentals,
+    Webhooks,
+    WebhookDeliveries,
+    CreateWebhook,
+    DeleteWebhook,
+    Buy,
+    Rent,
+    Release,
+}
+
+#[derive(Debug)]
+pub struct Route {
+    pub kind: RouteKind,
+    pub method: Method,
+    /// Path pattern; `:name` segments capture a parameter
+    pub pattern: &'static str,
+    /// Request headers the route accepts beyond the common ones (advertised in CORS preflights)
+    pub headers: &'static [&'static str],
+}
+
+impl RouteKind {
+    /// Catalog writes are forwarded to the order service rather than served from storage
+    pub fn is_proxied(&self) -> bool {
+        matches!(self, RouteKind::Buy | RouteKind::Rent | RouteKind::Release)
+    }
+}
+
+impl Route {
+    /// Stable label for logs and usage metering, e.g. `GET /api/public/tables/:id/items`
+    pub fn label(&self) -> String {
+        format!("{} {}", self.method, self.pattern)
+    }
+}
+
+/// Writes are proxied with their idempotency key so retries don't double-book
+const WRITE_HEADERS: &[&str] = &["Idempotency-Key"];
+
+/// Order matters: static segments must come before parameter segments at the same position
+pub static ROUTES: &[Route] = &[
+    Route { kind: RouteKind::Tables, method: Method::Get, pattern: "/api/public/tables", headers: &[] },
+    Route { kind: RouteKind::TablesSearch, method: Method::Get, pattern: "/api/public/tables/search", headers: &[] },
+    Route { kind: RouteKind::TableItems, method: Method::Get, pattern: "/api/public/tables/:id/items", headers: &[] },
+    Route { kind: RouteKind::TableItem, method: Method::Get, pattern: "/api/public/tables/:id/items/:itemId", headers: &[] },
+    Route { kind: RouteKind::ItemAvailability, method: Method::Get, pattern: "/api/public/tables/:id/items/:itemId/availability", headers: &[] },
+    Route { kind: RouteKind::Records, method: Method::Get, pattern: "/api/public/records", headers: &[] },
+    Route { kind: RouteKind::Values, method: Method::Get, pattern: "/api/public/values/:column", headers: &[] },
+    Route { kind: RouteKind::Usage, method: Method::Get, pattern: "/api/public/usage", headers: &[] },
+    Route { kind: RouteKind::Sales, method: Method::Get, pattern: "/api/public/sales", headers: &[] },
+    Route { kind: RouteKind::Rentals, method: Method::Get, pattern: "/api/public/rentals", headers: &[] },
+    Route { kind: RouteKind::Webhooks, method: Method::Get, pattern: "/api/public/webhooks", headers: &[] },
+    Route { kind: RouteKind::WebhookDeliveries, method: Method::Get, pattern: "/api/public/webhooks/deliveries", headers: &[] },
+    Route { kind: RouteKind::CreateWebhook, method: Method::Post, pattern: "/api/public/webhooks", headers: &[] },
+    Route { kind: RouteKind::DeleteWebhook, method: Method::Delete, pattern: "/api/public/webhooks/:id", headers: &[] },
+    // Write operations are proxied to the TypeScript API which has the business logic
+    Route { kind: RouteKind::Buy, method: Method::Post, pattern: "/api/public/buy", headers: WRITE_HEADERS },
+    Route { kind: RouteKind::Rent, method: Method::Post, pattern: "/api/public/rent", headers: WRITE_HEADERS },
+    Route { kind: RouteKind::Release, method: Method::Post, pattern: "/api/public/release", headers: WRITE_HEADERS },
+];
+
+/// Path parameters captured from a matched route, in pattern order
+pub type RouteParams = Vec<String>;
+
+/// Match a path against a pattern, capturing `:name` segments percent-decoded (table names may
+/// have spaces or non-ASCII letters); a segment that isn't UTF-8 once decoded matches nothing
+fn match_pattern(pattern: &str, path: &str) -> Option<RouteParams> {
+    let pattern_parts: Vec<&str> = pattern.split('/').collect();
+    let path_parts: Vec<&str> = path.split('/').collect();
+    if pattern_parts.len() != path_parts.len() {
+        return None;
+    }
+
+    let mut params = vec![];
+    for (p, s) in pattern_parts.iter().zip(&path_parts) {
+        if p.starts_with(':') {
+            if s.is_empty() {
+                return None;
+            }
+            params.push(percent_decode_str(s).decode_utf8().ok()?.into_owned());
+        } else if p != s {
+            return None;
+        }
+    }
+    Some(params)
+}
+
+/// Find the route for a request
+pub fn match_route(method: &Method, path: &str) -> Option<(&'static Route, RouteParams)> {
+    ROUTES
+        .iter()
+        .filter(|route| route.method == *method)
+        .find_map(|route| match_pattern(route.pattern, path).map(|params| (route, params)))
+}
+
+/// Routes whose pattern matches a path, or every route when none does
+fn routes_for(path: &str) -> Vec<&'static Route> {
+    let matching: Vec<&Route> = ROUTES.iter().filter(|route| match_pattern(route.pattern, path).is_some()).collect();
+    if matching.is_empty() { ROUTES.iter().collect() } else { matching }
+}
+
+/// Methods served at a path, for CORS preflights
+pub fn methods_for(path: &str) -> Vec<Method> {
+    let mut methods: Vec<Method> = vec![];
+    for route in routes_for(path) {
+        if !methods.contains(&route.method) {
+            methods.push(route.method.clone());
+        }
+    }
+    methods
+}
+
+/// Route-specific request headers accepted at a path, for CORS preflights
+pub fn headers_for(path: &str) -> Vec<&'static str> {
+    let mut headers: Vec<&'static str> = vec![];
+    for route in routes_for(path) {
+        for header in route.headers {
+            if !headers.contains(header) {
+                headers.push(header);
+            }
+        }
+    }
+    headers
+}
@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, HashMap};
+use serde::Deserialize;
+use worker::{Env, Result};
+
+use crate::cloudflare::D1Sql;
+use crate::store::{Cache, OrderStore, RentalStore, SqlStore, TableStore, UsageStore, WebhookStore};
+use crate::telemetry::RequestMetrics;
+use crate::webhooks::{self, WebhookSender};
+use crate::{
+    cache_bump_generation, cache_get_generation, cache_get_public_tables, cache_get_query_results,
+    cache_set_public_tables, cache_set_query_results, cache_try_lock, cache_unlock, load_records,
+    query_cache_key, utils, CacheLookup, RecordsQuery, Storage, CACHE_KEY_PUBLIC_TABLES,
+};
+
+// ============================================================================
+// SCHEDULED JOBS
+// ============================================================================
+//
+// Run by the worker's cron trigger and the native server's scheduler thread, in
+// this order: overdue rentals are expired first so the webhook scan picks up the
+// releases in the same run, then the caches are warmed, then webhooks go out.
+
+/// Rentals expired per run; the rest wait for the next one
+const EXPIRY_BATCH: u32 = 100;
+/// Who expiry actions are logged as in inventoryTransactions
+const EXPIRY_ACTOR: &str = "system:rental-expiry";
+/// Rental note left by an automatic release (existing notes are kept)
+const EXPIRY_NOTE: &str = "Released automatically: rental period expired";
+
+// ============================================================================
+// DATA STRUCTURES
+// ============================================================================
+
+/// An active rental past the deadline of its table's expiry rule
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverdueRental {
+    pub id: String,
+    pub table_id: String,
+    pub table_name: String,
+    pub item_id: String,
+    /// `flag` or `release`
+    pub action: String,
+}
+
+/// inventoryTransactions row to append
+#[derive(Debug)]
+pub struct NewTransaction {
+    pub id: String,
+    pub table_id: String,
+    pub table_name: String,
+    pub item_id: String,
+    pub transaction_type: &'static str,
+    pub previous_data: Option<String>,
+    pub new_data: Option<String>,
+    pub reference_id: String,
+    pub created_by: &'static str,
+}
+
+/// popularQueries row: a cacheable records query worth warming
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PopularQuery {
+    pub filters: String,
+    pub page_limit: u32,
+    pub page_offset: u32,
+}
+
+/// A request for a cacheable records query, counted after the response
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryHit {
+    pub filters: String,
+    pub limit: u32,
+    pub offset: u32,
+}
+
+impl QueryHit {
+    pub fn new(where_conditions: &HashMap<String, String>, limit: u32, offset: u32) -> Self {
+        // Sorted keys, so the same filters in any order count as one query
+        let sorted: BTreeMap<&String, &String> = where_conditions.iter().collect();
+        Self { filters: serde_json::to_string(&sorted).unwrap_or_default(), limit, offset }
+    }
+}
+
+/// What rental expiry did, for the logs
+#[derive(Debug, Default)]
+pub struct ExpiryStats {
+    pub flagged: usize,
+    pub released: usize,
+}
+
+/// What cache warming did, for the logs
+#[derive(Debug, Default)]
+pub struct WarmStats {
+    pub public_tables: bool,
+    pub queries: usize,
+}
+
+// ============================================================================
+// RENTAL EXPIRY
+// ============================================================================
+
+/// A boolean field of an item, accepting "true" as the admin UI may store it
+fn flag_field(data: &serde_json::Value, key: &str) -> bool {
+    matches!(data.get(key), Some(serde_json::Value::Bool(true))) || data.get(key).and_then(|v| v.as_str()) == Some("true")
+}
+
+/// Flag or release every overdue rental according to its table's rule, logging each action in
+/// inventoryTransactions. Releases leave the item used and unavailable, as a manual release does.
+pub async fn expire_rentals<S, C>(store: &S, cache: &C, now: i64) -> Result<ExpiryStats>
+where
+    S: TableStore + RentalStore,
+    C: Cache,
+{
+    let mut stats = ExpiryStats::default();
+    for rental in store.overdue_rentals(now, EXPIRY_BATCH).await? {
+        let row = store.table_row(&rental.table_id, &rental.item_id).await?;
+        let previous = row.and_then(|row| serde_json::from_str::<serde_json::Value>(&row.data).ok());
+        let transaction = |transaction_type, new_data: Option<&serde_json::Value>| NewTransaction {
+            id: utils::random_id(),
+            table_id: rental.table_id.clone(),
+            table_name: rental.table_name.clone(),
+            item_id: rental.item_id.clone(),
+            transaction_type,
+            previous_data: previous.as_ref().map(|data| data.to_string()),
+            new_data: new_data.map(|data| data.to_string()),
+            reference_id: rental.id.clone(),
+            created_by: EXPIRY_ACTOR,
+        };
+
+        if rental.action == "release" {
+            if !store.release_rental(&rental.id, EXPIRY_NOTE).await? {
+                continue;
+            }
+            // Only a rented item (not used, not available) changes state, like the TS API's canReleaseItem
+            let rented = previous.as_ref().is_some_and(|data| !flag_field(data, "used") && !flag_field(data, "available"));
+            let mut current = previous.clone();
+            if rented && let Some(serde_json::Value::Object(data)) = current.as_mut() {
+                data.insert("used".to_string(), true.into());
+                data.insert("available".to_string(), false.into());
+                let updated = serde_json::Value::Object(data.clone()).to_string();
+                store.update_row_data(&rental.table_id, &rental.item_id, &updated).await?;
+                cache_bump_generation(cache).await;
+            }
+            store.insert_inventory_transaction(&transaction("release", current.as_ref())).await?;
+            stats.released += 1;
+        } else if store.flag_rental_overdue(&rental.id).await? {
+            store.insert_inventory_transaction(&transaction("overdue", previous.as_ref())).await?;
+            stats.flagged += 1;
+        }
+    }
+    Ok(stats)
+}
+
+// ============================================================================
+// CACHE WARMING
+// ============================================================================
+
+/// Rebuild the public tables list and the most requested records queries (up to
+/// `CACHE_WARM_QUERIES`) unless they're fresh, so the next request doesn't pay for the miss.
+/// Entries another request is already rebuilding (locked) are left to it.
+pub async fn warm_caches<S, C>(storage: &Storage<S, C>) -> Result<WarmStats>
+where
+    S: TableStore + UsageStore,
+    C: Cache,
+{
+    let mut stats = WarmStats::default();
+    let lookup = cache_get_public_tables(&storage.cache, &storage.config).await;
+    if !matches!(lookup, CacheLookup::Fresh(_)) && cache_try_lock(&storage.cache, CACHE_KEY_PUBLIC_TABLES).await {
+        let tables = storage.store.list_tables(None).await;
+        if let Ok(ref tables) = tables {
+            cache_set_public_tables(&storage.cache, tables, &storage.config).await;
+        }
+        cache_unlock(&storage.cache, CACHE_KEY_PUBLIC_TABLES).await;
+        tables?;
+        stats.public_tables = true;
+    }
+
+    storage.store.prune_query_hits().await?;
+    if storage.config.warm_queries == 0 {
+        return Ok(stats);
+    }
+    let popular = storage.store.popular_queries(storage.config.warm_queries).await?;
+    if popular.is_empty() {
+        return Ok(stats);
+    }
+
+    // Cached query results are only kept for unrestricted tokens, so every key covers all public tables
+    let tables = storage.store.query_tables(None).await?;
+    if tables.is_empty() {
+        return Ok(stats);
+    }
+    let table_ids: Vec<String> = tables.iter().map(|t| t.id.clone()).collect();
+    let generation = cache_get_generation(&storage.cache).await;
+    let table_map: HashMap<_, _> = tables.into_iter().map(|t| (t.id.clone(), t)).collect();
+
+    for query in popular {
+        let Ok(where_conditions) = serde_json::from_str::<HashMap<String, String>>(&query.filters) else {
+            continue;
+        };
+        let cache_key = query_cache_key(&table_ids, &generation, &where_conditions, query.page_limit, query.page_offset);
+        let lookup = cache_get_query_results(&storage.cache, &cache_key, &storage.config).await;
+        if matches!(lookup, CacheLookup::Fresh(_)) || !cache_try_lock(&storage.cache, &cache_key).await {
+            continue;
+        }
+
+        let records_query = RecordsQuery {
+            table_ids: table_ids.clone(),
+            table_map: table_map.clone(),
+            where_conditions,
+            limit: query.page_limit,
+            offset: query.page_offset,
+        };
+        let loaded = load_records(&storage.store, &records_query).await;
+        if let Ok((ref records, total)) = loaded {
+            cache_set_query_results(&storage.cache, &cache_key, records, total, &storage.config).await;
+        }
+        cache_unlock(&storage.cache, &cache_key).await;
+        loaded?;
+        stats.queries += 1;
+    }
+    Ok(stats)
+}
+
+// ============================================================================
+// RUNNER
+// ============================================================================
+
+/// Run every scheduled job, logging each outcome on its own so one failing doesn't skip the rest
+pub async fn run_all<S, C, W>(storage: &Storage<S, C>, sender: &W, now: i64, request_id: &str)
+where
+    S: TableStore + OrderStore + RentalStore + UsageStore + WebhookStore,
+    C: Cache,
+    W: WebhookSender,
+{
+    match expire_rentals(&storage.store, &storage.cache, now).await {
+        Ok(stats) => utils::log(&format!("[{}] Rental expiry: {:?}", request_id, stats)),
+        Err(e) => utils::log_error(&format!("[{}] Rental expiry failed: {:?}", request_id, e)),
+    }
+    match warm_caches(storage).await {
+        Ok(stats) => utils::log(&format!("[{}] Cache warming: {:?}", request_id, stats)),
+        Err(e) => utils::log_error(&format!("[{}] Cache warming failed: {:?}", request_id, e)),
+    }
+    match webhooks::run_scheduled(&storage.store, &storage.cache, sender, now).await {
+        Ok(stats) => utils::log(&format!("[{}] Webhooks: {:?}", request_id, stats)),
+        Err(e) => utils::log_error(&format!("[{}] Webhooks failed: {:?}", request_id, e)),
+    }
+}
+
+/// Count the cacheable records queries a request made
+pub async fn record_query_hits<S: UsageStore>(store: &S, hits: &[QueryHit]) -> Result<()> {
+    for hit in hits {
+        store.record_query_hit(&hit.filters, hit.limit, hit.offset).await?;
+    }
+    Ok(())
+}
+
+// ============================================================================
+// WORKER GLUE
+// ============================================================================
+
+/// Count a request's records queries in D1 after the response is sent (via wait_until)
+pub async fn record_query_hits_in_background(env: Env, request_id: String, hits: Vec<QueryHit>) {
+    let metrics = RequestMetrics::new(request_id);
+    let result = async {
+        let store = SqlStore::new(D1Sql::new(&env, &metrics)?);
+        record_query_hits(&store, &hits).await
+    }
+    .await;
+    if let Err(e) = result {
+        utils::log_error(&format!("[{}] Query hit recording failed: {:?}", metrics.request_id, e));
+    }
+}
+
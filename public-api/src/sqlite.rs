@@ -0,0 +1,83 @@
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::Connection;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::store::SqlDatabase;
+
+// ============================================================================
+// SQLITE BACKEND
+// ============================================================================
+
+/// How long a query waits on another connection's write lock before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// SQLite behind the SqlDatabase trait - a local copy of the D1 database for the
+/// native server, or an in-memory one for tests. One connection per thread.
+#[derive(Clone)]
+pub struct SqliteDb {
+    conn: Rc<Connection>,
+}
+
+impl SqliteDb {
+    /// Open a database file (a D1 export with the Prisma schema already applied)
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        Ok(Self { conn: Rc::new(conn) })
+    }
+
+    /// Open an empty in-memory database
+    #[cfg(test)]
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Ok(Self { conn: Rc::new(Connection::open_in_memory()?) })
+    }
+
+    #[cfg(test)]
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+fn to_sql(value: &serde_json::Value) -> SqlValue {
+    match value {
+        serde_json::Value::Null => SqlValue::Null,
+        serde_json::Value::Bool(b) => SqlValue::Integer(*b as i64),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => SqlValue::Integer(i),
+            None => SqlValue::Real(n.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(s) => SqlValue::Text(s.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+fn to_json(value: ValueRef) -> serde_json::Value {
+    match value {
+        ValueRef::Null | ValueRef::Blob(_) => serde_json::Value::Null,
+        ValueRef::Integer(i) => i.into(),
+        ValueRef::Real(f) => f.into(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into(),
+    }
+}
+
+impl SqlDatabase for SqliteDb {
+    async fn query(&self, sql: &str, params: &[serde_json::Value]) -> worker::Result<Vec<serde_json::Value>> {
+        let err = |e: rusqlite::Error| worker::Error::RustError(format!("{}: {}", sql, e));
+        let mut stmt = self.conn.prepare(sql).map_err(err)?;
+        let names: Vec<String> = stmt.column_names().iter().map(|n| n.to_string()).collect();
+        let params: Vec<SqlValue> = params.iter().map(to_sql).collect();
+        let mut rows = stmt.query(rusqlite::params_from_iter(params)).map_err(err)?;
+
+        let mut out = vec![];
+        while let Some(row) = rows.next().map_err(err)? {
+            let mut obj = serde_json::Map::new();
+            for (i, name) in names.iter().enumerate() {
+                obj.insert(name.clone(), to_json(row.get_ref(i).map_err(err)?));
+            }
+            out.push(serde_json::Value::Object(obj));
+        }
+        Ok(out)
+    }
+}
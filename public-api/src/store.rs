@@ -0,0 +1,1112 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use worker::Result;
+
+use crate::currency::CurrencyColumn;
+use crate::labels::{ModuleColumn, ModuleManifest};
+use crate::masking::ColumnVisibility;
+use crate::orders::{OrderFilters, Rental, Sale};
+use crate::scheduled::{NewTransaction, OverdueRental, PopularQuery};
+use crate::tables::{TableColumn, TableMetadata, TableShare};
+use crate::usage::UsageRow;
+use crate::webhooks::{DeliveryFilters, DeliveryRow, DueDelivery, InventoryChange, NewDelivery, Subscriber, SubscriptionRow};
+use crate::{ColumnName, PublicTable, QueryTable, TableMatches, TableRow, TableValue, TokenInfo};
+
+// ============================================================================
+// STORAGE TRAITS
+// ============================================================================
+//
+// Handlers only talk to these traits. The worker backs them with D1 and KV
+// (see `cloudflare.rs`); tests back them with SQLite and an in-memory map.
+// Native async fns in traits: every implementation is single-threaded, so the
+// missing Send bounds don't matter.
+
+/// Catalog reads over userTables, tableColumns and tableData
+#[allow(async_fn_in_trait)]
+pub trait TableStore {
+    /// Sale/rent tables with row counts, ordered by name:
+    /// public ones when `ids` is None, otherwise exactly the given IDs
+    async fn list_tables(&self, ids: Option<&[String]>) -> Result<Vec<PublicTable>>;
+
+    /// Like `list_tables` but without row counts, for labelling records
+    async fn query_tables(&self, ids: Option<&[String]>) -> Result<Vec<QueryTable>>;
+
+    /// Any table by ID, whatever its type and visibility
+    async fn get_table(&self, table_id: &str) -> Result<Option<TableMetadata>>;
+
+    /// Tables whose slug is `handle` or whose name matches it ignoring case, slug match first
+    async fn find_tables(&self, handle: &str) -> Result<Vec<TableMetadata>>;
+
+    /// Every column of a table, in position order
+    async fn table_columns(&self, table_id: &str) -> Result<Vec<TableColumn>>;
+
+    /// IDs of the public/shared tables created by an account (userTables.createdBy or userId)
+    async fn owned_tables(&self, created_by: Option<&str>, user_id: Option<&str>) -> Result<Vec<String>>;
+
+    /// Shares naming a token or its user, whatever their table's visibility (shares only
+    /// count while it is 'shared', which callers check against the table's metadata)
+    async fn table_shares(&self, token_id: &str, user_id: Option<&str>) -> Result<Vec<TableShare>>;
+
+    /// Column names of the given tables, in position order
+    async fn column_names(&self, table_ids: &[String]) -> Result<Vec<ColumnName>>;
+
+    /// Money columns of the given tables with each table's base currency
+    async fn currency_columns(&self, table_ids: &[String]) -> Result<Vec<CurrencyColumn>>;
+
+    /// Columns of the given tables the public API hides or masks, or every column when `all`
+    async fn column_visibility(&self, table_ids: &[String], all: bool) -> Result<Vec<ColumnVisibility>>;
+
+    /// Module-typed columns (`<moduleId>:<typeId>`) of the given tables
+    async fn module_columns(&self, table_ids: &[String]) -> Result<Vec<ModuleColumn>>;
+
+    /// Manifests of the given modules that are active
+    async fn module_manifests(&self, module_ids: &[String]) -> Result<Vec<ModuleManifest>>;
+
+    /// All rows of a table, newest first
+    async fn table_rows(&self, table_id: &str) -> Result<Vec<TableRow>>;
+
+    /// One row of a table
+    async fn table_row(&self, table_id: &str, row_id: &str) -> Result<Option<TableRow>>;
+
+    /// One page of rows across tables matching every filter (case-insensitive equality),
+    /// most recently updated first, plus the total number of matches
+    async fn find_rows(
+        &self,
+        table_ids: &[String],
+        filters: &HashMap<String, String>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<(Vec<TableRow>, i64)>;
+
+    /// Distinct non-null values of a data column across tables, restricted by filters
+    async fn distinct_values(
+        &self,
+        table_ids: &[String],
+        column: &str,
+        filters: &HashMap<String, String>,
+    ) -> Result<Vec<serde_json::Value>>;
+
+    /// Like `distinct_values`, but distinct per table and with each value's table
+    async fn distinct_table_values(
+        &self,
+        table_ids: &[String],
+        column: &str,
+        filters: &HashMap<String, String>,
+    ) -> Result<Vec<TableValue>>;
+
+    /// Per table, the rows matching every filter, how many of them are available and their
+    /// cheapest price; tables without a matching row are left out
+    async fn match_rows(&self, table_ids: &[String], filters: &HashMap<String, String>) -> Result<Vec<TableMatches>>;
+}
+
+/// API token lookups
+#[allow(async_fn_in_trait)]
+pub trait TokenStore {
+    /// Token by its secret value, expired or not
+    async fn find_token(&self, token: &str) -> Result<Option<TokenInfo>>;
+}
+
+/// Hourly per-token request metering in apiUsage
+#[allow(async_fn_in_trait)]
+pub trait UsageStore {
+    /// Add one request to the current hour of a token's usage on a route
+    async fn record_usage(&self, token_id: &str, route: &str, is_error: bool, rows: u64, bytes: u64) -> Result<()>;
+
+    /// Hourly usage of a token between two `YYYY-MM-DD HH:MM:SS` datetimes, oldest first
+    async fn usage(&self, token_id: &str, from: &str, to: &str) -> Result<Vec<UsageRow>>;
+
+    /// Count one request for a cacheable records query; `filters` is a JSON object with sorted keys
+    async fn record_query_hit(&self, filters: &str, limit: u32, offset: u32) -> Result<()>;
+
+    /// The most requested records queries of the last day, most hits first
+    async fn popular_queries(&self, limit: u32) -> Result<Vec<PopularQuery>>;
+
+    /// Forget queries nobody asked for in a week
+    async fn prune_query_hits(&self) -> Result<()>;
+}
+
+/// Customer purchase history in sales and rentals
+#[allow(async_fn_in_trait)]
+pub trait OrderStore {
+    /// One page of sales in the given tables (public ones when `ids` is None), newest first,
+    /// plus the total number of matches
+    async fn sales(&self, ids: Option<&[String]>, filters: &OrderFilters, limit: u32, offset: u32) -> Result<(Vec<Sale>, i64)>;
+
+    /// One page of rentals in the given tables (public ones when `ids` is None), most recently
+    /// rented first, plus the total number of matches
+    async fn rentals(&self, ids: Option<&[String]>, filters: &OrderFilters, limit: u32, offset: u32) -> Result<(Vec<Rental>, i64)>;
+
+    /// Customer of a rental, by ID
+    async fn rental_customer(&self, rental_id: &str) -> Result<Option<String>>;
+
+    /// Customer of an item's active rental
+    async fn active_rental_customer(&self, table_id: &str, item_id: &str) -> Result<Option<String>>;
+
+    /// Table of a rental, by ID
+    async fn rental_table(&self, rental_id: &str) -> Result<Option<String>>;
+}
+
+/// Rental lifecycle writes for the scheduled expiry job
+#[allow(async_fn_in_trait)]
+pub trait RentalStore {
+    /// Active rentals of tables with an enabled expiry rule whose deadline (rentedAt plus the rule's
+    /// rental periods and grace) is at or before `now`, oldest first; already flagged rentals are
+    /// only returned when their rule releases
+    async fn overdue_rentals(&self, now: i64, limit: u32) -> Result<Vec<OverdueRental>>;
+
+    /// Set overdueAt on an active rental; false when it was released or flagged meanwhile
+    async fn flag_rental_overdue(&self, id: &str) -> Result<bool>;
+
+    /// Release an active rental, keeping its notes or setting `note`; false when it's no longer active
+    async fn release_rental(&self, id: &str, note: &str) -> Result<bool>;
+
+    /// Replace the data of a table row
+    async fn update_row_data(&self, table_id: &str, row_id: &str, data: &str) -> Result<()>;
+
+    /// Append to the inventoryTransactions audit log
+    async fn insert_inventory_transaction(&self, transaction: &NewTransaction) -> Result<()>;
+}
+
+/// Webhook subscriptions, their delivery queue/log, and the inventory change feed they're fed from
+#[allow(async_fn_in_trait)]
+pub trait WebhookStore {
+    /// A token's subscriptions, oldest first
+    async fn webhook_subscriptions(&self, token_id: &str) -> Result<Vec<SubscriptionRow>>;
+
+    /// Create a subscription; `events` is a JSON array
+    async fn insert_webhook_subscription(
+        &self,
+        token_id: &str,
+        id: &str,
+        url: &str,
+        events: &str,
+        secret: &str,
+    ) -> Result<SubscriptionRow>;
+
+    /// Delete a token's subscription with its deliveries; false when the token has no such subscription
+    async fn delete_webhook_subscription(&self, token_id: &str, id: &str) -> Result<bool>;
+
+    /// Active subscriptions to an event, with the access fields of their tokens
+    async fn webhook_subscribers(&self, event: &str) -> Result<Vec<Subscriber>>;
+
+    /// Queue a delivery; false when the subscription already has one for this event ID
+    async fn insert_webhook_delivery(&self, delivery: &NewDelivery) -> Result<bool>;
+
+    /// Claim up to `limit` pending deliveries due at `now`, pushing their next attempt to `lease_until`
+    /// so a concurrent run doesn't send them too
+    async fn claim_webhook_deliveries(&self, now: i64, lease_until: i64, limit: u32) -> Result<Vec<DueDelivery>>;
+
+    /// Record the outcome of an attempt
+    async fn update_webhook_delivery(
+        &self,
+        id: &str,
+        status: &str,
+        attempts: i64,
+        next_attempt_at: Option<i64>,
+        response_status: Option<u16>,
+        last_error: Option<&str>,
+    ) -> Result<()>;
+
+    /// One page of a token's delivery log, newest first, plus the total number of matches
+    async fn webhook_deliveries(
+        &self,
+        token_id: &str,
+        filters: &DeliveryFilters,
+        limit: u32,
+        offset: u32,
+    ) -> Result<(Vec<DeliveryRow>, i64)>;
+
+    /// inventoryTransactions recorded after a position (rowid), oldest first
+    async fn inventory_changes(&self, after: i64, limit: u32) -> Result<Vec<InventoryChange>>;
+
+    /// Position of the newest inventoryTransactions row (0 when empty)
+    async fn latest_inventory_position(&self) -> Result<i64>;
+
+    async fn webhook_cursor(&self, name: &str) -> Result<Option<i64>>;
+
+    async fn set_webhook_cursor(&self, name: &str, position: i64) -> Result<()>;
+}
+
+/// String key/value cache with optional expiry (KV semantics: failures read as misses)
+#[allow(async_fn_in_trait)]
+pub trait Cache {
+    async fn get(&self, key: &str) -> Option<String>;
+
+    /// Store a value, dropped after `ttl` seconds when given
+    async fn put(&self, key: &str, value: String, ttl: Option<u64>);
+
+    async fn delete(&self, key: &str);
+}
+
+// ============================================================================
+// SQL STORE
+// ============================================================================
+
+/// Raw SQL access with SQLite semantics; rows come back as JSON objects keyed by column
+#[allow(async_fn_in_trait)]
+pub trait SqlDatabase {
+    async fn query(&self, sql: &str, params: &[serde_json::Value]) -> Result<Vec<serde_json::Value>>;
+}
+
+/// Table, token, usage, order, rental and webhook stores over any SQL database - the SQL is shared by D1 and the test SQLite
+pub struct SqlStore<D> {
+    db: D,
+}
+
+impl<D: SqlDatabase> SqlStore<D> {
+    pub fn new(db: D) -> Self {
+        Self { db }
+    }
+
+    async fn all<T: DeserializeOwned>(&self, sql: &str, params: &[serde_json::Value]) -> Result<Vec<T>> {
+        self.db
+            .query(sql, params)
+            .await?
+            .into_iter()
+            .map(|row| serde_json::from_value(row).map_err(Into::into))
+            .collect()
+    }
+
+    async fn first<T: DeserializeOwned>(&self, sql: &str, params: &[serde_json::Value]) -> Result<Option<T>> {
+        Ok(self.all(sql, params).await?.into_iter().next())
+    }
+}
+
+/// `?, ?, ?` for an IN list
+fn placeholders(n: usize) -> String {
+    vec!["?"; n].join(",")
+}
+
+fn string_params(values: &[String]) -> Vec<serde_json::Value> {
+    values.iter().map(|v| serde_json::Value::from(v.as_str())).collect()
+}
+
+/// Append `AND` clauses for data filters on tableData rows. Each is a lookup in the
+/// tableDataAttributes index, which triggers keep in sync with tableData (migration 018),
+/// with the same case-insensitive equality as `LOWER(json_extract(data, '$.col'))`.
+/// Numeric values also match numbers equal to them, through `numValue` (migration 022).
+fn push_filters(sql: &mut String, params: &mut Vec<serde_json::Value>, filters: &HashMap<String, String>) {
+    for (col, val) in filters {
+        sql.push_str(" AND id IN (SELECT rowId FROM tableDataAttributes WHERE columnName = ? AND textValue = LOWER(?)");
+        params.push(col.as_str().into());
+        params.push(val.as_str().into());
+        if let Some(number) = val.trim().parse::<f64>().ok().filter(|n| n.is_finite()) {
+            sql.push_str(" UNION SELECT rowId FROM tableDataAttributes WHERE columnName = ? AND numValue = ?");
+            params.push(col.as_str().into());
+            params.push(number.into());
+        }
+        sql.push(')');
+    }
+}
+
+/// SELECT DISTINCT of a data column (as `val`, after `extra` columns) over rows of the given
+/// tables having it; only rows with a non-null value have the column in the attribute index
+fn distinct_values_sql(
+    extra: &str,
+    table_ids: &[String],
+    column: &str,
+    filters: &HashMap<String, String>,
+) -> (String, Vec<serde_json::Value>) {
+    let mut sql = format!(
+        "SELECT DISTINCT {}json_extract(data, '$.{}') as val FROM tableData
+         WHERE id IN (SELECT rowId FROM tableDataAttributes WHERE tableId IN ({}) AND columnName = ?)",
+        extra,
+        column,
+        placeholders(table_ids.len())
+    );
+    let mut params = string_params(table_ids);
+    params.push(column.into());
+    push_filters(&mut sql, &mut params, filters);
+    (sql, params)
+}
+
+/// A Prisma DateTime column as `YYYY-MM-DD HH:MM:SS`, for comparisons and ordering;
+/// Prisma may have written it as an ISO string or as epoch milliseconds
+fn datetime_sql(column: &str) -> String {
+    format!(
+        "(CASE WHEN typeof({0}) IN ('integer', 'real') THEN datetime({0} / 1000, 'unixepoch') ELSE datetime({0}) END)",
+        column
+    )
+}
+
+/// A Prisma DateTime column as an ISO 8601 string in UTC, for responses
+fn iso_datetime_sql(column: &str) -> String {
+    format!("strftime('%Y-%m-%dT%H:%M:%fZ', {})", datetime_sql(column))
+}
+
+/// userTables columns of `TableMetadata`, dates as the ISO strings the TS cache holds
+fn table_metadata_sql() -> String {
+    format!(
+        "id, name, slug, description, createdBy, userId, visibility, tableType, productIdColumn, rentalPeriod,
+         currency, {} AS createdAt, {} AS updatedAt",
+        iso_datetime_sql("createdAt"),
+        iso_datetime_sql("updatedAt")
+    )
+}
+
+/// WHERE clause selecting accessible sale/rent tables
+fn table_scope(ids: Option<&[String]>) -> (String, Vec<serde_json::Value>) {
+    match ids {
+        Some(ids) => (
+            format!("id IN ({}) AND tableType IN ('sale', 'rent')", placeholders(ids.len())),
+            string_params(ids),
+        ),
+        None => ("visibility = 'public' AND tableType IN ('sale', 'rent')".to_string(), vec![]),
+    }
+}
+
+impl<D: SqlDatabase> TableStore for SqlStore<D> {
+    async fn list_tables(&self, ids: Option<&[String]>) -> Result<Vec<PublicTable>> {
+        if ids.is_some_and(|ids| ids.is_empty()) {
+            return Ok(vec![]);
+        }
+        let (scope, params) = table_scope(ids);
+        let sql = format!(
+            "SELECT id, name, slug, description, tableType, rowCount
+             FROM userTables
+             WHERE {}
+             ORDER BY name ASC",
+            scope
+        );
+        self.all(&sql, &params).await
+    }
+
+    async fn query_tables(&self, ids: Option<&[String]>) -> Result<Vec<QueryTable>> {
+        if ids.is_some_and(|ids| ids.is_empty()) {
+            return Ok(vec![]);
+        }
+        let (scope, params) = table_scope(ids);
+        let sql = format!("SELECT id, name, tableType FROM userTables WHERE {} ORDER BY name ASC", scope);
+        self.all(&sql, &params).await
+    }
+
+    async fn get_table(&self, table_id: &str) -> Result<Option<TableMetadata>> {
+        self.first(&format!("SELECT {} FROM userTables WHERE id = ?", table_metadata_sql()), &[table_id.into()]).await
+    }
+
+    async fn find_tables(&self, handle: &str) -> Result<Vec<TableMetadata>> {
+        self.all(
+            &format!(
+                "SELECT {} FROM userTables
+                 WHERE slug = ? OR name = ? COLLATE NOCASE
+                 ORDER BY CASE WHEN slug = ? THEN 0 ELSE 1 END, name ASC",
+                table_metadata_sql()
+            ),
+            &[handle.into(), handle.into(), handle.into()],
+        )
+        .await
+    }
+
+    async fn table_columns(&self, table_id: &str) -> Result<Vec<TableColumn>> {
+        self.all(
+            &format!(
+                "SELECT id, tableId, name, type, isRequired, allowDuplicates, defaultValue, position, visibility,
+                        {} AS createdAt
+                 FROM tableColumns WHERE tableId = ? ORDER BY position ASC",
+                iso_datetime_sql("createdAt")
+            ),
+            &[table_id.into()],
+        )
+        .await
+    }
+
+    async fn owned_tables(&self, created_by: Option<&str>, user_id: Option<&str>) -> Result<Vec<String>> {
+        #[derive(Deserialize)]
+        struct Id {
+            id: String,
+        }
+        let ids: Vec<Id> = self
+            .all(
+                "SELECT id FROM userTables
+                 WHERE visibility IN ('public', 'shared') AND (createdBy = ? OR userId = ?)
+                 ORDER BY name ASC",
+                &[created_by.into(), user_id.into()],
+            )
+            .await?;
+        Ok(ids.into_iter().map(|row| row.id).collect())
+    }
+
+    async fn table_shares(&self, token_id: &str, user_id: Option<&str>) -> Result<Vec<TableShare>> {
+        self.all(
+            "SELECT tableId, rights FROM tableShares WHERE tokenId = ? OR userId = ?",
+            &[token_id.into(), user_id.into()],
+        )
+        .await
+    }
+
+    async fn column_names(&self, table_ids: &[String]) -> Result<Vec<ColumnName>> {
+        if table_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let sql = format!(
+            "SELECT tableId, name FROM tableColumns WHERE tableId IN ({}) ORDER BY tableId, position ASC",
+            placeholders(table_ids.len())
+        );
+        self.all(&sql, &string_params(table_ids)).await
+    }
+
+    async fn currency_columns(&self, table_ids: &[String]) -> Result<Vec<CurrencyColumn>> {
+        if table_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        // The protected price/fee columns are typed 'number' but hold the table's prices
+        let sql = format!(
+            "SELECT c.tableId, c.name, t.currency
+             FROM tableColumns c JOIN userTables t ON t.id = c.tableId
+             WHERE c.tableId IN ({})
+               AND (c.type = 'currency'
+                    OR (t.tableType = 'sale' AND c.name = 'price')
+                    OR (t.tableType = 'rent' AND c.name IN ('price', 'fee')))
+             ORDER BY c.tableId, c.position",
+            placeholders(table_ids.len())
+        );
+        self.all(&sql, &string_params(table_ids)).await
+    }
+
+    async fn column_visibility(&self, table_ids: &[String], all: bool) -> Result<Vec<ColumnVisibility>> {
+        if table_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let sql = format!(
+            "SELECT tableId, name, type, visibility FROM tableColumns WHERE tableId IN ({}){}",
+            placeholders(table_ids.len()),
+            if all { "" } else { " AND visibility <> 'visible'" }
+        );
+        self.all(&sql, &string_params(table_ids)).await
+    }
+
+    async fn module_columns(&self, table_ids: &[String]) -> Result<Vec<ModuleColumn>> {
+        if table_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let sql = format!(
+            "SELECT tableId, name, type FROM tableColumns WHERE tableId IN ({}) AND type LIKE '%:%'",
+            placeholders(table_ids.len())
+        );
+        self.all(&sql, &string_params(table_ids)).await
+    }
+
+    async fn module_manifests(&self, module_ids: &[String]) -> Result<Vec<ModuleManifest>> {
+        if module_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let sql = format!(
+            "SELECT id, manifest FROM installedModules WHERE id IN ({}) AND status = 'active'",
+            placeholders(module_ids.len())
+        );
+        self.all(&sql, &string_params(module_ids)).await
+    }
+
+    async fn table_rows(&self, table_id: &str) -> Result<Vec<TableRow>> {
+        self.all(
+            "SELECT id, tableId, data, createdAt, updatedAt FROM tableData WHERE tableId = ? ORDER BY createdAt DESC",
+            &[table_id.into()],
+        )
+        .await
+    }
+
+    async fn table_row(&self, table_id: &str, row_id: &str) -> Result<Option<TableRow>> {
+        self.first(
+            "SELECT id, tableId, data, createdAt, updatedAt FROM tableData WHERE id = ? AND tableId = ?",
+            &[row_id.into(), table_id.into()],
+        )
+        .await
+    }
+
+    async fn find_rows(
+        &self,
+        table_ids: &[String],
+        filters: &HashMap<String, String>,
+        limit: u32,
+        offset: u32,
+    ) -> Result<(Vec<TableRow>, i64)> {
+        if table_ids.is_empty() {
+            return Ok((vec![], 0));
+        }
+        let mut conditions = format!("tableId IN ({})", placeholders(table_ids.len()));
+        let mut params = string_params(table_ids);
+        push_filters(&mut conditions, &mut params, filters);
+
+        #[derive(serde::Deserialize)]
+        struct CountResult {
+            cnt: i64,
+        }
+        let count: Option<CountResult> = self
+            .first(&format!("SELECT COUNT(*) as cnt FROM tableData WHERE {}", conditions), &params)
+            .await?;
+        let total = count.map(|c| c.cnt).unwrap_or(0);
+
+        // Inline limit/offset - D1 doesn't like bigint bindings
+        let sql = format!(
+            "SELECT id, tableId, data, createdAt, updatedAt FROM tableData WHERE {} ORDER BY updatedAt DESC LIMIT {} OFFSET {}",
+            conditions, limit, offset
+        );
+        Ok((self.all(&sql, &params).await?, total))
+    }
+
+    async fn distinct_values(
+        &self,
+        table_ids: &[String],
+        column: &str,
+        filters: &HashMap<String, String>,
+    ) -> Result<Vec<serde_json::Value>> {
+        if table_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let (sql, params) = distinct_values_sql("", table_ids, column, filters);
+
+        #[derive(serde::Deserialize)]
+        struct ValueRow {
+            val: serde_json::Value,
+        }
+        let rows: Vec<ValueRow> = self.all(&sql, &params).await?;
+        Ok(rows.into_iter().map(|r| r.val).collect())
+    }
+
+    async fn distinct_table_values(
+        &self,
+        table_ids: &[String],
+        column: &str,
+        filters: &HashMap<String, String>,
+    ) -> Result<Vec<TableValue>> {
+        if table_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let (sql, params) = distinct_values_sql("tableId, ", table_ids, column, filters);
+        self.all(&sql, &params).await
+    }
+
+    async fn match_rows(&self, table_ids: &[String], filters: &HashMap<String, String>) -> Result<Vec<TableMatches>> {
+        if table_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        // Availability as get_item_availability sees it: an integer qty above 0 for sales,
+        // anything but used = true for rentals
+        let mut rows = format!(
+            "SELECT tableId,
+                    CASE WHEN tableId IN (SELECT id FROM userTables WHERE tableType = 'sale')
+                         THEN json_type(data, '$.qty') = 'integer' AND json_extract(data, '$.qty') > 0
+                         ELSE json_type(data, '$.used') IS NOT 'true' END AS available,
+                    CASE WHEN json_type(data, '$.price') IN ('integer', 'real') THEN json_extract(data, '$.price') END AS price
+             FROM tableData
+             WHERE tableId IN ({})",
+            placeholders(table_ids.len())
+        );
+        let mut params = string_params(table_ids);
+        push_filters(&mut rows, &mut params, filters);
+        let sql = format!(
+            "SELECT tableId, COUNT(*) AS matchingRows, SUM(available) AS availableRows,
+                    MIN(CASE WHEN available THEN price END) AS cheapestPrice
+             FROM ({})
+             GROUP BY tableId",
+            rows
+        );
+        self.all(&sql, &params).await
+    }
+}
+
+impl<D: SqlDatabase> TokenStore for SqlStore<D> {
+    async fn find_token(&self, token: &str) -> Result<Option<TokenInfo>> {
+        // expiresAt is normalised to epoch seconds here so expiry checks don't need a date parser;
+        // Prisma may have written it as an ISO string or as epoch milliseconds
+        self.first(
+            "SELECT id, isAdmin, tableAccess, createdBy, userId, rateLimits, allowedDomains, customerId, priceRules,
+                    columnRules,
+                    CASE WHEN typeof(expiresAt) IN ('integer', 'real') THEN CAST(expiresAt / 1000 AS INTEGER)
+                         ELSE CAST(strftime('%s', expiresAt) AS INTEGER) END AS expiresAt
+             FROM tokens WHERE token = ?",
+            &[token.into()],
+        )
+        .await
+    }
+}
+
+impl<D: SqlDatabase> UsageStore for SqlStore<D> {
+    async fn record_usage(&self, token_id: &str, route: &str, is_error: bool, rows: u64, bytes: u64) -> Result<()> {
+        self.db
+            .query(
+                "INSERT INTO apiUsage (tokenId, route, hour, requestCount, errorCount, rowsReturned, bytesReturned)
+                 VALUES (?, ?, strftime('%Y-%m-%d %H:00:00', 'now'), 1, ?, ?, ?)
+                 ON CONFLICT (tokenId, route, hour) DO UPDATE SET
+                    requestCount = requestCount + 1,
+                    errorCount = errorCount + excluded.errorCount,
+                    rowsReturned = rowsReturned + excluded.rowsReturned,
+                    bytesReturned = bytesReturned + excluded.bytesReturned",
+                &[token_id.into(), route.into(), (is_error as u64).into(), rows.into(), bytes.into()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn usage(&self, token_id: &str, from: &str, to: &str) -> Result<Vec<UsageRow>> {
+        self.all(
+            "SELECT hour, route, requestCount, errorCount, rowsReturned, bytesReturned
+             FROM apiUsage
+             WHERE tokenId = ? AND hour >= strftime('%Y-%m-%d %H:00:00', ?) AND hour <= ?
+             ORDER BY hour ASC, route ASC",
+            &[token_id.into(), from.into(), to.into()],
+        )
+        .await
+    }
+
+    async fn record_query_hit(&self, filters: &str, limit: u32, offset: u32) -> Result<()> {
+        self.db
+            .query(
+                "INSERT INTO popularQueries (filters, pageLimit, pageOffset, hits, lastRequestedAt)
+                 VALUES (?, ?, ?, 1, CURRENT_TIMESTAMP)
+                 ON CONFLICT (filters, pageLimit, pageOffset) DO UPDATE SET
+                    hits = hits + 1,
+                    lastRequestedAt = excluded.lastRequestedAt",
+                &[filters.into(), limit.into(), offset.into()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn popular_queries(&self, limit: u32) -> Result<Vec<PopularQuery>> {
+        self.all(
+            &format!(
+                "SELECT filters, pageLimit, pageOffset FROM popularQueries
+                 WHERE lastRequestedAt >= datetime('now', '-1 day')
+                 ORDER BY hits DESC, lastRequestedAt DESC LIMIT {}",
+                limit
+            ),
+            &[],
+        )
+        .await
+    }
+
+    async fn prune_query_hits(&self) -> Result<()> {
+        self.db
+            .query("DELETE FROM popularQueries WHERE lastRequestedAt < datetime('now', '-7 days')", &[])
+            .await?;
+        Ok(())
+    }
+}
+
+/// SQLite date modifier for the rule's rental periods (a week is 7 days; tables without a period rent by the month)
+const RENTAL_TERM: &str = "'+' || CASE COALESCE(ut.rentalPeriod, 'month')
+                                  WHEN 'hour' THEN x.periods || ' hours'
+                                  WHEN 'day' THEN x.periods || ' days'
+                                  WHEN 'week' THEN (x.periods * 7) || ' days'
+                                  WHEN 'year' THEN x.periods || ' years'
+                                  ELSE x.periods || ' months' END";
+
+/// The customer an order belongs to
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CustomerRow {
+    customer_id: String,
+}
+
+/// How an order table is filtered and sorted
+struct OrderTable {
+    name: &'static str,
+    status_column: &'static str,
+    date_column: &'static str,
+}
+
+const SALES: OrderTable = OrderTable { name: "sales", status_column: "o.saleStatus", date_column: "o.createdAt" };
+const RENTALS: OrderTable = OrderTable { name: "rentals", status_column: "o.rentalStatus", date_column: "o.rentedAt" };
+
+impl<D: SqlDatabase> SqlStore<D> {
+    /// One page of an order table in scope, newest first, plus the total number of matches
+    async fn order_page<T: DeserializeOwned>(
+        &self,
+        table: &OrderTable,
+        columns: &str,
+        ids: Option<&[String]>,
+        filters: &OrderFilters,
+        limit: u32,
+        offset: u32,
+    ) -> Result<(Vec<T>, i64)> {
+        let (mut conditions, mut params) = match ids {
+            Some(ids) => (format!("o.tableId IN ({})", placeholders(ids.len())), string_params(ids)),
+            None => (
+                "o.tableId IN (SELECT id FROM userTables WHERE visibility = 'public')".to_string(),
+                vec![],
+            ),
+        };
+        for (column, value) in [("o.customerId", &filters.customer_id), (table.status_column, &filters.status)] {
+            if let Some(value) = value {
+                conditions.push_str(&format!(" AND {} = ?", column));
+                params.push(value.as_str().into());
+            }
+        }
+        let date = datetime_sql(table.date_column);
+        for (operator, value) in [(">=", &filters.from), ("<=", &filters.to)] {
+            if let Some(value) = value {
+                conditions.push_str(&format!(" AND {} {} ?", date, operator));
+                params.push(value.as_str().into());
+            }
+        }
+
+        #[derive(serde::Deserialize)]
+        struct CountResult {
+            cnt: i64,
+        }
+        let count: Option<CountResult> = self
+            .first(&format!("SELECT COUNT(*) as cnt FROM {} o WHERE {}", table.name, conditions), &params)
+            .await?;
+        let total = count.map(|c| c.cnt).unwrap_or(0);
+
+        let sql = format!(
+            "SELECT {} FROM {} o WHERE {} ORDER BY {} DESC, o.id DESC LIMIT {} OFFSET {}",
+            columns, table.name, conditions, date, limit, offset
+        );
+        Ok((self.all(&sql, &params).await?, total))
+    }
+}
+
+impl<D: SqlDatabase> OrderStore for SqlStore<D> {
+    async fn sales(&self, ids: Option<&[String]>, filters: &OrderFilters, limit: u32, offset: u32) -> Result<(Vec<Sale>, i64)> {
+        let columns = format!(
+            "o.id, o.saleNumber, o.tableId, o.tableName, o.itemId, o.itemSnapshot, o.customerId, o.quantitySold,
+             o.unitPrice, o.totalAmount, o.saleStatus, o.paymentMethod, o.notes,
+             {} AS createdAt, {} AS updatedAt",
+            iso_datetime_sql("o.createdAt"),
+            iso_datetime_sql("o.updatedAt")
+        );
+        self.order_page(&SALES, &columns, ids, filters, limit, offset).await
+    }
+
+    async fn rentals(&self, ids: Option<&[String]>, filters: &OrderFilters, limit: u32, offset: u32) -> Result<(Vec<Rental>, i64)> {
+        let columns = format!(
+            "o.id, o.rentalNumber, o.tableId, o.tableName, o.itemId, o.itemSnapshot, o.customerId, o.unitPrice,
+             o.rentalStatus, {} AS rentedAt, {} AS releasedAt, {} AS overdueAt, o.notes,
+             {} AS createdAt, {} AS updatedAt",
+            iso_datetime_sql("o.rentedAt"),
+            iso_datetime_sql("o.releasedAt"),
+            iso_datetime_sql("o.overdueAt"),
+            iso_datetime_sql("o.createdAt"),
+            iso_datetime_sql("o.updatedAt")
+        );
+        self.order_page(&RENTALS, &columns, ids, filters, limit, offset).await
+    }
+
+    async fn rental_customer(&self, rental_id: &str) -> Result<Option<String>> {
+        let row: Option<CustomerRow> = self
+            .first("SELECT customerId FROM rentals WHERE id = ?", &[rental_id.into()])
+            .await?;
+        Ok(row.map(|r| r.customer_id))
+    }
+
+    async fn active_rental_customer(&self, table_id: &str, item_id: &str) -> Result<Option<String>> {
+        let row: Option<CustomerRow> = self
+            .first(
+                "SELECT customerId FROM rentals WHERE tableId = ? AND itemId = ? AND rentalStatus = 'active'
+                 ORDER BY rentedAt DESC LIMIT 1",
+                &[table_id.into(), item_id.into()],
+            )
+            .await?;
+        Ok(row.map(|r| r.customer_id))
+    }
+
+    async fn rental_table(&self, rental_id: &str) -> Result<Option<String>> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct TableRow {
+            table_id: String,
+        }
+        let row: Option<TableRow> = self.first("SELECT tableId FROM rentals WHERE id = ?", &[rental_id.into()]).await?;
+        Ok(row.map(|r| r.table_id))
+    }
+}
+
+impl<D: SqlDatabase> RentalStore for SqlStore<D> {
+    async fn overdue_rentals(&self, now: i64, limit: u32) -> Result<Vec<OverdueRental>> {
+        self.all(
+            &format!(
+                "SELECT r.id, r.tableId, ut.name AS tableName, r.itemId, x.action
+                 FROM rentals r
+                 JOIN rentalExpiryRules x ON x.tableId = r.tableId
+                 JOIN userTables ut ON ut.id = r.tableId
+                 WHERE r.rentalStatus = 'active' AND x.enabled = 1
+                   AND (x.action = 'release' OR r.overdueAt IS NULL)
+                   AND CAST(strftime('%s', {}, {}, '+' || x.graceMinutes || ' minutes') AS INTEGER) <= ?
+                 ORDER BY r.rentedAt ASC, r.id ASC LIMIT {}",
+                datetime_sql("r.rentedAt"), RENTAL_TERM, limit
+            ),
+            &[now.into()],
+        )
+        .await
+    }
+
+    async fn flag_rental_overdue(&self, id: &str) -> Result<bool> {
+        let flagged = self
+            .db
+            .query(
+                "UPDATE rentals SET overdueAt = CURRENT_TIMESTAMP, updatedAt = CURRENT_TIMESTAMP
+                 WHERE id = ? AND rentalStatus = 'active' AND overdueAt IS NULL
+                 RETURNING id",
+                &[id.into()],
+            )
+            .await?;
+        Ok(!flagged.is_empty())
+    }
+
+    async fn release_rental(&self, id: &str, note: &str) -> Result<bool> {
+        let released = self
+            .db
+            .query(
+                "UPDATE rentals
+                 SET rentalStatus = 'released', releasedAt = CURRENT_TIMESTAMP, notes = COALESCE(notes, ?),
+                     updatedAt = CURRENT_TIMESTAMP
+                 WHERE id = ? AND rentalStatus = 'active'
+                 RETURNING id",
+                &[note.into(), id.into()],
+            )
+            .await?;
+        Ok(!released.is_empty())
+    }
+
+    async fn update_row_data(&self, table_id: &str, row_id: &str, data: &str) -> Result<()> {
+        self.db
+            .query(
+                "UPDATE tableData SET data = ?, updatedAt = CURRENT_TIMESTAMP WHERE id = ? AND tableId = ?",
+                &[data.into(), row_id.into(), table_id.into()],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn insert_inventory_transaction(&self, transaction: &NewTransaction) -> Result<()> {
+        self.db
+            .query(
+                "INSERT INTO inventoryTransactions
+                    (id, tableId, tableName, itemId, transactionType, previousData, newData, referenceId, createdBy)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                &[
+                    transaction.id.as_str().into(),
+                    transaction.table_id.as_str().into(),
+                    transaction.table_name.as_str().into(),
+                    transaction.item_id.as_str().into(),
+                    transaction.transaction_type.into(),
+                    transaction.previous_data.as_deref().into(),
+                    transaction.new_data.as_deref().into(),
+                    transaction.reference_id.as_str().into(),
+                    transaction.created_by.into(),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct Position {
+    position: Option<i64>,
+}
+
+impl<D: SqlDatabase> WebhookStore for SqlStore<D> {
+    async fn webhook_subscriptions(&self, token_id: &str) -> Result<Vec<SubscriptionRow>> {
+        self.all(
+            "SELECT id, url, events, active, createdAt FROM webhookSubscriptions WHERE tokenId = ? ORDER BY createdAt ASC, id ASC",
+            &[token_id.into()],
+        )
+        .await
+    }
+
+    async fn insert_webhook_subscription(
+        &self,
+        token_id: &str,
+        id: &str,
+        url: &str,
+        events: &str,
+        secret: &str,
+    ) -> Result<SubscriptionRow> {
+        let inserted = self
+            .first(
+                "INSERT INTO webhookSubscriptions (id, tokenId, url, events, secret) VALUES (?, ?, ?, ?, ?)
+                 RETURNING id, url, events, active, createdAt",
+                &[id.into(), token_id.into(), url.into(), events.into(), secret.into()],
+            )
+            .await?;
+        inserted.ok_or_else(|| worker::Error::RustError("webhook subscription insert returned no row".to_string()))
+    }
+
+    async fn delete_webhook_subscription(&self, token_id: &str, id: &str) -> Result<bool> {
+        // Deliveries go with it (ON DELETE CASCADE)
+        let deleted = self
+            .db
+            .query(
+                "DELETE FROM webhookSubscriptions WHERE id = ? AND tokenId = ? RETURNING id",
+                &[id.into(), token_id.into()],
+            )
+            .await?;
+        Ok(!deleted.is_empty())
+    }
+
+    async fn webhook_subscribers(&self, event: &str) -> Result<Vec<Subscriber>> {
+        self.all(
+            "SELECT s.id, s.tokenId, t.isAdmin, t.tableAccess, t.createdBy, t.userId, t.customerId, t.priceRules, t.columnRules,
+                    CASE WHEN typeof(t.expiresAt) IN ('integer', 'real') THEN CAST(t.expiresAt / 1000 AS INTEGER)
+                         ELSE CAST(strftime('%s', t.expiresAt) AS INTEGER) END AS expiresAt
+             FROM webhookSubscriptions s
+             JOIN tokens t ON t.id = s.tokenId
+             WHERE s.active = 1 AND EXISTS (SELECT 1 FROM json_each(s.events) WHERE value = ?)",
+            &[event.into()],
+        )
+        .await
+    }
+
+    async fn insert_webhook_delivery(&self, delivery: &NewDelivery) -> Result<bool> {
+        let inserted = self
+            .db
+            .query(
+                "INSERT INTO webhookDeliveries (id, subscriptionId, tokenId, eventId, event, payload, status, attempts, nextAttemptAt)
+                 VALUES (?, ?, ?, ?, ?, ?, 'pending', 0, ?)
+                 ON CONFLICT (subscriptionId, eventId) DO NOTHING
+                 RETURNING id",
+                &[
+                    delivery.id.as_str().into(),
+                    delivery.subscription_id.as_str().into(),
+                    delivery.token_id.as_str().into(),
+                    delivery.event_id.as_str().into(),
+                    delivery.event.into(),
+                    delivery.payload.as_str().into(),
+                    delivery.next_attempt_at.into(),
+                ],
+            )
+            .await?;
+        Ok(!inserted.is_empty())
+    }
+
+    async fn claim_webhook_deliveries(&self, now: i64, lease_until: i64, limit: u32) -> Result<Vec<DueDelivery>> {
+        #[derive(serde::Deserialize)]
+        struct Claimed {
+            id: String,
+        }
+        // Inline limit - D1 doesn't like bigint bindings
+        let claimed: Vec<Claimed> = self
+            .all(
+                &format!(
+                    "UPDATE webhookDeliveries SET nextAttemptAt = ?
+                     WHERE id IN (SELECT id FROM webhookDeliveries
+                                  WHERE status = 'pending' AND nextAttemptAt <= ?
+                                  ORDER BY nextAttemptAt ASC LIMIT {})
+                     RETURNING id",
+                    limit
+                ),
+                &[lease_until.into(), now.into()],
+            )
+            .await?;
+        if claimed.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let ids: Vec<String> = claimed.into_iter().map(|c| c.id).collect();
+        let sql = format!(
+            "SELECT d.id, d.eventId, d.event, d.payload, d.attempts, s.url, s.secret
+             FROM webhookDeliveries d
+             JOIN webhookSubscriptions s ON s.id = d.subscriptionId
+             WHERE d.id IN ({})
+             ORDER BY d.nextAttemptAt ASC, d.createdAt ASC",
+            placeholders(ids.len())
+        );
+        self.all(&sql, &string_params(&ids)).await
+    }
+
+    async fn update_webhook_delivery(
+        &self,
+        id: &str,
+        status: &str,
+        attempts: i64,
+        next_attempt_at: Option<i64>,
+        response_status: Option<u16>,
+        last_error: Option<&str>,
+    ) -> Result<()> {
+        self.db
+            .query(
+                "UPDATE webhookDeliveries
+                 SET status = ?, attempts = ?, nextAttemptAt = ?, responseStatus = ?, lastError = ?,
+                     deliveredAt = CASE WHEN ? = 'delivered' THEN CURRENT_TIMESTAMP ELSE deliveredAt END
+                 WHERE id = ?",
+                &[
+                    status.into(),
+                    attempts.into(),
+                    next_attempt_at.into(),
+                    response_status.into(),
+                    last_error.into(),
+                    status.into(),
+                    id.into(),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn webhook_deliveries(
+        &self,
+        token_id: &str,
+        filters: &DeliveryFilters,
+        limit: u32,
+        offset: u32,
+    ) -> Result<(Vec<DeliveryRow>, i64)> {
+        let mut conditions = "tokenId = ?".to_string();
+        let mut params: Vec<serde_json::Value> = vec![token_id.into()];
+        for (column, value) in [
+            ("subscriptionId", &filters.subscription_id),
+            ("status", &filters.status),
+            ("event", &filters.event),
+        ] {
+            if let Some(value) = value {
+                conditions.push_str(&format!(" AND {} = ?", column));
+                params.push(value.as_str().into());
+            }
+        }
+
+        #[derive(serde::Deserialize)]
+        struct CountResult {
+            cnt: i64,
+        }
+        let count: Option<CountResult> = self
+            .first(&format!("SELECT COUNT(*) as cnt FROM webhookDeliveries WHERE {}", conditions), &params)
+            .await?;
+        let total = count.map(|c| c.cnt).unwrap_or(0);
+
+        let sql = format!(
+            "SELECT id, subscriptionId, eventId, event, payload, status, attempts, nextAttemptAt, responseStatus,
+                    lastError, createdAt, deliveredAt
+             FROM webhookDeliveries WHERE {} ORDER BY createdAt DESC, rowid DESC LIMIT {} OFFSET {}",
+            conditions, limit, offset
+        );
+        Ok((self.all(&sql, &params).await?, total))
+    }
+
+    async fn inventory_changes(&self, after: i64, limit: u32) -> Result<Vec<InventoryChange>> {
+        self.all(
+            &format!(
+                "SELECT rowid AS position, id, tableId, tableName, itemId, transactionType, referenceId, previousData, newData
+                 FROM inventoryTransactions WHERE rowid > ? ORDER BY rowid ASC LIMIT {}",
+                limit
+            ),
+            &[after.into()],
+        )
+        .await
+    }
+
+    async fn latest_inventory_position(&self) -> Result<i64> {
+        let latest: Option<Position> = self.first("SELECT MAX(rowid) AS position FROM inventoryTransactions", &[]).await?;
+        Ok(latest.and_then(|l| l.position).unwrap_or(0))
+    }
+
+    async fn webhook_cursor(&self, name: &str) -> Result<Option<i64>> {
+        let cursor: Option<Position> = self
+            .first("SELECT position FROM webhookCursors WHERE name = ?", &[name.into()])
+            .await?;
+        Ok(cursor.and_then(|c| c.position))
+    }
+
+    async fn set_webhook_cursor(&self, name: &str, position: i64) -> Result<()> {
+        self.db
+            .query(
+                "INSERT INTO webhookCursors (name, position) VALUES (?, ?)
+                 ON CONFLICT (name) DO UPDATE SET position = excluded.position",
+                &[name.into(), position.into()],
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,449 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use worker::Result;
+
+use crate::error::{ApiError, ApiResult};
+use crate::masking::ColumnVisibility;
+use crate::routes::RouteKind;
+use crate::store::{Cache, OrderStore, TableStore};
+use crate::utils::flag;
+use crate::TokenInfo;
+
+// ============================================================================
+// TABLE RESOLVER
+// ============================================================================
+//
+// Tables by ID, slug or name, and what a token may do with them: its own scope (`TokenScope`)
+// plus the share lists of 'shared' tables.
+
+// KV entries the TS API also keeps, without a TTL, and deletes when the table, its columns or
+// the token's shares change
+const CACHE_PREFIX_TABLE_METADATA: &str = "table:metadata:";
+const CACHE_PREFIX_TABLE_COLUMNS: &str = "table:columns:";
+const CACHE_PREFIX_TOKEN_SHARES: &str = "table:shares:token:";
+
+/// A userTables row, in the shape of the TS `UserTable` cached under `table:metadata:{id}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableMetadata {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub slug: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub created_by: String,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    pub visibility: String,
+    pub table_type: String,
+    #[serde(default)]
+    pub product_id_column: Option<String>,
+    #[serde(default)]
+    pub rental_period: Option<String>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+impl TableMetadata {
+    /// Sale and rent tables are the ones the public API serves items from
+    pub fn is_catalog(&self) -> bool {
+        self.table_type == "sale" || self.table_type == "rent"
+    }
+
+    fn is_public(&self) -> bool {
+        self.visibility == "public"
+    }
+
+    fn is_shared(&self) -> bool {
+        self.visibility == "shared"
+    }
+
+    /// Whether the account that created a token created this table
+    fn owned_by(&self, token: &TokenInfo) -> bool {
+        token.created_by.as_deref().is_some_and(|created_by| created_by == self.created_by)
+            || token.user_id.is_some() && token.user_id == self.user_id
+    }
+}
+
+/// A tableColumns row, in the shape of the TS `TableColumn` cached under `table:columns:{id}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableColumn {
+    pub id: String,
+    pub table_id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub column_type: String,
+    #[serde(default, deserialize_with = "flag")]
+    pub is_required: bool,
+    #[serde(default, deserialize_with = "flag")]
+    pub allow_duplicates: bool,
+    #[serde(default)]
+    pub default_value: Option<String>,
+    pub position: i64,
+    /// Public API visibility: visible, hidden or masked (cached before it existed: visible)
+    #[serde(default = "visible")]
+    pub visibility: String,
+    #[serde(default)]
+    pub created_at: Option<String>,
+}
+
+fn visible() -> String {
+    "visible".to_string()
+}
+
+/// A table a token may read, with its columns in position order
+#[derive(Debug, Clone)]
+pub struct TableContext {
+    pub table: TableMetadata,
+    pub columns: Vec<TableColumn>,
+}
+
+impl TableContext {
+    /// The columns' visibility, for `ColumnMasks::from_columns`
+    pub fn column_visibility(&self) -> Vec<ColumnVisibility> {
+        self.columns
+            .iter()
+            .map(|column| ColumnVisibility {
+                table_id: column.table_id.clone(),
+                name: column.name.clone(),
+                column_type: column.column_type.clone(),
+                visibility: column.visibility.clone(),
+            })
+            .collect()
+    }
+}
+
+/// A tableShares entry naming a token (or its user)
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableShare {
+    pub table_id: String,
+    /// JSON array of rights: read, buy
+    pub rights: String,
+}
+
+/// An operation a token can be granted on a table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Read,
+    Buy,
+    Rent,
+    Release,
+}
+
+impl Operation {
+    /// The operation a proxied write performs
+    fn of_write(kind: RouteKind) -> Option<Self> {
+        match kind {
+            RouteKind::Buy => Some(Operation::Buy),
+            RouteKind::Rent => Some(Operation::Rent),
+            RouteKind::Release => Some(Operation::Release),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Operation::Read => "read",
+            Operation::Buy => "buy",
+            Operation::Rent => "rent",
+            Operation::Release => "release",
+        }
+    }
+}
+
+/// What a token may do with a table
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TableRights {
+    pub read: bool,
+    pub buy: bool,
+    pub rent: bool,
+    pub release: bool,
+}
+
+impl TableRights {
+    const FULL: Self = Self { read: true, buy: true, rent: true, release: true };
+
+    fn of_operations(operations: &[Operation]) -> Self {
+        let has = |operation| operations.contains(&operation);
+        Self { read: has(Operation::Read), buy: has(Operation::Buy), rent: has(Operation::Rent), release: has(Operation::Release) }
+    }
+
+    /// A share's rights; every share can read, buy also rents, and unreadable rights grant nothing more
+    fn of_share(share: &TableShare) -> Self {
+        let rights: Vec<String> = serde_json::from_str(&share.rights).unwrap_or_default();
+        let buy = rights.iter().any(|right| right == "buy");
+        Self { read: true, buy, rent: buy, release: false }
+    }
+
+    pub fn allows(self, operation: Operation) -> bool {
+        match operation {
+            Operation::Read => self.read,
+            Operation::Buy => self.buy,
+            Operation::Rent => self.rent,
+            Operation::Release => self.release,
+        }
+    }
+
+    fn is_empty(self) -> bool {
+        self == Self::default()
+    }
+
+    fn union(self, other: Self) -> Self {
+        Self {
+            read: self.read || other.read,
+            buy: self.buy || other.buy,
+            rent: self.rent || other.rent,
+            release: self.release || other.release,
+        }
+    }
+}
+
+/// tokens.tableAccess: table IDs granted every operation, or each table's operations
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TableAccess {
+    Tables(Vec<String>),
+    Operations(HashMap<String, Vec<Operation>>),
+}
+
+/// Which tables a token may use, shares aside
+enum TokenScope {
+    /// Every public table, for every operation
+    All,
+    /// Exactly these tables, whatever their visibility, for the given operations
+    Listed(Vec<(String, TableRights)>),
+    /// The public/shared tables of the token's creator, for every operation
+    Owner,
+}
+
+fn token_scope(token: &TokenInfo) -> TokenScope {
+    if token.is_admin {
+        return TokenScope::All;
+    }
+    let Some(access) = token.table_access.as_deref() else {
+        return TokenScope::Owner;
+    };
+    // A list that doesn't parse grants nothing
+    let tables = match serde_json::from_str(access) {
+        Ok(TableAccess::Tables(ids)) => ids.into_iter().map(|id| (id, TableRights::FULL)).collect(),
+        Ok(TableAccess::Operations(tables)) => tables
+            .into_iter()
+            .map(|(id, operations)| (id, TableRights::of_operations(&operations)))
+            .collect(),
+        Err(_) => vec![],
+    };
+    TokenScope::Listed(tables)
+}
+
+/// IDs of the tables a token may read; None means every public table
+pub async fn allowed_table_ids<S: TableStore, C: Cache>(store: &S, cache: &C, token: &TokenInfo) -> Result<Option<Vec<String>>> {
+    let mut ids = match token_scope(token) {
+        TokenScope::All => return Ok(None),
+        TokenScope::Listed(tables) => tables.into_iter().filter(|(_, rights)| rights.read).map(|(id, _)| id).collect(),
+        TokenScope::Owner if token.created_by.is_none() && token.user_id.is_none() => vec![],
+        TokenScope::Owner => store.owned_tables(token.created_by.as_deref(), token.user_id.as_deref()).await?,
+    };
+    for share in token_shares(store, cache, token).await? {
+        if ids.contains(&share.table_id) {
+            continue;
+        }
+        if table_by_id(store, cache, &share.table_id).await?.is_some_and(|table| table.is_shared()) {
+            ids.push(share.table_id);
+        }
+    }
+    Ok(Some(ids))
+}
+
+/// The shares naming a token or its user, from KV or else D1 (then cached)
+async fn token_shares<S: TableStore, C: Cache>(store: &S, cache: &C, token: &TokenInfo) -> Result<Vec<TableShare>> {
+    let cache_key = format!("{}{}", CACHE_PREFIX_TOKEN_SHARES, token.id);
+    if let Some(shares) = cache.get(&cache_key).await.and_then(|json| serde_json::from_str(&json).ok()) {
+        return Ok(shares);
+    }
+    let shares = store.table_shares(&token.id, token.user_id.as_deref()).await?;
+    if let Ok(json) = serde_json::to_string(&shares) {
+        cache.put(&cache_key, json, None).await;
+    }
+    Ok(shares)
+}
+
+/// What a token's own scope lets it do with a table
+fn scope_rights(token: &TokenInfo, table: &TableMetadata) -> TableRights {
+    let granted = match token_scope(token) {
+        TokenScope::All => table.is_public(),
+        TokenScope::Listed(tables) => {
+            return tables.into_iter().find(|(id, _)| *id == table.id).map(|(_, rights)| rights).unwrap_or_default();
+        }
+        TokenScope::Owner => (table.is_public() || table.is_shared()) && table.owned_by(token),
+    };
+    if granted { TableRights::FULL } else { TableRights::default() }
+}
+
+/// What a token may do with a table: its scope's rights plus those of the shares naming it.
+/// `shares` starts as None and keeps the token's shares once loaded, so checking several
+/// tables in one request reads them once.
+pub async fn table_rights<S: TableStore, C: Cache>(
+    store: &S,
+    cache: &C,
+    token: &TokenInfo,
+    table: &TableMetadata,
+    shares: &mut Option<Vec<TableShare>>,
+) -> Result<TableRights> {
+    let rights = scope_rights(token, table);
+    if rights == TableRights::FULL || !table.is_shared() || token.is_admin {
+        return Ok(rights);
+    }
+    if shares.is_none() {
+        *shares = Some(token_shares(store, cache, token).await?);
+    }
+    Ok(shares
+        .iter()
+        .flatten()
+        .filter(|share| share.table_id == table.id)
+        .fold(rights, |rights, share| rights.union(TableRights::of_share(share))))
+}
+
+/// Whether a token may read a table (see `allowed_table_ids` and `table_rights`)
+pub async fn can_access_table<S: TableStore, C: Cache>(
+    store: &S,
+    cache: &C,
+    token: &TokenInfo,
+    table: &TableMetadata,
+    shares: &mut Option<Vec<TableShare>>,
+) -> Result<bool> {
+    Ok(table_rights(store, cache, token, table, shares).await?.read)
+}
+
+/// Check a buy, rent or release is within the token's rights on its table: the body's
+/// `tableId`, or for releases by `rentalId` the rental's. Tables and rentals that don't
+/// exist are left for the order service to reject.
+pub async fn check_write<S: TableStore + OrderStore, C: Cache>(
+    store: &S,
+    cache: &C,
+    token: &TokenInfo,
+    kind: RouteKind,
+    body: &str,
+) -> ApiResult<()> {
+    let Some(operation) = Operation::of_write(kind) else {
+        return Ok(());
+    };
+    let body: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+    let field = |key: &str| body.get(key).and_then(|v| v.as_str()).filter(|v| !v.is_empty());
+    let table_id = match (field("tableId"), field("rentalId")) {
+        (Some(table_id), _) => Some(table_id.to_string()),
+        (None, Some(rental_id)) if operation == Operation::Release => store.rental_table(rental_id).await?,
+        _ => None,
+    };
+    let Some(table_id) = table_id else {
+        return Ok(());
+    };
+    let Some(table) = store.get_table(&table_id).await? else {
+        return Ok(());
+    };
+    let rights = table_rights(store, cache, token, &table, &mut None).await?;
+    if rights.is_empty() {
+        return Err(ApiError::TableForbidden);
+    }
+    if !rights.allows(operation) {
+        return Err(ApiError::OperationForbidden(operation.name()));
+    }
+    Ok(())
+}
+
+/// A table by ID, from KV or else D1 (then cached)
+async fn table_by_id<S: TableStore, C: Cache>(store: &S, cache: &C, table_id: &str) -> Result<Option<TableMetadata>> {
+    let cache_key = format!("{}{}", CACHE_PREFIX_TABLE_METADATA, table_id);
+    if let Some(table) = cache.get(&cache_key).await.and_then(|json| serde_json::from_str(&json).ok()) {
+        return Ok(Some(table));
+    }
+    let table = store.get_table(table_id).await?;
+    if let Some(ref table) = table
+        && let Ok(json) = serde_json::to_string(table)
+    {
+        // No expiration - the TS API deletes the entry when the table changes
+        cache.put(&cache_key, json, None).await;
+    }
+    Ok(table)
+}
+
+/// A table's columns, from KV or else D1 (then cached)
+async fn table_columns<S: TableStore, C: Cache>(store: &S, cache: &C, table_id: &str) -> Result<Vec<TableColumn>> {
+    let cache_key = format!("{}{}", CACHE_PREFIX_TABLE_COLUMNS, table_id);
+    if let Some(columns) = cache.get(&cache_key).await.and_then(|json| serde_json::from_str(&json).ok()) {
+        return Ok(columns);
+    }
+    let columns = store.table_columns(table_id).await?;
+    if let Ok(json) = serde_json::to_string(&columns) {
+        cache.put(&cache_key, json, None).await;
+    }
+    Ok(columns)
+}
+
+/// A table by slug, else the one table the token can read with that name. Names only
+/// match readable tables, so private tables neither leak nor make a name ambiguous.
+async fn find_table<S: TableStore, C: Cache>(
+    store: &S,
+    cache: &C,
+    token: &TokenInfo,
+    handle: &str,
+    shares: &mut Option<Vec<TableShare>>,
+) -> ApiResult<TableMetadata> {
+    let mut tables = store.find_tables(handle).await?;
+    // Slugs are unique and sort first
+    if tables.first().is_some_and(|t| t.slug.as_deref() == Some(handle)) {
+        return Ok(tables.swap_remove(0));
+    }
+    let mut readable = vec![];
+    for table in tables {
+        if can_access_table(store, cache, token, &table, shares).await? {
+            readable.push(table);
+        }
+    }
+    let mut tables = readable;
+    match tables.len() {
+        0 => Err(ApiError::TableNotFound),
+        1 => Ok(tables.swap_remove(0)),
+        _ => Err(ApiError::TableAmbiguous(handle.to_string())),
+    }
+}
+
+/// Look up a table by ID, slug or name and check the token may read it: not found when no
+/// readable table goes by that name, forbidden when the table isn't the token's to read.
+/// `shares` is as for `table_rights`.
+pub async fn authorized_table<S: TableStore, C: Cache>(
+    store: &S,
+    cache: &C,
+    token: &TokenInfo,
+    table_ref: &str,
+    shares: &mut Option<Vec<TableShare>>,
+) -> ApiResult<TableMetadata> {
+    let table = match table_by_id(store, cache, table_ref).await? {
+        Some(table) => table,
+        None => find_table(store, cache, token, table_ref, shares).await?,
+    };
+    if !can_access_table(store, cache, token, &table, shares).await? {
+        return Err(ApiError::TableForbidden);
+    }
+    Ok(table)
+}
+
+/// `authorized_table` with the table's columns, for the table routes
+pub async fn resolve_table<S: TableStore, C: Cache>(
+    store: &S,
+    cache: &C,
+    token: &TokenInfo,
+    table_ref: &str,
+) -> ApiResult<TableContext> {
+    let table = authorized_table(store, cache, token, table_ref, &mut None).await?;
+    let columns = table_columns(store, cache, &table.id).await?;
+    Ok(TableContext { table, columns })
+}
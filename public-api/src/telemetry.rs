@@ -0,0 +1,160 @@
+use std::cell::Cell;
+use std::future::Future;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::{JsCast, JsValue};
+use worker::*;
+
+// ============================================================================
+// REQUEST IDS
+// ============================================================================
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Take the caller's X-Request-Id when it's sane, otherwise generate one
+pub fn request_id_from(req: &Request) -> String {
+    request_id_or_generate(req.headers().get(REQUEST_ID_HEADER).ok().flatten())
+}
+
+/// Keep a caller-supplied request ID that's short and safe to log, otherwise generate one
+pub fn request_id_or_generate(id: Option<String>) -> String {
+    match id {
+        Some(id)
+            if !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':')) =>
+        {
+            id
+        }
+        _ => generate_request_id(),
+    }
+}
+
+/// Generate a request ID via crypto.randomUUID(), falling back to time + Math.random
+#[cfg(target_arch = "wasm32")]
+fn generate_request_id() -> String {
+    let uuid = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("crypto"))
+        .and_then(|crypto| {
+            let random_uuid = js_sys::Reflect::get(&crypto, &JsValue::from_str("randomUUID"))?;
+            random_uuid.dyn_into::<js_sys::Function>()?.call0(&crypto)
+        })
+        .ok()
+        .and_then(|v| v.as_string());
+
+    uuid.unwrap_or_else(|| {
+        let random = (js_sys::Math::random() * u32::MAX as f64) as u32;
+        format!("{:x}-{:08x}", js_sys::Date::now() as u64, random)
+    })
+}
+
+/// Generate a request ID from the time, the process and a per-process counter
+#[cfg(not(target_arch = "wasm32"))]
+fn generate_request_id() -> String {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+    let sequence = COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{:x}-{:x}-{:08x}", crate::utils::now_ms() as u64, std::process::id(), sequence)
+}
+
+// ============================================================================
+// REQUEST METRICS
+// ============================================================================
+
+/// Per-request counters and timings for the log line and Server-Timing header
+/// Interior mutability so handlers can share it by reference across awaits
+#[derive(Debug)]
+pub struct RequestMetrics {
+    pub request_id: String,
+    started_at: f64,
+    auth_ms: Cell<f64>,
+    cache_ms: Cell<f64>,
+    cache_hits: Cell<u32>,
+    cache_misses: Cell<u32>,
+    db_ms: Cell<f64>,
+    db_queries: Cell<u32>,
+}
+
+impl RequestMetrics {
+    pub fn new(request_id: String) -> Self {
+        Self {
+            request_id,
+            started_at: crate::utils::now_ms(),
+            auth_ms: Cell::new(0.0),
+            cache_ms: Cell::new(0.0),
+            cache_hits: Cell::new(0),
+            cache_misses: Cell::new(0),
+            db_ms: Cell::new(0.0),
+            db_queries: Cell::new(0),
+        }
+    }
+
+    async fn timed<T>(cell: &Cell<f64>, fut: impl Future<Output = T>) -> T {
+        let start = crate::utils::now_ms();
+        let result = fut.await;
+        cell.set(cell.get() + crate::utils::now_ms() - start);
+        result
+    }
+
+    /// Time token validation
+    pub async fn auth<T>(&self, fut: impl Future<Output = T>) -> T {
+        Self::timed(&self.auth_ms, fut).await
+    }
+
+    /// Time a KV cache operation
+    pub async fn cache<T>(&self, fut: impl Future<Output = T>) -> T {
+        Self::timed(&self.cache_ms, fut).await
+    }
+
+    /// Time and count one D1 query
+    pub async fn db<T>(&self, fut: impl Future<Output = T>) -> T {
+        self.db_queries.set(self.db_queries.get() + 1);
+        Self::timed(&self.db_ms, fut).await
+    }
+
+    /// Count a cache lookup outcome
+    pub fn cache_lookup(&self, hit: bool) {
+        let counter = if hit { &self.cache_hits } else { &self.cache_misses };
+        counter.set(counter.get() + 1);
+    }
+
+    fn elapsed_ms(&self) -> f64 {
+        crate::utils::now_ms() - self.started_at
+    }
+
+    /// Server-Timing header value, e.g. `auth;dur=2, cache;dur=5, db;dur=31;desc="3 queries", total;dur=40`
+    pub fn server_timing(&self) -> String {
+        format!(
+            "auth;dur={:.0}, cache;dur={:.0}, db;dur={:.0};desc=\"{} queries\", total;dur={:.0}",
+            self.auth_ms.get(),
+            self.cache_ms.get(),
+            self.db_ms.get(),
+            self.db_queries.get(),
+            self.elapsed_ms()
+        )
+    }
+
+    /// Emit the single structured log line for this request
+    pub fn log(&self, method: &str, route: Option<&str>, path: &str, token_id: Option<&str>, status: u16) {
+        let line = serde_json::json!({
+            "requestId": self.request_id,
+            "method": method,
+            "route": route,
+            "path": path,
+            "tokenId": token_id,
+            "status": status,
+            "durationMs": self.elapsed_ms().round(),
+            "authMs": self.auth_ms.get().round(),
+            "cacheMs": self.cache_ms.get().round(),
+            "cacheHits": self.cache_hits.get(),
+            "cacheMisses": self.cache_misses.get(),
+            "dbMs": self.db_ms.get().round(),
+            "dbQueries": self.db_queries.get(),
+        });
+        if status >= 500 {
+            crate::utils::log_error(&line.to_string());
+        } else {
+            crate::utils::log(&line.to_string());
+        }
+    }
+}
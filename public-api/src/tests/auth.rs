@@ -0,0 +1,71 @@
+use super::*;
+use crate::authenticate;
+use crate::error::ApiError;
+
+#[test]
+fn unknown_token_is_unauthorized() {
+    let db = migrated_db();
+    let storage = storage(&db);
+
+    let result = block_on(authenticate(&storage, "nope", &metrics()));
+
+    assert!(matches!(result, Err(ApiError::Unauthorized)));
+}
+
+#[test]
+fn valid_token_is_cached_after_the_first_lookup() {
+    let db = migrated_db();
+    db.add_token("tok-1", "secret-1", Some(&["phones"]), None);
+    let storage = storage(&db);
+
+    let token = block_on(authenticate(&storage, "secret-1", &metrics())).unwrap();
+    assert_eq!(token.id, "tok-1");
+    assert_eq!(token.table_access.as_deref(), Some(r#"["phones"]"#));
+    assert!(storage.cache.entries.borrow().contains_key("auth:token:secret-1"));
+
+    // Served from cache until the API invalidates it
+    db.exec("DELETE FROM tokens", &[]);
+    let token = block_on(authenticate(&storage, "secret-1", &metrics())).unwrap();
+    assert_eq!(token.id, "tok-1");
+}
+
+#[test]
+fn expired_tokens_are_rejected_in_both_date_formats() {
+    let db = migrated_db();
+    db.add_token("iso", "iso-secret", None, Some(&"2001-02-03T04:05:06.000Z"));
+    db.add_token("millis", "millis-secret", None, Some(&981_173_106_000_i64));
+    let storage = storage(&db);
+
+    for secret in ["iso-secret", "millis-secret"] {
+        let result = block_on(authenticate(&storage, secret, &metrics()));
+        assert!(matches!(result, Err(ApiError::TokenExpired)), "{}", secret);
+        assert!(storage.cache.entries.borrow().is_empty(), "expired tokens must not be cached");
+    }
+}
+
+#[test]
+fn future_and_null_expiry_are_accepted() {
+    let db = migrated_db();
+    db.add_token("future", "future-secret", None, Some(&"2999-01-01T00:00:00.000Z"));
+    db.add_token("legacy", "legacy-secret", None, Some(&"null"));
+    let storage = storage(&db);
+
+    let future = block_on(authenticate(&storage, "future-secret", &metrics())).unwrap();
+    assert!(future.expires_at.is_some());
+    let legacy = block_on(authenticate(&storage, "legacy-secret", &metrics())).unwrap();
+    assert_eq!(legacy.expires_at, None);
+}
+
+#[test]
+fn cached_token_expires_without_a_database_round_trip() {
+    let db = migrated_db();
+    let storage = storage(&db);
+    storage.cache.entries.borrow_mut().insert(
+        "auth:token:old".to_string(),
+        serde_json::json!({"id": "old", "isAdmin": false, "tableAccess": null, "expiresAt": 1, "cachedAt": 0}).to_string(),
+    );
+
+    let result = block_on(authenticate(&storage, "old", &metrics()));
+
+    assert!(matches!(result, Err(ApiError::TokenExpired)));
+}
@@ -0,0 +1,139 @@
+use super::*;
+use crate::{
+    cache_bump_generation, current_timestamp, get_records, get_tables, run_refresh, Refresh, CACHE_KEY_LOCK_PREFIX,
+    CACHE_KEY_PUBLIC_TABLES, CACHE_KEY_DATA_GENERATION,
+};
+
+/// Rewind the cachedAt of a cached JSON entry
+fn age_entry(cache: &MemoryCache, key: &str, seconds: u64) {
+    let mut entries = cache.entries.borrow_mut();
+    let entry = entries.get_mut(key).expect("cached entry");
+    let mut value: serde_json::Value = serde_json::from_str(entry).unwrap();
+    value["cachedAt"] = (current_timestamp() - seconds).into();
+    *entry = value.to_string();
+}
+
+fn query_keys(cache: &MemoryCache) -> Vec<String> {
+    cache.entries.borrow().keys().filter(|k| k.starts_with("query:")).cloned().collect()
+}
+
+#[test]
+fn public_tables_are_served_from_cache_while_fresh() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    block_on(get_tables(&storage, &admin_token(), &metrics())).unwrap();
+    assert_eq!(
+        *storage.cache.ttls.borrow().get(CACHE_KEY_PUBLIC_TABLES).unwrap(),
+        Some(storage.config.public_tables_hard_ttl)
+    );
+
+    db.add_table("boats", "Boats", "public", "rent", &["name"]);
+    let response = block_on(get_tables(&storage, &admin_token(), &metrics())).unwrap();
+
+    assert_eq!(response.count, 2);
+    assert!(storage.take_refreshes().is_empty());
+}
+
+#[test]
+fn stale_public_tables_are_served_while_one_refresh_is_queued() {
+    let db = catalog();
+    let storage = storage(&db);
+    block_on(get_tables(&storage, &admin_token(), &metrics())).unwrap();
+    age_entry(&storage.cache, CACHE_KEY_PUBLIC_TABLES, storage.config.public_tables_soft_ttl + 1);
+    db.add_table("boats", "Boats", "public", "rent", &["name"]);
+
+    // Both requests get the stale list; only the first takes the lock and queues a rebuild
+    let stale = block_on(get_tables(&storage, &admin_token(), &metrics())).unwrap();
+    assert_eq!(stale.count, 2);
+    block_on(get_tables(&storage, &admin_token(), &metrics())).unwrap();
+    let refreshes = storage.take_refreshes();
+    assert_eq!(refreshes.len(), 1);
+
+    block_on(run_refresh(&storage, &refreshes[0])).unwrap();
+    let lock_key = format!("{}{}", CACHE_KEY_LOCK_PREFIX, CACHE_KEY_PUBLIC_TABLES);
+    assert!(!storage.cache.entries.borrow().contains_key(&lock_key));
+
+    let fresh = block_on(get_tables(&storage, &admin_token(), &metrics())).unwrap();
+    assert_eq!(fresh.count, 3);
+}
+
+#[test]
+fn expired_public_tables_are_reloaded_inline() {
+    let db = catalog();
+    let storage = storage(&db);
+    block_on(get_tables(&storage, &admin_token(), &metrics())).unwrap();
+    age_entry(&storage.cache, CACHE_KEY_PUBLIC_TABLES, storage.config.public_tables_hard_ttl);
+    db.add_table("boats", "Boats", "public", "rent", &["name"]);
+
+    let response = block_on(get_tables(&storage, &admin_token(), &metrics())).unwrap();
+
+    assert_eq!(response.count, 3);
+    assert!(storage.take_refreshes().is_empty());
+}
+
+#[test]
+fn query_results_are_cached_until_the_data_generation_changes() {
+    let db = catalog();
+    let storage = storage(&db);
+    let filter = query(&[("where[color]", "red")]);
+
+    let first = block_on(get_records(&storage, &admin_token(), &filter, &metrics())).unwrap();
+    assert_eq!(first.total, 3);
+    assert_eq!(query_keys(&storage.cache).len(), 1);
+
+    db.add_row("phones", "p4", serde_json::json!({"name": "Delta", "color": "red"}), 8);
+    let cached = block_on(get_records(&storage, &admin_token(), &filter, &metrics())).unwrap();
+    assert_eq!(cached.total, 3);
+
+    // The TS API replaces the generation on every write
+    storage.cache.entries.borrow_mut().insert(CACHE_KEY_DATA_GENERATION.to_string(), "5f0c2a9e".to_string());
+    let fresh = block_on(get_records(&storage, &admin_token(), &filter, &metrics())).unwrap();
+    assert_eq!(fresh.total, 4);
+    assert_eq!(fresh.records[0]["id"], "p4");
+    assert_eq!(query_keys(&storage.cache).len(), 2);
+}
+
+#[test]
+fn every_write_gets_a_generation_of_its_own() {
+    let cache = MemoryCache::default();
+    let generation = || cache.entries.borrow().get(CACHE_KEY_DATA_GENERATION).cloned();
+
+    block_on(cache_bump_generation(&cache));
+    let first = generation().expect("generation set");
+    block_on(cache_bump_generation(&cache));
+    assert_ne!(generation(), Some(first));
+}
+
+#[test]
+fn stale_query_results_queue_a_refresh_for_the_same_page() {
+    let db = catalog();
+    let storage = storage(&db);
+    let page = query(&[("limit", "2"), ("offset", "1")]);
+    block_on(get_records(&storage, &admin_token(), &page, &metrics())).unwrap();
+    let key = query_keys(&storage.cache).remove(0);
+    age_entry(&storage.cache, &key, storage.config.query_soft_ttl + 1);
+
+    let stale = block_on(get_records(&storage, &admin_token(), &page, &metrics())).unwrap();
+    assert_eq!(stale.count, 2);
+
+    let refreshes = storage.take_refreshes();
+    match &refreshes[..] {
+        [Refresh::QueryResults { cache_key, query }] => {
+            assert_eq!(cache_key, &key);
+            assert_eq!((query.limit, query.offset), (2, 1));
+        }
+        other => panic!("unexpected refreshes: {:?}", other),
+    }
+}
+
+#[test]
+fn scoped_tokens_and_column_projections_bypass_the_query_cache() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    block_on(get_records(&storage, &scoped_token(&["phones"]), &query(&[]), &metrics())).unwrap();
+    block_on(get_records(&storage, &admin_token(), &query(&[("columns", "name")]), &metrics())).unwrap();
+
+    assert!(query_keys(&storage.cache).is_empty());
+}
@@ -0,0 +1,396 @@
+use super::*;
+use crate::error::ApiError;
+use crate::store::SqlDatabase;
+use crate::{
+    authenticate, get_item_availability, get_records, get_table_item, get_table_items, get_tables, get_values, search_tables,
+};
+
+fn record_ids(records: &[serde_json::Value]) -> Vec<&str> {
+    records.iter().map(|r| r["id"].as_str().unwrap()).collect()
+}
+
+#[test]
+fn unrestricted_token_lists_public_sale_and_rent_tables() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    let response = block_on(get_tables(&storage, &admin_token(), &metrics())).unwrap();
+
+    let names: Vec<&str> = response.tables.iter().map(|t| t.name.as_str()).collect();
+    assert_eq!(names, ["Bikes", "Phones"]);
+    assert_eq!(response.tables[1].row_count, 3);
+}
+
+#[test]
+fn scoped_token_lists_only_granted_tables_whatever_their_visibility() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    let response = block_on(get_tables(&storage, &scoped_token(&["secret", "notes"]), &metrics())).unwrap();
+
+    // notes is a default table, never served by the public API
+    let ids: Vec<&str> = response.tables.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(ids, ["secret"]);
+}
+
+#[test]
+fn token_without_table_access_sees_nothing() {
+    let db = catalog();
+    let storage = storage(&db);
+    let token = TokenInfo { id: "plain".to_string(), is_admin: false, ..admin_token() };
+
+    let response = block_on(get_records(&storage, &token, &query(&[]), &metrics())).unwrap();
+
+    assert_eq!(response.total, 0);
+    assert!(response.records.is_empty());
+}
+
+#[test]
+fn token_without_table_access_reads_its_owners_public_tables() {
+    let db = catalog();
+    db.add_table("gadgets", "Gadgets", "public", "sale", &["name"]);
+    db.add_row("gadgets", "g1", serde_json::json!({"name": "Widget"}), 0);
+    db.exec("UPDATE userTables SET createdBy = 'other@x.com', userId = 'user-2' WHERE id = 'gadgets'", &[]);
+    db.add_token("owner-token", "owner-secret", None, None);
+    db.exec("UPDATE tokens SET createdBy = 'user-1' WHERE id = 'owner-token'", &[]);
+    db.add_token("flagged-token", "flagged-secret", Some(&[]), None);
+    db.exec("UPDATE tokens SET isAdmin = 1 WHERE id = 'flagged-token'", &[]);
+    let storage = storage(&db);
+
+    let owner = block_on(authenticate(&storage, "owner-secret", &metrics())).unwrap();
+    let tables = block_on(get_tables(&storage, &owner, &metrics())).unwrap();
+    let ids: Vec<&str> = tables.tables.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(ids, ["bikes", "phones"]);
+    let records = block_on(get_records(&storage, &owner, &query(&[]), &metrics())).unwrap();
+    assert_eq!(records.total, 5);
+    let result = block_on(get_table_items(&storage, &owner, "gadgets", &query(&[])));
+    assert!(matches!(result, Err(ApiError::TableForbidden)));
+    let result = block_on(get_table_items(&storage, &owner, "secret", &query(&[])));
+    assert!(matches!(result, Err(ApiError::TableForbidden)));
+
+    // The same owner by session user ID
+    let owner = TokenInfo { created_by: None, user_id: Some("user-2".to_string()), ..owner };
+    assert_eq!(block_on(get_table_items(&storage, &owner, "gadgets", &query(&[]))).unwrap().count, 1);
+
+    // Tokens from before owners were recorded read nothing without a list, as they always did
+    let ownerless = TokenInfo { user_id: None, ..owner };
+    assert_eq!(block_on(get_tables(&storage, &ownerless, &metrics())).unwrap().count, 0);
+    assert!(block_on(get_records(&storage, &ownerless, &query(&[]), &metrics())).unwrap().records.is_empty());
+    let result = block_on(get_table_items(&storage, &ownerless, "phones", &query(&[])));
+    assert!(matches!(result, Err(ApiError::TableForbidden)));
+
+    // isAdmin, not the token ID, makes a token unrestricted, even with an empty tableAccess
+    let flagged = block_on(authenticate(&storage, "flagged-secret", &metrics())).unwrap();
+    let tables = block_on(get_tables(&storage, &flagged, &metrics())).unwrap();
+    assert_eq!(tables.count, 3);
+}
+
+#[test]
+fn records_filters_are_case_insensitive_and_combined() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    let response = block_on(get_records(&storage, &admin_token(), &query(&[("where[color]", "red")]), &metrics())).unwrap();
+    assert_eq!(record_ids(&response.records), ["b1", "p3", "p1"]);
+    assert_eq!(response.total, 3);
+    assert_eq!(response.filters.unwrap()["color"], "red");
+
+    let response = block_on(get_records(
+        &storage,
+        &admin_token(),
+        &query(&[("where[color]", "RED"), ("where[name]", "alpha")]),
+        &metrics(),
+    ))
+    .unwrap();
+    assert_eq!(record_ids(&response.records), ["p1"]);
+}
+
+#[test]
+fn filters_use_the_attribute_index_and_follow_row_writes() {
+    let db = catalog();
+    let storage = storage(&db);
+    let matching = |column: &str, value: &str| {
+        let filter = format!("where[{}]", column);
+        let response = block_on(get_records(&storage, &scoped_token(&["phones", "bikes"]), &query(&[(&filter, value)]), &metrics()));
+        record_ids(&response.unwrap().records).iter().map(|id| id.to_string()).collect::<Vec<_>>()
+    };
+
+    assert_eq!(matching("qty", "3"), ["p1"]);
+    assert_eq!(matching("used", "0"), ["b1"]);
+    // Numbers match by value, not by how they are written
+    db.exec("UPDATE tableData SET data = json_set(data, '$.price', 12.5) WHERE id = 'p2'", &[]);
+    assert_eq!(matching("price", "12.50"), ["p2"]);
+    assert_eq!(matching("qty", "3.0"), ["p1"]);
+    db.exec("UPDATE tableData SET data = '{\"name\": \"Alpha\", \"color\": \"Teal\"}' WHERE id = 'p1'", &[]);
+    assert_eq!(matching("color", "teal"), ["p1"]);
+    assert_eq!(matching("qty", "3"), Vec::<String>::new());
+    db.exec("DELETE FROM tableData WHERE id = 'p1'", &[]);
+    assert_eq!(matching("color", "teal"), Vec::<String>::new());
+
+    let plan = |sql: &str| -> Vec<String> {
+        db.connection()
+            .prepare(&format!("EXPLAIN QUERY PLAN {}", sql))
+            .unwrap()
+            .query_map([], |row| row.get::<_, String>(3))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    };
+    let text = plan("SELECT rowId FROM tableDataAttributes WHERE columnName = 'color' AND textValue = 'red'");
+    assert!(text.iter().any(|step| step.contains("idx_tableDataAttributes_column_text")), "{:?}", text);
+    let number = plan("SELECT rowId FROM tableDataAttributes WHERE columnName = 'price' AND numValue = 12.5");
+    assert!(number.iter().any(|step| step.contains("idx_tableDataAttributes_column_num")), "{:?}", number);
+}
+
+#[test]
+fn records_are_flattened_with_table_labels() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    let response = block_on(get_records(&storage, &scoped_token(&["bikes"]), &query(&[("where[name]", "Racer")]), &metrics())).unwrap();
+
+    let record = &response.records[0];
+    assert_eq!(record["tableName"], "Bikes");
+    assert_eq!(record["tableType"], "rent");
+    assert_eq!(record["color"], "green");
+    assert_eq!(record["used"], true);
+}
+
+#[test]
+fn records_paginate_newest_first() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    let first = block_on(get_records(&storage, &admin_token(), &query(&[("limit", "2")]), &metrics())).unwrap();
+    assert_eq!(record_ids(&first.records), ["b2", "b1"]);
+    assert_eq!(first.pagination.total, 5);
+    assert_eq!(first.pagination.page, 1);
+    assert!(first.pagination.has_more);
+
+    let last = block_on(get_records(&storage, &admin_token(), &query(&[("limit", "2"), ("offset", "4")]), &metrics())).unwrap();
+    assert_eq!(record_ids(&last.records), ["p1"]);
+    assert_eq!(last.pagination.page, 3);
+    assert!(!last.pagination.has_more);
+}
+
+#[test]
+fn records_limit_is_clamped() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    let response = block_on(get_records(&storage, &admin_token(), &query(&[("limit", "0")]), &metrics())).unwrap();
+
+    assert_eq!(response.pagination.limit, 1);
+    assert_eq!(response.count, 1);
+}
+
+#[test]
+fn records_column_projection_keeps_identity_fields() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    let response = block_on(get_records(&storage, &admin_token(), &query(&[("columns", "name"), ("limit", "1")]), &metrics())).unwrap();
+
+    let record = response.records[0].as_object().unwrap();
+    let mut keys: Vec<&str> = record.keys().map(|k| k.as_str()).collect();
+    keys.sort();
+    assert_eq!(keys, ["id", "name", "tableId", "tableName", "tableType"]);
+}
+
+#[test]
+fn filter_columns_that_could_break_out_of_the_json_path_are_rejected() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    let result = block_on(get_records(&storage, &admin_token(), &query(&[("where[color')) OR 1=1 --]", "x")]), &metrics()));
+    assert!(matches!(result, Err(ApiError::InvalidFilter(_))));
+
+    let result = block_on(get_values(&storage, &admin_token(), "name'", &query(&[])));
+    assert!(matches!(result, Err(ApiError::InvalidParameter(_))));
+}
+
+#[test]
+fn table_items_check_existence_access_and_type() {
+    let db = catalog();
+    let storage = storage(&db);
+    let token = admin_token();
+
+    let items = block_on(get_table_items(&storage, &token, "phones", &query(&[]))).unwrap();
+    assert_eq!(items.count, 3);
+    assert_eq!(items.items[0]["id"], "p3");
+    assert_eq!(items.items[0]["data"]["name"], "Gamma");
+
+    let flat = block_on(get_table_items(&storage, &token, "phones", &query(&[("flat", "true")]))).unwrap();
+    assert_eq!(flat.items[0]["name"], "Gamma");
+
+    assert!(matches!(block_on(get_table_items(&storage, &token, "missing", &query(&[]))), Err(ApiError::TableNotFound)));
+    assert!(matches!(block_on(get_table_items(&storage, &token, "secret", &query(&[]))), Err(ApiError::TableForbidden)));
+    assert!(matches!(block_on(get_table_items(&storage, &token, "notes", &query(&[]))), Err(ApiError::TableTypeUnsupported)));
+
+    // A scoped token reaches private tables it was granted, but nothing else
+    let scoped = scoped_token(&["secret"]);
+    assert_eq!(block_on(get_table_items(&storage, &scoped, "secret", &query(&[]))).unwrap().count, 1);
+    assert!(matches!(block_on(get_table_items(&storage, &scoped, "phones", &query(&[]))), Err(ApiError::TableForbidden)));
+}
+
+#[test]
+fn single_item_lookup() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    let item = block_on(get_table_item(&storage, &admin_token(), "bikes", "b1", &query(&[]))).unwrap();
+    assert_eq!(item["name"], "Cruiser");
+    assert_eq!(item["tableName"], "Bikes");
+
+    // Items are looked up within their table only
+    assert!(matches!(block_on(get_table_item(&storage, &admin_token(), "bikes", "p1", &query(&[]))), Err(ApiError::ItemNotFound)));
+}
+
+#[test]
+fn availability_uses_qty_for_sales_and_used_for_rentals() {
+    let db = catalog();
+    let storage = storage(&db);
+    let token = admin_token();
+
+    let sale = block_on(get_item_availability(&storage, &token, "phones", "p1", &query(&[("quantity", "3")]))).unwrap();
+    assert!(sale.available);
+    assert_eq!(sale.available_qty, 3);
+
+    let sold_out = block_on(get_item_availability(&storage, &token, "phones", "p1", &query(&[("quantity", "4")]))).unwrap();
+    assert!(!sold_out.available);
+
+    let free = block_on(get_item_availability(&storage, &token, "bikes", "b1", &query(&[]))).unwrap();
+    assert!(free.available);
+    let rented = block_on(get_item_availability(&storage, &token, "bikes", "b2", &query(&[]))).unwrap();
+    assert!(!rented.available);
+}
+
+#[test]
+fn search_matches_tables_having_every_column() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    let response = block_on(search_tables(&storage, &admin_token(), &query(&[("columns", "Name, QTY")]))).unwrap();
+    let ids: Vec<&str> = response.tables.iter().map(|t| t.table.id.as_str()).collect();
+    assert_eq!(ids, ["phones"]);
+
+    let result = block_on(search_tables(&storage, &admin_token(), &query(&[("columns", " , ")])));
+    assert!(matches!(result, Err(ApiError::InvalidParameter(_))));
+}
+
+#[test]
+fn search_matches_rows_with_counts_and_cheapest_price() {
+    let db = catalog();
+    db.add_row("phones", "p4", serde_json::json!({"name": "Delta", "color": "red", "qty": 0, "price": 5}), 8);
+    db.exec(r#"UPDATE tableData SET data = json_set(data, '$.price', 20) WHERE id = 'p1'"#, &[]);
+    db.exec(r#"UPDATE tableData SET data = json_set(data, '$.price', 12.5) WHERE id = 'p3'"#, &[]);
+    db.exec(r#"UPDATE tableData SET data = json_set(data, '$.price', 9) WHERE id = 'b1'"#, &[]);
+    let storage = storage(&db);
+    let search = |pairs: &[(&str, &str)]| block_on(search_tables(&storage, &admin_token(), &query(pairs)));
+    let matches = |response: &crate::SearchResponse| -> Vec<(String, i64, i64, Option<f64>)> {
+        response
+            .tables
+            .iter()
+            .map(|t| {
+                let m = t.matches.as_ref().unwrap();
+                (t.table.id.clone(), m.matching_rows, m.available_rows, m.cheapest_price)
+            })
+            .collect()
+    };
+
+    // Out-of-stock rows count as matches but not towards availability or the cheapest price
+    let response = search(&[("match[color]", "Red")]).unwrap();
+    assert_eq!(
+        matches(&response),
+        [("bikes".to_string(), 1, 1, Some(9.0)), ("phones".to_string(), 3, 2, Some(12.5))]
+    );
+    assert_eq!(response.match_conditions.unwrap()["color"], "Red");
+    let response = search(&[("match[color]", "green")]).unwrap();
+    assert_eq!(matches(&response), [("bikes".to_string(), 1, 0, None)]);
+
+    // Combined with columns, and tables without a matching row are left out
+    let response = search(&[("match[color]", "red"), ("columns", "qty")]).unwrap();
+    assert_eq!(matches(&response), [("phones".to_string(), 3, 2, Some(12.5))]);
+    assert_eq!(search(&[("match[color]", "red"), ("match[name]", "gamma")]).unwrap().count, 1);
+    assert_eq!(search(&[("match[color]", "purple")]).unwrap().count, 0);
+
+    let result = search(&[("match[color']", "x")]);
+    assert!(matches!(result, Err(ApiError::InvalidFilter(_))));
+    let result = search(&[]);
+    assert!(matches!(result, Err(ApiError::InvalidParameter(_))));
+}
+
+#[test]
+fn values_are_distinct_and_respect_filters_and_access() {
+    let db = catalog();
+    let storage = storage(&db);
+
+    let response = block_on(get_values(&storage, &admin_token(), "name", &query(&[("where[color]", "red")]))).unwrap();
+    let mut values: Vec<&str> = response.values.iter().map(|v| v.as_str().unwrap()).collect();
+    values.sort();
+    // Hidden lives in a private table
+    assert_eq!(values, ["Alpha", "Cruiser", "Gamma"]);
+    assert_eq!(response.tables_sampled, ["Bikes", "Phones"]);
+
+    let response = block_on(get_values(&storage, &admin_token(), "qty", &query(&[]))).unwrap();
+    assert_eq!(response.tables_sampled, ["Phones"]);
+    assert_eq!(response.count, 3);
+}
+
+/// Counts the queries a handler sends, like D1 round trips
+struct CountingDb {
+    db: SqliteDb,
+    queries: std::rc::Rc<std::cell::Cell<usize>>,
+}
+
+impl SqlDatabase for CountingDb {
+    async fn query(&self, sql: &str, params: &[serde_json::Value]) -> worker::Result<Vec<serde_json::Value>> {
+        self.queries.set(self.queries.get() + 1);
+        self.db.query(sql, params).await
+    }
+}
+
+#[test]
+fn listing_costs_the_same_queries_for_any_number_of_tables() {
+    let queries = |extra_tables: usize| {
+        let db = catalog();
+        let mut ids = vec!["phones".to_string(), "bikes".to_string()];
+        for i in 0..extra_tables {
+            let id = format!("extra{}", i);
+            db.add_table(&id, &id, "public", "sale", &["name", "color"]);
+            db.add_row(&id, &format!("{}-1", id), serde_json::json!({"name": "X", "color": "red"}), 8);
+            ids.push(id);
+        }
+        let token = TokenInfo { price_rules: Some(r#"{"default": {"percent": 10}}"#.to_string()), ..scoped_token(&[]) };
+        let token = TokenInfo { table_access: Some(serde_json::json!(ids).to_string()), ..token };
+        let queries = std::rc::Rc::new(std::cell::Cell::new(0));
+        let counting = CountingDb { db, queries: queries.clone() };
+        let storage = Storage::new(SqlStore::new(counting), MemoryCache::default(), CacheConfig::default());
+
+        let tables = block_on(get_tables(&storage, &token, &metrics())).unwrap();
+        assert_eq!(tables.count, extra_tables + 2);
+        block_on(search_tables(&storage, &token, &query(&[("columns", "color")]))).unwrap();
+        block_on(get_values(&storage, &token, "color", &query(&[]))).unwrap();
+        block_on(get_values(&storage, &token, "price", &query(&[]))).unwrap();
+        queries.get()
+    };
+
+    assert_eq!(queries(0), queries(40));
+}
+
+#[test]
+fn row_counts_follow_row_writes() {
+    let db = catalog();
+    let storage = storage(&db);
+    let phones = || {
+        let tables = block_on(get_tables(&storage, &scoped_token(&["phones"]), &metrics())).unwrap();
+        tables.tables[0].row_count
+    };
+
+    assert_eq!(phones(), 3);
+    db.add_row("phones", "p4", serde_json::json!({"name": "Delta"}), 8);
+    assert_eq!(phones(), 4);
+    db.exec("UPDATE tableData SET tableId = 'bikes' WHERE id = 'p4'", &[]);
+    db.exec("DELETE FROM tableData WHERE id = 'p1'", &[]);
+    assert_eq!(phones(), 2);
+}
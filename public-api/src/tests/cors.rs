@@ -0,0 +1,31 @@
+use crate::cors::preflight_headers;
+use crate::utils::Vars;
+
+/// Worker vars from a list of pairs
+struct TestVars(&'static [(&'static str, &'static str)]);
+
+impl Vars for TestVars {
+    fn get(&self, name: &str) -> Option<String> {
+        self.0.iter().find(|(n, _)| *n == name).map(|(_, v)| v.to_string())
+    }
+}
+
+fn header(headers: &[(&'static str, String)], name: &str) -> Option<String> {
+    headers.iter().find(|(n, _)| *n == name).map(|(_, v)| v.clone())
+}
+
+#[test]
+fn preflights_only_allow_credentials_for_globally_allowed_origins() {
+    let vars = TestVars(&[("CORS_ALLOWED_ORIGINS", "https://shop.example.com")]);
+
+    let allowed = preflight_headers(&vars, Some("https://shop.example.com".to_string()), "/api/public/buy");
+    assert_eq!(header(&allowed, "Access-Control-Allow-Origin").as_deref(), Some("https://shop.example.com"));
+    assert_eq!(header(&allowed, "Access-Control-Allow-Credentials").as_deref(), Some("true"));
+
+    // Any other origin may still send the request (its token may allow it), but without credentials
+    for vars in [vars, TestVars(&[])] {
+        let other = preflight_headers(&vars, Some("https://evil.example.net".to_string()), "/api/public/buy");
+        assert_eq!(header(&other, "Access-Control-Allow-Origin").as_deref(), Some("https://evil.example.net"));
+        assert_eq!(header(&other, "Access-Control-Allow-Credentials"), None);
+    }
+}
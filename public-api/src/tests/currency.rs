@@ -0,0 +1,100 @@
+use super::*;
+use crate::currency::CACHE_KEY_CURRENCY_RATES;
+use crate::{get_records, get_table_item, get_table_items};
+
+const RATES: &str = r#"{"base":"USD","rates":{"USD":1,"EUR":0.5,"GBP":0.25},"updatedAt":"2026-01-01T00:00:00.000Z"}"#;
+
+fn add_column(db: &SqliteDb, table_id: &str, name: &str, column_type: &str) {
+    db.exec(
+        "INSERT INTO tableColumns (id, tableId, name, type, position) VALUES (?, ?, ?, ?, 10)",
+        &[&format!("{}-{}", table_id, name), &table_id, &name, &column_type],
+    );
+}
+
+fn set_data(db: &SqliteDb, row_id: &str, data: serde_json::Value) {
+    db.exec("UPDATE tableData SET data = ? WHERE id = ?", &[&data.to_string(), &row_id]);
+}
+
+/// Phones are priced in EUR (price plus a currency-typed shipping column), bikes in USD
+fn priced_catalog() -> SqliteDb {
+    let db = catalog();
+    db.exec("UPDATE userTables SET currency = 'EUR' WHERE id = 'phones'", &[]);
+    add_column(&db, "phones", "price", "number");
+    add_column(&db, "phones", "shipping", "currency");
+    add_column(&db, "bikes", "price", "number");
+    add_column(&db, "bikes", "fee", "number");
+    set_data(&db, "p1", serde_json::json!({"name": "Alpha", "color": "Red", "qty": 3, "price": 10, "shipping": "2.50"}));
+    set_data(&db, "b1", serde_json::json!({"name": "Cruiser", "color": "RED", "used": false, "price": 20, "fee": 4.5}));
+    db
+}
+
+fn priced_storage(db: &SqliteDb) -> TestStorage {
+    let storage = storage(db);
+    storage.cache.entries.borrow_mut().insert(CACHE_KEY_CURRENCY_RATES.to_string(), RATES.to_string());
+    storage
+}
+
+#[test]
+fn records_are_converted_on_the_way_out_of_the_cache() {
+    let db = priced_catalog();
+    let storage = priced_storage(&db);
+    let alpha = |currency: Option<&str>| {
+        let mut params = vec![("where[name]", "Alpha")];
+        params.extend(currency.map(|c| ("currency", c)));
+        let records = block_on(get_records(&storage, &admin_token(), &query(&params), &metrics())).unwrap();
+        serde_json::to_value(records).unwrap()["records"][0].clone()
+    };
+
+    let converted = alpha(Some("GBP"));
+    assert_eq!(converted["price"], 5.0);
+    assert_eq!(converted["shipping"], 1.25);
+    assert_eq!(converted["qty"], 3);
+    assert_eq!(
+        converted["currencyConversion"],
+        serde_json::json!({"from": "EUR", "to": "GBP", "rate": 0.5, "ratesUpdatedAt": "2026-01-01T00:00:00.000Z"})
+    );
+
+    // The cached page stays in base currency and is converted per request
+    let raw = alpha(None);
+    assert_eq!((&raw["price"], &raw["shipping"]), (&10.into(), &"2.50".into()));
+    assert!(raw.get("currencyConversion").is_none());
+    assert_eq!(alpha(Some("usd"))["price"], 20.0);
+}
+
+#[test]
+fn items_are_converted_in_both_shapes() {
+    let db = priced_catalog();
+    let storage = priced_storage(&db);
+
+    let items = block_on(get_table_items(&storage, &admin_token(), "bikes", &query(&[("currency", "EUR")]))).unwrap();
+    let items = serde_json::to_value(items).unwrap();
+    let cruiser = items["items"].as_array().unwrap().iter().find(|item| item["id"] == "b1").unwrap();
+    assert_eq!((&cruiser["data"]["price"], &cruiser["data"]["fee"]), (&10.0.into(), &2.25.into()));
+    assert_eq!(cruiser["currencyConversion"]["from"], "USD");
+
+    let item = block_on(get_table_item(&storage, &admin_token(), "bikes", "b1", &query(&[("currency", "GBP")]))).unwrap();
+    assert_eq!(item["price"], 5.0);
+    assert_eq!(item["currencyConversion"]["rate"], 0.25);
+
+    // Tables without money columns are left alone
+    let db = catalog();
+    let storage = priced_storage(&db);
+    let item = block_on(get_table_item(&storage, &admin_token(), "phones", "p1", &query(&[("currency", "EUR")]))).unwrap();
+    assert!(item.get("currencyConversion").is_none());
+}
+
+#[test]
+fn unknown_currencies_and_missing_rates_are_errors() {
+    let db = priced_catalog();
+    let storage = priced_storage(&db);
+    let item = |storage: &TestStorage, currency: &str| {
+        block_on(get_table_item(storage, &admin_token(), "bikes", "b1", &query(&[("currency", currency)])))
+    };
+
+    assert!(matches!(item(&storage, "euro"), Err(ApiError::InvalidParameter(_))));
+    assert!(matches!(item(&storage, "JPY"), Err(ApiError::InvalidParameter(_))));
+    assert!(matches!(item(&super::storage(&db), "EUR"), Err(ApiError::CurrencyUnavailable(_))));
+
+    db.exec("UPDATE userTables SET currency = 'CHF' WHERE id = 'bikes'", &[]);
+    assert!(matches!(item(&storage, "EUR"), Err(ApiError::CurrencyUnavailable(_))));
+}
@@ -0,0 +1,100 @@
+use super::*;
+use crate::{get_records, get_table_item, get_table_items};
+
+/// Phones carry a provider (API source, labels cached in KV), a band list (static, multi-value)
+/// and a carrier from a module that isn't active
+fn module_catalog() -> (SqliteDb, TestStorage) {
+    let db = catalog();
+    let manifest = serde_json::json!({
+        "columnTypes": [
+            {"id": "provider", "baseType": "text", "source": {"type": "api", "endpoint": "/providers", "cache": "1h"}},
+            {"id": "bands", "baseType": "text", "multiValue": true,
+             "source": {"type": "static", "values": [{"value": "lte", "label": "4G LTE"}, "5g"]}}
+        ]
+    });
+    for (id, status, manifest) in [
+        ("@store/phone-numbers", "active", manifest.to_string()),
+        ("@store/carriers", "disabled", r#"{"columnTypes": [{"id": "carrier", "source": {"type": "static", "values": ["x"]}}]}"#.to_string()),
+    ] {
+        db.exec(
+            "INSERT INTO installedModules (id, name, version, displayName, author, source, status, manifest)
+             VALUES (?, ?, '1.0.0', ?, '{}', '{}', ?, ?)",
+            &[&id, &id, &id, &status, &manifest],
+        );
+    }
+    for (name, column_type) in [
+        ("provider", "@store/phone-numbers:provider"),
+        ("bands", "@store/phone-numbers:bands"),
+        ("carrier", "@store/carriers:carrier"),
+    ] {
+        db.exec(
+            "INSERT INTO tableColumns (id, tableId, name, type, position) VALUES (?, 'phones', ?, ?, 10)",
+            &[&format!("phones-{}", name), &name, &column_type],
+        );
+    }
+    db.exec(
+        "UPDATE tableData SET data = json_set(data, '$.provider', 'p_12', '$.bands', json('[\"lte\",\"5g\"]'), '$.carrier', 'x')
+         WHERE id = 'p1'",
+        &[],
+    );
+    db.exec("UPDATE tableData SET data = json_set(data, '$.provider', 'p_99', '$.bands', '[\"lte\"]') WHERE id = 'p2'", &[]);
+
+    let storage = storage(&db);
+    storage.cache.entries.borrow_mut().insert(
+        "module:api-cache:@store/phone-numbers:provider".to_string(),
+        r#"{"options": [{"value": "p_12", "label": "Twilio"}], "cachedAt": "2026-01-01T00:00:00.000Z"}"#.to_string(),
+    );
+    (db, storage)
+}
+
+fn labels() -> HashMap<String, String> {
+    query(&[("expand", "labels")])
+}
+
+#[test]
+fn module_codes_are_expanded_with_labels() {
+    let (_db, storage) = module_catalog();
+    let token = admin_token();
+
+    let item = block_on(get_table_item(&storage, &token, "phones", "p1", &labels())).unwrap();
+    assert_eq!(item["provider"], serde_json::json!({"value": "p_12", "label": "Twilio"}));
+    assert_eq!(
+        item["bands"],
+        serde_json::json!([{"value": "lte", "label": "4G LTE"}, {"value": "5g", "label": "5g"}])
+    );
+    assert_eq!(item["carrier"], serde_json::json!({"value": "x", "label": null}));
+    assert_eq!(item["name"], "Alpha");
+
+    // Unknown codes keep a null label; JSON array strings are lists too
+    let item = block_on(get_table_item(&storage, &token, "phones", "p2", &labels())).unwrap();
+    assert_eq!(item["provider"], serde_json::json!({"value": "p_99", "label": null}));
+    assert_eq!(item["bands"], serde_json::json!([{"value": "lte", "label": "4G LTE"}]));
+
+    let items = block_on(get_table_items(&storage, &token, "phones", &labels())).unwrap();
+    let alpha = items.items.iter().find(|item| item["id"] == "p1").unwrap();
+    assert_eq!(alpha["data"]["provider"]["label"], "Twilio");
+
+    // Cached pages stay raw and are expanded per request
+    let alpha = |query: &[(&str, &str)]| {
+        let mut params = vec![("where[name]", "Alpha")];
+        params.extend_from_slice(query);
+        let records = block_on(get_records(&storage, &token, &super::query(&params), &metrics())).unwrap();
+        records.records[0]["provider"].clone()
+    };
+    assert_eq!(alpha(&[("expand", "labels")])["label"], "Twilio");
+    assert_eq!(alpha(&[]), "p_12");
+    assert_eq!(alpha(&[("expand", "labels")])["value"], "p_12");
+}
+
+#[test]
+fn labels_respect_masks_and_unknown_expansions_are_rejected() {
+    let (db, storage) = module_catalog();
+    db.exec("UPDATE tableColumns SET visibility = 'masked' WHERE id = 'phones-provider'", &[]);
+
+    let item = block_on(get_table_item(&storage, &admin_token(), "phones", "p1", &labels())).unwrap();
+    assert_eq!(item["provider"], "p***");
+    assert_eq!(item["bands"][0]["label"], "4G LTE");
+
+    let result = block_on(get_table_item(&storage, &admin_token(), "phones", "p1", &query(&[("expand", "labels,owner")])));
+    assert!(matches!(result, Err(ApiError::InvalidParameter(_))));
+}
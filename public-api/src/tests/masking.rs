@@ -0,0 +1,144 @@
+use super::*;
+use crate::orders::{list_rentals, list_sales};
+use crate::{get_records, get_table_item, get_table_items, get_values, search_tables};
+
+fn add_column(db: &SqliteDb, table_id: &str, name: &str, column_type: &str, visibility: &str) {
+    db.exec(
+        "INSERT INTO tableColumns (id, tableId, name, type, position, visibility) VALUES (?, ?, ?, ?, 10, ?)",
+        &[&format!("{}-{}", table_id, name), &table_id, &name, &column_type, &visibility],
+    );
+}
+
+/// Phones carry a supplier email and phone (masked) and a cost price (hidden)
+fn supplier_catalog() -> SqliteDb {
+    let db = catalog();
+    add_column(&db, "phones", "email", "email", "masked");
+    add_column(&db, "phones", "phone", "phone", "masked");
+    add_column(&db, "phones", "cost", "number", "hidden");
+    db.exec(
+        "UPDATE tableData SET data = json_set(data, '$.email', 'jane@x.com', '$.phone', '+1 (555) 010-1234', '$.cost', 4)
+         WHERE id = 'p1'",
+        &[],
+    );
+    db.exec("UPDATE tableData SET data = json_set(data, '$.email', 'sales@y.org', '$.cost', 6) WHERE id = 'p3'", &[]);
+    db
+}
+
+fn token_with_rules(rules: serde_json::Value) -> TokenInfo {
+    TokenInfo { column_rules: Some(rules.to_string()), ..admin_token() }
+}
+
+#[test]
+fn hidden_columns_are_dropped_and_masked_ones_masked() {
+    let db = supplier_catalog();
+    let storage = storage(&db);
+    let token = admin_token();
+
+    let item = block_on(get_table_item(&storage, &token, "phones", "p1", &query(&[]))).unwrap();
+    assert_eq!((&item["email"], &item["phone"]), (&"j***@x.com".into(), &"***1234".into()));
+    assert!(item.get("cost").is_none());
+    assert_eq!(item["name"], "Alpha");
+
+    let items = block_on(get_table_items(&storage, &token, "phones", &query(&[]))).unwrap();
+    let items = serde_json::to_value(items).unwrap();
+    let gamma = items["items"].as_array().unwrap().iter().find(|item| item["id"] == "p3").unwrap();
+    assert_eq!(gamma["data"]["email"], "s***@y.org");
+    assert!(gamma["data"].get("cost").is_none());
+
+    // Cached pages are masked on the way out too
+    for _ in 0..2 {
+        let records = block_on(get_records(&storage, &token, &query(&[("where[name]", "Alpha")]), &metrics())).unwrap();
+        let record = serde_json::to_value(records).unwrap()["records"][0].clone();
+        assert_eq!(record["email"], "j***@x.com");
+        assert!(record.get("cost").is_none());
+    }
+
+    let values = |column: &str| {
+        let values = block_on(get_values(&storage, &token, column, &query(&[]))).unwrap();
+        serde_json::to_value(values).unwrap()["values"].clone()
+    };
+    let mut emails: Vec<String> = serde_json::from_value(values("email")).unwrap();
+    emails.sort();
+    assert_eq!(emails, ["j***@x.com", "s***@y.org"]);
+    assert_eq!(values("cost"), serde_json::json!([]));
+
+    let search = block_on(search_tables(&storage, &token, &query(&[("columns", "cost")]))).unwrap();
+    assert_eq!(serde_json::to_value(search).unwrap()["count"], 0);
+    let search = block_on(search_tables(&storage, &token, &query(&[("columns", "email")]))).unwrap();
+    assert_eq!(serde_json::to_value(search).unwrap()["count"], 1);
+}
+
+#[test]
+fn filters_on_restricted_columns_are_refused() {
+    let db = supplier_catalog();
+    let storage = storage(&db);
+    let records = |token: &TokenInfo, filter: (&str, &str)| block_on(get_records(&storage, token, &query(&[filter]), &metrics()));
+
+    assert!(matches!(records(&admin_token(), ("where[cost]", "4")), Err(ApiError::InvalidFilter(_))));
+    assert!(matches!(records(&admin_token(), ("where[Email]", "jane@x.com")), Err(ApiError::InvalidFilter(_))));
+    let values = block_on(get_values(&storage, &admin_token(), "name", &query(&[("where[phone]", "1234")])));
+    assert!(matches!(values, Err(ApiError::InvalidFilter(_))));
+
+    // Tokens that may only read bikes aren't affected by the phones columns
+    let bikes = TokenInfo { id: "bikes-token".to_string(), ..scoped_token(&["bikes"]) };
+    assert!(records(&bikes, ("where[cost]", "4")).is_ok());
+}
+
+#[test]
+fn token_rules_override_the_table() {
+    let db = supplier_catalog();
+    let storage = storage(&db);
+    let buyer = token_with_rules(serde_json::json!({"phones": {"cost": "visible", "email": "visible", "color": "hidden"}}));
+    let teaser = token_with_rules(serde_json::json!({"phones": {"name": "masked"}}));
+
+    let item = block_on(get_table_item(&storage, &buyer, "phones", "p1", &query(&[]))).unwrap();
+    assert_eq!((&item["cost"], &item["email"], &item["phone"]), (&4.into(), &"jane@x.com".into(), &"***1234".into()));
+    assert!(item.get("color").is_none());
+
+    let filtered = block_on(get_records(&storage, &buyer, &query(&[("where[cost]", "6")]), &metrics())).unwrap();
+    assert_eq!(serde_json::to_value(filtered).unwrap()["records"][0]["id"], "p3");
+    let colored = block_on(get_records(&storage, &buyer, &query(&[("where[color]", "red")]), &metrics()));
+    assert!(matches!(colored, Err(ApiError::InvalidFilter(_))));
+
+    let item = block_on(get_table_item(&storage, &teaser, "phones", "p1", &query(&[]))).unwrap();
+    assert_eq!(item["name"], "A***");
+    assert!(item.get("cost").is_none());
+}
+
+#[test]
+fn order_snapshots_are_masked_like_items() {
+    let db = supplier_catalog();
+    add_column(&db, "bikes", "email", "email", "hidden");
+    db.exec(
+        r#"INSERT INTO sales (id, saleNumber, tableId, tableName, itemId, itemSnapshot, customerId, quantitySold, unitPrice,
+                              totalAmount, saleStatus)
+           VALUES ('s1', 'SAL-s1', 'phones', 'Phones', 'p1',
+                   '{"name":"Alpha","email":"jane@x.com","phone":"+1 (555) 010-1234","cost":4}', 'cust-1', 1, 9, 9, 'completed')"#,
+        &[],
+    );
+    db.exec(
+        r#"INSERT INTO rentals (id, rentalNumber, tableId, tableName, itemId, itemSnapshot, customerId, unitPrice, rentedAt)
+           VALUES ('r1', 'RNT-r1', 'bikes', 'Bikes', 'b1', '{"name":"Cruiser","email":"bob@z.net"}', 'cust-1', 10,
+                   '2025-01-01T10:00:00.000Z')"#,
+        &[],
+    );
+    let storage = storage(&db);
+    let sales = |token: &TokenInfo| {
+        let sales = block_on(list_sales(&storage.store, &storage.cache, token, &query(&[]))).unwrap();
+        serde_json::to_value(sales).unwrap()["sales"][0]["itemSnapshot"].clone()
+    };
+
+    let snapshot = sales(&admin_token());
+    assert_eq!((&snapshot["email"], &snapshot["phone"]), (&"j***@x.com".into(), &"***1234".into()));
+    assert!(snapshot.get("cost").is_none());
+    assert_eq!(snapshot["name"], "Alpha");
+
+    // Token rules apply as they do to items
+    let snapshot = sales(&token_with_rules(serde_json::json!({"phones": {"cost": "visible", "name": "hidden"}})));
+    assert_eq!(snapshot["cost"], 4);
+    assert!(snapshot.get("name").is_none());
+
+    let rentals = block_on(list_rentals(&storage.store, &storage.cache, &admin_token(), &query(&[]))).unwrap();
+    let snapshot = serde_json::to_value(rentals).unwrap()["rentals"][0]["itemSnapshot"].clone();
+    assert_eq!(snapshot, serde_json::json!({"name": "Cruiser"}));
+}
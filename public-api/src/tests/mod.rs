@@ -0,0 +1,182 @@
+//! Native tests: the real handlers over SQLite (with the Prisma migrations applied)
+//! and an in-memory cache, run with plain `cargo test`
+
+mod auth;
+mod cache;
+mod catalog;
+mod cors;
+mod currency;
+mod labels;
+mod masking;
+mod orders;
+mod pricing;
+mod scheduled;
+mod server;
+mod shares;
+mod slugs;
+mod tables;
+mod webhooks;
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use crate::sqlite::SqliteDb;
+use crate::error::ApiError;
+use crate::store::{Cache, SqlStore};
+use crate::telemetry::RequestMetrics;
+use crate::{CacheConfig, Storage, TokenInfo};
+
+// ============================================================================
+// MIGRATED SQLITE AND IN-MEMORY CACHE
+// ============================================================================
+
+/// In-memory SQLite shaped by the same migrations D1 runs, with fixture helpers
+pub fn migrated_db() -> SqliteDb {
+    let db = SqliteDb::open_in_memory().expect("open sqlite");
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../api/prisma/migrations");
+    let mut migrations: Vec<_> = std::fs::read_dir(dir)
+        .expect("read migrations")
+        .map(|entry| entry.expect("migration entry").path().join("migration.sql"))
+        .filter(|path| path.exists())
+        .collect();
+    migrations.sort();
+    for path in migrations {
+        let sql = std::fs::read_to_string(&path).expect("read migration");
+        db.connection().execute_batch(&sql).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    }
+    db
+}
+
+pub trait Fixtures {
+    fn exec(&self, sql: &str, params: &[&dyn rusqlite::ToSql]);
+    fn add_table(&self, id: &str, name: &str, visibility: &str, table_type: &str, columns: &[&str]);
+    fn add_row(&self, table_id: &str, row_id: &str, data: serde_json::Value, minute: u32);
+    fn add_token(&self, id: &str, token: &str, table_access: Option<&[&str]>, expires_at: Option<&dyn rusqlite::ToSql>);
+}
+
+impl Fixtures for SqliteDb {
+    fn exec(&self, sql: &str, params: &[&dyn rusqlite::ToSql]) {
+        self.connection().execute(sql, params).unwrap_or_else(|e| panic!("{}: {}", sql, e));
+    }
+
+    fn add_table(&self, id: &str, name: &str, visibility: &str, table_type: &str, columns: &[&str]) {
+        self.exec(
+            "INSERT INTO userTables (id, name, createdBy, visibility, tableType) VALUES (?, ?, 'user-1', ?, ?)",
+            &[&id, &name, &visibility, &table_type],
+        );
+        for (position, column) in columns.iter().enumerate() {
+            let column_id = format!("{}-{}", id, column);
+            self.exec(
+                "INSERT INTO tableColumns (id, tableId, name, type, position) VALUES (?, ?, ?, 'text', ?)",
+                &[&column_id, &id, column, &(position as i64)],
+            );
+        }
+    }
+
+    /// Rows get increasing timestamps so "newest first" ordering is deterministic
+    fn add_row(&self, table_id: &str, row_id: &str, data: serde_json::Value, minute: u32) {
+        let at = format!("2025-01-01 00:{:02}:00", minute);
+        self.exec(
+            "INSERT INTO tableData (id, tableId, data, createdAt, updatedAt) VALUES (?, ?, ?, ?, ?)",
+            &[&row_id, &table_id, &data.to_string(), &at, &at],
+        );
+    }
+
+    fn add_token(&self, id: &str, token: &str, table_access: Option<&[&str]>, expires_at: Option<&dyn rusqlite::ToSql>) {
+        let access = table_access.map(|ids| serde_json::json!(ids).to_string());
+        self.exec(
+            "INSERT INTO tokens (id, token, name, tableAccess, expiresAt, updatedAt) VALUES (?, ?, ?, ?, ?, CURRENT_TIMESTAMP)",
+            &[&id, &token, &id, &access, &expires_at.unwrap_or(&rusqlite::types::Null)],
+        );
+    }
+}
+
+/// Cache backed by a map; TTLs are recorded but never enforced
+#[derive(Default)]
+pub struct MemoryCache {
+    pub entries: RefCell<HashMap<String, String>>,
+    pub ttls: RefCell<HashMap<String, Option<u64>>>,
+}
+
+impl Cache for MemoryCache {
+    async fn get(&self, key: &str) -> Option<String> {
+        self.entries.borrow().get(key).cloned()
+    }
+
+    async fn put(&self, key: &str, value: String, ttl: Option<u64>) {
+        self.entries.borrow_mut().insert(key.to_string(), value);
+        self.ttls.borrow_mut().insert(key.to_string(), ttl);
+    }
+
+    async fn delete(&self, key: &str) {
+        self.entries.borrow_mut().remove(key);
+        self.ttls.borrow_mut().remove(key);
+    }
+}
+
+// ============================================================================
+// FIXTURES
+// ============================================================================
+
+pub type TestStorage = Storage<SqlStore<SqliteDb>, MemoryCache>;
+
+pub fn storage(db: &SqliteDb) -> TestStorage {
+    Storage::new(SqlStore::new(db.clone()), MemoryCache::default(), CacheConfig::default())
+}
+
+pub fn metrics() -> RequestMetrics {
+    RequestMetrics::new("test-request".to_string())
+}
+
+pub fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+    futures_executor::block_on(fut)
+}
+
+/// Token that sees every public table
+pub fn admin_token() -> TokenInfo {
+    TokenInfo {
+        id: "admin-token".to_string(),
+        is_admin: true,
+        table_access: None,
+        created_by: None,
+        user_id: None,
+        rate_limits: None,
+        allowed_domains: None,
+        expires_at: None,
+        customer_id: None,
+        price_rules: None,
+        column_rules: None,
+    }
+}
+
+/// Token limited to the given tables
+pub fn scoped_token(table_ids: &[&str]) -> TokenInfo {
+    TokenInfo {
+        id: "scoped-token".to_string(),
+        is_admin: false,
+        table_access: Some(serde_json::json!(table_ids).to_string()),
+        ..admin_token()
+    }
+}
+
+pub fn query(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+/// A small catalog: two public sale/rent tables, one private sale table, one public default table
+pub fn catalog() -> SqliteDb {
+    let db = migrated_db();
+    db.add_table("phones", "Phones", "public", "sale", &["name", "color", "qty"]);
+    db.add_table("bikes", "Bikes", "public", "rent", &["name", "color", "used"]);
+    db.add_table("secret", "Secret", "private", "sale", &["name", "color"]);
+    db.add_table("notes", "Notes", "public", "default", &["name"]);
+
+    db.add_row("phones", "p1", serde_json::json!({"name": "Alpha", "color": "Red", "qty": 3}), 1);
+    db.add_row("phones", "p2", serde_json::json!({"name": "Beta", "color": "blue", "qty": 0}), 2);
+    db.add_row("phones", "p3", serde_json::json!({"name": "Gamma", "color": "red", "qty": 7}), 3);
+    db.add_row("bikes", "b1", serde_json::json!({"name": "Cruiser", "color": "RED", "used": false}), 4);
+    db.add_row("bikes", "b2", serde_json::json!({"name": "Racer", "color": "green", "used": true}), 5);
+    db.add_row("secret", "s1", serde_json::json!({"name": "Hidden", "color": "red"}), 6);
+    db.add_row("notes", "n1", serde_json::json!({"name": "Memo"}), 7);
+    db
+}
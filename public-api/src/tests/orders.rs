@@ -0,0 +1,155 @@
+use super::*;
+use crate::authenticate;
+use crate::orders::{bind_write, list_rentals, list_sales};
+use crate::routes::RouteKind;
+
+fn add_sale(db: &SqliteDb, id: &str, table_id: &str, customer_id: &str, status: &str, created_at: &str) {
+    db.exec(
+        "INSERT INTO sales (id, saleNumber, tableId, tableName, itemId, itemSnapshot, customerId, quantitySold, unitPrice,
+                            totalAmount, saleStatus, createdAt, updatedAt)
+         VALUES (?, ?, ?, ?, 'p1', '{\"name\":\"Alpha\",\"qty\":3}', ?, 2, 9.5, 19, ?, ?, ?)",
+        &[&id, &format!("SAL-{}", id), &table_id, &table_id, &customer_id, &status, &created_at, &created_at],
+    );
+}
+
+fn add_rental(db: &SqliteDb, id: &str, customer_id: &str, status: &str, rented_at: &dyn rusqlite::ToSql) {
+    db.exec(
+        "INSERT INTO rentals (id, rentalNumber, tableId, tableName, itemId, itemSnapshot, customerId, unitPrice, rentalStatus, rentedAt)
+         VALUES (?, ?, 'bikes', 'Bikes', 'b1', '{\"name\":\"Cruiser\"}', ?, 10, ?, ?)",
+        &[&id, &format!("RNT-{}", id), &customer_id, &status, rented_at],
+    );
+}
+
+fn ids(values: Vec<serde_json::Value>) -> Vec<String> {
+    values.iter().map(|v| v["id"].as_str().unwrap().to_string()).collect()
+}
+
+fn sales_of(store: &SqlStore<SqliteDb>, token: &TokenInfo, params: &[(&str, &str)]) -> serde_json::Value {
+    serde_json::to_value(block_on(list_sales(store, &MemoryCache::default(), token, &query(params))).unwrap()).unwrap()
+}
+
+#[test]
+fn sales_are_limited_to_accessible_tables() {
+    let db = catalog();
+    add_sale(&db, "s1", "phones", "cust-1", "completed", "2025-01-01T10:00:00.000Z");
+    add_sale(&db, "s2", "phones", "cust-2", "refunded", "2025-02-01T10:00:00.000Z");
+    add_sale(&db, "s3", "secret", "cust-1", "completed", "2025-03-01T10:00:00.000Z");
+    let store = SqlStore::new(db.clone());
+
+    let all = sales_of(&store, &admin_token(), &[]);
+    assert_eq!(all["total"], 2);
+    assert_eq!(ids(all["sales"].as_array().unwrap().clone()), ["s2", "s1"]);
+
+    let customer = sales_of(&store, &admin_token(), &[("customerId", "cust-1")]);
+    assert_eq!(ids(customer["sales"].as_array().unwrap().clone()), ["s1"]);
+    assert_eq!(customer["sales"][0]["itemSnapshot"]["name"], "Alpha");
+    assert_eq!(customer["sales"][0]["totalAmount"], 19.0);
+    assert_eq!(customer["sales"][0]["createdAt"], "2025-01-01T10:00:00.000Z");
+
+    let scoped = sales_of(&store, &scoped_token(&["secret"]), &[("customerId", "cust-1")]);
+    assert_eq!(ids(scoped["sales"].as_array().unwrap().clone()), ["s3"]);
+
+    let forbidden = block_on(list_sales(&store, &MemoryCache::default(), &admin_token(), &query(&[("tableId", "secret")])));
+    assert!(matches!(forbidden, Err(ApiError::TableForbidden)));
+    let none = sales_of(&store, &scoped_token(&[]), &[]);
+    assert_eq!(none["total"], 0);
+}
+
+#[test]
+fn sales_filter_by_status_and_date_range() {
+    let db = catalog();
+    add_sale(&db, "s1", "phones", "cust-1", "completed", "2025-01-01T10:00:00.000Z");
+    add_sale(&db, "s2", "phones", "cust-1", "refunded", "2025-02-01T10:00:00.000Z");
+    add_sale(&db, "s3", "phones", "cust-1", "completed", "2025-03-01T10:00:00.000Z");
+    let store = SqlStore::new(db.clone());
+
+    let completed = sales_of(&store, &admin_token(), &[("status", "completed"), ("limit", "1")]);
+    assert_eq!(ids(completed["sales"].as_array().unwrap().clone()), ["s3"]);
+    assert_eq!(completed["pagination"]["hasMore"], true);
+
+    let february = sales_of(&store, &admin_token(), &[("from", "2025-01-15"), ("to", "2025-02-15")]);
+    assert_eq!(ids(february["sales"].as_array().unwrap().clone()), ["s2"]);
+
+    let invalid = block_on(list_sales(&store, &MemoryCache::default(), &admin_token(), &query(&[("status", "active")])));
+    assert!(matches!(invalid, Err(ApiError::InvalidParameter(_))));
+    let invalid = block_on(list_sales(&store, &MemoryCache::default(), &admin_token(), &query(&[("from", "last week")])));
+    assert!(matches!(invalid, Err(ApiError::InvalidParameter(_))));
+}
+
+#[test]
+fn rentals_filter_by_customer_and_status() {
+    let db = catalog();
+    add_rental(&db, "r1", "cust-1", "active", &"2025-01-01T10:00:00.000Z");
+    // Epoch milliseconds, as Prisma may store dates
+    add_rental(&db, "r2", "cust-1", "released", &1_738_404_000_000i64);
+    add_rental(&db, "r3", "cust-2", "active", &"2025-03-01T10:00:00.000Z");
+    let store = SqlStore::new(db.clone());
+
+    let active = block_on(list_rentals(&store, &MemoryCache::default(), &admin_token(), &query(&[("customerId", "cust-1"), ("status", "active")]))).unwrap();
+    let active = serde_json::to_value(active).unwrap();
+    assert_eq!(ids(active["rentals"].as_array().unwrap().clone()), ["r1"]);
+    assert_eq!(active["rentals"][0]["itemSnapshot"]["name"], "Cruiser");
+    assert_eq!(active["rentals"][0]["releasedAt"], serde_json::Value::Null);
+
+    let history = block_on(list_rentals(&store, &MemoryCache::default(), &admin_token(), &query(&[("customerId", "cust-1")]))).unwrap();
+    let history = serde_json::to_value(history).unwrap();
+    assert_eq!(ids(history["rentals"].as_array().unwrap().clone()), ["r2", "r1"]);
+    assert_eq!(history["rentals"][0]["rentedAt"], "2025-02-01T10:00:00.000Z");
+
+    let phones_only = block_on(list_rentals(&store, &MemoryCache::default(), &scoped_token(&["phones"]), &query(&[]))).unwrap();
+    assert_eq!(serde_json::to_value(phones_only).unwrap()["count"], 0);
+}
+
+#[test]
+fn customer_bound_tokens_only_read_their_customer() {
+    let db = catalog();
+    add_sale(&db, "s1", "phones", "cust-1", "completed", "2025-01-01T10:00:00.000Z");
+    add_sale(&db, "s2", "phones", "cust-2", "completed", "2025-02-01T10:00:00.000Z");
+    add_rental(&db, "r1", "cust-2", "active", &"2025-01-01T10:00:00.000Z");
+    db.add_token("shop-token", "shop-secret", Some(&["phones", "bikes"]), None);
+    db.exec("UPDATE tokens SET customerId = 'cust-1' WHERE id = 'shop-token'", &[]);
+    let storage = storage(&db);
+    let token = block_on(authenticate(&storage, "shop-secret", &metrics())).unwrap();
+    assert_eq!(token.customer_id.as_deref(), Some("cust-1"));
+
+    let own = sales_of(&storage.store, &token, &[]);
+    assert_eq!(ids(own["sales"].as_array().unwrap().clone()), ["s1"]);
+    let named = sales_of(&storage.store, &token, &[("customerId", "cust-1")]);
+    assert_eq!(named["total"], 1);
+    let rentals = block_on(list_rentals(&storage.store, &storage.cache, &token, &query(&[]))).unwrap();
+    assert_eq!(serde_json::to_value(rentals).unwrap()["count"], 0);
+
+    let other = block_on(list_sales(&storage.store, &storage.cache, &token, &query(&[("customerId", "cust-2")])));
+    assert!(matches!(other, Err(ApiError::CustomerMismatch)));
+}
+
+#[test]
+fn customer_bound_writes_are_filled_in_or_refused() {
+    let db = catalog();
+    add_rental(&db, "r1", "cust-1", "active", &"2025-01-01T10:00:00.000Z");
+    add_rental(&db, "r2", "cust-2", "released", &"2024-01-01T10:00:00.000Z");
+    let store = SqlStore::new(db.clone());
+    let token = TokenInfo { customer_id: Some("cust-1".to_string()), ..admin_token() };
+    let bind = |kind, body: &str| block_on(bind_write(&store, &token, kind, body));
+
+    // Unbound tokens forward bodies untouched, even malformed ones
+    assert!(matches!(block_on(bind_write(&store, &admin_token(), RouteKind::Buy, "nope")), Ok(None)));
+
+    let filled = bind(RouteKind::Buy, r#"{"tableId": "phones", "itemId": "p1"}"#).unwrap().unwrap();
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&filled).unwrap()["customerId"], "cust-1");
+    assert!(matches!(bind(RouteKind::Rent, r#"{"itemId": "b1", "customerId": "cust-1"}"#), Ok(None)));
+    assert!(matches!(bind(RouteKind::Rent, r#"{"itemId": "b1", "customerId": "cust-2"}"#), Err(ApiError::CustomerMismatch)));
+    assert!(matches!(bind(RouteKind::Buy, "[]"), Err(ApiError::InvalidParameter(_))));
+
+    assert!(matches!(bind(RouteKind::Release, r#"{"rentalId": "r1"}"#), Ok(None)));
+    assert!(matches!(bind(RouteKind::Release, r#"{"tableId": "bikes", "itemId": "b1"}"#), Ok(None)));
+    assert!(matches!(bind(RouteKind::Release, r#"{"rentalId": "r2"}"#), Err(ApiError::CustomerMismatch)));
+    // Unknown rentals are left for the order service to reject
+    assert!(matches!(bind(RouteKind::Release, r#"{"rentalId": "r9"}"#), Ok(None)));
+
+    db.exec("UPDATE rentals SET customerId = 'cust-2' WHERE id = 'r1'", &[]);
+    assert!(matches!(
+        bind(RouteKind::Release, r#"{"tableId": "bikes", "itemId": "b1"}"#),
+        Err(ApiError::CustomerMismatch)
+    ));
+}
@@ -0,0 +1,116 @@
+use super::*;
+use crate::currency::CACHE_KEY_CURRENCY_RATES;
+use crate::pricing::{PriceRule, Rounding};
+use crate::routes::RouteKind;
+use crate::{get_records, get_table_item, get_table_items, get_values, prepare_write};
+
+/// Phones at 10 and 12.30, bikes at 20 with a 4.50 fee
+fn priced_catalog() -> SqliteDb {
+    let db = catalog();
+    for (table_id, column) in [("phones", "price"), ("bikes", "price"), ("bikes", "fee")] {
+        db.exec(
+            "INSERT INTO tableColumns (id, tableId, name, type, position) VALUES (?, ?, ?, 'number', 10)",
+            &[&format!("{}-{}", table_id, column), &table_id, &column],
+        );
+    }
+    db.exec("UPDATE tableData SET data = json_set(data, '$.price', 10) WHERE id = 'p1'", &[]);
+    db.exec("UPDATE tableData SET data = json_set(data, '$.price', '12.30') WHERE id = 'p2'", &[]);
+    db.exec("UPDATE tableData SET data = json_set(data, '$.price', 20, '$.fee', 4.5) WHERE id = 'b1'", &[]);
+    db
+}
+
+/// 10% on everything, bikes instead get a fixed 5 rounded up to .99
+fn reseller_token() -> TokenInfo {
+    TokenInfo {
+        price_rules: Some(
+            r#"{"default": {"percent": 10}, "tables": {"bikes": {"fixed": 5, "rounding": "ninety_nine"}}}"#.to_string(),
+        ),
+        ..admin_token()
+    }
+}
+
+#[test]
+fn rules_mark_up_and_round() {
+    let rule = |percent, fixed, rounding| PriceRule { percent, fixed, rounding };
+
+    assert_eq!(rule(10.0, 0.0, Rounding::Cents).apply(12.34), 13.57);
+    assert_eq!(rule(0.0, 2.5, Rounding::Cents).apply(10.0), 12.5);
+    assert_eq!(rule(10.0, 1.0, Rounding::Whole).apply(10.0), 12.0);
+    assert_eq!(rule(10.0, 0.0, Rounding::Whole).apply(10.0), 11.0);
+    assert_eq!(rule(0.0, 0.3, Rounding::NinetyNine).apply(12.0), 12.99);
+    assert_eq!(rule(0.0, 0.99, Rounding::NinetyNine).apply(12.0), 12.99);
+    assert_eq!(rule(0.0, 1.0, Rounding::NinetyNine).apply(12.0), 13.99);
+    assert_eq!(rule(50.0, 0.0, Rounding::None).apply(0.01), 0.015);
+}
+
+#[test]
+fn reads_show_marked_up_prices() {
+    let db = priced_catalog();
+    let storage = storage(&db);
+    let token = reseller_token();
+
+    let item = block_on(get_table_item(&storage, &token, "phones", "p2", &query(&[]))).unwrap();
+    assert_eq!(item["price"], 13.53);
+    let item = block_on(get_table_item(&storage, &token, "bikes", "b1", &query(&[]))).unwrap();
+    assert_eq!((&item["price"], &item["fee"]), (&25.99.into(), &9.99.into()));
+
+    let items = block_on(get_table_items(&storage, &token, "phones", &query(&[]))).unwrap();
+    let items = serde_json::to_value(items).unwrap();
+    let alpha = items["items"].as_array().unwrap().iter().find(|item| item["id"] == "p1").unwrap();
+    assert_eq!(alpha["data"]["price"], 11.0);
+
+    // The shared cache entry keeps catalog prices for everyone else
+    let records = |token: &TokenInfo| {
+        let records = block_on(get_records(&storage, token, &query(&[("where[name]", "Alpha")]), &metrics())).unwrap();
+        serde_json::to_value(records).unwrap()["records"][0]["price"].clone()
+    };
+    assert_eq!(records(&token), 11.0);
+    assert_eq!(records(&admin_token()), 10);
+    assert_eq!(records(&token), 11.0);
+
+    let values = block_on(get_values(&storage, &token, "price", &query(&[]))).unwrap();
+    let mut values: Vec<f64> = serde_json::to_value(values).unwrap()["values"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v.as_f64().unwrap())
+        .collect();
+    values.sort_by(f64::total_cmp);
+    assert_eq!(values, [11.0, 13.53, 25.99]);
+
+    // Markup comes first, in the base currency
+    storage.cache.entries.borrow_mut().insert(
+        CACHE_KEY_CURRENCY_RATES.to_string(),
+        r#"{"base":"USD","rates":{"EUR":0.5},"updatedAt":"2026-01-01T00:00:00.000Z"}"#.to_string(),
+    );
+    let item = block_on(get_table_item(&storage, &token, "phones", "p1", &query(&[("currency", "EUR")]))).unwrap();
+    assert_eq!(item["price"], 5.5);
+}
+
+#[test]
+fn orders_carry_the_marked_up_unit_price() {
+    let db = priced_catalog();
+    let store = SqlStore::new(db.clone());
+    let cache = MemoryCache::default();
+    let token = TokenInfo { customer_id: Some("cust-1".to_string()), ..reseller_token() };
+    let write = |token: &TokenInfo, kind, body: &str| block_on(prepare_write(&store, &cache, token, kind, body));
+    let sent = |body: Option<String>| serde_json::from_str::<serde_json::Value>(&body.unwrap()).unwrap();
+
+    // Whatever the client claims, and with the bound customer filled in too
+    let buy = sent(write(&token, RouteKind::Buy, r#"{"tableId": "phones", "itemId": "p1", "unitPrice": 1}"#).unwrap());
+    assert_eq!((&buy["unitPrice"], &buy["customerId"]), (&11.0.into(), &"cust-1".into()));
+    let rent = sent(write(&token, RouteKind::Rent, r#"{"tableId": "bikes", "itemId": "b1", "customerId": "cust-1"}"#).unwrap());
+    assert_eq!(rent["unitPrice"], 25.99);
+
+    // Tables without a rule are charged the catalog price
+    let token = TokenInfo { price_rules: Some(r#"{"tables": {"bikes": {"percent": 5}}}"#.to_string()), ..admin_token() };
+    let buy = sent(write(&token, RouteKind::Buy, r#"{"tableId": "phones", "itemId": "p1"}"#).unwrap());
+    assert_eq!(buy["unitPrice"], 10.0);
+
+    // Unknown items are rejected before anything is forwarded
+    assert!(matches!(write(&token, RouteKind::Buy, r#"{"tableId": "phones", "itemId": "p9"}"#), Err(ApiError::ItemNotFound)));
+    assert!(matches!(write(&token, RouteKind::Rent, r#"{"tableId": "bikes", "itemId": "p1"}"#), Err(ApiError::ItemNotFound)));
+
+    assert!(matches!(write(&token, RouteKind::Release, r#"{"rentalId": "r1"}"#), Ok(None)));
+    assert!(matches!(write(&admin_token(), RouteKind::Buy, r#"{"tableId": "phones", "itemId": "p1"}"#), Ok(None)));
+}
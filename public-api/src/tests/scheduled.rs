@@ -0,0 +1,159 @@
+use super::*;
+use crate::scheduled::{self, QueryHit};
+use crate::{current_timestamp, get_records, CACHE_KEY_PUBLIC_TABLES, CACHE_KEY_DATA_GENERATION};
+
+fn add_rental(db: &SqliteDb, id: &str, item_id: &str, rented_at: &dyn rusqlite::ToSql) {
+    db.exec(
+        "INSERT INTO rentals (id, rentalNumber, tableId, tableName, itemId, itemSnapshot, customerId, unitPrice, rentedAt)
+         VALUES (?, ?, 'bikes', 'Bikes', ?, '{}', 'customer-1', 10, ?)",
+        &[&id, &format!("RNT-{}", id), &item_id, rented_at],
+    );
+}
+
+fn set_rule(db: &SqliteDb, action: &str, periods: i64, grace_minutes: i64) {
+    db.exec(
+        "INSERT INTO rentalExpiryRules (tableId, action, periods, graceMinutes) VALUES ('bikes', ?, ?, ?)
+         ON CONFLICT (tableId) DO UPDATE SET action = excluded.action, periods = excluded.periods,
+            graceMinutes = excluded.graceMinutes",
+        &[&action, &periods, &grace_minutes],
+    );
+}
+
+fn scalar(db: &SqliteDb, sql: &str) -> rusqlite::types::Value {
+    db.connection().query_row(sql, [], |row| row.get(0)).unwrap_or_else(|e| panic!("{}: {}", sql, e))
+}
+
+/// Daily bike rentals: r1 (ISO rentedAt, as Prisma writes it) and r2 (epoch milliseconds) are
+/// two days old, r3 isn't due for a long time
+fn rented_bikes() -> SqliteDb {
+    let db = catalog();
+    db.exec("UPDATE userTables SET rentalPeriod = 'day' WHERE id = 'bikes'", &[]);
+    db.add_row("bikes", "b3", serde_json::json!({"name": "Tandem", "used": false, "available": false}), 8);
+    db.add_row("bikes", "b4", serde_json::json!({"name": "Fixie", "used": "false", "available": "false"}), 9);
+    let two_days_ago = current_timestamp() as i64 - 2 * 86_400;
+    add_rental(&db, "r1", "b3", &rusqlite::types::Null);
+    db.exec(
+        "UPDATE rentals SET rentedAt = strftime('%Y-%m-%dT%H:%M:%S.000Z', ?, 'unixepoch') WHERE id = 'r1'",
+        &[&two_days_ago],
+    );
+    add_rental(&db, "r2", "b4", &(two_days_ago * 1000));
+    add_rental(&db, "r3", "b1", &"2999-01-01T00:00:00.000Z");
+    db
+}
+
+#[test]
+fn overdue_rentals_are_flagged_once() {
+    let db = rented_bikes();
+    let store = SqlStore::new(db.clone());
+    let cache = MemoryCache::default();
+    set_rule(&db, "flag", 1, 0);
+    let now = current_timestamp() as i64;
+
+    let first = block_on(scheduled::expire_rentals(&store, &cache, now)).unwrap();
+    let second = block_on(scheduled::expire_rentals(&store, &cache, now)).unwrap();
+
+    assert_eq!((first.flagged, first.released), (2, 0));
+    assert_eq!(second.flagged, 0);
+    assert_eq!(
+        scalar(&db, "SELECT COUNT(*) FROM rentals WHERE overdueAt IS NOT NULL AND rentalStatus = 'active'"),
+        2.into()
+    );
+    assert_eq!(
+        scalar(&db, "SELECT COUNT(*) FROM inventoryTransactions WHERE transactionType = 'overdue' AND createdBy = 'system:rental-expiry'"),
+        2.into()
+    );
+    // Flagging changes no item data
+    assert!(cache.entries.borrow().is_empty());
+}
+
+#[test]
+fn grace_and_periods_push_the_deadline_back() {
+    let db = rented_bikes();
+    let store = SqlStore::new(db.clone());
+    let now = current_timestamp() as i64;
+
+    set_rule(&db, "flag", 3, 0);
+    assert_eq!(block_on(scheduled::expire_rentals(&store, &MemoryCache::default(), now)).unwrap().flagged, 0);
+
+    set_rule(&db, "flag", 2, 60);
+    assert_eq!(block_on(scheduled::expire_rentals(&store, &MemoryCache::default(), now)).unwrap().flagged, 0);
+    assert_eq!(block_on(scheduled::expire_rentals(&store, &MemoryCache::default(), now + 3600)).unwrap().flagged, 2);
+
+    db.exec("UPDATE rentalExpiryRules SET enabled = 0", &[]);
+    db.exec("UPDATE rentals SET overdueAt = NULL", &[]);
+    assert_eq!(block_on(scheduled::expire_rentals(&store, &MemoryCache::default(), now + 3600)).unwrap().flagged, 0);
+}
+
+#[test]
+fn release_rules_release_flagged_rentals_and_mark_items_used() {
+    let db = rented_bikes();
+    let store = SqlStore::new(db.clone());
+    let cache = MemoryCache::default();
+    let now = current_timestamp() as i64;
+    set_rule(&db, "flag", 1, 0);
+    block_on(scheduled::expire_rentals(&store, &cache, now)).unwrap();
+    db.exec("UPDATE rentals SET notes = 'Handed over at the shop' WHERE id = 'r2'", &[]);
+
+    set_rule(&db, "release", 1, 0);
+    let stats = block_on(scheduled::expire_rentals(&store, &cache, now)).unwrap();
+
+    assert_eq!(stats.released, 2);
+    assert_eq!(scalar(&db, "SELECT rentalStatus FROM rentals WHERE id = 'r3'"), "active".to_string().into());
+    assert_eq!(
+        scalar(&db, "SELECT notes FROM rentals WHERE id = 'r1'"),
+        "Released automatically: rental period expired".to_string().into()
+    );
+    assert_eq!(scalar(&db, "SELECT notes FROM rentals WHERE id = 'r2'"), "Handed over at the shop".to_string().into());
+
+    let item: String = db
+        .connection()
+        .query_row("SELECT data FROM tableData WHERE id = 'b4'", [], |row| row.get(0))
+        .unwrap();
+    let item: serde_json::Value = serde_json::from_str(&item).unwrap();
+    assert_eq!((&item["used"], &item["available"]), (&true.into(), &false.into()));
+    assert!(cache.entries.borrow().contains_key(CACHE_KEY_DATA_GENERATION));
+
+    // The release transactions carry the rental ID, so the webhook scan reports rental.released
+    let released: String = db
+        .connection()
+        .query_row(
+            "SELECT newData FROM inventoryTransactions WHERE transactionType = 'release' AND referenceId = 'r1'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(serde_json::from_str::<serde_json::Value>(&released).unwrap()["used"], true);
+}
+
+#[test]
+fn popular_queries_are_warmed_into_the_cache() {
+    let db = catalog();
+    let config = CacheConfig { warm_queries: 1, ..CacheConfig::default() };
+    let storage = Storage::new(SqlStore::new(db.clone()), MemoryCache::default(), config);
+    let red = query(&[("where[color]", "red"), ("limit", "2")]);
+    for _ in 0..2 {
+        block_on(get_records(&storage, &admin_token(), &red, &metrics())).unwrap();
+    }
+    block_on(get_records(&storage, &admin_token(), &query(&[]), &metrics())).unwrap();
+    // Scoped tokens never read the query cache, so their queries aren't counted
+    block_on(get_records(&storage, &scoped_token(&["phones"]), &red, &metrics())).unwrap();
+
+    let hits = storage.take_query_hits();
+    assert_eq!(hits.len(), 3);
+    assert_eq!(hits[0], QueryHit { filters: r#"{"color":"red"}"#.to_string(), limit: 2, offset: 0 });
+    block_on(scheduled::record_query_hits(&storage.store, &hits)).unwrap();
+
+    storage.cache.entries.borrow_mut().clear();
+    let stats = block_on(scheduled::warm_caches(&storage)).unwrap();
+
+    assert!(stats.public_tables);
+    assert_eq!(stats.queries, 1);
+    assert!(storage.cache.entries.borrow().contains_key(CACHE_KEY_PUBLIC_TABLES));
+    let warmed: Vec<String> = storage.cache.entries.borrow().keys().filter(|k| k.starts_with("query:")).cloned().collect();
+    assert_eq!(warmed.len(), 1);
+    assert!(warmed[0].ends_with(":2:0"));
+
+    // Fresh entries are left alone
+    let again = block_on(scheduled::warm_caches(&storage)).unwrap();
+    assert_eq!((again.public_tables, again.queries), (false, 0));
+}
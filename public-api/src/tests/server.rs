@@ -0,0 +1,194 @@
+use super::*;
+use crate::native::{NativeRequest, Reply, Server, ServerConfig};
+use worker::Method;
+
+fn server(upstream: Option<&str>) -> Server {
+    Server::new(ServerConfig {
+        database: "unused.sqlite".into(),
+        listen: "127.0.0.1:0".to_string(),
+        threads: 1,
+        upstream: upstream.map(str::to_string),
+        schedule_interval: None,
+        currency_rates: None,
+    })
+}
+
+fn request(method: Method, url: &str, secret: Option<&str>) -> NativeRequest {
+    let mut headers = vec![("Origin".to_string(), "https://shop.example.com".to_string())];
+    if let Some(secret) = secret {
+        headers.push(("Authorization".to_string(), format!("Bearer {}", secret)));
+    }
+    NativeRequest { method, url: url.to_string(), headers, body: vec![] }
+}
+
+fn send(server: &Server, db: &SqliteDb, req: NativeRequest) -> Reply {
+    let mut sent = None;
+    server.respond(db, &req, |reply| sent = Some(reply));
+    sent.expect("reply sent")
+}
+
+fn body(reply: &Reply) -> serde_json::Value {
+    serde_json::from_str(&reply.body).expect("JSON body")
+}
+
+fn catalog_with_tokens() -> SqliteDb {
+    let db = catalog();
+    db.add_token("admin-token", "admin-secret", None, None);
+    db.exec("UPDATE tokens SET isAdmin = 1 WHERE id = 'admin-token'", &[]);
+    db.add_token("bikes-token", "bikes-secret", Some(&["bikes"]), None);
+    db
+}
+
+#[test]
+fn health_needs_no_token() {
+    let db = catalog_with_tokens();
+
+    let reply = send(&server(None), &db, request(Method::Get, "/health", None));
+
+    assert_eq!(reply.status, 200);
+    assert_eq!(body(&reply)["runtime"], "rust-native");
+}
+
+#[test]
+fn missing_token_is_a_problem_response_with_request_headers() {
+    let db = catalog_with_tokens();
+    let mut req = request(Method::Get, "/api/public/records", None);
+    req.headers.push(("X-Request-Id".to_string(), "req-42".to_string()));
+
+    let reply = send(&server(None), &db, req);
+
+    assert_eq!(reply.status, 401);
+    assert_eq!(reply.header("Content-Type"), Some("application/problem+json"));
+    assert_eq!(reply.header("X-Request-Id"), Some("req-42"));
+    assert_eq!(reply.header("Access-Control-Allow-Origin"), Some("*"));
+    assert_eq!(body(&reply)["code"], "UNAUTHORIZED");
+}
+
+#[test]
+fn routes_are_served_by_the_shared_handlers() {
+    let db = catalog_with_tokens();
+    let server = server(None);
+
+    let records = send(&server, &db, request(Method::Get, "/api/public/records?where[color]=red", Some("admin-secret")));
+    assert_eq!(records.status, 200);
+    assert_eq!(body(&records)["total"], 3);
+    assert!(records.header("X-RateLimit-Remaining").is_some());
+    assert!(records.header("Server-Timing").unwrap().contains("db;dur="));
+
+    let item = send(&server, &db, request(Method::Get, "/api/public/tables/bikes/items/b1", Some("bikes-secret")));
+    assert_eq!(body(&item)["name"], "Cruiser");
+
+    let forbidden = send(&server, &db, request(Method::Get, "/api/public/tables/phones/items", Some("bikes-secret")));
+    assert_eq!(forbidden.status, 403);
+
+    let missing = send(&server, &db, request(Method::Get, "/api/public/nope", Some("admin-secret")));
+    assert_eq!(missing.status, 404);
+}
+
+#[test]
+fn preflight_lists_the_methods_of_the_path() {
+    let db = catalog_with_tokens();
+
+    let reply = send(&server(None), &db, request(Method::Options, "/api/public/buy", None));
+
+    assert_eq!(reply.status, 204);
+    assert_eq!(reply.header("Access-Control-Allow-Origin"), Some("https://shop.example.com"));
+    assert_eq!(reply.header("Access-Control-Allow-Methods"), Some("POST, OPTIONS"));
+}
+
+#[test]
+fn tokens_are_rate_limited_in_process() {
+    let db = catalog_with_tokens();
+    db.exec("UPDATE tokens SET rateLimits = ? WHERE id = 'bikes-token'", &[&r#"{"read":{"perMinute":60,"burst":2}}"#]);
+    let server = server(None);
+
+    let first = send(&server, &db, request(Method::Get, "/api/public/tables", Some("bikes-secret")));
+    send(&server, &db, request(Method::Get, "/api/public/tables", Some("bikes-secret")));
+    let third = send(&server, &db, request(Method::Get, "/api/public/tables", Some("bikes-secret")));
+
+    assert_eq!(first.status, 200);
+    // Limit and remaining both count requests in the bucket
+    assert_eq!((first.header("X-RateLimit-Limit"), first.header("X-RateLimit-Remaining")), (Some("2"), Some("1")));
+    assert_eq!(third.status, 429);
+    assert!(third.header("Retry-After").is_some());
+}
+
+#[test]
+fn writes_without_an_upstream_are_unavailable() {
+    let db = catalog_with_tokens();
+
+    let reply = send(&server(None), &db, request(Method::Post, "/api/public/buy", Some("admin-secret")));
+
+    assert_eq!(reply.status, 503);
+    assert_eq!(body(&reply)["code"], "UPSTREAM_UNAVAILABLE");
+}
+
+#[test]
+fn usage_is_metered_after_each_reply() {
+    let db = catalog_with_tokens();
+    let server = server(None);
+
+    send(&server, &db, request(Method::Get, "/api/public/records?limit=2", Some("bikes-secret")));
+    let reply = send(&server, &db, request(Method::Get, "/api/public/usage?from=2020-01-01T02:00:00%2B02:00", Some("bikes-secret")));
+
+    let usage = body(&reply);
+    assert_eq!(usage["from"], "2020-01-01 00:00:00");
+    assert_eq!(usage["totals"]["requests"], 1);
+    assert_eq!(usage["totals"]["rows"], 2);
+    assert_eq!(usage["usage"][0]["route"], "GET /api/public/records");
+
+    let invalid = send(&server, &db, request(Method::Get, "/api/public/usage?from=yesterday", Some("bikes-secret")));
+    assert_eq!(invalid.status, 400);
+}
+
+#[test]
+fn webhook_subscriptions_are_created_and_deleted_over_http() {
+    let db = catalog_with_tokens();
+    let server = server(None);
+    let mut create = request(Method::Post, "/api/public/webhooks", Some("bikes-secret"));
+    create.body = br#"{"url": "https://hooks.example.com/in", "events": ["item.sold_out"]}"#.to_vec();
+
+    let created = send(&server, &db, create);
+    assert_eq!(created.status, 201);
+    let id = body(&created)["id"].as_str().unwrap().to_string();
+
+    let listed = send(&server, &db, request(Method::Get, "/api/public/webhooks", Some("bikes-secret")));
+    assert_eq!(body(&listed)["webhooks"][0]["id"], id.as_str());
+
+    let url = format!("/api/public/webhooks/{}", id);
+    let foreign = send(&server, &db, request(Method::Delete, &url, Some("admin-secret")));
+    assert_eq!(foreign.status, 404);
+    assert_eq!(body(&foreign)["code"], "WEBHOOK_NOT_FOUND");
+
+    let deleted = send(&server, &db, request(Method::Delete, &url, Some("bikes-secret")));
+    assert_eq!(deleted.status, 200);
+    assert_eq!(body(&deleted)["deleted"], true);
+
+    let log = send(&server, &db, request(Method::Get, "/api/public/webhooks/deliveries?status=sent", Some("bikes-secret")));
+    assert_eq!(log.status, 400);
+}
+
+#[test]
+fn accepted_writes_invalidate_cached_results_over_their_table() {
+    let db = catalog_with_tokens();
+    // The order service: accept one purchase of p1
+    let upstream = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", upstream.server_addr().to_ip().unwrap());
+    let order_service = std::thread::spawn(move || {
+        let request = upstream.recv().unwrap();
+        let sale = serde_json::json!({"sale": {"id": "sale-1", "tableId": "phones", "itemId": "p1"}});
+        request.respond(tiny_http::Response::from_string(sale.to_string())).unwrap();
+    });
+    let server = server(Some(&url));
+    let red = || body(&send(&server, &db, request(Method::Get, "/api/public/records?where[color]=red", Some("admin-secret"))));
+
+    assert_eq!(red()["total"], 3);
+    db.exec("UPDATE tableData SET data = json_set(data, '$.color', 'black') WHERE id = 'p1'", &[]);
+    assert_eq!(red()["total"], 3);
+
+    let mut buy = request(Method::Post, "/api/public/buy", Some("admin-secret"));
+    buy.body = br#"{"tableId": "phones", "itemId": "p1", "quantity": 1}"#.to_vec();
+    assert_eq!(send(&server, &db, buy).status, 200);
+    order_service.join().unwrap();
+    assert_eq!(red()["total"], 2);
+}
@@ -0,0 +1,111 @@
+use super::*;
+use crate::routes::RouteKind;
+use crate::tables::check_write;
+use crate::{get_records, get_table_items, get_tables, get_values, search_tables};
+
+/// The catalog plus a shared sale table, shared with one token (read) and one user (read, buy)
+fn shared_catalog() -> SqliteDb {
+    let db = catalog();
+    db.add_table("crates", "Crates", "shared", "sale", &["name", "color", "qty"]);
+    db.add_row("crates", "c1", serde_json::json!({"name": "Pallet", "color": "red", "qty": 2}), 8);
+    db.add_token("partner-token", "partner-secret", Some(&["phones"]), None);
+    db.add_token("member-token", "member-secret", Some(&[]), None);
+    db.exec("INSERT INTO tableShares (id, tableId, tokenId) VALUES ('s1', 'crates', 'partner-token')", &[]);
+    db.exec(
+        r#"INSERT INTO tableShares (id, tableId, userId, rights) VALUES ('s2', 'crates', 'user-9', '["read","buy"]')"#,
+        &[],
+    );
+    db
+}
+
+fn partner() -> TokenInfo {
+    TokenInfo { id: "partner-token".to_string(), ..scoped_token(&["phones"]) }
+}
+
+fn member() -> TokenInfo {
+    TokenInfo { id: "member-token".to_string(), user_id: Some("user-9".to_string()), ..scoped_token(&[]) }
+}
+
+fn table_ids(tables: &[crate::PublicTable]) -> Vec<&str> {
+    tables.iter().map(|t| t.id.as_str()).collect()
+}
+
+#[test]
+fn shared_tables_are_read_only_by_the_tokens_and_users_they_are_shared_with() {
+    let db = shared_catalog();
+    let storage = storage(&db);
+
+    let tables = block_on(get_tables(&storage, &partner(), &metrics())).unwrap();
+    assert_eq!(table_ids(&tables.tables), ["crates", "phones"]);
+    let tables = block_on(get_tables(&storage, &member(), &metrics())).unwrap();
+    assert_eq!(table_ids(&tables.tables), ["crates"]);
+    let search = block_on(search_tables(&storage, &member(), &query(&[("columns", "qty")]))).unwrap();
+    let ids: Vec<&str> = search.tables.iter().map(|t| t.table.id.as_str()).collect();
+    assert_eq!(ids, ["crates"]);
+    let items = block_on(get_table_items(&storage, &member(), "Crates", &query(&[]))).unwrap();
+    assert_eq!(items.count, 1);
+    let records = block_on(get_records(&storage, &partner(), &query(&[("where[color]", "red")]), &metrics())).unwrap();
+    assert_eq!(records.total, 3);
+    let values = block_on(get_values(&storage, &member(), "name", &query(&[]))).unwrap();
+    assert_eq!(values.values, ["Pallet"]);
+
+    // Admin tokens read public tables, not shared ones they aren't on the list of
+    let tables = block_on(get_tables(&storage, &admin_token(), &metrics())).unwrap();
+    assert_eq!(table_ids(&tables.tables), ["bikes", "phones"]);
+    let result = block_on(get_table_items(&storage, &admin_token(), "crates", &query(&[])));
+    assert!(matches!(result, Err(ApiError::TableForbidden)));
+    let other = TokenInfo { id: "other-token".to_string(), user_id: Some("user-8".to_string()), ..member() };
+    let result = block_on(get_table_items(&storage, &other, "crates", &query(&[])));
+    assert!(matches!(result, Err(ApiError::TableForbidden)));
+
+    // Shares only count while the table is shared
+    db.exec("UPDATE userTables SET visibility = 'private' WHERE id = 'crates'", &[]);
+    storage.cache.entries.borrow_mut().clear();
+    let result = block_on(get_table_items(&storage, &member(), "crates", &query(&[])));
+    assert!(matches!(result, Err(ApiError::TableForbidden)));
+    let records = block_on(get_records(&storage, &partner(), &query(&[("where[color]", "red")]), &metrics())).unwrap();
+    assert_eq!(records.total, 2);
+}
+
+#[test]
+fn orders_need_the_buy_right() {
+    let db = shared_catalog();
+    let store = SqlStore::new(db.clone());
+    let cache = MemoryCache::default();
+    let order = |table_id: &str| serde_json::json!({"tableId": table_id, "itemId": "c1", "quantity": 1}).to_string();
+
+    block_on(check_write(&store, &cache, &member(), RouteKind::Buy, &order("crates"))).unwrap();
+    let result = block_on(check_write(&store, &cache, &partner(), RouteKind::Buy, &order("crates")));
+    assert!(matches!(result, Err(ApiError::OperationForbidden("buy"))));
+    let result = block_on(check_write(&store, &cache, &partner(), RouteKind::Rent, &order("crates")));
+    assert!(matches!(result, Err(ApiError::OperationForbidden("rent"))));
+    let result = block_on(check_write(&store, &cache, &admin_token(), RouteKind::Buy, &order("crates")));
+    assert!(matches!(result, Err(ApiError::TableForbidden)));
+
+    // A token's own tables take every order; unknown tables are the order service's to reject
+    block_on(check_write(&store, &cache, &partner(), RouteKind::Buy, &order("phones"))).unwrap();
+    block_on(check_write(&store, &cache, &partner(), RouteKind::Buy, &order("missing"))).unwrap();
+}
+
+#[test]
+fn share_lists_are_cached_per_token_until_the_ts_api_deletes_them() {
+    let db = shared_catalog();
+    let storage = storage(&db);
+    let cached = |token_id: &str| storage.cache.entries.borrow().contains_key(&format!("table:shares:token:{}", token_id));
+
+    block_on(get_tables(&storage, &admin_token(), &metrics())).unwrap();
+    block_on(get_tables(&storage, &member(), &metrics())).unwrap();
+    assert!(!cached(&admin_token().id));
+    assert!(cached("member-token"));
+
+    db.exec("DELETE FROM tableShares WHERE id = 's2'", &[]);
+    let tables = block_on(get_tables(&storage, &member(), &metrics())).unwrap();
+    assert_eq!(table_ids(&tables.tables), ["crates"]);
+    // Name lookups and order checks read the same cached list
+    assert_eq!(block_on(get_table_items(&storage, &member(), "Crates", &query(&[]))).unwrap().count, 1);
+    let order = serde_json::json!({"tableId": "crates", "itemId": "c1"}).to_string();
+    block_on(check_write(&storage.store, &storage.cache, &member(), RouteKind::Buy, &order)).unwrap();
+    storage.cache.entries.borrow_mut().remove("table:shares:token:member-token");
+    let tables = block_on(get_tables(&storage, &member(), &metrics())).unwrap();
+    assert!(tables.tables.is_empty());
+}
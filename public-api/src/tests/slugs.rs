@@ -0,0 +1,90 @@
+use super::*;
+use crate::error::ApiResult;
+use crate::routes::{match_route, RouteKind};
+use worker::Method;
+use crate::{get_records, get_table_item, get_table_items, get_tables};
+
+/// Phones has the slug spring-phones, the private table the slug vault
+fn slugged_catalog() -> SqliteDb {
+    let db = catalog();
+    db.exec("UPDATE userTables SET slug = 'spring-phones' WHERE id = 'phones'", &[]);
+    db.exec("UPDATE userTables SET slug = 'vault' WHERE id = 'secret'", &[]);
+    db
+}
+
+fn record_ids(storage: &TestStorage, token: &TokenInfo, tables: &str) -> ApiResult<Vec<String>> {
+    let records = block_on(get_records(storage, token, &query(&[("tables", tables)]), &metrics()))?;
+    let mut ids: Vec<String> = records.records.iter().map(|r| r["id"].as_str().unwrap().to_string()).collect();
+    ids.sort();
+    Ok(ids)
+}
+
+#[test]
+fn table_routes_accept_slugs_and_names() {
+    let db = slugged_catalog();
+    let storage = storage(&db);
+    let token = admin_token();
+
+    let items = block_on(get_table_items(&storage, &token, "spring-phones", &query(&[]))).unwrap();
+    assert_eq!((items.count, items.table_id.as_str()), (3, "phones"));
+    let item = block_on(get_table_item(&storage, &token, "bikes", "b1", &query(&[]))).unwrap();
+    assert_eq!(item["name"], "Cruiser");
+    let item = block_on(get_table_item(&storage, &token, "pHoNeS", "p1", &query(&[]))).unwrap();
+    assert_eq!(item["tableId"], "phones");
+
+    let tables = serde_json::to_value(block_on(get_tables(&storage, &token, &metrics())).unwrap()).unwrap();
+    assert_eq!(tables["tables"][1]["slug"], "spring-phones");
+
+    // A private table's slug is refused, but its name isn't even found
+    assert!(matches!(block_on(get_table_items(&storage, &token, "vault", &query(&[]))), Err(ApiError::TableForbidden)));
+    assert!(matches!(block_on(get_table_items(&storage, &token, "Secret", &query(&[]))), Err(ApiError::TableNotFound)));
+    let scoped = scoped_token(&["secret"]);
+    assert_eq!(block_on(get_table_items(&storage, &scoped, "Secret", &query(&[]))).unwrap().count, 1);
+}
+
+#[test]
+fn shared_names_are_ambiguous_unless_only_one_is_readable() {
+    let db = slugged_catalog();
+    db.add_table("phones2", "Phones", "public", "sale", &["name"]);
+    let storage = storage(&db);
+
+    let result = block_on(get_table_items(&storage, &admin_token(), "phones", &query(&[])));
+    assert_eq!(result.unwrap().table_id, "phones");
+    let result = block_on(get_table_items(&storage, &admin_token(), "Phones", &query(&[])));
+    assert!(matches!(result, Err(ApiError::TableAmbiguous(_))));
+    let result = block_on(get_table_items(&storage, &scoped_token(&["phones2"]), "Phones", &query(&[])));
+    assert_eq!(result.unwrap().table_id, "phones2");
+}
+
+#[test]
+fn records_can_be_restricted_to_named_tables() {
+    let db = slugged_catalog();
+    let storage = storage(&db);
+    let token = admin_token();
+
+    assert_eq!(record_ids(&storage, &token, "bikes").unwrap(), ["b1", "b2"]);
+    assert_eq!(record_ids(&storage, &token, "Bikes, spring-phones,phones").unwrap(), ["b1", "b2", "p1", "p2", "p3"]);
+
+    assert!(matches!(record_ids(&storage, &token, "vault"), Err(ApiError::TableForbidden)));
+    assert!(matches!(record_ids(&storage, &token, "notes"), Err(ApiError::TableTypeUnsupported)));
+    assert!(matches!(record_ids(&storage, &token, " , "), Err(ApiError::InvalidParameter(_))));
+    assert!(matches!(record_ids(&storage, &scoped_token(&["secret"]), "bikes"), Err(ApiError::TableForbidden)));
+    assert_eq!(record_ids(&storage, &scoped_token(&["secret"]), "vault").unwrap(), ["s1"]);
+}
+
+#[test]
+fn route_segments_are_percent_decoded_before_lookup() {
+    let db = slugged_catalog();
+    db.add_table("wheels", "Frühjahrs Räder", "public", "rent", &["name"]);
+    db.add_row("wheels", "w1", serde_json::json!({"name": "Tandem"}), 1);
+    let storage = storage(&db);
+
+    let (route, params) = match_route(&Method::Get, "/api/public/tables/Fr%C3%BChjahrs%20R%C3%A4der/items/w1").unwrap();
+    assert_eq!(route.kind, RouteKind::TableItem);
+    assert_eq!(params, ["Frühjahrs Räder", "w1"]);
+    let item = block_on(get_table_item(&storage, &admin_token(), &params[0], &params[1], &query(&[]))).unwrap();
+    assert_eq!(item["name"], "Tandem");
+
+    // Not UTF-8 once decoded
+    assert!(match_route(&Method::Get, "/api/public/tables/%FF/items").is_none());
+}
@@ -0,0 +1,109 @@
+use super::*;
+use crate::routes::RouteKind;
+use crate::tables::check_write;
+use crate::{get_item_availability, get_table_item, get_table_items, get_tables};
+
+fn cached(storage: &TestStorage, key: &str) -> Option<serde_json::Value> {
+    storage.cache.entries.borrow().get(key).map(|json| serde_json::from_str(json).unwrap())
+}
+
+#[test]
+fn table_metadata_and_columns_are_read_through_kv() {
+    let db = catalog();
+    let storage = storage(&db);
+    let token = admin_token();
+
+    let items = block_on(get_table_items(&storage, &token, "phones", &query(&[]))).unwrap();
+    assert_eq!(items.table_name, "Phones");
+    let metadata = cached(&storage, "table:metadata:phones").unwrap();
+    assert_eq!((metadata["tableType"].as_str(), metadata["visibility"].as_str()), (Some("sale"), Some("public")));
+    assert_eq!(metadata["rentalPeriod"], "month");
+    let columns = cached(&storage, "table:columns:phones").unwrap();
+    assert_eq!(columns[0]["name"], "name");
+    assert_eq!(columns[0]["isRequired"], false);
+    assert_eq!(columns[0]["visibility"], "visible");
+
+    // Until the TS API deletes the entries, the cached table and columns are what's served
+    db.exec("UPDATE userTables SET name = 'Handsets' WHERE id = 'phones'", &[]);
+    db.exec("UPDATE tableColumns SET visibility = 'hidden' WHERE tableId = 'phones' AND name = 'name'", &[]);
+    let item = block_on(get_table_item(&storage, &token, "phones", "p1", &query(&[]))).unwrap();
+    assert_eq!((item["tableName"].as_str(), item["name"].as_str()), (Some("Phones"), Some("Alpha")));
+
+    storage.cache.entries.borrow_mut().remove("table:metadata:phones");
+    storage.cache.entries.borrow_mut().remove("table:columns:phones");
+    let item = block_on(get_table_item(&storage, &token, "phones", "p1", &query(&[]))).unwrap();
+    assert_eq!(item["tableName"], "Handsets");
+    assert!(item.get("name").is_none());
+}
+
+#[test]
+fn entries_cached_by_the_ts_api_are_used_and_access_is_checked() {
+    let db = catalog();
+    let storage = storage(&db);
+    // As CacheService stores them: booleans, Date strings and fields the resolver doesn't use
+    storage.cache.entries.borrow_mut().insert(
+        "table:metadata:bikes".to_string(),
+        r#"{"id": "bikes", "name": "Bicycles", "description": null, "createdBy": "a@b.c", "userId": null,
+            "visibility": "public", "tableType": "rent", "productIdColumn": "name", "rentalPeriod": "day",
+            "currency": "USD", "slug": null, "rowCount": 2,
+            "createdAt": "2025-01-01T00:00:00.000Z", "updatedAt": "2025-01-01T00:00:00.000Z"}"#
+            .to_string(),
+    );
+    storage.cache.entries.borrow_mut().insert(
+        "table:columns:bikes".to_string(),
+        r#"[{"id": "c1", "tableId": "bikes", "name": "name", "type": "text", "isRequired": true,
+             "allowDuplicates": true, "defaultValue": null, "position": 0, "createdAt": "2025-01-01T00:00:00.000Z"}]"#
+            .to_string(),
+    );
+
+    let items = block_on(get_table_items(&storage, &admin_token(), "bikes", &query(&[]))).unwrap();
+    assert_eq!((items.table_name.as_str(), items.count), ("Bicycles", 2));
+    let availability = block_on(get_item_availability(&storage, &admin_token(), "bikes", "b2", &query(&[]))).unwrap();
+    assert!(!availability.available);
+
+    let result = block_on(get_table_item(&storage, &admin_token(), "secret", "s1", &query(&[])));
+    assert!(matches!(result, Err(ApiError::TableForbidden)));
+    let result = block_on(get_item_availability(&storage, &admin_token(), "missing", "x", &query(&[])));
+    assert!(matches!(result, Err(ApiError::TableNotFound)));
+    let result = block_on(get_table_item(&storage, &scoped_token(&["phones"]), "bikes", "b1", &query(&[])));
+    assert!(matches!(result, Err(ApiError::TableForbidden)));
+}
+
+#[test]
+fn table_access_objects_grant_operations_per_table() {
+    let db = catalog();
+    db.exec(
+        "INSERT INTO rentals (id, rentalNumber, tableId, tableName, itemId, itemSnapshot, customerId, unitPrice, rentedAt)
+         VALUES ('r1', 'RNT-r1', 'bikes', 'Bikes', 'b2', '{}', 'customer-1', 10, '2025-01-01 00:00:00')",
+        &[],
+    );
+    let storage = storage(&db);
+    let access = r#"{"phones": ["read", "buy"], "bikes": ["read", "rent"], "secret": ["buy"]}"#;
+    let token = TokenInfo { table_access: Some(access.to_string()), ..scoped_token(&[]) };
+    let write = |kind, body: serde_json::Value| block_on(check_write(&storage.store, &storage.cache, &token, kind, &body.to_string()));
+
+    // Reads need "read": secret can be bought from but not listed or read
+    let tables = block_on(get_tables(&storage, &token, &metrics())).unwrap();
+    let ids: Vec<&str> = tables.tables.iter().map(|t| t.id.as_str()).collect();
+    assert_eq!(ids, ["bikes", "phones"]);
+    let result = block_on(get_table_items(&storage, &token, "secret", &query(&[])));
+    assert!(matches!(result, Err(ApiError::TableForbidden)));
+    assert!(write(RouteKind::Buy, serde_json::json!({"tableId": "secret", "itemId": "s1"})).is_ok());
+
+    // Writes need their own operation on the target table
+    assert!(write(RouteKind::Buy, serde_json::json!({"tableId": "phones", "itemId": "p1"})).is_ok());
+    let result = write(RouteKind::Rent, serde_json::json!({"tableId": "phones", "itemId": "p1"}));
+    assert!(matches!(result, Err(ApiError::OperationForbidden("rent"))));
+    assert!(write(RouteKind::Rent, serde_json::json!({"tableId": "bikes", "itemId": "b1"})).is_ok());
+    let result = write(RouteKind::Release, serde_json::json!({"rentalId": "r1"}));
+    assert!(matches!(result, Err(ApiError::OperationForbidden("release"))));
+    let result = write(RouteKind::Buy, serde_json::json!({"tableId": "notes", "itemId": "n1"}));
+    assert!(matches!(result, Err(ApiError::TableForbidden)));
+
+    // The array format still grants every operation; unknown operations void the whole grant
+    let listed = scoped_token(&["bikes"]);
+    let body = serde_json::json!({"rentalId": "r1"}).to_string();
+    assert!(block_on(check_write(&storage.store, &storage.cache, &listed, RouteKind::Release, &body)).is_ok());
+    let invalid = TokenInfo { table_access: Some(r#"{"phones": ["read", "sell"]}"#.to_string()), ..scoped_token(&[]) };
+    assert_eq!(block_on(get_tables(&storage, &invalid, &metrics())).unwrap().count, 0);
+}
@@ -0,0 +1,331 @@
+use super::*;
+use crate::routes::RouteKind;
+use crate::store::WebhookStore;
+use crate::webhooks::{self, DeliveryFilters, DeliveryStats, InventoryChange, WebhookSender};
+
+/// URL, headers and body of a sent webhook
+type SentRequest = (String, Vec<(String, String)>, String);
+
+/// Records every request and answers with a fixed status (or a connection error when None)
+struct RecordingSender {
+    status: Option<u16>,
+    sent: RefCell<Vec<SentRequest>>,
+}
+
+impl RecordingSender {
+    fn answering(status: Option<u16>) -> Self {
+        Self { status, sent: RefCell::new(vec![]) }
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+}
+
+impl WebhookSender for RecordingSender {
+    async fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> Result<u16, String> {
+        let headers = headers.iter().map(|(n, v)| (n.to_string(), v.clone())).collect();
+        self.sent.borrow_mut().push((url.to_string(), headers, body.to_string()));
+        self.status.ok_or_else(|| "connection refused".to_string())
+    }
+}
+
+fn change(transaction_type: &str, previous: serde_json::Value, current: serde_json::Value) -> InventoryChange {
+    InventoryChange {
+        position: 1,
+        id: "tx-1".to_string(),
+        table_id: "phones".to_string(),
+        table_name: Some("Phones".to_string()),
+        item_id: "p1".to_string(),
+        transaction_type: transaction_type.to_string(),
+        reference_id: None,
+        previous_data: Some(previous.to_string()),
+        new_data: Some(current.to_string()),
+    }
+}
+
+fn add_transaction(db: &SqliteDb, id: &str, table_id: &str, item_id: &str, kind: &str, previous: serde_json::Value, current: serde_json::Value) {
+    db.exec(
+        "INSERT INTO inventoryTransactions (id, tableId, tableName, itemId, transactionType, previousData, newData, createdBy)
+         VALUES (?, ?, ?, ?, ?, ?, ?, 'admin@example.com')",
+        &[&id, &table_id, &table_id, &item_id, &kind, &previous.to_string(), &current.to_string()],
+    );
+}
+
+/// The catalog with an unrestricted and a bikes-only token, each subscribed to every event
+fn subscribed_catalog() -> (SqliteDb, SqlStore<SqliteDb>) {
+    let db = catalog();
+    db.add_token("admin-token", "admin-secret", None, None);
+    db.exec("UPDATE tokens SET isAdmin = 1 WHERE id = 'admin-token'", &[]);
+    db.add_token("bikes-token", "bikes-secret", Some(&["bikes"]), None);
+    let store = SqlStore::new(db.clone());
+    let body = serde_json::json!({"url": "https://hooks.example.com/in", "events": webhooks::EVENT_TYPES}).to_string();
+    for token in [admin_token(), TokenInfo { id: "bikes-token".to_string(), ..scoped_token(&["bikes"]) }] {
+        block_on(webhooks::create_subscription(&store, &token, &body)).unwrap();
+    }
+    (db, store)
+}
+
+fn deliveries(store: &SqlStore<SqliteDb>, token_id: &str) -> Vec<serde_json::Value> {
+    let token = TokenInfo { id: token_id.to_string(), ..admin_token() };
+    let log = block_on(webhooks::list_deliveries(store, &token, &query(&[]))).unwrap();
+    serde_json::to_value(log).unwrap()["deliveries"].as_array().unwrap().clone()
+}
+
+#[test]
+fn stock_and_price_changes_become_events() {
+    let sold_out = webhooks::events_from_change(&change(
+        "update",
+        serde_json::json!({"qty": 2, "price": 10}),
+        serde_json::json!({"qty": 0, "price": "12.5"}),
+    ));
+    let types: Vec<&str> = sold_out.iter().map(|e| e.event_type).collect();
+    assert_eq!(types, ["item.sold_out", "item.price_changed"]);
+    assert_eq!(sold_out[0].id, "tx-1:item.sold_out");
+    assert_eq!(sold_out[0].data["item"]["qty"], 0);
+    assert_eq!(sold_out[1].data["previousPrice"], 10.0);
+    assert_eq!(sold_out[1].data["price"], 12.5);
+
+    let restocked = webhooks::events_from_change(&change("adjust", serde_json::json!({"qty": 0}), serde_json::json!({"qty": 5})));
+    assert_eq!(restocked[0].event_type, "item.restocked");
+
+    let unchanged = webhooks::events_from_change(&change("update", serde_json::json!({"qty": 3}), serde_json::json!({"qty": 2})));
+    assert!(unchanged.is_empty());
+
+    // Rentals are keyed by the rental, whatever the snapshot says
+    let rental = InventoryChange {
+        reference_id: Some("rental-1".to_string()),
+        ..change("rent", serde_json::json!({"price": 1}), serde_json::json!({"price": 2, "used": true}))
+    };
+    let started = webhooks::events_from_change(&rental);
+    assert_eq!(started.len(), 1);
+    assert_eq!(started[0].id, "rental-1:rental.started");
+    assert_eq!(started[0].data["rentalId"], "rental-1");
+}
+
+#[test]
+fn subscriptions_are_validated_and_scoped_to_their_token() {
+    let db = migrated_db();
+    db.add_token("admin-token", "admin-secret", None, None);
+    db.exec("UPDATE tokens SET isAdmin = 1 WHERE id = 'admin-token'", &[]);
+    let store = SqlStore::new(db.clone());
+    let token = admin_token();
+
+    for body in [
+        r#"{"url": "http://hooks.example.com", "events": ["item.sold_out"]}"#,
+        r#"{"url": "https://hooks.example.com", "events": ["item.deleted"]}"#,
+        r#"{"url": "https://hooks.example.com", "events": []}"#,
+        "not json",
+    ] {
+        let result = block_on(webhooks::create_subscription(&store, &token, body));
+        assert!(matches!(result, Err(ApiError::InvalidParameter(_))), "{}", body);
+    }
+
+    let body = r#"{"url": "http://localhost:9000/hook", "events": ["item.sold_out", "item.sold_out"]}"#;
+    let created = serde_json::to_value(block_on(webhooks::create_subscription(&store, &token, body)).unwrap()).unwrap();
+    assert!(created["secret"].as_str().unwrap().starts_with("whsec_"));
+    assert_eq!(created["events"], serde_json::json!(["item.sold_out"]));
+
+    let listed = serde_json::to_value(block_on(webhooks::list_subscriptions(&store, &token)).unwrap()).unwrap();
+    assert_eq!(listed["count"], 1);
+    assert!(listed["webhooks"][0].get("secret").is_none());
+
+    let id = created["id"].as_str().unwrap();
+    let other = scoped_token(&[]);
+    assert!(matches!(
+        block_on(webhooks::delete_subscription(&store, &other, id)),
+        Err(ApiError::WebhookNotFound)
+    ));
+    block_on(webhooks::delete_subscription(&store, &token, id)).unwrap();
+    assert!(block_on(store.webhook_subscriptions("admin-token")).unwrap().is_empty());
+}
+
+#[test]
+fn scheduled_scan_fans_out_to_tokens_that_can_read_the_table() {
+    let (db, store) = subscribed_catalog();
+    let sender = RecordingSender::answering(Some(204));
+
+    // History before the first run isn't replayed
+    add_transaction(&db, "tx-old", "phones", "p1", "update", serde_json::json!({"qty": 1}), serde_json::json!({"qty": 0}));
+    let first = block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 1_000)).unwrap();
+    assert_eq!(first.changes, 0);
+
+    add_transaction(&db, "tx-1", "phones", "p1", "update", serde_json::json!({"qty": 3}), serde_json::json!({"qty": 0}));
+    add_transaction(&db, "tx-2", "bikes", "b1", "update", serde_json::json!({"price": 5}), serde_json::json!({"price": 6}));
+    add_transaction(&db, "tx-3", "secret", "s1", "update", serde_json::json!({"qty": 1}), serde_json::json!({"qty": 0}));
+    let stats = block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 2_000)).unwrap();
+
+    // Phones and bikes reach the unrestricted token, bikes the scoped one; the private table nobody
+    assert_eq!(stats.changes, 3);
+    assert_eq!(stats.queued, 3);
+    assert_eq!(stats.deliveries, DeliveryStats { delivered: 3, retrying: 0, failed: 0 });
+    assert_eq!(deliveries(&store, "admin-token").len(), 2);
+    let bikes = deliveries(&store, "bikes-token");
+    assert_eq!(bikes.len(), 1);
+    assert_eq!(bikes[0]["event"], "item.price_changed");
+    assert_eq!(bikes[0]["status"], "delivered");
+    assert_eq!(bikes[0]["payload"]["data"]["itemId"], "b1");
+
+    // Signed with the subscription secret over `<timestamp>.<body>`
+    let secret: String = db
+        .connection()
+        .query_row("SELECT secret FROM webhookSubscriptions WHERE tokenId = 'bikes-token'", [], |row| row.get(0))
+        .unwrap();
+    let sent = sender.sent.borrow();
+    let signed_for_bikes = sent.iter().find(|(_, headers, body)| {
+        let expected = format!("t=2000,v1={}", webhooks::signature(&secret, 2_000, body));
+        header(headers, webhooks::SIGNATURE_HEADER) == Some(expected.as_str())
+    });
+    let (url, headers, _) = signed_for_bikes.expect("a delivery signed with the bikes secret");
+    assert_eq!(url, "https://hooks.example.com/in");
+    assert_eq!(header(headers, "X-Webhook-Id"), Some("tx-2:item.price_changed"));
+    drop(sent);
+
+    // Nothing new: nothing sent
+    let idle = block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 3_000)).unwrap();
+    assert_eq!(idle.changes, 0);
+    assert_eq!(sender.sent.borrow().len(), 3);
+}
+
+#[test]
+fn failed_deliveries_back_off_then_give_up() {
+    let (db, store) = subscribed_catalog();
+    let sender = RecordingSender::answering(Some(500));
+    block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 0)).unwrap();
+    add_transaction(&db, "tx-1", "bikes", "b1", "update", serde_json::json!({"price": 5}), serde_json::json!({"price": 6}));
+
+    let first = block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 1_000)).unwrap();
+    assert_eq!(first.deliveries.retrying, 2);
+    let pending = deliveries(&store, "bikes-token");
+    assert_eq!(pending[0]["status"], "pending");
+    assert_eq!(pending[0]["responseStatus"], 500);
+    assert_eq!(pending[0]["nextAttemptAt"], 1_060);
+
+    // Not due yet
+    let early = block_on(webhooks::deliver_due(&store, &sender, 1_030)).unwrap();
+    assert_eq!(early, DeliveryStats::default());
+
+    let failing = RecordingSender::answering(None);
+    let mut now = 1_060;
+    for _ in 0..20 {
+        block_on(webhooks::deliver_due(&store, &failing, now)).unwrap();
+        now += 3_600;
+    }
+    let failed = deliveries(&store, "bikes-token");
+    assert_eq!(failed[0]["status"], "failed");
+    assert_eq!(failed[0]["attempts"], 8);
+    assert_eq!(failed[0]["lastError"], "connection refused");
+    assert_eq!(failed[0]["nextAttemptAt"], serde_json::Value::Null);
+
+    let token = TokenInfo { id: "bikes-token".to_string(), ..admin_token() };
+    let filters = DeliveryFilters { status: Some("pending".to_string()), ..Default::default() };
+    let (rows, total) = block_on(store.webhook_deliveries(&token.id, &filters, 10, 0)).unwrap();
+    assert!(rows.is_empty());
+    assert_eq!(total, 0);
+}
+
+#[test]
+fn a_public_api_sale_and_its_transaction_queue_one_delivery() {
+    let (db, store) = subscribed_catalog();
+    let sender = RecordingSender::answering(Some(200));
+    block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 0)).unwrap();
+
+    // The order service sold the last units of p1
+    db.exec("UPDATE tableData SET data = ? WHERE id = 'p1'", &[&serde_json::json!({"name": "Alpha", "qty": 0}).to_string()]);
+    let snapshot = serde_json::json!({"name": "Alpha", "qty": 3}).to_string();
+    let response = serde_json::json!({
+        "message": "Purchase completed successfully",
+        "sale": {"id": "sale-1", "tableId": "phones", "tableName": "Phones", "itemId": "p1", "itemSnapshot": snapshot}
+    });
+    let stats = block_on(webhooks::on_write(&store, &MemoryCache::default(), &sender, RouteKind::Buy, &response.to_string(), 1_000)).unwrap();
+    assert_eq!(stats.delivered, 1);
+
+    // The scan later sees the sale's own transaction and recognises the event
+    db.exec(
+        "INSERT INTO inventoryTransactions (id, tableId, tableName, itemId, transactionType, previousData, newData, referenceId, createdBy)
+         VALUES ('tx-sale', 'phones', 'Phones', 'p1', 'sale', ?, ?, 'sale-1', 'api')",
+        &[&snapshot, &serde_json::json!({"name": "Alpha", "qty": 0}).to_string()],
+    );
+    let scan = block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 2_000)).unwrap();
+    assert_eq!(scan.changes, 1);
+    assert_eq!(scan.queued, 0);
+
+    let log = deliveries(&store, "admin-token");
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0]["eventId"], "sale-1:item.sold_out");
+    assert_eq!(log[0]["payload"]["data"]["previousQty"], 3.0);
+}
+
+#[test]
+fn payloads_respect_column_visibility() {
+    let (db, store) = subscribed_catalog();
+    let sender = RecordingSender::answering(Some(204));
+    block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 0)).unwrap();
+    db.exec("UPDATE tableColumns SET visibility = 'masked' WHERE tableId = 'phones' AND name = 'color'", &[]);
+    db.exec(
+        "INSERT INTO tableColumns (id, tableId, name, type, position) VALUES ('bikes-price', 'bikes', 'price', 'number', 10)",
+        &[],
+    );
+    db.exec(r#"UPDATE tokens SET columnRules = '{"bikes": {"price": "hidden"}}' WHERE id = 'bikes-token'"#, &[]);
+
+    add_transaction(&db, "tx-1", "phones", "p1", "update", serde_json::json!({"qty": 3}), serde_json::json!({"qty": 0, "color": "Red"}));
+    add_transaction(&db, "tx-2", "bikes", "b1", "update", serde_json::json!({"price": 5}), serde_json::json!({"price": 6}));
+    block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 1_000)).unwrap();
+
+    let admin = deliveries(&store, "admin-token");
+    let sold_out = admin.iter().find(|d| d["event"] == "item.sold_out").unwrap();
+    assert_eq!(sold_out["payload"]["data"]["item"]["color"], "R***");
+    assert_eq!(admin.len(), 2);
+    // A price change is no news to a token that can't see prices
+    assert!(deliveries(&store, "bikes-token").is_empty());
+}
+
+#[test]
+fn payloads_carry_the_subscribers_marked_up_prices() {
+    let (db, store) = subscribed_catalog();
+    let sender = RecordingSender::answering(Some(204));
+    block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 0)).unwrap();
+    db.exec(r#"UPDATE tokens SET priceRules = '{"default": {"percent": 10}}' WHERE id = 'bikes-token'"#, &[]);
+
+    add_transaction(&db, "tx-1", "bikes", "b1", "update", serde_json::json!({"price": 5}), serde_json::json!({"price": 6, "fee": 2}));
+    block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 1_000)).unwrap();
+
+    let data = |token_id| deliveries(&store, token_id)[0]["payload"]["data"].clone();
+    let (bikes, admin) = (data("bikes-token"), data("admin-token"));
+    assert_eq!((&bikes["previousPrice"], &bikes["price"]), (&5.5.into(), &6.6.into()));
+    assert_eq!((&bikes["item"]["price"], &bikes["item"]["fee"]), (&6.6.into(), &2.2.into()));
+    assert_eq!((&admin["previousPrice"], &admin["price"], &admin["item"]["price"]), (&5.0.into(), &6.0.into(), &6.into()));
+}
+
+#[test]
+fn customer_bound_tokens_only_hear_of_their_customers_rentals() {
+    let (db, store) = subscribed_catalog();
+    let sender = RecordingSender::answering(Some(204));
+    block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 0)).unwrap();
+    db.exec("UPDATE tokens SET customerId = 'cust-1' WHERE id = 'bikes-token'", &[]);
+
+    for (rental, customer) in [("r1", "cust-1"), ("r2", "cust-2")] {
+        db.exec(
+            "INSERT INTO rentals (id, rentalNumber, tableId, tableName, itemId, itemSnapshot, customerId, unitPrice, rentedAt)
+             VALUES (?, ?, 'bikes', 'Bikes', 'b1', '{}', ?, 10, '2025-01-01T10:00:00.000Z')",
+            &[&rental, &format!("RNT-{}", rental), &customer],
+        );
+        db.exec(
+            "INSERT INTO inventoryTransactions (id, tableId, tableName, itemId, transactionType, newData, referenceId, createdBy)
+             VALUES (?, 'bikes', 'Bikes', 'b1', 'rent', '{}', ?, 'api')",
+            &[&format!("tx-{}", rental), &rental],
+        );
+    }
+    block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 1_000)).unwrap();
+
+    let event_ids = |token_id| -> Vec<String> {
+        deliveries(&store, token_id).iter().map(|d| d["eventId"].as_str().unwrap().to_string()).collect()
+    };
+    assert_eq!(event_ids("bikes-token"), ["r1:rental.started"]);
+    assert_eq!(event_ids("admin-token").len(), 2);
+
+    // Item events aren't about a customer
+    add_transaction(&db, "tx-3", "bikes", "b1", "update", serde_json::json!({"price": 5}), serde_json::json!({"price": 6}));
+    block_on(webhooks::run_scheduled(&store, &MemoryCache::default(), &sender, 2_000)).unwrap();
+    assert_eq!(event_ids("bikes-token").len(), 2);
+}
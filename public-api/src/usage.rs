@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use worker::*;
+
+use crate::cloudflare::D1Sql;
+use crate::error::{ApiError, ApiResult};
+use crate::store::{SqlStore, UsageStore};
+use crate::telemetry::RequestMetrics;
+use crate::utils;
+use crate::TokenInfo;
+
+// ============================================================================
+// USAGE METERING
+// ============================================================================
+
+/// Default report window when `from` is omitted
+const DEFAULT_REPORT_DAYS: f64 = 30.0;
+
+/// Record one request in the hourly usage table, counting the rows
+/// (`count` field for lists, 1 for a single object) and bytes of its response body
+pub async fn record<S: UsageStore>(store: &S, token_id: &str, route: &str, status: u16, body: &str) {
+    let rows = if status < 400 { count_rows(body) } else { 0 };
+    if let Err(e) = store.record_usage(token_id, route, status >= 400, rows, body.len() as u64).await {
+        utils::log_error(&format!("Usage metering failed for {} {}: {:?}", token_id, route, e));
+    }
+}
+
+/// Meter a worker response after it's sent (via wait_until), reading a clone of its body
+pub async fn record_response(env: Env, request_id: String, token_id: String, route: String, mut response: Response) {
+    let status = response.status_code();
+    let body = response.text().await.unwrap_or_default();
+    let metrics = RequestMetrics::new(request_id);
+    match D1Sql::new(&env, &metrics) {
+        Ok(db) => record(&SqlStore::new(db), &token_id, &route, status, &body).await,
+        Err(e) => utils::log_error(&format!("Usage metering failed for {} {}: {:?}", token_id, route, e)),
+    }
+}
+
+/// Rows in a JSON response body: the `count` field of list responses, otherwise 1 per object
+fn count_rows(body: &str) -> u64 {
+    match serde_json::from_str::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(obj)) => obj.get("count").and_then(|c| c.as_u64()).unwrap_or(1),
+        Ok(serde_json::Value::Array(items)) => items.len() as u64,
+        _ => 0,
+    }
+}
+
+// ============================================================================
+// USAGE REPORT
+// ============================================================================
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UsageRow {
+    hour: String,
+    route: String,
+    #[serde(rename = "requestCount")]
+    request_count: i64,
+    #[serde(rename = "errorCount")]
+    error_count: i64,
+    #[serde(rename = "rowsReturned")]
+    rows_returned: i64,
+    #[serde(rename = "bytesReturned")]
+    bytes_returned: i64,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct UsageTotals {
+    requests: i64,
+    errors: i64,
+    rows: i64,
+    bytes: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageResponse {
+    #[serde(rename = "tokenId")]
+    token_id: String,
+    from: String,
+    to: String,
+    totals: UsageTotals,
+    usage: Vec<UsageRow>,
+    count: usize,
+}
+
+/// Parse a `from`/`to` query value (date or ISO timestamp) into SQLite datetime format
+#[cfg(target_arch = "wasm32")]
+pub fn parse_datetime(value: &str) -> Option<String> {
+    let millis = js_sys::Date::parse(value);
+    if millis.is_nan() {
+        return None;
+    }
+    Some(format_datetime(millis))
+}
+
+/// Format epoch milliseconds as `YYYY-MM-DD HH:MM:SS` (UTC), matching the `hour` column
+#[cfg(target_arch = "wasm32")]
+fn format_datetime(millis: f64) -> String {
+    let iso: String = js_sys::Date::new(&millis.into()).to_iso_string().into();
+    iso[..19].replace('T', " ")
+}
+
+/// Parse a `from`/`to` query value into SQLite datetime format
+/// Natively there's no Date.parse: accepts `YYYY-MM-DD` and ISO 8601 date-times
+/// (`T` or space separated, optional seconds, fraction and `Z`/`+HH:MM` offset), read as UTC
+#[cfg(not(target_arch = "wasm32"))]
+pub fn parse_datetime(value: &str) -> Option<String> {
+    let (date, time) = match value.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+    let mut parts = date.splitn(3, '-').map(|p| p.parse::<i64>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let mut seconds = days_from_civil(year, month, day) * 86_400;
+
+    if let Some(time) = time {
+        let (clock, offset) = match time.strip_suffix('Z') {
+            Some(clock) => (clock, 0),
+            None => match time.rfind(['+', '-']) {
+                Some(i) => {
+                    let (hours, minutes) = time[i + 1..].split_once(':')?;
+                    let offset = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+                    (&time[..i], if &time[i..i + 1] == "-" { -offset } else { offset })
+                }
+                None => (time, 0),
+            },
+        };
+        let mut fields = clock.split(':');
+        let hour: i64 = fields.next()?.parse().ok()?;
+        let minute: i64 = fields.next()?.parse().ok()?;
+        let second: f64 = match fields.next() {
+            Some(s) => s.parse().ok()?,
+            None => 0.0,
+        };
+        if fields.next().is_some() || hour > 23 || minute > 59 || !(0.0..60.0).contains(&second) {
+            return None;
+        }
+        seconds += hour * 3600 + minute * 60 + second as i64 - offset;
+    }
+    Some(format_datetime(seconds as f64 * 1000.0))
+}
+
+/// Format epoch milliseconds as `YYYY-MM-DD HH:MM:SS` (UTC), matching the `hour` column
+#[cfg(not(target_arch = "wasm32"))]
+fn format_datetime(millis: f64) -> String {
+    let seconds = (millis / 1000.0).floor() as i64;
+    let (days, time) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date (Howard Hinnant's algorithm)
+#[cfg(not(target_arch = "wasm32"))]
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`
+#[cfg(not(target_arch = "wasm32"))]
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    (year_of_era + era * 400 + if month <= 2 { 1 } else { 0 }, month, day)
+}
+
+/// GET /api/public/usage?from=&to= - Hourly usage of the calling token
+pub async fn get_usage<S: UsageStore>(store: &S, token: &TokenInfo, query: &HashMap<String, String>) -> ApiResult<UsageResponse> {
+    let now = utils::now_ms();
+    let from = match query.get("from") {
+        Some(v) => match parse_datetime(v) {
+            Some(dt) => dt,
+            None => return Err(ApiError::InvalidParameter("from must be a date or ISO timestamp".to_string())),
+        },
+        None => format_datetime(now - DEFAULT_REPORT_DAYS * 86_400_000.0),
+    };
+    let to = match query.get("to") {
+        Some(v) => match parse_datetime(v) {
+            Some(dt) => dt,
+            None => return Err(ApiError::InvalidParameter("to must be a date or ISO timestamp".to_string())),
+        },
+        None => format_datetime(now),
+    };
+
+    let usage = store.usage(&token.id, &from, &to).await?;
+
+    let totals = usage.iter().fold(UsageTotals::default(), |mut t, row| {
+        t.requests += row.request_count;
+        t.errors += row.error_count;
+        t.rows += row.rows_returned;
+        t.bytes += row.bytes_returned;
+        t
+    });
+
+    Ok(UsageResponse {
+        token_id: token.id.clone(),
+        from,
+        to,
+        totals,
+        count: usage.len(),
+        usage,
+    })
+}
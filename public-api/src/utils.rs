@@ -1,3 +1,94 @@
+use serde::{Deserialize, Deserializer};
+use std::str::FromStr;
+
 pub fn set_panic_hook() {
     console_error_panic_hook::set_once();
 }
+
+/// Milliseconds since the UNIX epoch: Date.now() in the worker, the system clock natively
+#[cfg(target_arch = "wasm32")]
+pub fn now_ms() -> f64 {
+    js_sys::Date::now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_ms() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or_default()
+}
+
+/// Log a line: the worker console, or stdout natively
+#[cfg(target_arch = "wasm32")]
+pub fn log(line: &str) {
+    worker::console_log!("{}", line);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log(line: &str) {
+    println!("{}", line);
+}
+
+/// Log an error line: the worker console, or stderr natively
+#[cfg(target_arch = "wasm32")]
+pub fn log_error(line: &str) {
+    worker::console_error!("{}", line);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log_error(line: &str) {
+    eprintln!("{}", line);
+}
+
+/// Configuration variables: wrangler vars in the worker, process environment natively
+pub trait Vars {
+    fn get(&self, name: &str) -> Option<String>;
+}
+
+impl Vars for worker::Env {
+    fn get(&self, name: &str) -> Option<String> {
+        self.var(name).ok().map(|v| v.to_string())
+    }
+}
+
+/// Parse a variable, falling back to a default when it's unset or malformed
+pub fn var_or<T: FromStr>(vars: &impl Vars, name: &str, default: T) -> T {
+    vars.get(name).and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+/// A boolean that may come back from SQLite as 0/1, or from a cached TS object as true/false
+pub fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Int(i64),
+    }
+    Ok(match Flag::deserialize(deserializer)? {
+        Flag::Bool(value) => value,
+        Flag::Int(value) => value != 0,
+    })
+}
+
+/// Lowercase hex encoding
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// `n` random bytes as hex: crypto.getRandomValues in the worker, the OS generator natively
+pub fn random_hex(n: usize) -> String {
+    let mut bytes = vec![0u8; n];
+    getrandom::getrandom(&mut bytes).expect("random bytes");
+    hex(&bytes)
+}
+
+/// Random UUID v4, the format Prisma uses for IDs
+pub fn random_id() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("random bytes");
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex(&bytes);
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
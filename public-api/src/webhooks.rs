@@ -0,0 +1,756 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use worker::*;
+
+use crate::cloudflare::{D1Sql, FetchSender, KvCache};
+use crate::error::{ApiError, ApiResult};
+use crate::masking::ColumnMasks;
+use crate::pricing::PriceRules;
+use crate::routes::RouteKind;
+use crate::store::{Cache, OrderStore, SqlStore, TableStore, WebhookStore};
+use crate::tables::can_access_table;
+use crate::telemetry::RequestMetrics;
+use crate::utils;
+use crate::{current_timestamp, token_expired, PaginationInfo, TokenInfo};
+
+// ============================================================================
+// WEBHOOKS
+// ============================================================================
+//
+// Events come from two places: public API writes (as soon as the order service
+// answers) and a cron scan of inventoryTransactions, which also sees changes made
+// in the admin UI. Event IDs are derived from the change that caused them, so the
+// same sale seen by both only queues one delivery per subscription.
+
+/// Every event a subscription can ask for
+pub const EVENT_TYPES: &[&str] = &[
+    "item.sold_out",
+    "item.restocked",
+    "item.price_changed",
+    "rental.started",
+    "rental.released",
+];
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+const MAX_SUBSCRIPTIONS_PER_TOKEN: usize = 10;
+const MAX_URL_LENGTH: usize = 2048;
+/// Attempts before a delivery is marked failed (about two hours of retries)
+const MAX_ATTEMPTS: i64 = 8;
+/// Retry delays double from this...
+const RETRY_BASE_DELAY: i64 = 60;
+/// ...up to this
+const RETRY_MAX_DELAY: i64 = 3600;
+/// A claimed delivery isn't picked up by another run for this long
+const DELIVERY_LEASE: i64 = 300;
+/// Deliveries sent per run
+const DELIVERY_BATCH: u32 = 50;
+/// inventoryTransactions read per query, and queries per scheduled run
+const SCAN_BATCH: u32 = 200;
+const SCAN_MAX_BATCHES: usize = 5;
+const CURSOR_INVENTORY: &str = "inventoryTransactions";
+
+// ============================================================================
+// DATA STRUCTURES
+// ============================================================================
+
+/// webhookSubscriptions row, without the secret
+#[derive(Debug, Deserialize)]
+pub struct SubscriptionRow {
+    pub id: String,
+    pub url: String,
+    /// JSON array of event types
+    pub events: String,
+    pub active: i64,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<String>,
+}
+
+/// A subscription to an event with its token's access fields
+#[derive(Debug, Deserialize)]
+pub struct Subscriber {
+    pub id: String,
+    #[serde(rename = "tokenId")]
+    pub token_id: String,
+    #[serde(rename = "isAdmin", default, deserialize_with = "utils::flag")]
+    pub is_admin: bool,
+    #[serde(rename = "tableAccess")]
+    pub table_access: Option<String>,
+    #[serde(rename = "createdBy", default)]
+    pub created_by: Option<String>,
+    #[serde(rename = "userId", default)]
+    pub user_id: Option<String>,
+    #[serde(rename = "customerId", default)]
+    pub customer_id: Option<String>,
+    #[serde(rename = "priceRules", default)]
+    pub price_rules: Option<String>,
+    #[serde(rename = "columnRules", default)]
+    pub column_rules: Option<String>,
+    #[serde(rename = "expiresAt", default)]
+    pub expires_at: Option<i64>,
+}
+
+impl Subscriber {
+    fn token(&self) -> TokenInfo {
+        TokenInfo {
+            id: self.token_id.clone(),
+            is_admin: self.is_admin,
+            table_access: self.table_access.clone(),
+            created_by: self.created_by.clone(),
+            user_id: self.user_id.clone(),
+            rate_limits: None,
+            allowed_domains: None,
+            expires_at: self.expires_at,
+            customer_id: self.customer_id.clone(),
+            price_rules: self.price_rules.clone(),
+            column_rules: self.column_rules.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NewDelivery {
+    pub id: String,
+    pub subscription_id: String,
+    pub token_id: String,
+    pub event_id: String,
+    pub event: &'static str,
+    pub payload: String,
+    pub next_attempt_at: i64,
+}
+
+/// A claimed delivery with where and how to send it
+#[derive(Debug, Deserialize)]
+pub struct DueDelivery {
+    pub id: String,
+    #[serde(rename = "eventId")]
+    pub event_id: String,
+    pub event: String,
+    pub payload: String,
+    pub attempts: i64,
+    pub url: String,
+    pub secret: String,
+}
+
+/// webhookDeliveries row for the delivery log
+#[derive(Debug, Deserialize)]
+pub struct DeliveryRow {
+    id: String,
+    #[serde(rename = "subscriptionId")]
+    subscription_id: String,
+    #[serde(rename = "eventId")]
+    event_id: String,
+    event: String,
+    payload: String,
+    status: String,
+    attempts: i64,
+    #[serde(rename = "nextAttemptAt")]
+    next_attempt_at: Option<i64>,
+    #[serde(rename = "responseStatus")]
+    response_status: Option<i64>,
+    #[serde(rename = "lastError")]
+    last_error: Option<String>,
+    #[serde(rename = "createdAt")]
+    created_at: Option<String>,
+    #[serde(rename = "deliveredAt")]
+    delivered_at: Option<String>,
+}
+
+/// Delivery log filters from the query string
+#[derive(Debug, Default)]
+pub struct DeliveryFilters {
+    pub subscription_id: Option<String>,
+    pub status: Option<String>,
+    pub event: Option<String>,
+}
+
+/// inventoryTransactions row; `position` is its rowid
+#[derive(Debug, Deserialize)]
+pub struct InventoryChange {
+    pub position: i64,
+    pub id: String,
+    #[serde(rename = "tableId")]
+    pub table_id: String,
+    #[serde(rename = "tableName")]
+    pub table_name: Option<String>,
+    #[serde(rename = "itemId")]
+    pub item_id: String,
+    #[serde(rename = "transactionType")]
+    pub transaction_type: String,
+    #[serde(rename = "referenceId")]
+    pub reference_id: Option<String>,
+    #[serde(rename = "previousData")]
+    pub previous_data: Option<String>,
+    #[serde(rename = "newData")]
+    pub new_data: Option<String>,
+}
+
+/// An event to fan out to subscribers
+#[derive(Debug, PartialEq)]
+pub struct WebhookEvent {
+    /// `<source ID>:<type>` - the sale/rental ID, or the transaction ID for other changes
+    pub id: String,
+    pub event_type: &'static str,
+    pub table_id: String,
+    pub data: serde_json::Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Subscription {
+    id: String,
+    url: String,
+    events: Vec<String>,
+    active: bool,
+    #[serde(rename = "createdAt")]
+    created_at: Option<String>,
+    /// Signing secret, only returned when the subscription is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<String>,
+}
+
+impl From<SubscriptionRow> for Subscription {
+    fn from(row: SubscriptionRow) -> Self {
+        Self {
+            events: serde_json::from_str(&row.events).unwrap_or_default(),
+            id: row.id,
+            url: row.url,
+            active: row.active != 0,
+            created_at: row.created_at,
+            secret: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubscriptionsResponse {
+    webhooks: Vec<Subscription>,
+    count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeletedResponse {
+    id: String,
+    deleted: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Delivery {
+    id: String,
+    #[serde(rename = "subscriptionId")]
+    subscription_id: String,
+    #[serde(rename = "eventId")]
+    event_id: String,
+    event: String,
+    status: String,
+    attempts: i64,
+    /// Epoch seconds of the next retry while pending
+    #[serde(rename = "nextAttemptAt")]
+    next_attempt_at: Option<i64>,
+    #[serde(rename = "responseStatus")]
+    response_status: Option<i64>,
+    #[serde(rename = "lastError")]
+    last_error: Option<String>,
+    #[serde(rename = "createdAt")]
+    created_at: Option<String>,
+    #[serde(rename = "deliveredAt")]
+    delivered_at: Option<String>,
+    payload: serde_json::Value,
+}
+
+impl From<DeliveryRow> for Delivery {
+    fn from(row: DeliveryRow) -> Self {
+        Self {
+            payload: serde_json::from_str(&row.payload).unwrap_or(serde_json::Value::Null),
+            id: row.id,
+            subscription_id: row.subscription_id,
+            event_id: row.event_id,
+            event: row.event,
+            status: row.status,
+            attempts: row.attempts,
+            next_attempt_at: row.next_attempt_at,
+            response_status: row.response_status,
+            last_error: row.last_error,
+            created_at: row.created_at,
+            delivered_at: row.delivered_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveriesResponse {
+    deliveries: Vec<Delivery>,
+    count: usize,
+    total: i64,
+    pagination: PaginationInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreateSubscriptionRequest {
+    url: String,
+    events: Vec<String>,
+}
+
+/// What a delivery run did, for the logs
+#[derive(Debug, Default, PartialEq)]
+pub struct DeliveryStats {
+    pub delivered: usize,
+    pub retrying: usize,
+    pub failed: usize,
+}
+
+/// What a scheduled run did, for the logs
+#[derive(Debug, Default, PartialEq)]
+pub struct ScanStats {
+    pub changes: usize,
+    pub queued: usize,
+    pub deliveries: DeliveryStats,
+}
+
+/// Sends webhook requests: fetch in the worker, an HTTP client natively
+#[allow(async_fn_in_trait)]
+pub trait WebhookSender {
+    /// POST a body, returning the response status, or why no response was received
+    async fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> std::result::Result<u16, String>;
+}
+
+// ============================================================================
+// SUBSCRIPTION HANDLERS
+// ============================================================================
+
+/// GET /api/public/webhooks - The token's subscriptions (secrets are never listed)
+pub async fn list_subscriptions<S: WebhookStore>(store: &S, token: &TokenInfo) -> ApiResult<SubscriptionsResponse> {
+    let webhooks: Vec<Subscription> = store
+        .webhook_subscriptions(&token.id)
+        .await?
+        .into_iter()
+        .map(Subscription::from)
+        .collect();
+    Ok(SubscriptionsResponse { count: webhooks.len(), webhooks })
+}
+
+/// POST /api/public/webhooks - Subscribe a URL to events; the signing secret is only returned here
+pub async fn create_subscription<S: WebhookStore>(store: &S, token: &TokenInfo, body: &str) -> ApiResult<Subscription> {
+    let request: CreateSubscriptionRequest = serde_json::from_str(body).map_err(|_| {
+        ApiError::InvalidParameter("Body must be a JSON object with url and events".to_string())
+    })?;
+    validate_url(&request.url)?;
+
+    let mut events: Vec<String> = vec![];
+    for event in request.events {
+        if !EVENT_TYPES.contains(&event.as_str()) {
+            return Err(ApiError::InvalidParameter(format!(
+                "Unknown event: {} (expected one of {})",
+                event,
+                EVENT_TYPES.join(", ")
+            )));
+        }
+        if !events.contains(&event) {
+            events.push(event);
+        }
+    }
+    if events.is_empty() {
+        return Err(ApiError::InvalidParameter("events must list at least one event".to_string()));
+    }
+
+    if store.webhook_subscriptions(&token.id).await?.len() >= MAX_SUBSCRIPTIONS_PER_TOKEN {
+        return Err(ApiError::InvalidParameter(format!(
+            "A token can have at most {} webhook subscriptions",
+            MAX_SUBSCRIPTIONS_PER_TOKEN
+        )));
+    }
+
+    let secret = format!("whsec_{}", utils::random_hex(32));
+    let row = store
+        .insert_webhook_subscription(&token.id, &utils::random_id(), &request.url, &serde_json::to_string(&events)?, &secret)
+        .await?;
+    Ok(Subscription { secret: Some(secret), ..row.into() })
+}
+
+/// DELETE /api/public/webhooks/:id - Unsubscribe, dropping the subscription's delivery log
+pub async fn delete_subscription<S: WebhookStore>(store: &S, token: &TokenInfo, id: &str) -> ApiResult<DeletedResponse> {
+    if !store.delete_webhook_subscription(&token.id, id).await? {
+        return Err(ApiError::WebhookNotFound);
+    }
+    Ok(DeletedResponse { id: id.to_string(), deleted: true })
+}
+
+/// GET /api/public/webhooks/deliveries - The token's delivery log, newest first
+pub async fn list_deliveries<S: WebhookStore>(
+    store: &S,
+    token: &TokenInfo,
+    query: &HashMap<String, String>,
+) -> ApiResult<DeliveriesResponse> {
+    let limit: u32 = query.get("limit").and_then(|l| l.parse().ok()).unwrap_or(50).clamp(1, 200);
+    let offset: u32 = query.get("offset").and_then(|o| o.parse().ok()).unwrap_or(0);
+    let filters = DeliveryFilters {
+        subscription_id: query.get("subscriptionId").cloned(),
+        status: query.get("status").cloned(),
+        event: query.get("event").cloned(),
+    };
+    if let Some(ref status) = filters.status
+        && !matches!(status.as_str(), "pending" | "delivered" | "failed")
+    {
+        return Err(ApiError::InvalidParameter(
+            "status must be one of pending, delivered, failed".to_string(),
+        ));
+    }
+
+    let (rows, total) = store.webhook_deliveries(&token.id, &filters, limit, offset).await?;
+    let deliveries: Vec<Delivery> = rows.into_iter().map(Delivery::from).collect();
+    Ok(DeliveriesResponse {
+        count: deliveries.len(),
+        deliveries,
+        total,
+        pagination: PaginationInfo {
+            total,
+            page: (offset / limit) + 1,
+            limit,
+            has_more: (offset + limit) < total as u32,
+        },
+    })
+}
+
+/// Webhook URLs must be HTTPS, except plain HTTP to localhost for development
+fn validate_url(url: &str) -> ApiResult<()> {
+    let invalid = || ApiError::InvalidParameter("url must be an absolute https:// URL".to_string());
+    if url.len() > MAX_URL_LENGTH {
+        return Err(invalid());
+    }
+    let parsed = Url::parse(url).map_err(|_| invalid())?;
+    let local = matches!(parsed.host_str(), Some("localhost" | "127.0.0.1"));
+    match parsed.scheme() {
+        "https" => Ok(()),
+        "http" if local => Ok(()),
+        _ => Err(invalid()),
+    }
+}
+
+// ============================================================================
+// EVENTS
+// ============================================================================
+
+/// A number field of an item, accepting numeric strings as the admin UI may store them
+fn number_field(data: &serde_json::Value, key: &str) -> Option<f64> {
+    match data.get(key)? {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn parse_data(data: Option<&str>) -> serde_json::Value {
+    data.and_then(|d| serde_json::from_str(d).ok()).unwrap_or(serde_json::Value::Null)
+}
+
+/// Events caused by one inventory change: rentals start/end on rent/release, other changes
+/// are compared field by field - `qty` crossing zero and `price` changing
+pub fn events_from_change(change: &InventoryChange) -> Vec<WebhookEvent> {
+    let previous = parse_data(change.previous_data.as_deref());
+    let current = parse_data(change.new_data.as_deref());
+    let source = change.reference_id.as_deref().unwrap_or(&change.id);
+
+    let event = |event_type: &'static str, extra: serde_json::Value| {
+        let mut data = serde_json::json!({
+            "tableId": change.table_id,
+            "tableName": change.table_name,
+            "itemId": change.item_id,
+            "item": current,
+        });
+        if let (Some(data), serde_json::Value::Object(extra)) = (data.as_object_mut(), extra) {
+            data.extend(extra);
+        }
+        WebhookEvent {
+            id: format!("{}:{}", source, event_type),
+            event_type,
+            table_id: change.table_id.clone(),
+            data,
+        }
+    };
+
+    match change.transaction_type.as_str() {
+        // The snapshot of a rental may predate later edits, so it's not compared
+        "rent" => return vec![event("rental.started", serde_json::json!({"rentalId": change.reference_id}))],
+        "release" => return vec![event("rental.released", serde_json::json!({"rentalId": change.reference_id}))],
+        _ => {}
+    }
+
+    let mut events = vec![];
+    if let (Some(before), Some(after)) = (number_field(&previous, "qty"), number_field(&current, "qty")) {
+        let qty = serde_json::json!({"previousQty": before, "qty": after});
+        if before > 0.0 && after <= 0.0 {
+            events.push(event("item.sold_out", qty));
+        } else if before <= 0.0 && after > 0.0 {
+            events.push(event("item.restocked", qty));
+        }
+    }
+    if let (Some(before), Some(after)) = (number_field(&previous, "price"), number_field(&current, "price"))
+        && before != after
+    {
+        events.push(event("item.price_changed", serde_json::json!({"previousPrice": before, "price": after})));
+    }
+    events
+}
+
+/// The inventory change a successful public API write made, rebuilt from the order service's
+/// response (`sale` or `rental`, with the item as it was) and the item as it is now
+pub async fn change_from_write<S: TableStore>(store: &S, kind: RouteKind, body: &str) -> Result<Option<InventoryChange>> {
+    let (key, transaction_type) = match kind {
+        RouteKind::Buy => ("sale", "sale"),
+        RouteKind::Rent => ("rental", "rent"),
+        RouteKind::Release => ("rental", "release"),
+        _ => return Ok(None),
+    };
+    let response: serde_json::Value = serde_json::from_str(body).unwrap_or_default();
+    let Some(record) = response.get(key) else {
+        return Ok(None);
+    };
+    let field = |name: &str| record.get(name).and_then(|v| v.as_str()).map(str::to_string);
+    let (Some(id), Some(table_id), Some(item_id)) = (field("id"), field("tableId"), field("itemId")) else {
+        return Ok(None);
+    };
+
+    let item = store.table_row(&table_id, &item_id).await?;
+    Ok(Some(InventoryChange {
+        position: 0,
+        id: id.clone(),
+        table_id,
+        table_name: field("tableName"),
+        item_id,
+        transaction_type: transaction_type.to_string(),
+        reference_id: Some(id),
+        previous_data: field("itemSnapshot"),
+        new_data: item.map(|row| row.data),
+    }))
+}
+
+/// The item field an event reports on, which a subscriber must be allowed to see in full
+fn event_column(event_type: &str) -> Option<&'static str> {
+    match event_type {
+        "item.sold_out" | "item.restocked" => Some("qty"),
+        "item.price_changed" => Some("price"),
+        _ => None,
+    }
+}
+
+/// Queue an event for every subscriber whose token can read its table, with the item (and any
+/// prices) as that token may see it; returns how many were queued. Tokens bound to a customer
+/// only hear of that customer's rentals.
+pub async fn enqueue<S: TableStore + OrderStore + WebhookStore, C: Cache>(
+    store: &S,
+    cache: &C,
+    event: &WebhookEvent,
+    now: i64,
+) -> Result<usize> {
+    let subscribers = store.webhook_subscribers(event.event_type).await?;
+    if subscribers.is_empty() {
+        return Ok(0);
+    }
+    let Some(table) = store.get_table(&event.table_id).await? else {
+        return Ok(0);
+    };
+    if !table.is_catalog() {
+        return Ok(0);
+    }
+
+    let payload = |data: &serde_json::Value| {
+        serde_json::json!({
+            "id": event.id,
+            "type": event.event_type,
+            "timestamp": now,
+            "data": data,
+        })
+        .to_string()
+    };
+
+    // Looked up once, the first time a customer-bound subscriber needs it
+    let mut rental_customer: Option<Option<String>> = None;
+    let mut queued = 0;
+    for subscriber in subscribers {
+        let token = subscriber.token();
+        if token_expired(&token) || !can_access_table(store, cache, &token, &table, &mut None).await? {
+            continue;
+        }
+        if let Some(ref bound) = token.customer_id
+            && event.event_type.starts_with("rental.")
+        {
+            if rental_customer.is_none() {
+                let customer = match event.data.get("rentalId").and_then(|id| id.as_str()) {
+                    Some(rental_id) => store.rental_customer(rental_id).await?,
+                    None => None,
+                };
+                rental_customer = Some(customer);
+            }
+            if rental_customer.as_ref().and_then(Option::as_ref) != Some(bound) {
+                continue;
+            }
+        }
+        let masks = ColumnMasks::load(store, &token, std::slice::from_ref(&table.id)).await?;
+        if event_column(event.event_type).is_some_and(|column| masks.restricts(&table.id, column)) {
+            continue;
+        }
+        let mut data = event.data.clone();
+        if let Some(serde_json::Value::Object(item)) = data.get_mut("item") {
+            masks.apply(&table.id, item);
+        }
+        if let (Some(rules), serde_json::Value::Object(fields)) = (PriceRules::for_token(&token), &mut data) {
+            if let Some(serde_json::Value::Object(item)) = fields.get_mut("item") {
+                rules.mark_up(&table.id, item);
+            }
+            for field in ["previousPrice", "price"] {
+                if let Some(value) = fields.get_mut(field) {
+                    *value = rules.mark_up_value(&table.id, value);
+                }
+            }
+        }
+        let delivery = NewDelivery {
+            id: utils::random_id(),
+            subscription_id: subscriber.id,
+            token_id: subscriber.token_id,
+            event_id: event.id.clone(),
+            event: event.event_type,
+            payload: payload(&data),
+            next_attempt_at: now,
+        };
+        if store.insert_webhook_delivery(&delivery).await? {
+            queued += 1;
+        }
+    }
+    Ok(queued)
+}
+
+// ============================================================================
+// DELIVERY
+// ============================================================================
+
+/// Hex HMAC-SHA256 of `<timestamp>.<body>` - receivers recompute it with their secret
+/// and reject stale timestamps to prevent replays
+pub fn signature(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    utils::hex(&mac.finalize().into_bytes())
+}
+
+/// Delay before retrying after `attempts` failed attempts
+fn retry_delay(attempts: i64) -> i64 {
+    let exponent = (attempts - 1).clamp(0, 16) as u32;
+    (RETRY_BASE_DELAY * 2i64.pow(exponent)).min(RETRY_MAX_DELAY)
+}
+
+/// Send every due delivery once, then schedule a retry or give up on the failed ones
+pub async fn deliver_due<S: WebhookStore, W: WebhookSender>(store: &S, sender: &W, now: i64) -> Result<DeliveryStats> {
+    let mut stats = DeliveryStats::default();
+    for delivery in store.claim_webhook_deliveries(now, now + DELIVERY_LEASE, DELIVERY_BATCH).await? {
+        let headers = [
+            ("Content-Type", "application/json".to_string()),
+            ("User-Agent", "store-public-api-webhooks".to_string()),
+            ("X-Webhook-Id", delivery.event_id.clone()),
+            ("X-Webhook-Event", delivery.event.clone()),
+            (SIGNATURE_HEADER, format!("t={},v1={}", now, signature(&delivery.secret, now, &delivery.payload))),
+        ];
+        let attempts = delivery.attempts + 1;
+        let (response_status, error) = match sender.post(&delivery.url, &headers, &delivery.payload).await {
+            Ok(status) if (200..300).contains(&status) => (Some(status), None),
+            Ok(status) => (Some(status), Some(format!("Endpoint answered HTTP {}", status))),
+            Err(e) => (None, Some(e)),
+        };
+
+        let (status, next_attempt_at) = match error {
+            None => {
+                stats.delivered += 1;
+                ("delivered", None)
+            }
+            Some(_) if attempts >= MAX_ATTEMPTS => {
+                stats.failed += 1;
+                ("failed", None)
+            }
+            Some(_) => {
+                stats.retrying += 1;
+                ("pending", Some(now + retry_delay(attempts)))
+            }
+        };
+        store
+            .update_webhook_delivery(&delivery.id, status, attempts, next_attempt_at, response_status, error.as_deref())
+            .await?;
+    }
+    Ok(stats)
+}
+
+/// After a successful public API write: queue its events and send them straight away
+pub async fn on_write<S, C, W>(store: &S, cache: &C, sender: &W, kind: RouteKind, body: &str, now: i64) -> Result<DeliveryStats>
+where
+    S: TableStore + OrderStore + WebhookStore,
+    C: Cache,
+    W: WebhookSender,
+{
+    let Some(change) = change_from_write(store, kind, body).await? else {
+        return Ok(DeliveryStats::default());
+    };
+    let mut queued = 0;
+    for event in events_from_change(&change) {
+        queued += enqueue(store, cache, &event, now).await?;
+    }
+    if queued == 0 {
+        return Ok(DeliveryStats::default());
+    }
+    deliver_due(store, sender, now).await
+}
+
+/// Scheduled run: queue events for inventory changes since the last run, then send (and retry)
+/// everything due. The first run starts from the newest change rather than replaying history.
+pub async fn run_scheduled<S, C, W>(store: &S, cache: &C, sender: &W, now: i64) -> Result<ScanStats>
+where
+    S: TableStore + OrderStore + WebhookStore,
+    C: Cache,
+    W: WebhookSender,
+{
+    let mut stats = ScanStats::default();
+    let mut position = match store.webhook_cursor(CURSOR_INVENTORY).await? {
+        Some(position) => position,
+        None => {
+            let latest = store.latest_inventory_position().await?;
+            store.set_webhook_cursor(CURSOR_INVENTORY, latest).await?;
+            latest
+        }
+    };
+
+    for _ in 0..SCAN_MAX_BATCHES {
+        let changes = store.inventory_changes(position, SCAN_BATCH).await?;
+        for change in &changes {
+            for event in events_from_change(change) {
+                stats.queued += enqueue(store, cache, &event, now).await?;
+            }
+            position = change.position;
+        }
+        stats.changes += changes.len();
+        if !changes.is_empty() {
+            store.set_webhook_cursor(CURSOR_INVENTORY, position).await?;
+        }
+        if changes.len() < SCAN_BATCH as usize {
+            break;
+        }
+    }
+
+    stats.deliveries = deliver_due(store, sender, now).await?;
+    Ok(stats)
+}
+
+// ============================================================================
+// WORKER GLUE
+// ============================================================================
+
+/// Fire webhooks for a proxied write after the response is sent (via wait_until)
+pub async fn notify_write(env: Env, request_id: String, kind: RouteKind, mut response: Response) {
+    let body = response.text().await.unwrap_or_default();
+    let metrics = RequestMetrics::new(request_id);
+    let result = async {
+        let store = SqlStore::new(D1Sql::new(&env, &metrics)?);
+        let cache = KvCache::new(&env, &metrics)?;
+        on_write(&store, &cache, &FetchSender, kind, &body, current_timestamp() as i64).await
+    }
+    .await;
+    if let Err(e) = result {
+        utils::log_error(&format!("[{}] Webhook dispatch failed: {:?}", metrics.request_id, e));
+    }
+}
@@ -22,6 +22,8 @@
  *   --preset=tables        Test /api/tables endpoint (admin token required)
  *   --preset=public        Test /api/public/records via Rust worker (port 8788)
  *   --preset=public-tables Test /api/public/tables via Rust worker (port 8788)
+ *   --preset=public-filtered Test a where[...] filter on /api/public/records via Rust worker (port 8788);
+ *                          use a token with tableAccess so results come from D1, not the KV cache
  *   --preset=health        Test /health endpoint (no auth)
  *
  * Examples:
@@ -168,6 +170,11 @@ function parseArgs(): FloodConfig {
         // Public endpoints target Rust worker by default
         config.baseUrl = URLS.rust
         config.environment = 'local (Rust)'
+      } else if (preset === 'public-filtered') {
+        // Filters are attribute index lookups (migration 018); compare before/after on a large catalog
+        config.endpoint = '/api/public/records?where[color]=red&limit=100'
+        config.baseUrl = URLS.rust
+        config.environment = 'local (Rust)'
       } else if (preset === 'health') {
         config.endpoint = '/health'
       }
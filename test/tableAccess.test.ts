@@ -0,0 +1,39 @@
+import { describe, it, expect } from 'vitest'
+import {
+  getAllowedTableIds,
+  parseTableAccess,
+  readableTableIds,
+  restrictedTableIds,
+  restrictTableAccess,
+  tableAccessIds
+} from '../api/src/utils/tableAccess'
+
+const user = (tableAccess: string | null): any => ({ tokenId: 'partner-token', token: { tableAccess } })
+
+describe('tableAccess', () => {
+  const objectAccess = JSON.stringify({ tbl1: ['read'], tbl2: ['read', 'buy'], tbl3: ['buy'] })
+
+  it('reads the array format as every operation on each table', () => {
+    const access = parseTableAccess('["tbl1","tbl2"]')!
+    expect(tableAccessIds(access)).toEqual(['tbl1', 'tbl2'])
+    expect(readableTableIds(access)).toEqual(['tbl1', 'tbl2'])
+    expect(restrictTableAccess(access, ['tbl2'])).toEqual(['tbl2'])
+  })
+
+  it('reads the object format as per-table operations', () => {
+    const access = parseTableAccess(objectAccess)!
+    expect(tableAccessIds(access)).toEqual(['tbl1', 'tbl2', 'tbl3'])
+    expect(readableTableIds(access)).toEqual(['tbl1', 'tbl2'])
+    expect(restrictTableAccess(access, ['tbl2', 'tbl3'])).toEqual({ tbl2: ['read', 'buy'], tbl3: ['buy'] })
+  })
+
+  it('restricts tokens with the object format to the tables they can read', () => {
+    expect(restrictedTableIds(objectAccess)).toEqual(['tbl1', 'tbl2'])
+    expect(restrictedTableIds(JSON.stringify({ tbl3: ['buy'] }))).toEqual([])
+    expect(restrictedTableIds(null)).toBeNull()
+    expect(restrictedTableIds('[]')).toBeNull()
+    expect(getAllowedTableIds(user(objectAccess))).toEqual(['tbl1', 'tbl2'])
+    expect(getAllowedTableIds(user(null))).toEqual([])
+    expect(getAllowedTableIds({ tokenId: 'admin-token' } as any)).toBeNull()
+  })
+})